@@ -0,0 +1,35 @@
+//! RUST_LOG=info cargo run --example capture-deps,
+//! then request http://127.0.0.1:8000/visits.
+
+use log::info;
+use roa::logger::logger;
+use roa::preload::*;
+use roa::router::{get, Router};
+use roa::{endpoint_fn, App};
+use std::error::Error as StdError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[async_std::main]
+async fn main() -> Result<(), Box<dyn StdError>> {
+    pretty_env_logger::init();
+    // a dependency shared by handlers, like a database pool.
+    let visits = Arc::new(AtomicUsize::new(0));
+    let router = Router::new().on(
+        "/visits",
+        get(endpoint_fn(move |ctx| {
+            let visits = visits.clone();
+            Box::pin(async move {
+                let count = visits.fetch_add(1, Ordering::SeqCst) + 1;
+                ctx.write(format!("visits: {}", count));
+                Ok(())
+            })
+        })),
+    );
+    let app = App::new().gate(logger).end(router.routes("/")?);
+    app.listen("127.0.0.1:8000", |addr| {
+        info!("Server is listening on {}", addr)
+    })?
+    .await?;
+    Ok(())
+}
@@ -1,17 +1,22 @@
 #[cfg(feature = "runtime")]
-mod runtime;
+pub(crate) mod runtime;
 
 mod future;
+mod monitor;
 mod stream;
+mod transport;
 use crate::{
     Chain, Context, Endpoint, Middleware, MiddlewareExt, Request, Response, State,
 };
 use future::SendFuture;
+use futures::channel::oneshot::channel;
 use futures::io::{AsyncRead, AsyncWrite};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use http::{Request as HttpRequest, Response as HttpResponse};
 use hyper::service::Service;
 use hyper::Body as HyperBody;
 use hyper::Server;
+use monitor::{ErrorHook, Monitor};
 use std::error::Error;
 use std::future::Future;
 use std::net::SocketAddr;
@@ -23,6 +28,10 @@ use crate::Accept;
 use crate::{Executor, Spawn};
 use std::convert::Infallible;
 pub use stream::AddrStream;
+pub use transport::Transport;
+
+/// Minimum buffer size of http/1 connections, hyper panics on smaller ones.
+const MIN_BUF_SIZE: usize = 8192;
 
 /// The Application of roa.
 /// ### Example
@@ -86,13 +95,19 @@ pub struct App<S, T> {
     service: T,
     exec: Executor,
     state: S,
+    headers: Arc<HeaderMap>,
+    error_hook: Option<ErrorHook>,
+    max_buf_size: Option<usize>,
 }
 
 /// An implementation of hyper HttpService.
 pub struct HttpService<S, E> {
     endpoint: Arc<E>,
     remote_addr: SocketAddr,
+    secure: bool,
+    monitor: Option<Arc<Monitor>>,
     exec: Executor,
+    headers: Arc<HeaderMap>,
     pub(crate) state: S,
 }
 
@@ -103,13 +118,88 @@ impl<S, T> App<S, T> {
             exec,
             state,
             service,
+            headers,
+            error_hook,
+            max_buf_size,
         } = self;
         App {
             service: mapper(service),
             exec,
             state,
+            headers,
+            error_hook,
+            max_buf_size,
         }
     }
+
+    /// Set a callback invoked with the peer address and the error, when a connection fails,
+    /// like a TLS handshake failure or a connection reset by peer.
+    ///
+    /// Such errors never reach a `Context`, only the first error of each connection is reported.
+    /// Malformed requests, answered by hyper directly, are reported as errors of kind `InvalidData`.
+    ///
+    /// Connections of every server constructed by this app are monitored,
+    /// whether it's constructed by `accept`, `serve_on` or extensions like `run` and `run_tls`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::App;
+    /// use log::warn;
+    ///
+    /// let app = App::new()
+    ///     .on_connection_error(|addr, err| warn!("connection from {} failed: {}", addr, err))
+    ///     .end("Hello, World");
+    /// ```
+    pub fn on_connection_error(
+        mut self,
+        hook: impl 'static + Send + Sync + Fn(SocketAddr, &std::io::Error),
+    ) -> Self {
+        self.error_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set default headers of every response, they are set before middlewares and endpoint run,
+    /// so handlers can override them.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::App;
+    /// use roa_core::http::header::{HeaderValue, CACHE_CONTROL, SERVER};
+    ///
+    /// let app = App::new()
+    ///     .default_headers(vec![
+    ///         (SERVER, HeaderValue::from_static("roa")),
+    ///         (CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+    ///     ])
+    ///     .end("Hello, World");
+    /// ```
+    pub fn default_headers(
+        mut self,
+        headers: impl IntoIterator<Item = (HeaderName, HeaderValue)>,
+    ) -> Self {
+        let map = Arc::make_mut(&mut self.headers);
+        for (name, value) in headers {
+            map.append(name, value);
+        }
+        self
+    }
+
+    /// Set the maximum buffer size of each http/1 connection, 8 KiB at least.
+    ///
+    /// Hyper buffers the whole request head before any middleware runs,
+    /// a head larger than this buffer is rejected by hyper with 431 REQUEST HEADER FIELDS TOO LARGE.
+    /// It's about 400 KiB by default.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::App;
+    ///
+    /// let app = App::new().max_buf_size(16 * 1024).end("Hello, World");
+    /// ```
+    pub fn max_buf_size(mut self, bytes: usize) -> Self {
+        self.max_buf_size = Some(bytes.max(MIN_BUF_SIZE));
+        self
+    }
 }
 
 impl<S> App<S, ()> {
@@ -119,6 +209,9 @@ impl<S> App<S, ()> {
             service: (),
             exec: Executor(Arc::new(exec)),
             state,
+            headers: Arc::new(HeaderMap::new()),
+            error_hook: None,
+            max_buf_size: None,
         }
     }
 }
@@ -156,9 +249,25 @@ where
         I: Accept<Conn = AddrStream<IO>>,
         I::Error: Into<Box<dyn Error + Send + Sync>>,
     {
-        Server::builder(incoming)
-            .executor(self.exec.clone())
-            .serve(self)
+        let mut builder = Server::builder(incoming).executor(self.exec.clone());
+        if let Some(size) = self.max_buf_size {
+            builder = builder.http1_max_buf_size(size);
+        }
+        builder.serve(self)
+    }
+
+    /// Construct a hyper server by a transport, return it and the local addr.
+    pub fn serve_on<T>(
+        self,
+        transport: T,
+    ) -> std::io::Result<(SocketAddr, Server<T::Incoming, Self, Executor>)>
+    where
+        S: State,
+        T: Transport,
+        <T::Incoming as Accept>::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let (addr, incoming) = transport.incoming()?;
+        Ok((addr, self.accept(incoming)))
     }
 
     /// Make a fake http service for test.
@@ -171,14 +280,18 @@ where
         let addr = ([127, 0, 0, 1], 0);
         let state = self.state.clone();
         let exec = self.exec.clone();
-        HttpService::new(endpoint, addr.into(), exec, state)
+        let headers = self.headers.clone();
+        HttpService::new(endpoint, addr.into(), exec, headers, state)
     }
 }
 
 macro_rules! impl_poll_ready {
     () => {
         #[inline]
-        fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> Poll<Result<(), Self::Error>> {
             Poll::Ready(Ok(()))
         }
     };
@@ -202,9 +315,20 @@ where
     fn call(&mut self, stream: &AddrStream<IO>) -> Self::Future {
         let endpoint = self.service.clone();
         let addr = stream.remote_addr;
+        let secure = stream.is_secure();
+        let monitor = self.error_hook.clone().map(|hook| {
+            stream.monitor.install(hook);
+            stream.monitor.clone()
+        });
         let state = self.state.clone();
         let exec = self.exec.clone();
-        Box::pin(async move { Ok(HttpService::new(endpoint, addr, exec, state)) })
+        let headers = self.headers.clone();
+        Box::pin(async move {
+            let mut service = HttpService::new(endpoint, addr, exec, headers, state);
+            service.secure = secure;
+            service.monitor = monitor;
+            Ok(service)
+        })
     }
 }
 
@@ -228,10 +352,14 @@ where
 
     #[inline]
     fn call(&mut self, req: HttpRequest<HyperBody>) -> Self::Future {
+        if let Some(ref monitor) = self.monitor {
+            monitor.dispatch();
+        }
         let service = self.clone();
         Box::pin(async move {
+            let exec = service.exec.clone();
             let serve_future = SendFuture(Box::pin(service.serve(req.into())));
-            Ok(serve_future.await.into())
+            Ok(serve_future.await.into_resp_with(&exec))
         })
     }
 }
@@ -241,12 +369,16 @@ impl<S, E> HttpService<S, E> {
         endpoint: Arc<E>,
         remote_addr: SocketAddr,
         exec: Executor,
+        headers: Arc<HeaderMap>,
         state: S,
     ) -> Self {
         Self {
             endpoint,
             remote_addr,
+            secure: false,
+            monitor: None,
             exec,
+            headers,
             state,
         }
     }
@@ -261,20 +393,41 @@ impl<S, E> HttpService<S, E> {
         let Self {
             endpoint,
             remote_addr,
+            secure,
             exec,
+            monitor: _,
+            headers,
             state,
         } = self;
-        let mut ctx = Context::new(req, state, exec, remote_addr);
+        // the request is cancelled if `finished` is dropped before sending.
+        let (finished, cancelled) = channel();
+        let mut ctx = Context::new(req, state, exec, remote_addr, cancelled);
+        ctx.secure = secure;
+        if !headers.is_empty() {
+            ctx.resp.headers = (*headers).clone();
+        }
         if let Err(status) = endpoint.call(&mut ctx).await {
             ctx.resp.status = status.status_code;
             if status.expose {
                 ctx.resp.write(status.message);
             } else {
+                // unexposed client errors are usually caused by bad requests, not by bugs.
+                let level = if status.is_client_error() {
+                    log::Level::Warn
+                } else {
+                    log::Level::Error
+                };
                 ctx.exec
-                    .spawn_blocking(move || log::error!("Uncaught status: {}", status))
+                    .spawn_blocking(move || {
+                        log::log!(level, "Uncaught status: {}", status)
+                    })
                     .await;
             }
         }
+        if finished.send(()).is_err() {
+            // no one is waiting for cancellation.
+        }
+        ctx.resp.req_version = ctx.req.version;
         ctx.resp
     }
 }
@@ -285,15 +438,20 @@ impl<S: Clone, E> Clone for HttpService<S, E> {
             endpoint: self.endpoint.clone(),
             state: self.state.clone(),
             exec: self.exec.clone(),
+            headers: self.headers.clone(),
             remote_addr: self.remote_addr,
+            secure: self.secure,
+            monitor: self.monitor.clone(),
         }
     }
 }
 
 #[cfg(all(test, feature = "runtime"))]
 mod tests {
-    use crate::{App, Request};
-    use http::StatusCode;
+    use crate::{App, Context, Request};
+    use http::header::{HeaderMap, HeaderName, HeaderValue, SERVER, TRAILER};
+    use http::{StatusCode, Version};
+    use hyper::body::HttpBody;
 
     #[async_std::test]
     async fn gate_simple() -> Result<(), Box<dyn std::error::Error>> {
@@ -302,4 +460,66 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status);
         Ok(())
     }
+
+    #[test]
+    fn max_buf_size() {
+        assert_eq!(None, App::new().max_buf_size);
+        assert_eq!(Some(8192), App::new().max_buf_size(1024).max_buf_size);
+        assert_eq!(Some(16384), App::new().max_buf_size(16384).max_buf_size);
+    }
+
+    #[async_std::test]
+    async fn default_headers() -> Result<(), Box<dyn std::error::Error>> {
+        let powered_by = HeaderName::from_static("x-powered-by");
+        async fn end(ctx: &mut Context) -> crate::Result {
+            assert_eq!("roa", ctx.resp.headers[SERVER]);
+            ctx.resp
+                .headers
+                .insert(SERVER, HeaderValue::from_static("custom"));
+            Ok(())
+        }
+        let service = App::new()
+            .default_headers(vec![
+                (SERVER, HeaderValue::from_static("roa")),
+                (powered_by.clone(), HeaderValue::from_static("roa")),
+            ])
+            .end(end)
+            .http_service();
+        let resp = service.serve(Request::default()).await;
+        assert_eq!("custom", resp.headers[SERVER]);
+        assert_eq!("roa", resp.headers[powered_by]);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn trailers() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.write("Hello, World");
+            let checksum = HeaderName::from_static("x-checksum");
+            ctx.resp.set_trailers(vec![checksum.clone()], move || {
+                let mut trailers = HeaderMap::new();
+                trailers.insert(checksum, HeaderValue::from_static("deadbeef"));
+                trailers
+            });
+            Ok(())
+        }
+        let service = App::new().end(end).http_service();
+        let exec = service.exec.clone();
+        let resp = service.clone().serve(Request::default()).await;
+        assert!(!resp.trailers_supported());
+        let resp = resp.into_resp_with(&exec);
+        assert!(!resp.headers().contains_key(TRAILER));
+
+        let mut req = Request::default();
+        req.version = Version::HTTP_2;
+        let resp = service.serve(req).await;
+        assert!(resp.trailers_supported());
+        assert_eq!("x-checksum", resp.headers[TRAILER]);
+        let mut body = resp.into_resp_with(&exec).into_body();
+        assert_eq!(&b"Hello, World"[..], &*body.data().await.unwrap()?);
+        assert!(body.data().await.is_none());
+        let trailers = body.trailers().await?.unwrap();
+        assert_eq!("deadbeef", trailers["x-checksum"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,93 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A callback on connection errors, set by `App::on_connection_error`.
+pub(crate) type ErrorHook = Arc<dyn 'static + Send + Sync + Fn(SocketAddr, &io::Error)>;
+
+/// Monitor of a connection, shared by the stream and its http service.
+#[derive(Default)]
+pub(crate) struct Monitor {
+    hook: Mutex<Option<ErrorHook>>,
+    dispatched: AtomicUsize,
+}
+
+impl Monitor {
+    /// Install a hook, the connection is not monitored until a hook is installed.
+    #[inline]
+    pub(crate) fn install(&self, hook: ErrorHook) {
+        if let Ok(mut guard) = self.hook.lock() {
+            *guard = Some(hook);
+        }
+    }
+
+    /// Count a request dispatched to the http service.
+    #[inline]
+    pub(crate) fn dispatch(&self) {
+        self.dispatched.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of requests dispatched to the http service.
+    #[inline]
+    pub(crate) fn dispatched(&self) -> usize {
+        self.dispatched.load(Ordering::Relaxed)
+    }
+
+    /// Report an error, connections are usually broken after an error,
+    /// so only the first one is reported.
+    #[inline]
+    pub(crate) fn report(&self, addr: SocketAddr, err: &io::Error) {
+        let hook = match self.hook.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(_) => None,
+        };
+        if let Some(hook) = hook {
+            hook(addr, err)
+        }
+    }
+}
+
+/// Parse status code of a response head written by hyper.
+///
+/// Returns None if the buffer doesn't start with a http/1 status line.
+#[inline]
+pub(crate) fn status_code(buf: &[u8]) -> Option<u16> {
+    if buf.len() < 12 || !buf.starts_with(b"HTTP/1.") || buf[8] != b' ' {
+        return None;
+    }
+    std::str::from_utf8(&buf[9..12]).ok()?.parse().ok()
+}
+
+/// Build the error reported for a malformed request, answered by hyper directly.
+#[inline]
+pub(crate) fn malformed(buf: &[u8]) -> io::Error {
+    let line = buf.split(|&byte| byte == b'\r').next().unwrap_or_default();
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "malformed request, answered with `{}`",
+            String::from_utf8_lossy(line)
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{malformed, status_code};
+
+    #[test]
+    fn parse_status_code() {
+        assert_eq!(
+            Some(400),
+            status_code(b"HTTP/1.1 400 Bad Request\r\ncontent-length: 0\r\n\r\n")
+        );
+        assert_eq!(Some(200), status_code(b"HTTP/1.0 200 OK\r\n\r\n"));
+        assert_eq!(None, status_code(b"HTTP/2 200\r\n"));
+        assert_eq!(None, status_code(b"Hello, World"));
+        assert_eq!(
+            "malformed request, answered with `HTTP/1.1 400 Bad Request`",
+            malformed(b"HTTP/1.1 400 Bad Request\r\n\r\n").to_string()
+        );
+    }
+}
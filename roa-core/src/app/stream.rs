@@ -1,8 +1,10 @@
+use super::monitor::{self, Monitor};
 use futures::io::{AsyncRead, AsyncWrite};
 use std::io;
 use std::mem::MaybeUninit;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{self, Poll};
 use tokio::io::{AsyncRead as TokioRead, AsyncWrite as TokioWrite};
 
@@ -13,6 +15,13 @@ pub struct AddrStream<IO> {
 
     /// The inner stream.
     pub stream: IO,
+
+    secure: bool,
+
+    pub(crate) monitor: Arc<Monitor>,
+
+    /// Number of response heads written.
+    responses: usize,
 }
 
 impl<IO> AddrStream<IO> {
@@ -22,6 +31,51 @@ impl<IO> AddrStream<IO> {
         AddrStream {
             remote_addr,
             stream,
+            secure: false,
+            monitor: Arc::new(Monitor::default()),
+            responses: 0,
+        }
+    }
+
+    /// Mark this stream as secure, like a TLS stream terminated by this server.
+    #[inline]
+    pub fn secure(mut self) -> Self {
+        self.secure = true;
+        self
+    }
+
+    /// Check if this stream is secure.
+    #[inline]
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Report the error of an io result to the monitor.
+    #[inline]
+    fn report<T>(&self, result: io::Result<T>) -> io::Result<T> {
+        if let Err(ref err) = result {
+            self.monitor.report(self.remote_addr, err)
+        }
+        result
+    }
+
+    /// Inspect response heads written by hyper.
+    ///
+    /// Every request dispatched to the http service gets one final response,
+    /// an extra error response is written by hyper itself on a malformed request.
+    #[inline]
+    fn inspect(&mut self, buf: &[u8]) {
+        match monitor::status_code(buf) {
+            // informational responses, like `100 Continue`, are not final.
+            Some(code) if code < 200 && code != 101 => (),
+            Some(code) => {
+                self.responses += 1;
+                if code >= 400 && self.responses > self.monitor.dispatched() {
+                    self.monitor
+                        .report(self.remote_addr, &monitor::malformed(buf))
+                }
+            }
+            None => (),
         }
     }
 }
@@ -41,7 +95,8 @@ where
         cx: &mut task::Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.stream).poll_read(cx, buf)
+        let result = futures::ready!(Pin::new(&mut self.stream).poll_read(cx, buf));
+        Poll::Ready(self.report(result))
     }
 }
 
@@ -55,7 +110,13 @@ where
         cx: &mut task::Context<'_>,
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.stream).poll_write(cx, buf)
+        let result = futures::ready!(Pin::new(&mut self.stream).poll_write(cx, buf));
+        if let Ok(size) = result {
+            if size > 0 {
+                self.inspect(buf);
+            }
+        }
+        Poll::Ready(self.report(result))
     }
 
     #[inline]
@@ -63,7 +124,8 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.stream).poll_flush(cx)
+        let result = futures::ready!(Pin::new(&mut self.stream).poll_flush(cx));
+        Poll::Ready(self.report(result))
     }
 
     #[inline]
@@ -71,6 +133,7 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<io::Result<()>> {
-        Pin::new(&mut self.stream).poll_close(cx)
+        let result = futures::ready!(Pin::new(&mut self.stream).poll_close(cx));
+        Poll::Ready(self.report(result))
     }
 }
@@ -0,0 +1,22 @@
+use super::AddrStream;
+use crate::Accept;
+use futures::io::{AsyncRead, AsyncWrite};
+use std::io;
+use std::net::SocketAddr;
+
+/// A transport layer accepting connections for an app, like TCP or TLS over TCP.
+///
+/// The middleware stack is independent of it, so an alternative transport
+/// can be plugged by `App::serve_on` without changing middlewares or endpoints.
+///
+/// `roa::tcp` implements it for `std::net::TcpListener` and `roa::tcp::TcpIncoming`.
+pub trait Transport {
+    /// The stream of each connection.
+    type Io: 'static + Send + Sync + Unpin + AsyncRead + AsyncWrite;
+
+    /// The acceptor yielding connections.
+    type Incoming: Accept<Conn = AddrStream<Self::Io>>;
+
+    /// Start accepting connections, return the local addr and the acceptor.
+    fn incoming(self) -> io::Result<(SocketAddr, Self::Incoming)>;
+}
@@ -1,7 +1,9 @@
 use bytes::{Buf, Bytes, BytesMut};
+use futures::channel::mpsc;
 use futures::future::ok;
 use futures::io::{self, AsyncRead};
-use futures::stream::{once, Stream, StreamExt};
+use futures::stream::{once, Stream, StreamExt, TryStreamExt};
+use futures::SinkExt;
 use std::mem;
 use std::pin::Pin;
 use std::task::{Context, Poll};
@@ -49,7 +51,46 @@ pub struct Segment(
     Option<Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Sync + Send + 'static>>>,
 );
 
+/// A sender to push chunks to a body constructed by `Body::channel`.
+///
+/// Chunks are sent to client as soon as the connection takes them,
+/// the sender waits if the previous chunk has not been taken (back-pressure).
+pub struct BodySender(mpsc::Sender<io::Result<Bytes>>);
+
 impl Body {
+    /// Construct a stream body with a sender, to push chunks after the endpoint returns.
+    ///
+    /// It's useful for server-sent events and long-polling,
+    /// the body ends when the sender is dropped.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Body, Context, Result};
+    /// use std::io;
+    ///
+    /// async fn events(ctx: &mut Context) -> Result {
+    ///     let (mut sender, body) = Body::channel();
+    ///     ctx.resp.body = body;
+    ///     ctx.exec.spawn(async move {
+    ///         for i in 0..3 {
+    ///             sender.send(format!("data: {}\n\n", i)).await?;
+    ///             // wait until this event is taken by the connection.
+    ///             sender.flush().await?;
+    ///         }
+    ///         Ok::<_, io::Error>(())
+    ///     });
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(events);
+    /// ```
+    #[inline]
+    pub fn channel() -> (BodySender, Self) {
+        let (sender, receiver) = mpsc::channel(0);
+        (BodySender(sender), Self::stream(receiver))
+    }
+
     /// Construct an empty body.
     #[inline]
     pub fn empty() -> Self {
@@ -93,6 +134,9 @@ impl Body {
     }
 
     /// Write reader with default chunk size.
+    ///
+    /// The reader is adapted into the body stream without buffering,
+    /// it will not be read until the body is polled.
     #[inline]
     pub fn write_reader(
         &mut self,
@@ -124,6 +168,37 @@ impl Body {
     }
 }
 
+impl BodySender {
+    /// Send a chunk, wait if the previous chunk has not been taken.
+    ///
+    /// An error of `BrokenPipe` kind is returned if the body is dropped,
+    /// usually because the connection is closed.
+    #[inline]
+    pub async fn send(&mut self, data: impl Into<Bytes>) -> io::Result<()> {
+        self.0.send(Ok(data.into())).await.map_err(broken_pipe)
+    }
+
+    /// Wait until all chunks sent are taken by the connection.
+    #[inline]
+    pub async fn flush(&mut self) -> io::Result<()> {
+        self.0.flush().await.map_err(broken_pipe)
+    }
+
+    /// Abort the body with an error, the connection will be closed.
+    #[inline]
+    pub async fn abort(mut self, err: io::Error) {
+        if self.0.send(Err(err)).await.is_err() {
+            // body is dropped, do nothing.
+        }
+    }
+}
+
+/// Convert a send error to io error.
+#[inline]
+fn broken_pipe(err: mpsc::SendError) -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, err)
+}
+
 impl Segment {
     #[inline]
     fn new(
@@ -144,6 +219,14 @@ impl From<Body> for hyper::Body {
     }
 }
 
+impl From<hyper::Body> for Body {
+    /// Wrap a raw hyper body, errors are converted to io errors.
+    #[inline]
+    fn from(body: hyper::Body) -> Self {
+        Body::stream(body.map_err(|err| io::Error::new(io::ErrorKind::Other, err)))
+    }
+}
+
 impl Default for Body {
     #[inline]
     fn default() -> Self {
@@ -223,8 +306,12 @@ impl Stream for Segment {
 mod tests {
     use super::Body;
     use async_std::fs::File;
-    use futures::{AsyncReadExt, TryStreamExt};
+    use futures::{AsyncRead, AsyncReadExt, StreamExt, TryStreamExt};
     use std::io;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
 
     async fn read_body(body: Body) -> io::Result<String> {
         let mut data = String::new();
@@ -266,4 +353,48 @@ mod tests {
         assert_eq!("Hello, HexileeHexilee.", read_body(body).await?);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn body_reader_lazy() -> std::io::Result<()> {
+        struct Counted<R>(R, Arc<AtomicUsize>);
+        impl<R: AsyncRead + Unpin> AsyncRead for Counted<R> {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                Pin::new(&mut self.0).poll_read(cx, buf)
+            }
+        }
+        let polls = Arc::new(AtomicUsize::new(0));
+        let mut body = Body::empty();
+        body.write_reader(Counted(&b"Hello, World"[..], polls.clone()));
+        assert_eq!(0, polls.load(Ordering::SeqCst));
+        assert_eq!("Hello, World", read_body(body).await?);
+        assert!(polls.load(Ordering::SeqCst) > 0);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_channel() -> std::io::Result<()> {
+        let (mut sender, mut body) = Body::channel();
+        let task = async_std::task::spawn(async move {
+            sender.send("Hello, ").await?;
+            sender.flush().await?;
+            sender.send("World").await?;
+            Ok::<_, io::Error>(())
+        });
+        assert_eq!(&b"Hello, "[..], &*body.next().await.unwrap()?);
+        assert_eq!(&b"World"[..], &*body.next().await.unwrap()?);
+        assert!(body.next().await.is_none());
+        task.await?;
+
+        // body is dropped.
+        let (mut sender, body) = Body::channel();
+        drop(body);
+        let err = sender.send("Hello, World").await.unwrap_err();
+        assert_eq!(io::ErrorKind::BrokenPipe, err.kind());
+        Ok(())
+    }
 }
@@ -1,14 +1,19 @@
 mod storage;
 
 use crate::{status, Executor, Request, Response};
-use http::header::AsHeaderName;
-use http::StatusCode;
+use futures::channel::oneshot::Receiver;
+use futures::future::{FutureExt, Shared};
+use http::header::{AsHeaderName, ValueIter};
+use http::{HeaderValue, StatusCode};
 use http::{Method, Uri, Version};
 use std::any::Any;
 use std::borrow::Cow;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{self, Poll};
 
 pub use storage::Variable;
 use storage::{Storage, Value};
@@ -46,18 +51,42 @@ pub struct Context<S = ()> {
     /// Socket addr of last client or proxy.
     pub remote_addr: SocketAddr,
 
+    pub(crate) secure: bool,
     storage: Storage,
     state: S,
+    cancelled: Cancelled,
+}
+
+/// A future resolving when the request is cancelled,
+/// which means it's dropped before a response is produced, usually because the client disconnects.
+///
+/// It never resolves if the response is produced.
+#[derive(Clone)]
+pub struct Cancelled(Shared<Receiver<()>>);
+
+impl Future for Cancelled {
+    type Output = ();
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<()> {
+        match futures::ready!(self.0.poll_unpin(cx)) {
+            // the response is produced.
+            Ok(()) => Poll::Pending,
+            Err(_) => Poll::Ready(()),
+        }
+    }
 }
 
 impl<S> Context<S> {
     /// Construct a context from a request, an app and a addr_stream.
+    ///
+    /// The request is cancelled if the sender of `cancelled` is dropped without sending.
     #[inline]
     pub(crate) fn new(
         request: Request,
         state: S,
         exec: Executor,
         remote_addr: SocketAddr,
+        cancelled: Receiver<()>,
     ) -> Self {
         Self {
             req: request,
@@ -66,9 +95,102 @@ impl<S> Context<S> {
             exec,
             storage: Storage::default(),
             remote_addr,
+            secure: false,
+            cancelled: Cancelled(cancelled.shared()),
         }
     }
 
+    /// Get a future resolving when the request is cancelled, usually because the client disconnects.
+    ///
+    /// Middlewares and endpoints are dropped when the request is cancelled,
+    /// so it's useful for tasks spawned by them to stop work.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use futures::future::{select, Either};
+    /// use futures::FutureExt;
+    ///
+    /// async fn heavy_computation() -> u64 {
+    ///     42
+    /// }
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     let cancelled = ctx.cancelled();
+    ///     let result = ctx
+    ///         .exec
+    ///         .spawn(async move {
+    ///             match select(heavy_computation().boxed(), cancelled).await {
+    ///                 Either::Left((result, _)) => Some(result),
+    ///                 // abandoned.
+    ///                 Either::Right(_) => None,
+    ///             }
+    ///         })
+    ///         .await;
+    ///     ctx.resp.write(format!("{:?}", result));
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    #[inline]
+    pub fn cancelled(&self) -> Cancelled {
+        self.cancelled.clone()
+    }
+
+    /// Get a reference of state.
+    ///
+    /// `Context` also dereferences to state, so `ctx.state().field` is the same as `ctx.field`.
+    ///
+    /// The state is cloned from `App::state` for each request,
+    /// mutations by `state_mut` are visible to following middlewares and endpoint of this request only.
+    /// To share data between requests, like a database pool or a cache, wrap it in an `Arc`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Next, Result};
+    /// use std::collections::HashMap;
+    /// use std::sync::{Arc, RwLock};
+    ///
+    /// #[derive(Clone)]
+    /// struct State {
+    ///     // shared by all requests.
+    ///     cache: Arc<RwLock<HashMap<String, String>>>,
+    ///     // owned by each request.
+    ///     user: Option<String>,
+    /// }
+    ///
+    /// let state = State {
+    ///     cache: Arc::new(RwLock::new(HashMap::new())),
+    ///     user: None,
+    /// };
+    /// let app = App::state(state).gate(auth).end(end);
+    ///
+    /// async fn auth(ctx: &mut Context<State>, next: Next<'_>) -> Result {
+    ///     ctx.state_mut().user = Some("Hexilee".to_string());
+    ///     next.await
+    /// }
+    ///
+    /// async fn end(ctx: &mut Context<State>) -> Result {
+    ///     let state = ctx.state();
+    ///     let user = state.user.clone().unwrap();
+    ///     state.cache.write().unwrap().insert(user, "online".to_string());
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Get a mutable reference of state, which is owned by this request.
+    ///
+    /// See `state` for more details.
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+
     /// Clone URI.
     ///
     /// ### Example
@@ -106,6 +228,126 @@ impl<S> Context<S> {
         &self.req.method
     }
 
+    /// Check if the request is received over a TLS connection terminated by this app,
+    /// like a server constructed by `roa::tls::TlsListener`.
+    ///
+    /// It's always false behind a proxy terminating TLS.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     println!("tls: {}", ctx.is_tls());
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn is_tls(&self) -> bool {
+        self.secure
+    }
+
+    /// Get path of request::uri.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     assert_eq!("/", ctx.path());
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn path(&self) -> &str {
+        self.req.uri.path()
+    }
+
+    /// Search for a raw header value.
+    ///
+    /// Use `get` to get its string reference.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::header::CONTENT_TYPE;
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     assert_eq!(
+    ///         Some(&b"text/plain"[..]),
+    ///         ctx.header(CONTENT_TYPE).map(|value| value.as_bytes()),
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn header(&self, name: impl AsHeaderName) -> Option<&HeaderValue> {
+        self.req.headers.get(name)
+    }
+
+    /// Get all values of a repeated header, like `Accept`, `Cookie` or `Via`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::header::VIA;
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     for via in ctx.header_all(VIA) {
+    ///         println!("via: {:?}", via);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn header_all(&self, name: impl AsHeaderName) -> ValueIter<'_, HeaderValue> {
+        self.req.headers.get_all(name).iter()
+    }
+
+    /// Get all values of a repeated header and join them with ", ".
+    ///
+    /// Values which are not valid strings are skipped,
+    /// return `None` if there is no valid value.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::header::{HeaderValue, VIA};
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     // a request with two "Via" lines.
+    ///     ctx.req.headers.append(VIA, HeaderValue::from_static("1.0 fred"));
+    ///     ctx.req.headers.append(VIA, HeaderValue::from_static("1.1 p.example.net"));
+    ///     assert_eq!(
+    ///         Some("1.0 fred, 1.1 p.example.net".to_string()),
+    ///         ctx.get_joined(VIA),
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_joined(&self, name: impl AsHeaderName) -> Option<String> {
+        let values: Vec<&str> = self
+            .header_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.join(", "))
+        }
+    }
+
     /// Search for a header value and try to get its string reference.
     ///
     /// ### Example
@@ -161,6 +403,60 @@ impl<S> Context<S> {
             .to_str()
             .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
     }
+
+    /// Split "Authorization" into scheme and credentials.
+    ///
+    /// Return None if the header is missing, not visible ASCII, or either part is empty.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     if let Some((scheme, credentials)) = ctx.authorization() {
+    ///         println!("scheme: {}, credentials: {}", scheme, credentials);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn authorization(&self) -> Option<(&str, &str)> {
+        let value = self.get(http::header::AUTHORIZATION)?.trim();
+        let index = value.find(' ')?;
+        let (scheme, credentials) = (&value[..index], value[index..].trim());
+        if scheme.is_empty() || credentials.is_empty() {
+            None
+        } else {
+            Some((scheme, credentials))
+        }
+    }
+
+    /// Get credentials of "Authorization" if its scheme matches, case-insensitively.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{App, Context, Result, throw};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// let app = App::new().end(get);
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     match ctx.credentials("Bearer") {
+    ///         Some(token) => println!("token: {}", token),
+    ///         None => throw!(StatusCode::UNAUTHORIZED),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn credentials(&self, scheme: &str) -> Option<&str> {
+        self.authorization()
+            .filter(|(actual, _)| actual.eq_ignore_ascii_case(scheme))
+            .map(|(_, credentials)| credentials)
+    }
+
     /// Clone response::status.
     ///
     /// ### Example
@@ -348,16 +644,118 @@ impl<S: Clone> Clone for Context<S> {
             exec: self.exec.clone(),
             storage: self.storage.clone(),
             remote_addr: self.remote_addr,
+            secure: self.secure,
+            cancelled: self.cancelled.clone(),
         }
     }
 }
 
+#[cfg(feature = "fake")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "fake")))]
+impl Context<()> {
+    /// Construct a fake context from a request, to test middlewares and endpoints without an app.
+    ///
+    /// The context is spawned by default runtime with remote addr "127.0.0.1:0",
+    /// and it's never cancelled.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{Context, Next, Result};
+    /// use roa_core::http::{Request, StatusCode};
+    ///
+    /// async fn teapot(ctx: &mut Context, next: Next<'_>) -> Result {
+    ///     next.await?;
+    ///     ctx.resp.status = StatusCode::IM_A_TEAPOT;
+    ///     Ok(())
+    /// }
+    ///
+    /// # #[async_std::main]
+    /// # async fn main() -> Result {
+    /// let mut ctx = Context::fake(Request::get("/").body(Default::default()).unwrap());
+    /// ctx.run_middleware(&teapot).await?;
+    /// assert_eq!(StatusCode::IM_A_TEAPOT, ctx.resp.status);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn fake(request: http::Request<hyper::Body>) -> Self {
+        Self::fake_with_state(request, ())
+    }
+}
+
+#[cfg(feature = "fake")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "fake")))]
+impl<S> Context<S> {
+    /// Construct a fake context from a request and a state, see `Context::fake`.
+    #[inline]
+    pub fn fake_with_state(request: http::Request<hyper::Body>, state: S) -> Self {
+        use crate::app::runtime::Exec;
+        use crate::Executor;
+        use futures::channel::oneshot::channel;
+        let (finished, cancelled) = channel();
+        // a produced response, so it's never cancelled.
+        let _ = finished.send(());
+        Self::new(
+            request.into(),
+            state,
+            Executor(Arc::new(Exec)),
+            ([127, 0, 0, 1], 0).into(),
+            cancelled,
+        )
+    }
+
+    /// Run a middleware with a `next` which does nothing.
+    #[inline]
+    pub async fn run_middleware<M>(&mut self, middleware: &M) -> crate::Result
+    where
+        M: for<'a> crate::Middleware<'a, S>,
+    {
+        middleware
+            .handle(self, &mut futures::future::ok::<_, crate::Status>(()))
+            .await
+    }
+}
+
 #[cfg(all(test, feature = "runtime"))]
 mod tests_with_runtime {
-    use crate::{App, Context, Next, Request, Status};
+    use crate::{endpoint_fn, App, Cancelled, Context, Next, Request, Status};
     use http::{HeaderValue, StatusCode, Version};
     use std::error::Error;
 
+    #[async_std::test]
+    async fn cancelled() -> Result<(), Box<dyn Error>> {
+        use std::sync::{Arc, Mutex};
+        let slot: Arc<Mutex<Option<Cancelled>>> = Arc::new(Mutex::new(None));
+        let cancelled = slot.clone();
+        let service = App::new()
+            .end(endpoint_fn(move |ctx| {
+                let slot = slot.clone();
+                Box::pin(async move {
+                    *slot.lock().unwrap() = Some(ctx.cancelled());
+                    if ctx.uri().path() == "/pending" {
+                        futures::future::pending::<()>().await;
+                    }
+                    Ok(())
+                })
+            }))
+            .http_service();
+
+        // the response is produced.
+        service.clone().serve(Request::default()).await;
+        let mut done = cancelled.lock().unwrap().take().unwrap();
+        assert!(futures::poll!(&mut done).is_pending());
+
+        // the request is dropped.
+        let mut req = Request::default();
+        req.uri = "/pending".parse()?;
+        let mut serving = Box::pin(service.serve(req));
+        assert!(futures::poll!(&mut serving).is_pending());
+        drop(serving);
+        let dropped = cancelled.lock().unwrap().take().unwrap();
+        dropped.await;
+        Ok(())
+    }
+
     #[async_std::test]
     async fn status_and_version() -> Result<(), Box<dyn Error>> {
         async fn test(ctx: &mut Context) -> Result<(), Status> {
@@ -394,6 +792,43 @@ mod tests_with_runtime {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn authorization() -> Result<(), Box<dyn Error>> {
+        use http::header::AUTHORIZATION;
+        async fn test(ctx: &mut Context) -> Result<(), Status> {
+            match ctx.uri().path() {
+                "/bearer" => {
+                    assert_eq!(Some(("bearer", "token")), ctx.authorization());
+                    assert_eq!(Some("token"), ctx.credentials("Bearer"));
+                    assert_eq!(None, ctx.credentials("Basic"));
+                }
+                _ => {
+                    assert_eq!(None, ctx.authorization());
+                    assert_eq!(None, ctx.credentials("Bearer"));
+                }
+            }
+            Ok(())
+        }
+        let service = App::new().end(test).http_service();
+        for (path, value) in &[
+            ("/bearer", Some("bearer   token ")),
+            ("/", None),
+            ("/", Some("Bearer")),
+            ("/", Some("Bearer ")),
+            ("/", Some(" token")),
+        ] {
+            let mut req = Request::default();
+            req.uri = path.parse()?;
+            if let Some(value) = value {
+                req.headers
+                    .insert(AUTHORIZATION, HeaderValue::from_str(value)?);
+            }
+            let resp = service.clone().serve(req).await;
+            assert_eq!(StatusCode::OK, resp.status);
+        }
+        Ok(())
+    }
+
     #[async_std::test]
     async fn must_get() -> Result<(), Box<dyn Error>> {
         use http::header::{CONTENT_TYPE, HOST};
@@ -410,4 +845,126 @@ mod tests_with_runtime {
         assert_eq!(StatusCode::BAD_REQUEST, resp.status);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn shorthands() -> Result<(), Box<dyn Error>> {
+        use http::header::HOST;
+        use http::Method;
+        async fn test(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!(Method::POST, ctx.method());
+            assert_eq!("/path", ctx.path());
+            assert_eq!("/path?name=roa", ctx.uri().to_string());
+            assert_eq!(
+                Some(&HeaderValue::from_static("github.com")),
+                ctx.header(HOST)
+            );
+            Ok(())
+        }
+        let service = App::new().end(test).http_service();
+        let mut req = Request::default();
+        req.method = Method::POST;
+        req.uri = "/path?name=roa".parse()?;
+        req.headers
+            .insert(HOST, HeaderValue::from_static("github.com"));
+        let resp = service.serve(req).await;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn repeated_header() -> Result<(), Box<dyn Error>> {
+        use http::header::{ACCEPT, VIA};
+        async fn test(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!(2, ctx.header_all(ACCEPT).count());
+            assert_eq!(Some("text/html"), ctx.get(ACCEPT));
+            assert_eq!(
+                Some("text/html, application/json".to_string()),
+                ctx.get_joined(ACCEPT)
+            );
+            assert_eq!(0, ctx.header_all(VIA).count());
+            assert_eq!(None, ctx.get_joined(VIA));
+            Ok(())
+        }
+        let service = App::new().end(test).http_service();
+        let mut req = Request::default();
+        req.headers
+            .append(ACCEPT, HeaderValue::from_static("text/html"));
+        req.headers
+            .append(ACCEPT, HeaderValue::from_static("application/json"));
+        let resp = service.serve(req).await;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn state() -> Result<(), Box<dyn Error>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        #[derive(Clone, Default)]
+        struct State {
+            counter: Arc<AtomicUsize>,
+            id: usize,
+        }
+
+        async fn gate(ctx: &mut Context<State>, next: Next<'_>) -> Result<(), Status> {
+            ctx.state().counter.fetch_add(1, Ordering::SeqCst);
+            ctx.state_mut().id = 1;
+            next.await
+        }
+
+        async fn end(ctx: &mut Context<State>) -> Result<(), Status> {
+            assert_eq!(1, ctx.state().id);
+            assert_eq!(1, ctx.id);
+            Ok(())
+        }
+
+        let state = State::default();
+        let counter = state.counter.clone();
+        let service = App::state(state).gate(gate).end(end).http_service();
+        for _ in 0..2 {
+            // id is per-request, counter is shared.
+            let resp = service.clone().serve(Request::default()).await;
+            assert_eq!(StatusCode::OK, resp.status);
+        }
+        assert_eq!(2, counter.load(Ordering::SeqCst));
+        assert_eq!(0, service.state.id);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "fake"))]
+mod tests_with_fake {
+    use crate::{Context, Next, Result, Status};
+    use futures::future::ok;
+    use http::header::SERVER;
+    use http::{Request, StatusCode};
+
+    async fn server(ctx: &mut Context, next: Next<'_>) -> Result {
+        next.await?;
+        ctx.resp.headers.insert(SERVER, "roa".parse()?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn fake() -> Result {
+        let req = Request::post("/user?name=roa")
+            .header("x-id", "1")
+            .body(Default::default())?;
+        let mut ctx = Context::fake(req);
+        assert_eq!("/user", ctx.uri().path());
+        assert_eq!(Some("1"), ctx.get("x-id"));
+        assert!(futures::poll!(ctx.cancelled()).is_pending());
+        ctx.run_middleware(&server).await?;
+        assert_eq!(StatusCode::OK, ctx.resp.status);
+        assert_eq!("roa", ctx.resp.headers[SERVER]);
+
+        let ctx = Context::fake_with_state(Default::default(), 1usize);
+        assert_eq!(1, *ctx);
+
+        let mut ctx = Context::fake(Default::default());
+        server(&mut ctx, &mut ok::<_, Status>(())).await?;
+        assert_eq!("roa", ctx.resp.headers[SERVER]);
+        Ok(())
+    }
 }
@@ -81,6 +81,26 @@ macro_rules! throw {
 }
 
 /// The `Status` of roa.
+///
+/// It's the error type of `Result`, so errors thrown by downstream
+/// can be classified by `kind`, `is_client_error` and `is_server_error`.
+///
+/// ### Example
+/// ```rust
+/// use roa_core::{Context, Next, Result};
+///
+/// async fn logger(ctx: &mut Context, next: Next<'_>) -> Result {
+///     let result = next.await;
+///     if let Err(ref status) = result {
+///         if status.is_server_error() {
+///             log::error!("{}", status);
+///         } else if status.is_client_error() {
+///             log::info!("{}", status);
+///         }
+///     }
+///     result
+/// }
+/// ```
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Status {
     /// StatusCode will be responded to client if Error is thrown by the top middleware.
@@ -124,6 +144,43 @@ pub struct Status {
     pub expose: bool,
 }
 
+/// Classes of status code, defined in RFC 7231.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StatusKind {
+    /// 1xx.
+    Informational,
+
+    /// 2xx.
+    Success,
+
+    /// 3xx.
+    Redirection,
+
+    /// 4xx.
+    ClientError,
+
+    /// 5xx.
+    ServerError,
+
+    /// Status code out of 100-599.
+    Unknown,
+}
+
+impl StatusKind {
+    /// Classify a status code.
+    #[inline]
+    pub fn of(status_code: StatusCode) -> Self {
+        match status_code.as_u16() / 100 {
+            1 => StatusKind::Informational,
+            2 => StatusKind::Success,
+            3 => StatusKind::Redirection,
+            4 => StatusKind::ClientError,
+            5 => StatusKind::ServerError,
+            _ => StatusKind::Unknown,
+        }
+    }
+}
+
 impl Status {
     /// Construct an error.
     #[inline]
@@ -134,6 +191,39 @@ impl Status {
             expose,
         }
     }
+
+    /// Get class of status code.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa_core::{status, StatusKind};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// let status = status!(StatusCode::NOT_FOUND);
+    /// assert_eq!(StatusKind::ClientError, status.kind());
+    /// assert!(status.is_client_error());
+    /// assert!(!status.is_server_error());
+    ///
+    /// let status = status!(StatusCode::BAD_GATEWAY);
+    /// assert_eq!(StatusKind::ServerError, status.kind());
+    /// assert!(status.is_server_error());
+    /// ```
+    #[inline]
+    pub fn kind(&self) -> StatusKind {
+        StatusKind::of(self.status_code)
+    }
+
+    /// Check if status code is 4xx.
+    #[inline]
+    pub fn is_client_error(&self) -> bool {
+        self.kind() == StatusKind::ClientError
+    }
+
+    /// Check if status code is 5xx.
+    #[inline]
+    pub fn is_server_error(&self) -> bool {
+        self.kind() == StatusKind::ServerError
+    }
 }
 
 impl<E> From<E> for Status
@@ -146,6 +236,51 @@ where
     }
 }
 
+/// An extension to map errors of a result to `Status` with a specific status code.
+///
+/// Every `std::error::Error` (like `std::io::Error`) can be converted to
+/// a 500 INTERNAL SERVER ERROR by `?` directly,
+/// this extension is for errors caused by clients, like `serde_json::Error` or `std::str::Utf8Error`.
+///
+/// The original message is kept internal,
+/// the root handler logs it at warn level rather than error, as it's a client error.
+///
+/// ### Example
+/// ```rust
+/// use roa_core::{Context, Result, ResultExt};
+/// use roa_core::http::StatusCode;
+///
+/// fn name(data: &[u8]) -> Result<&str> {
+///     std::str::from_utf8(data).bad_request()
+/// }
+///
+/// let err = name(&[0xff]).unwrap_err();
+/// assert_eq!(StatusCode::BAD_REQUEST, err.status_code);
+/// assert!(!err.expose);
+/// ```
+pub trait ResultExt<T> {
+    /// Map error to status with status code.
+    fn status(self, status_code: StatusCode) -> Result<T>;
+
+    /// Map error to a 400 BAD REQUEST.
+    fn bad_request(self) -> Result<T>;
+}
+
+impl<T, E> ResultExt<T> for StdResult<T, E>
+where
+    E: Display,
+{
+    #[inline]
+    fn status(self, status_code: StatusCode) -> Result<T> {
+        self.map_err(|err| Status::new(status_code, err, false))
+    }
+
+    #[inline]
+    fn bad_request(self) -> Result<T> {
+        self.status(StatusCode::BAD_REQUEST)
+    }
+}
+
 impl Display for Status {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> StdResult<(), std::fmt::Error> {
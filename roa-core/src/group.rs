@@ -1,4 +1,7 @@
 use crate::{async_trait, Context, Endpoint, Middleware, Next, Result};
+use futures::future::{select, Either};
+use http::Method;
+use std::mem;
 use std::sync::Arc;
 
 /// A set of method to chain middleware/endpoint to middleware
@@ -38,6 +41,37 @@ pub trait EndpointExt<S>: Sized + for<'a> Endpoint<'a, S> {
     {
         Boxed(Box::new(self))
     }
+
+    /// Race two endpoints, respond by whichever completes first and cancel the other.
+    ///
+    /// The other endpoint runs on a cloned context, which shares state and storage,
+    /// and has a copy of method, uri, version and headers of request, but request body.
+    /// It starts with a copy of response status and headers as well;
+    /// if it wins, only headers changed by it are merged into the response.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Context, EndpointExt, Result};
+    ///
+    /// async fn cache(ctx: &mut Context) -> Result {
+    ///     // lookup cache
+    ///     Ok(())
+    /// }
+    ///
+    /// async fn compute(ctx: &mut Context) -> Result {
+    ///     // compute response
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(cache.race(compute));
+    /// ```
+    fn race<E>(self, other: E) -> Race<Self, E>
+    where
+        E: for<'a> Endpoint<'a, S>,
+    {
+        Race(self, other)
+    }
 }
 
 impl<S, T> MiddlewareExt<S> for T where T: for<'a> Middleware<'a, S> {}
@@ -52,6 +86,9 @@ pub struct Shared<S>(Arc<dyn for<'a> Middleware<'a, S>>);
 /// Boxed endpoint.
 pub struct Boxed<S>(Box<dyn for<'a> Endpoint<'a, S>>);
 
+/// An endpoint racing two endpoints.
+pub struct Race<T, U>(T, U);
+
 #[async_trait(?Send)]
 impl<'a, S, T, U> Middleware<'a, S> for Chain<T, U>
 where
@@ -93,6 +130,11 @@ where
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
         self.0.call(ctx).await
     }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.0.methods()
+    }
 }
 
 #[async_trait(?Send)]
@@ -107,12 +149,64 @@ where
         let mut next = self.1.call(unsafe { &mut *ptr });
         self.0.handle(ctx, &mut next).await
     }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.1.methods()
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, T, U> Endpoint<'a, S> for Race<T, U>
+where
+    S: Clone,
+    T: for<'b> Endpoint<'b, S>,
+    U: for<'b> Endpoint<'b, S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let mut other = ctx.clone();
+        other.req.method = ctx.req.method.clone();
+        other.req.uri = ctx.req.uri.clone();
+        other.req.version = ctx.req.version;
+        other.req.headers = ctx.req.headers.clone();
+        other.resp.status = ctx.resp.status;
+        other.resp.headers = ctx.resp.headers.clone();
+        other.resp.req_version = ctx.resp.req_version;
+        let result = match select(self.0.call(ctx), self.1.call(&mut other)).await {
+            Either::Left((result, _)) => return result,
+            Either::Right((result, first)) => {
+                // cancel the first endpoint
+                drop(first);
+                result
+            }
+        };
+        // the winner starts from a copy of response, changes of the loser are dropped.
+        ctx.resp = mem::take(&mut other.resp);
+        result
+    }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        let mut methods = <T as Endpoint<'a, S>>::methods(&self.0)?;
+        for method in <U as Endpoint<'a, S>>::methods(&self.1)? {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        Some(methods)
+    }
 }
 
 #[cfg(all(test, feature = "runtime"))]
 mod tests {
-    use crate::{async_trait, App, Context, Middleware, Next, Request, Status};
+    use crate::{
+        async_trait, App, Body, Context, EndpointExt, Middleware, Next, Request,
+        Response, Status,
+    };
+    use futures::future::pending;
     use futures::lock::Mutex;
+    use http::header::HeaderValue;
     use http::StatusCode;
     use std::sync::Arc;
 
@@ -165,4 +259,66 @@ mod tests {
         }
         Ok(())
     }
+
+    #[async_std::test]
+    async fn race() -> Result<(), Box<dyn std::error::Error>> {
+        async fn never(_ctx: &mut Context) -> Result<(), Status> {
+            pending().await
+        }
+        async fn hello(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!("/path", ctx.uri().path());
+            ctx.resp.write("Hello, World");
+            Ok(())
+        }
+        fn request() -> Result<Request, Box<dyn std::error::Error>> {
+            let mut req = Request::default();
+            req.uri = "/path".parse()?;
+            Ok(req)
+        }
+        fn assert_hello(resp: Response) {
+            assert_eq!(StatusCode::OK, resp.status);
+            match resp.body {
+                Body::Once(bytes) => assert_eq!(&b"Hello, World"[..], &*bytes),
+                _ => panic!("body should be once"),
+            }
+        }
+        let service = App::new().end(never.race(hello)).http_service();
+        assert_hello(service.serve(request()?).await);
+        let service = App::new().end(hello.race(never)).http_service();
+        assert_hello(service.serve(request()?).await);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn race_headers() -> Result<(), Box<dyn std::error::Error>> {
+        async fn outer(ctx: &mut Context, next: Next<'_>) -> Result<(), Status> {
+            ctx.resp
+                .headers
+                .insert("x-outer", HeaderValue::from_static("outer"));
+            next.await
+        }
+        async fn loser(ctx: &mut Context) -> Result<(), Status> {
+            ctx.resp
+                .headers
+                .insert("x-loser", HeaderValue::from_static("loser"));
+            ctx.resp
+                .headers
+                .insert("x-outer", HeaderValue::from_static("loser"));
+            pending().await
+        }
+        async fn hello(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!("outer", ctx.resp.headers["x-outer"]);
+            ctx.resp
+                .headers
+                .insert("x-hello", HeaderValue::from_static("hello"));
+            Ok(())
+        }
+        let service = App::new().gate(outer).end(loser.race(hello)).http_service();
+        let resp = service.serve(Request::default()).await;
+        assert_eq!(StatusCode::OK, resp.status);
+        assert_eq!("hello", resp.headers["x-hello"]);
+        assert_eq!("outer", resp.headers["x-outer"]);
+        assert!(!resp.headers.contains_key("x-loser"));
+        Ok(())
+    }
 }
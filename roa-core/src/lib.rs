@@ -14,34 +14,36 @@ mod response;
 mod state;
 
 #[doc(inline)]
-pub use app::{AddrStream, App};
+pub use app::{AddrStream, App, Transport};
 
 #[doc(inline)]
 pub use executor::{Executor, JoinHandle, Spawn};
 
 #[doc(inline)]
-pub use context::{Context, Variable};
+pub use context::{Cancelled, Context, Variable};
 
 #[doc(inline)]
-pub use err::{Result, Status};
+pub use err::{Result, ResultExt, Status, StatusKind};
 
 #[doc(inline)]
-pub use middleware::{Endpoint, Middleware, Next};
+pub use middleware::{
+    endpoint_fn, middleware_fn, BoxFuture, Endpoint, Middleware, Next,
+};
 
 #[doc(inline)]
-pub use group::{Boxed, Chain, EndpointExt, MiddlewareExt, Shared};
+pub use group::{Boxed, Chain, EndpointExt, MiddlewareExt, Race, Shared};
 
 #[doc(inline)]
 pub use state::State;
 
 #[doc(inline)]
-pub use request::Request;
+pub use request::{PayloadTooLarge, Request};
 
 #[doc(inline)]
 pub use response::Response;
 
 #[doc(inline)]
-pub use body::Body;
+pub use body::{Body, BodySender};
 
 pub use http;
 
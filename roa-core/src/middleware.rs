@@ -1,7 +1,8 @@
 use crate::{async_trait, throw, Context, Result, Status};
 use http::header::LOCATION;
-use http::{StatusCode, Uri};
+use http::{Method, StatusCode, Uri};
 use std::future::Future;
+use std::pin::Pin;
 
 /// ### Middleware
 ///
@@ -164,6 +165,14 @@ where
 pub trait Endpoint<'a, S = ()>: 'static + Sync + Send {
     /// Call this endpoint.
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result;
+
+    /// Http methods this endpoint accepts, `None` means any method.
+    ///
+    /// It's only used for introspection, like listing routes of a router.
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        None
+    }
 }
 
 #[async_trait(?Send)]
@@ -311,9 +320,70 @@ impl<'a, S> Endpoint<'a, S> for Uri {
 ///
 pub type Next<'a> = &'a mut (dyn Unpin + Future<Output = Result>);
 
+/// A boxed future borrowing context, returned by closures passed to `endpoint_fn` or `middleware_fn`.
+pub type BoxFuture<'a, T = Result> = Pin<Box<dyn 'a + Future<Output = T>>>;
+
+/// Construct an endpoint by a closure, which can capture dependencies like `Arc`.
+///
+/// The returned future borrows context, the closure cannot express it without boxing,
+/// so it should return `Box::pin(async move { ... })`.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa_core::{endpoint_fn, App};
+/// use std::sync::Arc;
+///
+/// let greeting = Arc::new("Hello, World".to_string());
+/// let app = App::new().end(endpoint_fn(move |ctx| {
+///     let greeting = greeting.clone();
+///     Box::pin(async move {
+///         ctx.resp.write(greeting.to_string());
+///         Ok(())
+///     })
+/// }));
+/// ```
+#[inline]
+pub fn endpoint_fn<S, F>(f: F) -> F
+where
+    F: 'static + Send + Sync + for<'a> Fn(&'a mut Context<S>) -> BoxFuture<'a>,
+{
+    f
+}
+
+/// Construct a middleware by a closure, which can capture dependencies like `Arc`.
+///
+/// See `endpoint_fn` for more details.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa_core::{middleware_fn, App};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let counter = Arc::new(AtomicUsize::new(0));
+/// let app = App::new()
+///     .gate(middleware_fn(move |_ctx, next| {
+///         let counter = counter.clone();
+///         Box::pin(async move {
+///             counter.fetch_add(1, Ordering::SeqCst);
+///             next.await
+///         })
+///     }))
+///     .end(());
+/// ```
+#[inline]
+pub fn middleware_fn<S, F>(f: F) -> F
+where
+    F: 'static + Send + Sync + for<'a> Fn(&'a mut Context<S>, Next<'a>) -> BoxFuture<'a>,
+{
+    f
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{status, App, Request};
+    use crate::{endpoint_fn, middleware_fn, status, App, Request};
     use futures::{AsyncReadExt, TryStreamExt};
     use http::header::LOCATION;
     use http::{StatusCode, Uri};
@@ -366,4 +436,40 @@ mod tests {
         assert_eq!(StatusCode::PERMANENT_REDIRECT, resp.status);
         assert_eq!("/target", resp.headers[LOCATION].to_str().unwrap())
     }
+
+    #[async_std::test]
+    async fn closure_capturing_deps() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        let counter = Arc::new(AtomicUsize::new(0));
+        let greeting = Arc::new(HELLO.to_string());
+        let gate_counter = counter.clone();
+        let app = App::new()
+            .gate(middleware_fn(move |_ctx, next| {
+                let counter = gate_counter.clone();
+                Box::pin(async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    next.await
+                })
+            }))
+            .end(endpoint_fn(move |ctx| {
+                let greeting = greeting.clone();
+                Box::pin(async move {
+                    ctx.resp.write(greeting.to_string());
+                    Ok(())
+                })
+            }));
+        let service = app.http_service();
+        let mut data = String::new();
+        service
+            .serve(Request::default())
+            .await
+            .body
+            .into_async_read()
+            .read_to_string(&mut data)
+            .await
+            .unwrap();
+        assert_eq!(HELLO, data);
+        assert_eq!(1, counter.load(Ordering::SeqCst));
+    }
 }
@@ -1,8 +1,11 @@
 use bytes::Bytes;
+use futures::future;
 use futures::stream::TryStreamExt;
 use futures::{AsyncRead, Stream};
 use http::{HeaderMap, HeaderValue, Method, Uri, Version};
 use hyper::Body;
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
 use std::io;
 
 /// Http request type of roa.
@@ -20,30 +23,195 @@ pub struct Request {
     pub headers: HeaderMap<HeaderValue>,
 
     body: Body,
+
+    body_limit: Option<u64>,
+
+    body_taken: bool,
+}
+
+/// An error yielded by request body streams when the body exceeds a limit.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct PayloadTooLarge {
+    /// The limit in bytes.
+    pub limit: u64,
+}
+
+impl Display for PayloadTooLarge {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "payload too large: body exceeds the limit of {} bytes",
+            self.limit
+        ))
+    }
 }
 
+impl StdError for PayloadTooLarge {}
+
 impl Request {
-    /// Get raw hyper body.
+    /// Get raw hyper body, which is not limited by `body_limit`.
     #[inline]
     pub fn raw_body(&mut self) -> Body {
+        self.body_taken = true;
         std::mem::take(&mut self.body)
     }
+
+    /// Move body out as an owned hyper body, an empty body is left in place.
+    ///
+    /// Unlike `raw_body`, the taken body respects `body_limit`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     let body: hyper::Body = ctx.req.take_body();
+    ///     // pass it to a library expecting an owned body.
+    ///     assert!(ctx.req.body_taken());
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn take_body(&mut self) -> Body {
+        Body::wrap_stream(self.stream())
+    }
+
+    /// Get the HTTP version of this request.
+    #[inline]
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Check if body has been taken by `raw_body`, `take_body`, `stream` or `reader`.
+    #[inline]
+    pub fn body_taken(&self) -> bool {
+        self.body_taken
+    }
+
+    /// Limit size of body read by `stream` and `reader`,
+    /// the smaller one wins if it's set more than once.
+    ///
+    /// Body readers of other crates (like roa-multipart) should respect it as well.
+    #[inline]
+    pub fn limit_body(&mut self, bytes: u64) {
+        self.body_limit = Some(match self.body_limit {
+            Some(limit) if limit < bytes => limit,
+            _ => bytes,
+        });
+    }
+
+    /// Get the active limit of body.
+    #[inline]
+    pub fn body_limit(&self) -> Option<u64> {
+        self.body_limit
+    }
+
+    /// Replace raw hyper body, return the old one.
+    #[inline]
+    pub fn replace_body(&mut self, body: Body) -> Body {
+        self.body_taken = false;
+        std::mem::replace(&mut self.body, body)
+    }
+
     /// Get body as Stream.
     /// This method will consume inner body.
+    ///
+    /// It yields a `PayloadTooLarge` error when body exceeds `body_limit`.
     #[inline]
     pub fn stream(
         &mut self,
     ) -> impl Stream<Item = io::Result<Bytes>> + Sync + Send + Unpin + 'static {
+        let limit = self.body_limit;
+        let mut read = 0u64;
         self.raw_body()
             .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+            .and_then(move |bytes| {
+                read += bytes.len() as u64;
+                future::ready(match limit {
+                    Some(limit) if read > limit => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        PayloadTooLarge { limit },
+                    )),
+                    _ => Ok(bytes),
+                })
+            })
     }
 
-    /// Get body as AsyncRead.
+    /// Get body as AsyncRead, to pipe it into readers of other libraries.
     /// This method will consume inner body.
+    ///
+    /// Like `stream`, it respects `body_limit`, an io error caused by `PayloadTooLarge`
+    /// will be returned when body exceeds the limit.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{Context, Result};
+    /// use futures::io::{copy, sink, BufReader};
+    /// use futures::AsyncBufReadExt;
+    ///
+    /// async fn lines(ctx: &mut Context) -> Result {
+    ///     ctx.req.limit_body(1024 * 1024);
+    ///     let mut count = 0;
+    ///     let mut reader = BufReader::new(ctx.req.reader());
+    ///     let mut line = String::new();
+    ///     while reader.read_line(&mut line).await? != 0 {
+    ///         count += 1;
+    ///         line.clear();
+    ///     }
+    ///     ctx.resp.write(count.to_string());
+    ///     Ok(())
+    /// }
+    ///
+    /// async fn discard(ctx: &mut Context) -> Result {
+    ///     let size = copy(ctx.req.reader(), &mut sink()).await?;
+    ///     ctx.resp.write(size.to_string());
+    ///     Ok(())
+    /// }
+    /// ```
     #[inline]
     pub fn reader(&mut self) -> impl AsyncRead + Sync + Send + Unpin + 'static {
         self.stream().into_async_read()
     }
+
+    /// Take this request as a raw `http::Request`, to interoperate with other hyper-based libraries.
+    /// The request in place will be replaced by a default one.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     let req: http::Request<hyper::Body> = ctx.req.take_raw();
+    ///     // pass it to another hyper service.
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn take_raw(&mut self) -> http::Request<Body> {
+        std::mem::take(self).into()
+    }
+
+    /// Consume this request, return raw parts and body.
+    #[inline]
+    pub fn into_parts(self) -> (http::request::Parts, Body) {
+        let (mut parts, _) = http::Request::new(()).into_parts();
+        let Request {
+            method,
+            uri,
+            version,
+            headers,
+            body,
+            ..
+        } = self;
+        parts.method = method;
+        parts.uri = uri;
+        parts.version = version;
+        parts.headers = headers;
+        (parts, body)
+    }
 }
 
 impl From<http::Request<Body>> for Request {
@@ -56,10 +224,20 @@ impl From<http::Request<Body>> for Request {
             version: parts.version,
             headers: parts.headers,
             body,
+            body_limit: None,
+            body_taken: false,
         }
     }
 }
 
+impl From<Request> for http::Request<Body> {
+    #[inline]
+    fn from(req: Request) -> Self {
+        let (parts, body) = req.into_parts();
+        http::Request::from_parts(parts, body)
+    }
+}
+
 impl Default for Request {
     #[inline]
     fn default() -> Self {
@@ -69,9 +247,9 @@ impl Default for Request {
 
 #[cfg(all(test, feature = "runtime"))]
 mod tests {
-    use crate::{App, Context, Request, Status};
+    use crate::{App, Context, Next, PayloadTooLarge, Request, Status};
     use futures::AsyncReadExt;
-    use http::StatusCode;
+    use http::{Method, StatusCode};
     use hyper::Body;
 
     async fn test(ctx: &mut Context) -> Result<(), Status> {
@@ -90,4 +268,65 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status);
         Ok(())
     }
+
+    #[async_std::test]
+    async fn take_raw() -> Result<(), Box<dyn std::error::Error>> {
+        let mut req = Request::from(
+            http::Request::post("/path")
+                .header("x-id", "0")
+                .body(Body::from("Hello, World!"))?,
+        );
+        let raw = req.take_raw();
+        assert_eq!(Method::GET, req.method);
+        assert_eq!(Method::POST, raw.method());
+        assert_eq!("/path", raw.uri().path());
+        assert_eq!("0", raw.headers()["x-id"]);
+        let body = hyper::body::to_bytes(raw.into_body()).await?;
+        assert_eq!(&b"Hello, World!"[..], &*body);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn take_body() -> Result<(), Box<dyn std::error::Error>> {
+        let mut req = Request::from(http::Request::new(Body::from("Hello, World!")));
+        assert!(!req.body_taken());
+        let body = req.take_body();
+        assert!(req.body_taken());
+        let data = hyper::body::to_bytes(body).await?;
+        assert_eq!(&b"Hello, World!"[..], &*data);
+        assert!(hyper::body::to_bytes(req.take_body()).await?.is_empty());
+        req.replace_body(Body::from("Hello"));
+        assert!(!req.body_taken());
+
+        req.limit_body(2);
+        assert!(hyper::body::to_bytes(req.take_body()).await.is_err());
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn body_limit() -> Result<(), Box<dyn std::error::Error>> {
+        async fn limit(ctx: &mut Context, next: Next<'_>) -> Result<(), Status> {
+            ctx.req.limit_body(8);
+            ctx.req.limit_body(16);
+            assert_eq!(Some(8), ctx.req.body_limit());
+            next.await
+        }
+        async fn read(ctx: &mut Context) -> Result<(), Status> {
+            let mut data = String::new();
+            let err = ctx
+                .req
+                .reader()
+                .read_to_string(&mut data)
+                .await
+                .unwrap_err();
+            let too_large = err.get_ref().unwrap().downcast_ref::<PayloadTooLarge>();
+            assert_eq!(Some(&PayloadTooLarge { limit: 8 }), too_large);
+            Ok(())
+        }
+        let service = App::new().gate(limit).end(read).http_service();
+        let req = Request::from(http::Request::new(Body::from("Hello, World!")));
+        let resp = service.serve(req).await;
+        assert_eq!(StatusCode::OK, resp.status);
+        Ok(())
+    }
 }
@@ -1,9 +1,20 @@
 //! A module for Response and its body
+use crate::{Executor, Status};
+use bytes::Bytes;
+use futures::StreamExt;
+use http::header::{
+    HeaderName, IntoHeaderName, CONNECTION, CONTENT_LENGTH, TRAILER, TRANSFER_ENCODING,
+};
 use http::{HeaderMap, HeaderValue, StatusCode, Version};
+use std::convert::TryInto;
+use std::fmt::Display;
 use std::ops::{Deref, DerefMut};
 
 pub use crate::Body;
 
+/// A callback to produce trailers after body is sent.
+type Trailers = Box<dyn 'static + FnOnce() -> HeaderMap + Sync + Send>;
+
 /// Http response type of roa.
 pub struct Response {
     /// Status code.
@@ -17,6 +28,11 @@ pub struct Response {
 
     /// Response body.
     pub body: Body,
+
+    trailers: Option<Trailers>,
+
+    /// Version of the request, features invalid for it are dropped.
+    pub(crate) req_version: Version,
 }
 
 impl Response {
@@ -27,23 +43,343 @@ impl Response {
             version: Version::default(),
             headers: HeaderMap::default(),
             body: Body::default(),
+            trailers: None,
+            req_version: Version::default(),
         }
     }
 
+    /// Reset a partially-built response, to respond from scratch.
+    ///
+    /// Status is reset to 200 OK, headers, body and trailers are dropped.
+    /// Version is kept.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Context, Next, Result};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// async fn catch(ctx: &mut Context, next: Next<'_>) -> Result {
+    ///     if let Err(status) = next.await {
+    ///         // discard what the failed handler wrote.
+    ///         ctx.resp.clear();
+    ///         ctx.resp.status = status.status_code;
+    ///         ctx.resp.write("something went wrong");
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().gate(catch).end(());
+    /// ```
     #[inline]
-    fn into_resp(self) -> http::Response<hyper::Body> {
+    pub fn clear(&mut self) -> &mut Self {
+        self.status = StatusCode::default();
+        self.headers.clear();
+        self.body = Body::default();
+        self.trailers = None;
+        self
+    }
+
+    /// Insert a header, replace the old values of the same name.
+    ///
+    /// An invalid value makes a 500 INTERNAL SERVER ERROR.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::header::CACHE_CONTROL;
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     ctx.resp.set_header(CACHE_CONTROL, "no-cache")?;
+    ///     ctx.resp.set_header("x-request-id", format!("{}", 1024))?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    #[inline]
+    pub fn set_header<N, V>(&mut self, name: N, value: V) -> crate::Result
+    where
+        N: IntoHeaderName,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        self.headers.insert(name, to_header_value(value)?);
+        Ok(())
+    }
+
+    /// Append a header, keep the old values of the same name.
+    ///
+    /// An invalid value makes a 500 INTERNAL SERVER ERROR.
+    #[inline]
+    pub fn append_header<N, V>(&mut self, name: N, value: V) -> crate::Result
+    where
+        N: IntoHeaderName,
+        V: TryInto<HeaderValue>,
+        V::Error: Display,
+    {
+        self.headers.append(name, to_header_value(value)?);
+        Ok(())
+    }
+
+    /// Close the connection after this response is sent, by "Connection: close".
+    ///
+    /// It's useful after a protocol error, or when the connection cannot be reused safely.
+    /// It takes no effect on HTTP/2, connection-specific headers are dropped.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::StatusCode;
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     ctx.resp.status = StatusCode::BAD_REQUEST;
+    ///     ctx.resp.close_connection();
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    #[inline]
+    pub fn close_connection(&mut self) -> &mut Self {
+        self.headers
+            .insert(CONNECTION, HeaderValue::from_static("close"));
+        self
+    }
+
+    /// Ask to keep the connection alive by "Connection: keep-alive",
+    /// overriding a previous `close_connection`.
+    ///
+    /// Whether the connection is reused still depends on the client and the protocol,
+    /// HTTP/1.0 clients must ask for keep-alive as well.
+    #[inline]
+    pub fn keep_alive(&mut self) -> &mut Self {
+        self.headers
+            .insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        self
+    }
+
+    /// Check if the connection will be closed after this response, by "Connection: close".
+    #[inline]
+    pub fn closes_connection(&self) -> bool {
+        self.headers
+            .get_all(CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|option| option.trim().eq_ignore_ascii_case("close"))
+    }
+
+    /// Construct a response from a raw `http::Response`,
+    /// to interoperate with other hyper-based libraries.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{Context, Response, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     // get response from another hyper service.
+    ///     let resp = http::Response::new(hyper::Body::from("Hello, World"));
+    ///     ctx.resp = Response::from_hyper(resp);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline]
+    pub fn from_hyper(resp: http::Response<hyper::Body>) -> Self {
+        let (parts, body) = resp.into_parts();
+        Self {
+            status: parts.status,
+            version: parts.version,
+            headers: parts.headers,
+            body: body.into(),
+            trailers: None,
+            req_version: Version::default(),
+        }
+    }
+
+    /// Set trailers, which will be sent after body completes.
+    ///
+    /// Names of trailers will be advertised by header "Trailer",
+    /// and the callback will be called once body is sent.
+    ///
+    /// Trailers are only supported over HTTP/2, as HTTP/1.1 responses are framed by hyper,
+    /// which cannot write a trailer section into chunked body.
+    /// Responding to an older request, trailers are neither advertised nor sent,
+    /// and the callback is never called.
+    ///
+    /// Return `false` if trailers will be dropped, fall back to headers in this case.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa_core::{App, Context, Result};
+    /// use roa_core::http::header::{HeaderMap, HeaderName};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     ctx.resp.write("Hello, World");
+    ///     let checksum = HeaderName::from_static("x-checksum");
+    ///     let name = checksum.clone();
+    ///     let supported = ctx.resp.set_trailers(vec![checksum.clone()], move || {
+    ///         let mut trailers = HeaderMap::new();
+    ///         trailers.insert(name, "deadbeef".parse().unwrap());
+    ///         trailers
+    ///     });
+    ///     if !supported {
+    ///         ctx.resp.headers.insert(checksum, "deadbeef".parse().unwrap());
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    #[inline]
+    pub fn set_trailers<F>(
+        &mut self,
+        names: impl IntoIterator<Item = HeaderName>,
+        trailers: F,
+    ) -> bool
+    where
+        F: 'static + FnOnce() -> HeaderMap + Sync + Send,
+    {
+        let names = names
+            .into_iter()
+            .map(|name| name.as_str().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if let Ok(value) = HeaderValue::from_str(&names) {
+            self.headers.insert(TRAILER, value);
+        }
+        self.trailers = Some(Box::new(trailers));
+        let supported = self.trailers_supported();
+        if !supported {
+            log::debug!("trailers are dropped over {:?}", self.req_version);
+        }
+        supported
+    }
+
+    /// Check if trailers can be sent in response to the current request, only HTTP/2 supports it.
+    #[inline]
+    pub fn trailers_supported(&self) -> bool {
+        self.req_version >= Version::HTTP_2
+    }
+
+    /// Replace body with bytes, return the old one.
+    ///
+    /// "Content-Length" is removed as it may be stale.
+    #[inline]
+    pub fn set_body(&mut self, body: impl Into<Bytes>) -> Body {
+        self.headers.remove(CONTENT_LENGTH);
+        std::mem::replace(&mut self.body, Body::once(body))
+    }
+
+    /// Check if body is empty, a streaming body is treated as non-empty.
+    #[inline]
+    pub fn body_is_empty(&self) -> bool {
+        match &self.body {
+            Body::Empty => true,
+            Body::Once(bytes) => bytes.is_empty(),
+            Body::Stream(_) => false,
+        }
+    }
+
+    /// Split into parts, body is suppressed if status forbids it,
+    /// "Transfer-Encoding" is dropped if the request is older than HTTP/1.1,
+    /// and trailers are dropped if the request is older than HTTP/2.
+    #[inline]
+    fn into_parts(self) -> (http::response::Parts, Body, Option<Trailers>) {
         let (mut parts, _) = http::Response::new(()).into_parts();
         let Response {
             status,
             version,
-            headers,
-            body,
+            mut headers,
+            mut body,
+            mut trailers,
+            req_version,
         } = self;
+        if req_version < Version::HTTP_11 {
+            headers.remove(TRANSFER_ENCODING);
+        }
+        if req_version < Version::HTTP_2 {
+            trailers = None;
+            headers.remove(TRAILER);
+        }
+        if forbids_body(status) {
+            body = Body::empty();
+            trailers = None;
+            headers.remove(CONTENT_LENGTH);
+            headers.remove(TRANSFER_ENCODING);
+            headers.remove(TRAILER);
+        }
         parts.status = status;
         parts.version = version;
         parts.headers = headers;
+        (parts, body, trailers)
+    }
+
+    #[inline]
+    fn into_resp(self) -> http::Response<hyper::Body> {
+        let (parts, body, _) = self.into_parts();
         http::Response::from_parts(parts, body.into())
     }
+
+    /// Convert into hyper response, spawn a task to send body and trailers if necessary.
+    #[inline]
+    pub(crate) fn into_resp_with(self, exec: &Executor) -> http::Response<hyper::Body> {
+        let (parts, mut body, trailers) = self.into_parts();
+        let trailers = match trailers {
+            None => return http::Response::from_parts(parts, body.into()),
+            Some(trailers) => trailers,
+        };
+        let (mut sender, hyper_body) = hyper::Body::channel();
+        exec.spawn(async move {
+            while let Some(item) = body.next().await {
+                match item {
+                    Ok(data) => {
+                        if sender.send_data(data).await.is_err() {
+                            // connection is closed.
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        log::error!("body error: {}", err);
+                        sender.abort();
+                        return;
+                    }
+                }
+            }
+            if let Err(err) = sender.send_trailers(trailers()).await {
+                log::debug!("fail to send trailers: {}", err);
+            }
+        });
+        http::Response::from_parts(parts, hyper_body)
+    }
+}
+
+/// Check if a status forbids body, like 1xx, 204 NO CONTENT and 304 NOT MODIFIED.
+#[inline]
+fn forbids_body(status: StatusCode) -> bool {
+    status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED
+}
+
+/// Convert a value to header value, map error to 500 INTERNAL SERVER ERROR.
+#[inline]
+fn to_header_value<V>(value: V) -> crate::Result<HeaderValue>
+where
+    V: TryInto<HeaderValue>,
+    V::Error: Display,
+{
+    value.try_into().map_err(|err| {
+        Status::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{}\nNot a valid header value", err),
+            false,
+        )
+    })
 }
 
 impl Deref for Response {
@@ -74,3 +410,154 @@ impl Default for Response {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Response;
+    use crate::Body;
+    use http::header::{
+        HeaderMap, HeaderName, HeaderValue, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH,
+        TRAILER, TRANSFER_ENCODING,
+    };
+    use http::{StatusCode, Version};
+
+    #[test]
+    fn set_and_append_header() -> Result<(), Box<dyn std::error::Error>> {
+        let mut resp = Response::new();
+        resp.set_header(CACHE_CONTROL, "no-cache")?;
+        resp.set_header(CACHE_CONTROL, "no-store".to_string())?;
+        resp.append_header(CACHE_CONTROL, HeaderValue::from_static("private"))?;
+        let values: Vec<_> = resp
+            .headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .map(HeaderValue::to_str)
+            .collect::<Result<_, _>>()?;
+        assert_eq!(vec!["no-store", "private"], values);
+
+        let status = resp.set_header("x-invalid", "line\nbreak").unwrap_err();
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, status.status_code);
+        assert!(!status.expose);
+        Ok(())
+    }
+
+    #[test]
+    fn body_is_empty() {
+        let mut resp = Response::new();
+        assert!(resp.body_is_empty());
+        resp.write("");
+        assert!(resp.body_is_empty());
+        resp.write("Hello");
+        assert!(!resp.body_is_empty());
+    }
+
+    #[async_std::test]
+    async fn suppress_body() -> Result<(), Box<dyn std::error::Error>> {
+        for status in vec![
+            StatusCode::CONTINUE,
+            StatusCode::NO_CONTENT,
+            StatusCode::NOT_MODIFIED,
+        ] {
+            let mut resp = Response::new();
+            resp.status = status;
+            resp.headers.insert(CONTENT_LENGTH, HeaderValue::from(5));
+            resp.write("Hello");
+            let resp: http::Response<hyper::Body> = resp.into();
+            assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+            assert!(hyper::body::to_bytes(resp.into_body()).await?.is_empty());
+        }
+        let mut resp = Response::new();
+        resp.write("Hello");
+        let resp: http::Response<hyper::Body> = resp.into();
+        assert_eq!(
+            &b"Hello"[..],
+            &*hyper::body::to_bytes(resp.into_body()).await?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn connection() {
+        let mut resp = Response::new();
+        assert!(!resp.closes_connection());
+        resp.close_connection();
+        assert_eq!("close", resp.headers[CONNECTION]);
+        assert!(resp.closes_connection());
+        resp.keep_alive();
+        assert_eq!("keep-alive", resp.headers[CONNECTION]);
+        assert!(!resp.closes_connection());
+    }
+
+    #[test]
+    fn set_body() {
+        let mut resp = Response::new();
+        resp.write("Hello");
+        resp.headers
+            .insert(CONTENT_LENGTH, HeaderValue::from_static("5"));
+        match resp.set_body("Hello, World") {
+            Body::Once(bytes) => assert_eq!("Hello", bytes),
+            _ => panic!("body should be once"),
+        }
+        assert!(!resp.headers.contains_key(CONTENT_LENGTH));
+        match resp.body {
+            Body::Once(ref bytes) => assert_eq!("Hello, World", *bytes),
+            _ => panic!("body should be once"),
+        }
+    }
+
+    #[test]
+    fn http11() {
+        let mut resp = Response::new();
+        resp.req_version = Version::HTTP_11;
+        resp.headers
+            .insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        assert!(!resp
+            .set_trailers(vec![HeaderName::from_static("x-checksum")], HeaderMap::new));
+        let (parts, _, trailers) = resp.into_parts();
+        assert!(trailers.is_none());
+        assert!(parts.headers.contains_key(TRANSFER_ENCODING));
+        assert!(!parts.headers.contains_key(TRAILER));
+    }
+
+    #[test]
+    fn http2() {
+        let mut resp = Response::new();
+        resp.req_version = Version::HTTP_2;
+        assert!(resp
+            .set_trailers(vec![HeaderName::from_static("x-checksum")], HeaderMap::new));
+        let (parts, _, trailers) = resp.into_parts();
+        assert!(trailers.is_some());
+        assert_eq!("x-checksum", parts.headers[TRAILER]);
+    }
+
+    #[test]
+    fn http10() {
+        let mut resp = Response::new();
+        resp.req_version = Version::HTTP_10;
+        resp.headers
+            .insert(TRANSFER_ENCODING, HeaderValue::from_static("gzip, chunked"));
+        resp.set_trailers(vec![HeaderName::from_static("x-checksum")], HeaderMap::new);
+        let (parts, _, trailers) = resp.into_parts();
+        assert!(trailers.is_none());
+        assert!(!parts.headers.contains_key(TRANSFER_ENCODING));
+        assert!(!parts.headers.contains_key(TRAILER));
+    }
+
+    #[test]
+    fn clear() {
+        let mut resp = Response::new();
+        resp.status = StatusCode::BAD_REQUEST;
+        resp.headers
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        resp.write("partial");
+        resp.set_trailers(vec![HeaderName::from_static("x-checksum")], HeaderMap::new);
+        resp.clear();
+        assert_eq!(StatusCode::OK, resp.status);
+        assert!(resp.headers.is_empty());
+        assert!(resp.trailers.is_none());
+        match resp.body {
+            Body::Empty => (),
+            _ => panic!("body should be empty"),
+        }
+    }
+}
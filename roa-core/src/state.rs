@@ -2,6 +2,7 @@
 /// The `App::state` will be cloned when a request inbounds.
 ///
 /// `State` is designed to share data or handler between middlewares.
+/// It can be accessed by `Context::state`, `Context::state_mut` or dereference of `Context`.
 ///
 /// ### Example
 /// ```rust
@@ -0,0 +1,264 @@
+//! This crate provides route attributes of roa,
+//! refer to `roa::router` for usage.
+//!
+//! An attribute like `#[get("/user/:id")]` turns an async function into a unit struct
+//! implementing `roa::Endpoint` and `roa::router::Route`.
+//!
+//! - The first argument must be the context, like `ctx: &mut Context` or `ctx: &mut Context<S>`.
+//! - Other arguments are router variables, they are parsed by `FromStr` before the function is called,
+//! and a parse failure is thrown as 400 BAD REQUEST.
+//! - Names of other arguments must be variables of the path, or it fails to compile.
+//! Their types must be owned, like `String` rather than `&str`.
+//! - The function can be generic over the state, like `async fn get<S: State>(ctx: &mut Context<S>)`,
+//! other generic type parameters fail to compile.
+
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, quote_spanned};
+use std::collections::HashSet;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Error, FnArg, GenericArgument, GenericParam, Ident,
+    ItemFn, LitStr, Pat, PathArguments, Type,
+};
+
+macro_rules! impl_route_attributes {
+    ($($end:ident => $method:ident, $doc:literal),*) => {
+        $(
+            #[doc = $doc]
+            #[proc_macro_attribute]
+            pub fn $end(attr: TokenStream, item: TokenStream) -> TokenStream {
+                let path = parse_macro_input!(attr as LitStr);
+                let item = parse_macro_input!(item as ItemFn);
+                let method = Ident::new(stringify!($method), Span::call_site());
+                route(method, path, item)
+                    .unwrap_or_else(|err| err.to_compile_error())
+                    .into()
+            }
+        )*
+    };
+}
+
+impl_route_attributes!(
+    get => GET, "Route an async function on GET of a path.",
+    post => POST, "Route an async function on POST of a path.",
+    put => PUT, "Route an async function on PUT of a path.",
+    patch => PATCH, "Route an async function on PATCH of a path.",
+    options => OPTIONS, "Route an async function on OPTIONS of a path.",
+    delete => DELETE, "Route an async function on DELETE of a path.",
+    head => HEAD, "Route an async function on HEAD of a path.",
+    trace => TRACE, "Route an async function on TRACE of a path.",
+    connect => CONNECT, "Route an async function on CONNECT of a path."
+);
+
+/// Collect variables of a path template, like "/:id" or "/*{path}".
+fn variables(path: &LitStr) -> syn::Result<HashSet<String>> {
+    let value = path.value();
+    let mut vars = Vec::new();
+    for segment in value.split('/') {
+        if segment.starts_with(':') {
+            vars.push(segment[1..].to_string());
+        }
+    }
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find("*{") {
+        rest = &rest[start + 2..];
+        match rest.find('}') {
+            Some(end) => {
+                vars.push(rest[..end].to_string());
+                rest = &rest[end + 1..];
+            }
+            None => return Err(Error::new(path.span(), "unclosed wildcard `*{`")),
+        }
+    }
+    let mut set = HashSet::new();
+    for var in vars {
+        if var.is_empty() || !var.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(Error::new(
+                path.span(),
+                format!("invalid variable `{}` in path", var),
+            ));
+        }
+        if !set.insert(var.clone()) {
+            return Err(Error::new(
+                path.span(),
+                format!("variable `{}` occurs more than once in path", var),
+            ));
+        }
+    }
+    Ok(set)
+}
+
+/// Get state type of a context argument, like `&mut Context<S>`.
+fn state_type(arg: Option<&FnArg>, span: Span) -> syn::Result<TokenStream2> {
+    let error = |span| {
+        Error::new(
+            span,
+            "the first argument must be the context, like `ctx: &mut Context`",
+        )
+    };
+    let ty = match arg {
+        Some(FnArg::Typed(arg)) => &*arg.ty,
+        Some(arg) => return Err(error(arg.span())),
+        None => return Err(error(span)),
+    };
+    let path = match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => {
+            match &*reference.elem {
+                Type::Path(path) => &path.path,
+                _ => return Err(error(ty.span())),
+            }
+        }
+        _ => return Err(error(ty.span())),
+    };
+    let segment = match path.segments.last() {
+        Some(segment) if segment.ident == "Context" => segment,
+        _ => return Err(error(ty.span())),
+    };
+    match &segment.arguments {
+        PathArguments::None => Ok(quote!(())),
+        PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(GenericArgument::Type(state)) => Ok(quote!(#state)),
+            _ => Err(error(ty.span())),
+        },
+        PathArguments::Parenthesized(_) => Err(error(ty.span())),
+    }
+}
+
+/// Check if tokens mention an identifier.
+fn mentions(tokens: TokenStream2, ident: &Ident) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(other) => other == *ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        _ => false,
+    })
+}
+
+/// Generic type and const parameters must be used by the state,
+/// as the generated struct is not generic.
+fn check_generics(item: &ItemFn, state: &TokenStream2) -> syn::Result<()> {
+    for param in item.sig.generics.params.iter() {
+        let ident = match param {
+            GenericParam::Type(param) => &param.ident,
+            GenericParam::Const(param) => &param.ident,
+            GenericParam::Lifetime(_) => continue,
+        };
+        if !mentions(state.clone(), ident) {
+            return Err(Error::new(
+                ident.span(),
+                format!(
+                    "generic parameter `{}` must be the state, like `ctx: &mut Context<{}>`",
+                    ident, ident
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Expand a route attribute.
+fn route(method: Ident, path: LitStr, item: ItemFn) -> syn::Result<TokenStream2> {
+    if item.sig.asyncness.is_none() {
+        return Err(Error::new(
+            item.sig.fn_token.span(),
+            "route attributes only apply to async functions",
+        ));
+    }
+    let vars = variables(&path)?;
+    let state = state_type(item.sig.inputs.first(), item.sig.ident.span())?;
+    check_generics(&item, &state)?;
+
+    let mut names = Vec::new();
+    let mut parsers = Vec::new();
+    for arg in item.sig.inputs.iter().skip(1) {
+        let arg = match arg {
+            FnArg::Typed(arg) => arg,
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new(receiver.span(), "unexpected receiver"))
+            }
+        };
+        let name = match &*arg.pat {
+            Pat::Ident(pat) => &pat.ident,
+            pat => {
+                return Err(Error::new(
+                    pat.span(),
+                    "arguments must be named by router variables",
+                ))
+            }
+        };
+        let var = name.to_string();
+        let var = var.trim_start_matches("r#");
+        if !vars.contains(var) {
+            return Err(Error::new(
+                name.span(),
+                format!("no variable `{}` in path `{}`", var, path.value()),
+            ));
+        }
+        let ty = &arg.ty;
+        if let Type::Reference(_) = &**ty {
+            return Err(Error::new(
+                ty.span(),
+                "router variables are parsed by `FromStr`, use an owned type like `String`",
+            ));
+        }
+        parsers.push(quote_spanned! {ty.span()=>
+            let #name: #ty = ::roa::router::parse_param(__roa_ctx, #var)?;
+        });
+        names.push(name.clone());
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    let name = sig.ident.clone();
+    let docs = attrs.iter().filter(|attr| attr.path.is_ident("doc"));
+    let mut handler = sig;
+    handler.ident = Ident::new("handler", Span::call_site());
+    let (impl_generics, _, where_clause) = handler.generics.split_for_impl();
+    let mut endpoint_generics = handler.generics.clone();
+    endpoint_generics.params.insert(0, parse_quote!('__roa));
+    let (endpoint_generics, _, _) = endpoint_generics.split_for_impl();
+
+    Ok(quote! {
+        #(#docs)*
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, Copy, Clone)]
+        #vis struct #name;
+
+        impl #name {
+            #(#attrs)*
+            #vis #handler #block
+        }
+
+        #[::roa::async_trait(?Send)]
+        impl #endpoint_generics ::roa::Endpoint<'__roa, #state> for #name #where_clause {
+            #[inline]
+            async fn call(
+                &'__roa self,
+                __roa_ctx: &'__roa mut ::roa::Context<#state>,
+            ) -> ::roa::Result {
+                #(#parsers)*
+                Self::handler(__roa_ctx, #(#names),*).await
+            }
+        }
+
+        impl #impl_generics ::roa::router::Route<#state> for #name #where_clause {
+            #[inline]
+            fn path(&self) -> &'static str {
+                #path
+            }
+
+            #[inline]
+            fn method(&self) -> ::roa::http::Method {
+                ::roa::http::Method::#method
+            }
+        }
+    })
+}
@@ -7,17 +7,35 @@ use actix_http::http::HeaderMap;
 use actix_multipart::Field as ActixField;
 use actix_multipart::Multipart as ActixMultipart;
 use actix_multipart::MultipartError as ActixMultipartError;
-use bytes::Bytes;
-use futures::Stream;
+use bytes::{Bytes, BytesMut};
+use futures::io::{AsyncWrite, AsyncWriteExt};
+use futures::{Stream, StreamExt};
 use hyper::Body;
-use roa_core::http::{header::CONTENT_TYPE, StatusCode};
-use roa_core::{Context, Status};
+use roa_core::http::{
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+    StatusCode,
+};
+use roa_core::{Context, PayloadTooLarge, Status};
 use std::fmt::{self, Display, Formatter};
+use std::future::Future;
 use std::io;
 use std::ops::Deref;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::task::{self, Poll};
 
+/// Default limit of nesting depth.
+const DEFAULT_MAX_DEPTH: usize = 4;
+
+/// Max length of headers of a part to scan for nested multipart.
+const MAX_HEADERS_LEN: usize = 16 * 1024;
+
+/// Media type "multipart" of nested parts is disguised as this one in the stream,
+/// so that actix yields them as fields rather than rejecting them.
+/// It has the same length as "multipart".
+const DISGUISE: &str = "x-roa-mpt";
+
 /// A context extensio nwrapped `actix_multipart::Multipart`.
 pub trait MultipartForm {
     /// Read request body as multipart form.
@@ -25,30 +43,344 @@ pub trait MultipartForm {
 }
 
 impl<S> MultipartForm for Context<S> {
+    /// Read request body as multipart form with default policy.
     fn form(&mut self) -> Multipart {
-        let mut map = HeaderMap::new();
-        if let Some(value) = self.req.headers.get(CONTENT_TYPE) {
-            map.insert(CONTENT_TYPE, value.clone())
-        }
-        Multipart(ActixMultipart::new(
-            &map,
-            WrapStream(Some(self.req.raw_body())),
-        ))
+        Multipart::with_policy(self, UploadPolicy::default())
     }
 }
 
+/// Upload policy of multipart form.
+///
+/// By default, there is no limit.
+///
+/// The body limit of request (set by `roa::limit::BodyLimit` or `Request::limit_body`)
+/// applies as well, the smaller one of it and `max_size` wins.
+///
+/// A request declaring a "Content-Length" over the limit is rejected with 413 PAYLOAD TOO LARGE
+/// before its body is read, so a client sending "Expect: 100-continue" never uploads it.
+///
+/// Any multipart media type with a boundary is accepted by default,
+/// like "multipart/form-data" or "multipart/mixed", use `accept` to restrict them.
+///
+/// Parts of a nested multipart (a part whose media type is "multipart/*") are parsed recursively
+/// and yielded in order, see `max_depth`.
+#[derive(Debug, Clone)]
+pub struct UploadPolicy {
+    max_size: Option<u64>,
+    max_fields: Option<usize>,
+    max_depth: usize,
+    media_types: Vec<String>,
+}
+
+/// A trait to get upload policy from `State`, to configure uploads in a single place.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{App, Context};
+/// use roa_multipart::{Multipart, UploadConfig, UploadPolicy};
+/// use futures::StreamExt;
+///
+/// #[derive(Clone)]
+/// struct State {
+///     upload: UploadPolicy,
+/// }
+///
+/// impl UploadConfig for State {
+///     fn upload_policy(&self) -> &UploadPolicy {
+///         &self.upload
+///     }
+/// }
+///
+/// async fn post_file(ctx: &mut Context<State>) -> roa::Result {
+///     // 413 PAYLOAD TOO LARGE if the form exceeds limits.
+///     let mut form = Multipart::new(ctx);
+///     while let Some(field) = form.next().await {
+///         let field = field?;
+///     }
+///     Ok(())
+/// }
+///
+/// let upload = UploadPolicy::new().max_size(16 * 1024 * 1024).max_fields(8);
+/// let app = App::state(State { upload }).end(post_file);
+/// ```
+pub trait UploadConfig {
+    /// Get upload policy.
+    fn upload_policy(&self) -> &UploadPolicy;
+}
+
 /// A wrapper for actix multipart.
-pub struct Multipart(ActixMultipart);
+///
+/// Parts of nested multipart are yielded in place of the nested one,
+/// their depth can be got by `Field::depth`.
+pub struct Multipart {
+    inner: ActixMultipart,
+    nested: Vec<ActixMultipart>,
+    progress: UploadProgress,
+    limit: Option<u64>,
+    fields: usize,
+    policy: UploadPolicy,
+    rejection: Option<ErrorKind>,
+    terminated: bool,
+}
 
 /// A wrapper for actix multipart field.
-pub struct Field(ActixField);
+///
+/// A field exceeding the size limit yields an io error caused by `PayloadTooLarge`,
+/// it can be converted to 413 PAYLOAD TOO LARGE by `roa::body::handle_body_error`.
+pub struct Field(ActixField, Option<u64>, usize);
 
 /// A wrapper for actix multipart field.
 #[derive(Debug)]
-pub struct MultipartError(ActixMultipartError);
+pub struct MultipartError(ErrorKind);
+
+/// Kinds of multipart error.
+#[derive(Debug)]
+enum ErrorKind {
+    Actix(ActixMultipartError),
+    TooManyFields(usize),
+    TooDeep(usize),
+    UnsupportedMediaType(String),
+    ContentTooLarge { length: u64, limit: u64 },
+}
+
+/// A shared counter of bytes of multipart body received so far.
+///
+/// It's updated as the body streams, so a concurrent task can observe upload progress.
+#[derive(Debug, Clone, Default)]
+pub struct UploadProgress(Arc<AtomicU64>);
+
+/// A wrapper for hyper::Body, with a limit of total bytes.
+struct WrapStream {
+    body: Option<Body>,
+    read: UploadProgress,
+    limit: Option<u64>,
+}
+
+/// A wrapper of a field, as body of a nested multipart.
+struct FieldStream(ActixField);
+
+/// A stream wrapper disguising media type of nested multipart parts as `DISGUISE`.
+///
+/// Only headers of parts at this level are scanned,
+/// those of deeper levels are scanned by wrappers of nested multipart.
+struct Nesting<S> {
+    stream: S,
+    delimiter: Option<Vec<u8>>,
+    buf: BytesMut,
+    headers: bool,
+    skip: usize,
+    eof: bool,
+}
+
+impl UploadPolicy {
+    /// Construct a default policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set limit of total bytes of multipart body.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Set limit of field count, parts of nested multipart are counted as well.
+    pub fn max_fields(mut self, count: usize) -> Self {
+        self.max_fields = Some(count);
+        self
+    }
+
+    /// Set limit of nesting depth, default 4.
+    ///
+    /// Parts at top level are of depth 0, parts of a nested multipart at top level are of depth 1,
+    /// and so on. A multipart nested deeper than the limit is rejected with 415 UNSUPPORTED MEDIA TYPE,
+    /// set it to 0 to reject any nested multipart.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Accept a top-level media type, like "multipart/mixed".
+    ///
+    /// Once it's called, requests of other media types are rejected with 415 UNSUPPORTED MEDIA TYPE.
+    pub fn accept(mut self, media_type: impl AsRef<str>) -> Self {
+        self.media_types
+            .push(media_type.as_ref().trim().to_ascii_lowercase());
+        self
+    }
+}
+
+impl Default for UploadPolicy {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            max_fields: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            media_types: Vec::new(),
+        }
+    }
+}
 
-/// A wrapper for hyper::Body.
-struct WrapStream(Option<Body>);
+impl Multipart {
+    /// Read request body as multipart form, with policy of `State`.
+    pub fn new<S: UploadConfig>(ctx: &mut Context<S>) -> Self {
+        let policy = ctx.state().upload_policy().clone();
+        Self::with_policy(ctx, policy)
+    }
+
+    /// Read request body as multipart form, with a specific policy.
+    pub fn with_policy<S>(ctx: &mut Context<S>, policy: UploadPolicy) -> Self {
+        let mut map = HeaderMap::new();
+        if let Some(value) = ctx.req.headers.get(CONTENT_TYPE) {
+            map.insert(CONTENT_TYPE, value.clone())
+        }
+        let boundary = ctx
+            .req
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(boundary);
+        let media_type = ctx
+            .req
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|media_type| media_type.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        let limit = match (policy.max_size, ctx.req.body_limit()) {
+            (Some(max_size), Some(body_limit)) => Some(max_size.min(body_limit)),
+            (max_size, body_limit) => max_size.or(body_limit),
+        };
+        let length: Option<u64> = ctx
+            .req
+            .headers
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+        let rejection = match (length, limit) {
+            _ if !(policy.media_types.is_empty()
+                || policy.media_types.contains(&media_type)) =>
+            {
+                Some(ErrorKind::UnsupportedMediaType(media_type))
+            }
+            (Some(length), Some(limit)) if length > limit => {
+                Some(ErrorKind::ContentTooLarge { length, limit })
+            }
+            _ => None,
+        };
+        // body of a rejected request is never polled,
+        // so hyper won't send "100 Continue" to a client expecting it.
+        let body = if rejection.is_none() {
+            Some(ctx.req.raw_body())
+        } else {
+            None
+        };
+        let progress = UploadProgress::default();
+        let stream = WrapStream {
+            body,
+            read: progress.clone(),
+            limit,
+        };
+        Self {
+            inner: ActixMultipart::new(&map, Nesting::new(stream, boundary)),
+            nested: Vec::new(),
+            progress,
+            limit,
+            fields: 0,
+            policy,
+            rejection,
+            terminated: false,
+        }
+    }
+
+    /// Get a handle of upload progress, to observe bytes received so far.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{App, Context};
+    /// use roa_multipart::MultipartForm;
+    /// use futures::io::AsyncReadExt;
+    /// use futures::stream::TryStreamExt;
+    ///
+    /// async fn upload(ctx: &mut Context) -> roa::Result {
+    ///     let form = ctx.form();
+    ///     let progress = form.progress();
+    ///     form.for_each_field(|field| async move {
+    ///         let mut data = Vec::new();
+    ///         field.into_async_read().read_to_end(&mut data).await?;
+    ///         Ok(())
+    ///     })
+    ///     .await?;
+    ///     ctx.resp.write(format!("received {} bytes", progress.get()));
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(upload);
+    /// ```
+    pub fn progress(&self) -> UploadProgress {
+        self.progress.clone()
+    }
+
+    /// Invoke a callback for each field as it arrives.
+    ///
+    /// The next field is not read until the future of callback completes,
+    /// so the callback can decide to buffer or stream each field without collecting the form.
+    /// It stops at the first error of form or callback.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::body::handle_body_error;
+    /// use roa::{App, Context};
+    /// use roa_multipart::MultipartForm;
+    /// use futures::io::AsyncReadExt;
+    /// use futures::stream::TryStreamExt;
+    ///
+    /// async fn post_form(ctx: &mut Context) -> roa::Result {
+    ///     ctx.form()
+    ///         .for_each_field(|field| async move {
+    ///             let mut data = Vec::new();
+    ///             // 413 PAYLOAD TOO LARGE if the field exceeds the limit.
+    ///             field
+    ///                 .into_async_read()
+    ///                 .read_to_end(&mut data)
+    ///                 .await
+    ///                 .map_err(handle_body_error)?;
+    ///             Ok(())
+    ///         })
+    ///         .await
+    /// }
+    ///
+    /// let app = App::new().end(post_form);
+    /// ```
+    pub async fn for_each_field<F, Fut>(mut self, mut callback: F) -> Result<(), Status>
+    where
+        F: FnMut(Field) -> Fut,
+        Fut: Future<Output = Result<(), Status>>,
+    {
+        while let Some(field) = self.next().await {
+            callback(field?).await?;
+        }
+        Ok(())
+    }
+}
+
+impl UploadProgress {
+    /// Get bytes received so far.
+    #[inline]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Add bytes received, return the total.
+    #[inline]
+    fn add(&self, bytes: u64) -> u64 {
+        self.0.fetch_add(bytes, Ordering::SeqCst) + bytes
+    }
+}
 
 impl Stream for WrapStream {
     type Item = Result<Bytes, PayloadError>;
@@ -57,15 +389,24 @@ impl Stream for WrapStream {
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        match &mut self.0 {
+        match &mut self.body {
             None => Poll::Ready(None),
             Some(body) => match futures::ready!(Pin::new(body).poll_next(cx)) {
                 None => {
-                    self.0 = None;
+                    self.body = None;
                     self.poll_next(cx)
                 }
                 Some(item) => Poll::Ready(Some(match item {
-                    Ok(data) => Ok(data),
+                    Ok(data) => {
+                        let read = self.read.add(data.len() as u64);
+                        match self.limit {
+                            Some(limit) if read > limit => {
+                                self.body = None;
+                                Err(PayloadError::Overflow)
+                            }
+                            _ => Ok(data),
+                        }
+                    }
                     Err(err) => Err(if err.is_incomplete_message() {
                         PayloadError::Incomplete(Some(io::Error::new(
                             io::ErrorKind::UnexpectedEof,
@@ -88,13 +429,224 @@ impl Stream for Multipart {
         mut self: Pin<&mut Self>,
         cx: &mut task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        match futures::ready!(Pin::new(&mut self.0).poll_next(cx)) {
-            None => Poll::Ready(None),
-            Some(item) => Poll::Ready(Some(match item {
-                Ok(field) => Ok(Field(field)),
-                Err(err) => Err(MultipartError(err)),
-            })),
+        if self.terminated {
+            return Poll::Ready(None);
         }
+        if let Some(kind) = self.rejection.take() {
+            self.terminated = true;
+            return Poll::Ready(Some(Err(MultipartError(kind))));
+        }
+        let this = &mut *self;
+        loop {
+            let depth = this.nested.len();
+            let item = match this.nested.last_mut() {
+                Some(nested) => futures::ready!(Pin::new(nested).poll_next(cx)),
+                None => futures::ready!(Pin::new(&mut this.inner).poll_next(cx)),
+            };
+            let field = match item {
+                None if depth > 0 => {
+                    this.nested.pop();
+                    continue;
+                }
+                None => return Poll::Ready(None),
+                Some(Err(err)) => {
+                    return Poll::Ready(Some(Err(MultipartError(ErrorKind::Actix(err)))))
+                }
+                Some(Ok(field)) => field,
+            };
+            this.fields += 1;
+            if let Some(max) = this.policy.max_fields {
+                if this.fields > max {
+                    return Poll::Ready(Some(Err(MultipartError(
+                        ErrorKind::TooManyFields(max),
+                    ))));
+                }
+            }
+            if field.content_type().type_().as_str() != DISGUISE {
+                return Poll::Ready(Some(Ok(Field(field, this.limit, depth))));
+            }
+            if depth >= this.policy.max_depth {
+                this.terminated = true;
+                return Poll::Ready(Some(Err(MultipartError(ErrorKind::TooDeep(
+                    this.policy.max_depth,
+                )))));
+            }
+            this.nested.push(nested(field));
+        }
+    }
+}
+
+/// Construct a multipart reading a nested one from a field, whose media type is restored.
+fn nested(field: ActixField) -> ActixMultipart {
+    let mut map = HeaderMap::new();
+    if let Some(value) = field.headers().get(CONTENT_TYPE) {
+        let mut content_type = value.as_bytes().to_vec();
+        if content_type.starts_with(DISGUISE.as_bytes()) {
+            content_type[..DISGUISE.len()].copy_from_slice(b"multipart");
+        }
+        if let Ok(value) = HeaderValue::from_bytes(&content_type) {
+            map.insert(CONTENT_TYPE, value)
+        }
+    }
+    let boundary = map
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(boundary);
+    ActixMultipart::new(&map, Nesting::new(FieldStream(field), boundary))
+}
+
+/// Get boundary parameter of a multipart media type.
+fn boundary(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let mut pair = param.splitn(2, '=');
+        let name = pair.next()?.trim();
+        let value = pair.next()?.trim();
+        if name.eq_ignore_ascii_case("boundary") {
+            Some(value.trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Find position of needle in haystack.
+#[inline]
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Disguise media type "multipart/*" in a header block.
+fn disguise(block: &mut [u8]) {
+    const NAME: &[u8] = b"content-type:";
+    let mut start = 0;
+    while start < block.len() {
+        let end = find(&block[start..], b"\r\n")
+            .map(|index| start + index)
+            .unwrap_or(block.len());
+        let line = &mut block[start..end];
+        if line.len() > NAME.len() && line[..NAME.len()].eq_ignore_ascii_case(NAME) {
+            let offset = NAME.len()
+                + line[NAME.len()..]
+                    .iter()
+                    .take_while(|byte| **byte == b' ' || **byte == b'\t')
+                    .count();
+            let value = &mut line[offset..];
+            if value.len() > DISGUISE.len()
+                && value[..DISGUISE.len() + 1].eq_ignore_ascii_case(b"multipart/")
+            {
+                value[..DISGUISE.len()].copy_from_slice(DISGUISE.as_bytes());
+            }
+        }
+        start = end + 2;
+    }
+}
+
+impl<S> Nesting<S> {
+    fn new(stream: S, boundary: Option<String>) -> Self {
+        // a virtual CRLF, so the first delimiter can be found like the others.
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"\r\n");
+        Self {
+            stream,
+            delimiter: boundary
+                .map(|boundary| format!("\r\n--{}", boundary).into_bytes()),
+            buf,
+            headers: false,
+            skip: 2,
+            eof: false,
+        }
+    }
+
+    /// Split scanned bytes out of buffer, return `None` if more bytes are needed.
+    fn scan(&mut self) -> Option<Bytes> {
+        let len = match self.delimiter {
+            None => self.buf.len(),
+            Some(_) if self.headers => match find(&self.buf, b"\r\n\r\n") {
+                Some(index) => {
+                    disguise(&mut self.buf[..index + 4]);
+                    self.headers = false;
+                    index + 4
+                }
+                None if self.buf.len() > MAX_HEADERS_LEN => {
+                    self.headers = false;
+                    self.buf.len()
+                }
+                None => 0,
+            },
+            Some(ref delimiter) => match find(&self.buf, delimiter) {
+                Some(index) => {
+                    self.headers = true;
+                    index + delimiter.len()
+                }
+                // the tail may be a part of delimiter.
+                None => self.buf.len().saturating_sub(delimiter.len() - 1),
+            },
+        };
+        self.split(len)
+    }
+
+    /// Split bytes out of buffer, the virtual CRLF is skipped.
+    fn split(&mut self, len: usize) -> Option<Bytes> {
+        let mut bytes = self.buf.split_to(len);
+        let skip = self.skip.min(bytes.len());
+        self.skip -= skip;
+        let bytes = bytes.split_off(skip);
+        if bytes.is_empty() {
+            None
+        } else {
+            Some(bytes.freeze())
+        }
+    }
+}
+
+impl<S> Stream for Nesting<S>
+where
+    S: Unpin + Stream<Item = Result<Bytes, PayloadError>>,
+{
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(bytes) = self.scan() {
+                return Poll::Ready(Some(Ok(bytes)));
+            }
+            if self.eof {
+                let len = self.buf.len();
+                return Poll::Ready(self.split(len).map(Ok));
+            }
+            match futures::ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(bytes)) => self.buf.extend_from_slice(&bytes),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => self.eof = true,
+            }
+        }
+    }
+}
+
+impl Stream for FieldStream {
+    type Item = Result<Bytes, PayloadError>;
+
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        Poll::Ready(
+            futures::ready!(Pin::new(&mut self.0).poll_next(cx)).map(|item| {
+                item.map_err(|err| match err {
+                    ActixMultipartError::Payload(err) => err,
+                    err => PayloadError::Io(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        err.to_string(),
+                    )),
+                })
+            }),
+        )
     }
 }
 
@@ -111,6 +663,14 @@ impl Stream for Field {
                 Ok(bytes) => Ok(bytes),
                 Err(err) => Err(match err {
                     ActixMultipartError::Payload(PayloadError::Io(err)) => err,
+                    ActixMultipartError::Payload(PayloadError::Overflow) => {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            PayloadTooLarge {
+                                limit: self.1.unwrap_or_default(),
+                            },
+                        )
+                    }
                     err => io::Error::new(
                         io::ErrorKind::Other,
                         Status::new(
@@ -126,6 +686,53 @@ impl Stream for Field {
     }
 }
 
+impl Field {
+    /// Get nesting depth of this field, 0 if it's at top level.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.2
+    }
+
+    /// Pipe this field into a sink chunk by chunk as it streams, return bytes written.
+    ///
+    /// Each chunk is written before the next one is read, so memory usage stays constant
+    /// regardless of upload size. A transformation like hashing, scanning or resizing
+    /// can be plugged as a sink implementing `AsyncWrite`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{App, Context};
+    /// use roa_multipart::MultipartForm;
+    /// use async_std::fs::File;
+    ///
+    /// async fn upload(ctx: &mut Context) -> roa::Result {
+    ///     ctx.form()
+    ///         .for_each_field(|field| async move {
+    ///             let mut file = File::create("upload.bin").await?;
+    ///             field.pipe_through(&mut file).await?;
+    ///             Ok(())
+    ///         })
+    ///         .await
+    /// }
+    ///
+    /// let app = App::new().end(upload);
+    /// ```
+    pub async fn pipe_through<W>(mut self, sink: &mut W) -> io::Result<u64>
+    where
+        W: ?Sized + Unpin + AsyncWrite,
+    {
+        let mut written = 0;
+        while let Some(chunk) = self.next().await {
+            let chunk = chunk?;
+            sink.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        sink.flush().await?;
+        Ok(written)
+    }
+}
+
 impl Deref for Field {
     type Target = ActixField;
     #[inline]
@@ -137,20 +744,54 @@ impl Deref for Field {
 impl From<MultipartError> for Status {
     #[inline]
     fn from(err: MultipartError) -> Self {
-        Status::new(StatusCode::BAD_REQUEST, err, true)
+        let status_code = match &err.0 {
+            ErrorKind::Actix(ActixMultipartError::Payload(PayloadError::Overflow))
+            | ErrorKind::TooManyFields(_)
+            | ErrorKind::ContentTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorKind::Actix(ActixMultipartError::Nested)
+            | ErrorKind::TooDeep(_)
+            | ErrorKind::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Status::new(status_code, err, true)
     }
 }
 
 impl Display for MultipartError {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.write_fmt(format_args!("{}\nmultipart form read error.", self.0))
+        match &self.0 {
+            ErrorKind::Actix(ActixMultipartError::Payload(PayloadError::Overflow)) => {
+                f.write_str("multipart body exceeds the limit.")
+            }
+            ErrorKind::Actix(ActixMultipartError::Nested) => {
+                f.write_str("nested multipart is not supported.")
+            }
+            ErrorKind::Actix(err) => {
+                f.write_fmt(format_args!("{}\nmultipart form read error.", err))
+            }
+            ErrorKind::TooManyFields(max) => {
+                f.write_fmt(format_args!("too many multipart fields, limit is {}.", max))
+            }
+            ErrorKind::TooDeep(max) => f.write_fmt(format_args!(
+                "multipart is nested too deep, limit of depth is {}.",
+                max
+            )),
+            ErrorKind::UnsupportedMediaType(media_type) => f.write_fmt(format_args!(
+                "media type `{}` of multipart is not accepted.",
+                media_type
+            )),
+            ErrorKind::ContentTooLarge { length, limit } => f.write_fmt(format_args!(
+                "multipart body of {} bytes exceeds the limit of {} bytes.",
+                length, limit
+            )),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::MultipartForm;
+    use super::{Multipart, MultipartForm, UploadConfig, UploadPolicy};
     use async_std::fs::{read, read_to_string};
     use futures::stream::TryStreamExt;
     use futures::{AsyncReadExt, StreamExt};
@@ -159,6 +800,7 @@ mod tests {
         Client,
     };
     use roa::http::{header::CONTENT_TYPE, StatusCode};
+    use roa::limit::BodyLimit;
     use roa::router::{post, Router};
     use roa::tcp::Listener;
     use roa::{throw, App, Context};
@@ -219,4 +861,454 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status());
         Ok(())
     }
+
+    #[derive(Clone)]
+    struct State {
+        upload: UploadPolicy,
+    }
+
+    impl UploadConfig for State {
+        fn upload_policy(&self) -> &UploadPolicy {
+            &self.upload
+        }
+    }
+
+    async fn consume(ctx: &mut Context<State>) -> roa::Result {
+        let mut form = Multipart::new(ctx);
+        while let Some(item) = form.next().await {
+            let mut content = Vec::new();
+            item?.into_async_read().read_to_end(&mut content).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upload_policy() -> Result<(), Box<dyn StdError>> {
+        for (policy, status) in vec![
+            (UploadPolicy::new(), StatusCode::OK),
+            (
+                UploadPolicy::new().max_size(16),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                UploadPolicy::new().max_fields(0),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+        ] {
+            let router = Router::new().on("/file", post(consume));
+            let app = App::state(State { upload: policy }).end(router.routes("/")?);
+            let (addr, server) = app.run()?;
+            async_std::task::spawn(server);
+
+            let form = Form::new().part(
+                FIELD_NAME,
+                Part::bytes(read(FILE_PATH).await?).file_name(FILE_NAME),
+            );
+            let boundary = form.boundary().to_string();
+            let resp = Client::new()
+                .post(&format!("http://{}/file", addr))
+                .body(form.stream())
+                .header(
+                    CONTENT_TYPE,
+                    format!(r#"multipart/form-data; boundary="{}""#, boundary),
+                )
+                .send()
+                .await?;
+            assert_eq!(status, resp.status());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn for_each_field() -> Result<(), Box<dyn StdError>> {
+        async fn count(ctx: &mut Context) -> roa::Result {
+            let mut names = Vec::new();
+            ctx.form()
+                .for_each_field(|field| {
+                    if let Some(name) = field
+                        .content_disposition()
+                        .and_then(|disposition| disposition.get_name().map(String::from))
+                    {
+                        names.push(name);
+                    }
+                    async move {
+                        let mut content = Vec::new();
+                        field.into_async_read().read_to_end(&mut content).await?;
+                        Ok(())
+                    }
+                })
+                .await?;
+            ctx.resp.write(names.join(","));
+            Ok(())
+        }
+        let router = Router::new().on("/file", post(count));
+        let (addr, server) = App::new().end(router.routes("/")?).run()?;
+        async_std::task::spawn(server);
+
+        let form = Form::new().text("name", "Hexilee").part(
+            FIELD_NAME,
+            Part::bytes(read(FILE_PATH).await?).file_name(FILE_NAME),
+        );
+        let boundary = form.boundary().to_string();
+        let resp = Client::new()
+            .post(&format!("http://{}/file", addr))
+            .body(form.stream())
+            .header(
+                CONTENT_TYPE,
+                format!(r#"multipart/form-data; boundary="{}""#, boundary),
+            )
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("name,file", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pipe_through() -> Result<(), Box<dyn StdError>> {
+        use futures::io::AsyncWrite;
+        use std::pin::Pin;
+        use std::task::{self, Poll};
+
+        /// A sink counting bytes and their checksum, without buffering them.
+        #[derive(Default)]
+        struct Checksum {
+            len: usize,
+            sum: u64,
+        }
+
+        impl AsyncWrite for Checksum {
+            fn poll_write(
+                mut self: Pin<&mut Self>,
+                _cx: &mut task::Context<'_>,
+                buf: &[u8],
+            ) -> Poll<std::io::Result<usize>> {
+                self.len += buf.len();
+                self.sum += buf.iter().map(|byte| *byte as u64).sum::<u64>();
+                Poll::Ready(Ok(buf.len()))
+            }
+
+            fn poll_flush(
+                self: Pin<&mut Self>,
+                _cx: &mut task::Context<'_>,
+            ) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(
+                self: Pin<&mut Self>,
+                _cx: &mut task::Context<'_>,
+            ) -> Poll<std::io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        async fn checksum(ctx: &mut Context) -> roa::Result {
+            let mut sink = Checksum::default();
+            let mut written = 0;
+            let mut form = ctx.form();
+            while let Some(field) = form.next().await {
+                written += field?.pipe_through(&mut sink).await?;
+            }
+            assert_eq!(written, sink.len as u64);
+            ctx.resp.write(sink.sum.to_string());
+            Ok(())
+        }
+
+        let router = Router::new().on("/file", post(checksum));
+        let (addr, server) = App::new().end(router.routes("/")?).run()?;
+        async_std::task::spawn(server);
+
+        let content = read(FILE_PATH).await?;
+        let expected: u64 = content.iter().map(|byte| *byte as u64).sum();
+        let form =
+            Form::new().part(FIELD_NAME, Part::bytes(content).file_name(FILE_NAME));
+        let boundary = form.boundary().to_string();
+        let resp = Client::new()
+            .post(&format!("http://{}/file", addr))
+            .body(form.stream())
+            .header(
+                CONTENT_TYPE,
+                format!(r#"multipart/form-data; boundary="{}""#, boundary),
+            )
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(expected.to_string(), resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn body_limit() -> Result<(), Box<dyn StdError>> {
+        let router = Router::new().on("/file", post(consume));
+        let app = App::state(State {
+            upload: UploadPolicy::new().max_size(1024 * 1024),
+        })
+        .gate(BodyLimit::new(16))
+        .end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        async_std::task::spawn(server);
+
+        let form = Form::new().part(
+            FIELD_NAME,
+            Part::bytes(read(FILE_PATH).await?).file_name(FILE_NAME),
+        );
+        let boundary = form.boundary().to_string();
+        let resp = Client::new()
+            .post(&format!("http://{}/file", addr))
+            .body(form.stream())
+            .header(
+                CONTENT_TYPE,
+                format!(r#"multipart/form-data; boundary="{}""#, boundary),
+            )
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expect_continue() -> Result<(), Box<dyn StdError>> {
+        use async_std::net::TcpStream;
+        use futures::io::AsyncWriteExt;
+
+        let router = Router::new().on("/file", post(consume));
+        let app = App::state(State {
+            upload: UploadPolicy::new().max_size(16),
+        })
+        .end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        async_std::task::spawn(server);
+
+        // the body is never sent, as the client waits for "100 Continue".
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"POST /file HTTP/1.1\r\n\
+                  Host: localhost\r\n\
+                  Content-Type: multipart/form-data; boundary=boundary\r\n\
+                  Content-Length: 1024\r\n\
+                  Expect: 100-continue\r\n\r\n",
+            )
+            .await?;
+        let mut buf = vec![0; 1024];
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        let response = String::from_utf8(response)?;
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn field_too_large() -> Result<(), Box<dyn StdError>> {
+        use async_std::net::TcpStream;
+        use async_std::task::sleep;
+        use futures::io::AsyncWriteExt;
+        use roa::body::handle_body_error;
+        use std::time::Duration;
+
+        async fn read_field(ctx: &mut Context) -> roa::Result {
+            let mut form =
+                Multipart::with_policy(ctx, UploadPolicy::new().max_size(256));
+            while let Some(field) = form.next().await {
+                let mut data = Vec::new();
+                field?
+                    .into_async_read()
+                    .read_to_end(&mut data)
+                    .await
+                    .map_err(handle_body_error)?;
+            }
+            Ok(())
+        }
+
+        let (addr, server) = App::new().end(read_field).run()?;
+        async_std::task::spawn(server);
+
+        // the limit is exceeded after the field arrives.
+        let head = "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"data.bin\"\r\n\r\n\
+                    Hello, World!";
+        let data = "x".repeat(1024);
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                format!(
+                    "POST / HTTP/1.1\r\n\
+                     Host: localhost\r\n\
+                     Content-Type: multipart/form-data; boundary=boundary\r\n\
+                     Transfer-Encoding: chunked\r\n\r\n\
+                     {:x}\r\n{}\r\n",
+                    head.len(),
+                    head
+                )
+                .as_bytes(),
+            )
+            .await?;
+        sleep(Duration::from_millis(100)).await;
+        stream
+            .write_all(format!("{:x}\r\n{}\r\n", data.len(), data).as_bytes())
+            .await?;
+        let mut buf = vec![0; 1024];
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            response.extend_from_slice(&buf[..n]);
+        }
+        let response = String::from_utf8(response)?;
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn progress() -> Result<(), Box<dyn StdError>> {
+        const FORM: &str = "--boundary\r\n\
+                            Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                            Hexilee\r\n\
+                            --boundary--\r\n";
+        async fn upload(ctx: &mut Context) -> roa::Result {
+            let form = ctx.form();
+            let progress = form.progress();
+            assert_eq!(0, progress.get());
+            form.for_each_field(|field| async move {
+                let mut content = Vec::new();
+                field.into_async_read().read_to_end(&mut content).await?;
+                Ok(())
+            })
+            .await?;
+            ctx.resp.write(progress.get().to_string());
+            Ok(())
+        }
+        let (addr, server) = App::new().end(upload).run()?;
+        async_std::task::spawn(server);
+        let resp = Client::new()
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=boundary")
+            .body(FORM)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(FORM.len().to_string(), resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multipart_mixed() -> Result<(), Box<dyn StdError>> {
+        const MIXED: &str = "--outer\r\n\
+                             Content-Type: text/plain\r\n\r\n\
+                             Hello\r\n\
+                             --outer\r\n\
+                             Content-Type: application/json\r\n\r\n\
+                             {}\r\n\
+                             --outer--\r\n";
+        let router = Router::new().on("/file", post(consume));
+        let app = App::state(State {
+            upload: UploadPolicy::new().accept("multipart/mixed"),
+        })
+        .end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        async_std::task::spawn(server);
+        let client = Client::new();
+        let send = |content_type: &'static str, body: &'static str| {
+            client
+                .post(&format!("http://{}/file", addr))
+                .header(CONTENT_TYPE, content_type)
+                .body(body)
+                .send()
+        };
+        let resp = send("multipart/mixed; boundary=outer", MIXED).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = send("multipart/form-data; boundary=outer", MIXED).await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nested() -> Result<(), Box<dyn StdError>> {
+        const TWO_LEVEL: &str = "--outer\r\n\
+                                 Content-Type: text/plain\r\n\r\n\
+                                 a\r\n\
+                                 --outer\r\n\
+                                 Content-Type: multipart/mixed; boundary=inner\r\n\r\n\
+                                 --inner\r\n\
+                                 Content-Type: text/plain\r\n\r\n\
+                                 b\r\n\
+                                 --inner\r\n\
+                                 Content-Type: application/json\r\n\r\n\
+                                 {}\r\n\
+                                 --inner--\r\n\
+                                 \r\n--outer\r\n\
+                                 Content-Type: text/plain\r\n\r\n\
+                                 c\r\n\
+                                 --outer--\r\n";
+        const THREE_LEVEL: &str = "--outer\r\n\
+                                   Content-Type: multipart/mixed; boundary=inner\r\n\r\n\
+                                   --inner\r\n\
+                                   Content-Type: multipart/alternative; boundary=deep\r\n\r\n\
+                                   --deep\r\n\
+                                   Content-Type: text/plain\r\n\r\n\
+                                   a\r\n\
+                                   --deep--\r\n\
+                                   \r\n--inner--\r\n\
+                                   \r\n--outer--\r\n";
+
+        async fn leaves(ctx: &mut Context<State>) -> roa::Result {
+            let mut form = Multipart::new(ctx);
+            let mut leaves = Vec::new();
+            while let Some(field) = form.next().await {
+                let field = field?;
+                let depth = field.depth();
+                let mut content = String::new();
+                field.into_async_read().read_to_string(&mut content).await?;
+                leaves.push(format!("{}:{}", depth, content));
+            }
+            ctx.resp.write(leaves.join(","));
+            Ok(())
+        }
+
+        for (policy, body, status, text) in vec![
+            (
+                UploadPolicy::new().max_depth(1),
+                TWO_LEVEL,
+                StatusCode::OK,
+                "0:a,1:b,1:{},0:c",
+            ),
+            (
+                UploadPolicy::new().max_depth(0),
+                TWO_LEVEL,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "",
+            ),
+            (UploadPolicy::new(), THREE_LEVEL, StatusCode::OK, "2:a"),
+            (
+                UploadPolicy::new().max_depth(1),
+                THREE_LEVEL,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "",
+            ),
+        ] {
+            let app = App::state(State { upload: policy }).end(leaves);
+            let (addr, server) = app.run()?;
+            async_std::task::spawn(server);
+            let resp = Client::new()
+                .post(&format!("http://{}", addr))
+                .header(CONTENT_TYPE, "multipart/mixed; boundary=outer")
+                .body(body)
+                .send()
+                .await?;
+            assert_eq!(status, resp.status());
+            if status == StatusCode::OK {
+                assert_eq!(text, resp.text().await?);
+            }
+        }
+        Ok(())
+    }
 }
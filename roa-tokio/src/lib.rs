@@ -3,10 +3,14 @@
 #![cfg_attr(feature = "docs", warn(missing_docs))]
 
 mod net;
+mod process;
 mod runtime;
 
 #[doc(inline)]
 pub use net::TcpIncoming;
 
+#[doc(inline)]
+pub use process::ServeProcess;
+
 #[doc(inline)]
 pub use runtime::Exec;
@@ -0,0 +1,212 @@
+use roa::http::StatusCode;
+use roa::{async_trait, throw, Body, BodySender, Context, Result, State};
+use std::io;
+use std::process::ExitStatus;
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, ChildStderr, ChildStdout};
+
+/// Size of chunks read from stdout, 4 KiB.
+const CHUNK_SIZE: usize = 4096;
+
+/// Limit of stderr captured for error reporting, 64 KiB.
+const STDERR_LIMIT: usize = 64 * 1024;
+
+/// A context extension to stream stdout of a child process as response body.
+///
+/// It works only in tokio runtime, like an app with `roa_tokio::Exec`.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{App, Context};
+/// use roa_tokio::{Exec, ServeProcess};
+/// use std::process::Stdio;
+/// use tokio::process::Command;
+///
+/// async fn render(ctx: &mut Context) -> roa::Result {
+///     let child = Command::new("echo")
+///         .arg("Hello, World")
+///         .stdout(Stdio::piped())
+///         .stderr(Stdio::piped())
+///         .kill_on_drop(true)
+///         .spawn()?;
+///     ctx.write_child(child).await
+/// }
+///
+/// let app = App::with_exec((), Exec).end(render);
+/// ```
+#[async_trait]
+pub trait ServeProcess {
+    /// Stream stdout of a child process as response body, stdout must be piped.
+    ///
+    /// Stderr, if piped, is captured for error reporting.
+    /// Chunks are read from stdout only when the previous one is taken by the connection,
+    /// so the child is blocked rather than buffered if it outruns the client.
+    ///
+    /// A child exiting with non-zero status before writing anything to stdout
+    /// gets a 500 INTERNAL SERVER ERROR, and its stderr is kept internal in the status.
+    /// Once the body starts, the response cannot be changed,
+    /// so a failure of child later aborts the body and is logged with stderr.
+    /// The child is killed and reaped if reading stdout fails,
+    /// or if the connection is closed before stdout ends.
+    async fn write_child(&mut self, child: Child) -> Result;
+}
+
+#[async_trait]
+impl<S: State> ServeProcess for Context<S> {
+    async fn write_child(&mut self, mut child: Child) -> Result {
+        let mut stdout = match child.stdout.take() {
+            Some(stdout) => stdout,
+            None => throw!(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "stdout of child process is not piped",
+                false
+            ),
+        };
+        // read stderr concurrently, so the child is never blocked by a full stderr pipe.
+        let stderr = self.exec.spawn(capture(child.stderr.take()));
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let size = match stdout.read(&mut chunk).await {
+            Ok(size) => size,
+            Err(err) => {
+                if child.kill().is_err() {
+                    // child has exited.
+                }
+                // reap the child, its status is meaningless after the read error.
+                let _ = child.await;
+                return Err(err.into());
+            }
+        };
+        if size == 0 {
+            let status = child.await?;
+            if !status.success() {
+                throw!(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    failure(status, &stderr.await),
+                    false
+                )
+            }
+            return Ok(());
+        }
+        chunk.truncate(size);
+
+        let (mut sender, body) = Body::channel();
+        self.resp.body = body;
+        self.exec.spawn(async move {
+            if let Err(err) = pipe(&mut sender, chunk, &mut stdout).await {
+                if child.kill().is_err() {
+                    // child has exited.
+                }
+                sender.abort(err).await;
+                return;
+            }
+            match child.await {
+                Ok(status) if status.success() => (),
+                Ok(status) => {
+                    let message = failure(status, &stderr.await);
+                    log::error!("{}", message);
+                    sender
+                        .abort(io::Error::new(io::ErrorKind::Other, message))
+                        .await;
+                }
+                Err(err) => sender.abort(err).await,
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Send the first chunk and the rest of stdout.
+async fn pipe(
+    sender: &mut BodySender,
+    first: Vec<u8>,
+    stdout: &mut ChildStdout,
+) -> io::Result<()> {
+    sender.send(first).await?;
+    loop {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let size = stdout.read(&mut chunk).await?;
+        if size == 0 {
+            return Ok(());
+        }
+        chunk.truncate(size);
+        sender.send(chunk).await?;
+    }
+}
+
+/// Read stderr to the end, only the first 64 KiB is kept.
+async fn capture(stderr: Option<ChildStderr>) -> String {
+    let mut stderr = match stderr {
+        Some(stderr) => stderr,
+        None => return String::new(),
+    };
+    let mut captured = Vec::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        match stderr.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(size) => {
+                let rest = STDERR_LIMIT.saturating_sub(captured.len());
+                captured.extend_from_slice(&chunk[..size.min(rest)]);
+            }
+        }
+    }
+    String::from_utf8_lossy(&captured).into_owned()
+}
+
+/// Describe failure of a child process.
+#[inline]
+fn failure(status: ExitStatus, stderr: &str) -> String {
+    format!(
+        "child process exited with {}\n{}",
+        status,
+        stderr.trim_end()
+    )
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::ServeProcess;
+    use crate::Exec;
+    use roa::http::StatusCode;
+    use roa::tcp::Listener;
+    use roa::{App, Context};
+    use std::error::Error;
+    use std::process::Stdio;
+    use tokio::process::Command;
+
+    async fn sh(ctx: &mut Context) -> roa::Result {
+        let script = match ctx.path() {
+            "/fail" => "echo oops 1>&2; exit 1",
+            "/abort" => "echo hello; exit 1",
+            _ => "echo hello",
+        };
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        ctx.write_child(child).await
+    }
+
+    #[tokio::test]
+    async fn write_child() -> Result<(), Box<dyn Error>> {
+        let app = App::with_exec((), Exec).end(sh);
+        let (addr, server) = app.bind("127.0.0.1:0")?;
+        tokio::spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("hello\n", resp.text().await?);
+
+        let resp = reqwest::get(&format!("http://{}/fail", addr)).await?;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+
+        // body is aborted after it starts.
+        let resp = reqwest::get(&format!("http://{}/abort", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(resp.text().await.is_err());
+        Ok(())
+    }
+}
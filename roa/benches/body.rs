@@ -0,0 +1,86 @@
+//! Throughput of `read_body_bytes`, zero-copy for a body in a single chunk.
+//!
+//! `read` is measured as a baseline, it always copies body into a `Vec<u8>`.
+//!
+//! ```bash
+//! cargo bench -p roa --features fake --bench body
+//! ```
+
+use async_std::task::block_on;
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use futures::stream;
+use roa::http::header::CONTENT_LENGTH;
+use roa::http::Request;
+use roa::preload::*;
+use roa::Context;
+use std::io;
+
+/// Size of a chunk in a chunked body.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Construct a context with a body in a single chunk.
+fn single(data: Bytes) -> Context {
+    Context::fake(
+        Request::post("/")
+            .header(CONTENT_LENGTH, data.len())
+            .body(data.into())
+            .unwrap(),
+    )
+}
+
+/// Construct a context with a body in chunks of `CHUNK_SIZE`.
+fn chunked(data: Bytes) -> Context {
+    let chunks: Vec<io::Result<Bytes>> = (0..data.len())
+        .step_by(CHUNK_SIZE)
+        .map(|start| Ok(data.slice(start..data.len().min(start + CHUNK_SIZE))))
+        .collect();
+    Context::fake(
+        Request::post("/")
+            .header(CONTENT_LENGTH, data.len())
+            .body(hyper::Body::wrap_stream(stream::iter(chunks)))
+            .unwrap(),
+    )
+}
+
+fn read_body_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_body_bytes");
+    for &size in &[1024, 1024 * 1024] {
+        let data = Bytes::from(vec![b'x'; size]);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(format!("single/{}", size), |b| {
+            b.iter_batched(
+                || single(data.clone()),
+                |mut ctx| block_on(ctx.read_body_bytes()).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(format!("chunked/{}", size), |b| {
+            b.iter_batched(
+                || chunked(data.clone()),
+                |mut ctx| block_on(ctx.read_body_bytes()).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read");
+    for &size in &[1024, 1024 * 1024] {
+        let data = Bytes::from(vec![b'x'; size]);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_function(format!("single/{}", size), |b| {
+            b.iter_batched(
+                || single(data.clone()),
+                |mut ctx| block_on(ctx.read()).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read_body_bytes, read);
+criterion_main!(benches);
@@ -0,0 +1,105 @@
+//! Throughput of the json backend behind `read_json` and `write_json`.
+//!
+//! Backends are compared if feature "json-simd" is enabled:
+//!
+//! ```bash
+//! cargo bench -p roa --features json-simd,fake --bench json
+//! ```
+
+use async_std::task::block_on;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use futures::future::ready;
+use roa::body::JsonBackend;
+use roa::http::header::CONTENT_TYPE;
+use roa::http::Request;
+use roa::preload::*;
+use roa::{Context, Middleware};
+use serde::{Deserialize, Serialize};
+
+/// Json backends enabled, with their names.
+#[allow(unused_mut)]
+fn backends() -> Vec<(&'static str, JsonBackend)> {
+    let mut backends = vec![("serde_json", JsonBackend::SerdeJson)];
+    #[cfg(feature = "json-simd")]
+    backends.push(("simd-json", JsonBackend::SimdJson));
+    backends
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct User {
+    id: u64,
+    name: String,
+    email: String,
+    score: f64,
+    active: bool,
+    tags: Vec<String>,
+}
+
+/// Generate a document of users.
+fn users(count: u64) -> Vec<User> {
+    (0..count)
+        .map(|id| User {
+            id,
+            name: format!("user-{}", id),
+            email: format!("user-{}@example.com", id),
+            score: id as f64 / 3.0,
+            active: id % 2 == 0,
+            tags: vec!["roa".to_string(), "bench".to_string()],
+        })
+        .collect()
+}
+
+/// Construct a context with a json body, using the backend.
+fn context(body: Vec<u8>, backend: JsonBackend) -> Context {
+    let mut ctx = Context::fake(
+        Request::post("/")
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap(),
+    );
+    block_on(backend.handle(&mut ctx, &mut ready(Ok::<_, roa::Status>(())))).unwrap();
+    ctx
+}
+
+fn read_json(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_json");
+    for &count in &[10, 1000] {
+        let data = serde_json::to_vec(&users(count)).unwrap();
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        for (name, backend) in backends() {
+            group.bench_function(format!("{}/{}", name, count), |b| {
+                b.iter_batched(
+                    || context(data.clone(), backend),
+                    |mut ctx| block_on(ctx.read_json::<Vec<User>>()).unwrap(),
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn write_json(c: &mut Criterion) {
+    let mut group = c.benchmark_group("write_json");
+    for &count in &[10, 1000] {
+        let users = users(count);
+        let size = serde_json::to_vec(&users).unwrap().len();
+        group.throughput(Throughput::Bytes(size as u64));
+        for (name, backend) in backends() {
+            group.bench_function(format!("{}/{}", name, count), |b| {
+                b.iter_batched(
+                    || context(Vec::new(), backend),
+                    |mut ctx| {
+                        ctx.write_json(&users).unwrap();
+                        ctx
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, read_json, write_json);
+criterion_main!(benches);
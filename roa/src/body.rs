@@ -68,6 +68,10 @@
 //!     // set "Content-Type" and "Content-Disposition"
 //!     ctx.write_file("assets/welcome.html", Inline).await?;
 //!
+//!     // open file and write it to body as an attachment named "welcome.html",
+//!     // set "Content-Type" and "Content-Disposition"
+//!     ctx.download_file("assets/welcome.html", "welcome.html").await?;
+//!
 //!     // write text,
 //!     // set "Content-Type"
 //!     ctx.write("Hello, World!");
@@ -82,47 +86,294 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ### JSON backend
+//!
+//! JSON is (de)serialized by `serde_json` by default, handlers stay the same with other backends:
+//!
+//! - feature "json-simd" enables `JsonBackend::SimdJson`, which is faster on large documents,
+//!   switch to it by mounting `JsonBackend::SimdJson` as a middleware.
+//! - feature "json-preserve-order" keeps order of object keys in `serde_json::Value`.
 
-use crate::{async_trait, http, Context, Result, State};
-use bytes::Bytes;
-use futures::{AsyncRead, AsyncReadExt};
+use crate::{async_trait, http, status, throw, Body, Context, Result, State, Status};
+use bytes::{Bytes, BytesMut};
+use futures::{AsyncRead, AsyncReadExt, Stream, StreamExt, TryStreamExt};
 use lazy_static::lazy_static;
+#[cfg(feature = "json")]
+use std::convert::TryFrom;
+use std::error::Error as StdError;
+use std::io;
 
 #[cfg(feature = "template")]
 use askama::Template;
+mod csv;
 #[cfg(feature = "file")]
 mod file;
 #[cfg(feature = "file")]
-pub use file::DispositionType;
+use file::{download_file, set_attachment, write_cached_file, write_file, Path};
 #[cfg(feature = "file")]
-use file::{write_file, Path};
+pub use file::{DispositionType, FileCache};
+#[cfg(feature = "urlencoded")]
+mod form;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use json::JsonBackend;
+#[cfg(feature = "json")]
+mod ndjson;
+#[cfg(feature = "json")]
+pub use ndjson::NdJson;
+#[cfg(feature = "json")]
+mod patch;
+#[cfg(feature = "json")]
+use patch::expect_patch_type;
+#[cfg(feature = "json")]
+pub use patch::{merge_patch, JsonPatch, PatchOperation, JSON_PATCH, MERGE_PATCH};
 #[cfg(any(feature = "json", feature = "urlencoded"))]
 use serde::de::DeserializeOwned;
 
-use http::{header, HeaderValue};
+use http::{header, HeaderValue, StatusCode};
 #[cfg(feature = "json")]
 use serde::Serialize;
 
+pub use crate::PayloadTooLarge;
+
+/// Convert an io error occurring in reading body to status.
+///
+/// Return 413 PAYLOAD TOO LARGE if it's caused by `PayloadTooLarge`,
+/// otherwise return 500 INTERNAL SERVER ERROR.
+#[inline]
+pub fn handle_body_error(err: io::Error) -> Status {
+    let mut source = err.get_ref().map(|err| err as &(dyn StdError + 'static));
+    while let Some(cause) = source {
+        if let Some(too_large) = cause.downcast_ref::<PayloadTooLarge>() {
+            return status!(StatusCode::PAYLOAD_TOO_LARGE, too_large);
+        }
+        source = match cause.downcast_ref::<io::Error>() {
+            Some(io_err) => io_err.get_ref().map(|err| err as &(dyn StdError + 'static)),
+            None => cause.source(),
+        };
+    }
+    err.into()
+}
+
+/// Get media type of a "Content-Type" value, parameters are ignored.
+#[inline]
+pub(crate) fn media_type(value: &str) -> &str {
+    value.split(';').next().unwrap_or_default().trim()
+}
+
+/// Get charset parameter of a "Content-Type" value.
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+#[inline]
+fn charset(value: &str) -> Option<&str> {
+    value.split(';').skip(1).find_map(|param| {
+        let mut pair = param.splitn(2, '=');
+        let name = pair.next()?.trim();
+        if name.eq_ignore_ascii_case("charset") {
+            Some(pair.next()?.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+/// Policy to handle request body declared in a charset other than utf-8,
+/// `Ignore` by default.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CharsetPolicy {
+    /// Ignore the charset, assume body is utf-8.
+    Ignore,
+
+    /// Reject body with a charset other than utf-8 (or us-ascii) by 415 UNSUPPORTED MEDIA TYPE.
+    Reject,
+
+    /// Transcode body to utf-8, reject unknown charsets by 415 UNSUPPORTED MEDIA TYPE.
+    #[cfg(feature = "charset")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "charset")))]
+    Transcode,
+}
+
+impl Default for CharsetPolicy {
+    fn default() -> Self {
+        CharsetPolicy::Ignore
+    }
+}
+
+/// Handle charset of request body by policy.
+#[cfg(any(feature = "json", feature = "urlencoded"))]
+#[inline]
+fn decode_charset<S>(
+    ctx: &Context<S>,
+    data: Vec<u8>,
+    policy: CharsetPolicy,
+) -> Result<Vec<u8>> {
+    let charset = match ctx.get(header::CONTENT_TYPE).and_then(charset) {
+        Some(charset) => charset,
+        None => return Ok(data),
+    };
+    let is_utf8 = ["utf-8", "utf8", "us-ascii"]
+        .iter()
+        .any(|name| charset.eq_ignore_ascii_case(name));
+    if is_utf8 {
+        return Ok(data);
+    }
+    match policy {
+        CharsetPolicy::Ignore => Ok(data),
+        CharsetPolicy::Reject => throw!(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("unsupported charset `{}`", charset)
+        ),
+        #[cfg(feature = "charset")]
+        CharsetPolicy::Transcode => {
+            match encoding_rs::Encoding::for_label(charset.as_bytes()) {
+                Some(encoding) => {
+                    let (text, _) = encoding.decode_without_bom_handling(&data);
+                    Ok(text.into_owned().into_bytes())
+                }
+                None => throw!(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("unknown charset `{}`", charset)
+                ),
+            }
+        }
+    }
+}
+
+/// Check if media type of request matches `expected`.
+#[inline]
+pub(crate) fn content_type_is<S>(ctx: &Context<S>, expected: &str) -> bool {
+    ctx.get(header::CONTENT_TYPE)
+        .map(|value| media_type(value).eq_ignore_ascii_case(media_type(expected)))
+        .unwrap_or(false)
+}
+
 /// A context extension to read/write body more simply.
 #[async_trait]
 pub trait PowerBody {
+    /// check media type of request, parameters like "charset" are ignored.
+    ///
+    /// Throw 400 BAD REQUEST if "Content-Type" is missing or mismatches.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::body::PowerBody;
+    /// use serde_json::Value;
+    ///
+    /// async fn post(ctx: &mut Context) -> Result {
+    ///     ctx.expect_content_type(mime::APPLICATION_JSON)?;
+    ///     let data: Value = ctx.read_json().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    fn expect_content_type<M>(&self, mime: M) -> Result
+    where
+        M: AsRef<str>;
+
     /// read request body as Bytes.
     async fn read(&mut self) -> Result<Vec<u8>>;
 
+    /// read request body as `Bytes`.
+    ///
+    /// A body delivered in a single chunk is returned without copying,
+    /// chunks of other bodies are concatenated.
+    async fn read_body_bytes(&mut self) -> Result<Bytes>;
+
     /// read request body as "json".
+    ///
+    /// Charset is ignored and body is assumed to be utf-8,
+    /// use `read_json_with` to change the policy.
     #[cfg(feature = "json")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
     async fn read_json<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned;
 
+    /// read request body as "json", handle charset by policy.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::body::{PowerBody, CharsetPolicy};
+    /// use serde_json::Value;
+    ///
+    /// async fn post(ctx: &mut Context) -> Result {
+    ///     // throw 415 UNSUPPORTED MEDIA TYPE if charset is declared and it's not utf-8.
+    ///     let data: Value = ctx.read_json_with(CharsetPolicy::Reject).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    async fn read_json_with<B>(&mut self, policy: CharsetPolicy) -> Result<B>
+    where
+        B: DeserializeOwned;
+
+    /// read request body as newline delimited json (NDJSON) in streaming,
+    /// without buffering the whole body.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::body::PowerBody;
+    /// use futures::StreamExt;
+    /// use serde_json::Value;
+    ///
+    /// async fn ingest(ctx: &mut Context) -> Result {
+    ///     let mut records = ctx.read_ndjson::<Value>().max_line(64 * 1024);
+    ///     while let Some(record) = records.next().await {
+    ///         println!("{}", record?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    fn read_ndjson<T>(&mut self) -> NdJson<T>
+    where
+        T: DeserializeOwned;
+
+    /// read request body as "application/json-patch+json" (RFC 6902).
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" mismatches,
+    /// throw 400 BAD REQUEST if patch is malformed.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    async fn read_json_patch(&mut self) -> Result<JsonPatch>;
+
+    /// read request body as "application/merge-patch+json" (RFC 7386),
+    /// apply it by `merge_patch`.
+    ///
+    /// Throw 415 UNSUPPORTED MEDIA TYPE if "Content-Type" mismatches,
+    /// throw 400 BAD REQUEST if body is not a valid json.
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+    async fn read_merge_patch(&mut self) -> Result<serde_json::Value>;
+
     /// read request body as "urlencoded form".
+    ///
+    /// Repeated keys (like "tag=a&tag=b" or "tag[]=a&tag[]=b") are collected into sequences,
+    /// like `Vec<T>`, an empty value of `Option` field is `None`.
+    ///
+    /// Charset is ignored and body is assumed to be utf-8,
+    /// use `read_form_with` to change the policy.
     #[cfg(feature = "urlencoded")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
     async fn read_form<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned;
 
+    /// read request body as "urlencoded form", handle charset by policy.
+    #[cfg(feature = "urlencoded")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "urlencoded")))]
+    async fn read_form_with<B>(&mut self, policy: CharsetPolicy) -> Result<B>
+    where
+        B: DeserializeOwned;
+
     /// write object to response body as "application/json"
     #[cfg(feature = "json")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
@@ -147,12 +398,118 @@ pub trait PowerBody {
     where
         B: 'static + AsyncRead + Unpin + Sync + Send;
 
+    /// write records from a stream to response body as "text/csv",
+    /// fields are quoted and escaped as RFC 4180 describes.
+    ///
+    /// Records are encoded one by one as the body is polled, so the whole dataset is never buffered.
+    /// The header line is omitted if `headers` is empty.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::body::PowerBody;
+    /// use futures::stream::{self, StreamExt};
+    ///
+    /// async fn export(ctx: &mut Context) -> Result {
+    ///     let rows = stream::iter(0..1_000_000)
+    ///         .map(|id| Ok(vec![id.to_string(), format!("user {}", id)]));
+    ///     ctx.write_csv(&["id", "name"], rows);
+    ///     Ok(())
+    /// }
+    /// ```
+    fn write_csv<H, St, R>(&mut self, headers: H, rows: St)
+    where
+        H: IntoIterator,
+        H::Item: AsRef<str>,
+        St: 'static + Stream<Item = io::Result<R>> + Sync + Send,
+        R: IntoIterator,
+        R::Item: AsRef<str>;
+
+    /// write records to response body as a "text/csv" attachment with a download filename,
+    /// see `write_csv`.
+    #[cfg(feature = "file")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
+    fn download_csv<H, St, R>(&mut self, headers: H, rows: St, filename: &str) -> Result
+    where
+        H: IntoIterator,
+        H::Item: AsRef<str>,
+        St: 'static + Stream<Item = io::Result<R>> + Sync + Send,
+        R: IntoIterator,
+        R::Item: AsRef<str>;
+
+    /// Buffer response body into memory for post-processing,
+    /// return `None` if it's larger than `limit` bytes or it's a stream.
+    ///
+    /// The buffered body is left as it is, replace it by `ctx.resp.set_body`.
+    /// Streaming bodies (like server-sent events or files) are passed through untouched,
+    /// as they may never end; build the body with `ctx.write` to make it bufferable.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{App, Context, Next, Result};
+    /// use roa::body::PowerBody;
+    ///
+    /// async fn sign(ctx: &mut Context, next: Next<'_>) -> Result {
+    ///     next.await?;
+    ///     if let Some(body) = ctx.buffer_response(1024 * 1024).await? {
+    ///         let mut signed = body.to_vec();
+    ///         signed.extend_from_slice(b"\n-- signed by roa");
+    ///         ctx.resp.set_body(signed);
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().gate(sign).end("Hello, World");
+    /// ```
+    async fn buffer_response(&mut self, limit: usize) -> Result<Option<Bytes>>;
+
+    /// Stream request body to response body without buffering, "Content-Type" is copied.
+    ///
+    /// The body limit of request (set by `roa::limit::BodyLimit`) applies,
+    /// a declared "Content-Length" exceeding it gets a 413 PAYLOAD TOO LARGE before streaming.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{App, Context, Result};
+    /// use roa::body::PowerBody;
+    ///
+    /// async fn echo(ctx: &mut Context) -> Result {
+    ///     ctx.echo_body()
+    /// }
+    ///
+    /// let app = App::new().end(echo);
+    /// ```
+    fn echo_body(&mut self) -> Result;
+
     /// write object to response body as extension name of file
     #[cfg(feature = "file")]
     #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
     async fn write_file<P>(&mut self, path: P, typ: DispositionType) -> Result
     where
         P: Send + AsRef<Path>;
+
+    /// write file to response body through a `FileCache`, with a precomputed "ETag",
+    /// files too large to be cached are read from filesystem each time.
+    #[cfg(feature = "file")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
+    async fn write_cached_file<P>(
+        &mut self,
+        cache: &FileCache,
+        path: P,
+        typ: DispositionType,
+    ) -> Result
+    where
+        P: Send + AsRef<Path>;
+
+    /// write file to response body as an attachment with a download filename,
+    /// non-ascii filename will be encoded as RFC 5987 describes.
+    #[cfg(feature = "file")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "file")))]
+    async fn download_file<P>(&mut self, path: P, filename: &str) -> Result
+    where
+        P: Send + AsRef<Path>;
 }
 
 // Static header value.
@@ -164,10 +521,27 @@ lazy_static! {
     static ref TEXT_PLAIN: HeaderValue = HeaderValue::from_static("text/plain");
     static ref APPLICATION_OCTET_STREM: HeaderValue =
         HeaderValue::from_static("application/octet-stream");
+    static ref TEXT_CSV: HeaderValue =
+        HeaderValue::from_static("text/csv; charset=utf-8");
 }
 
 #[async_trait]
 impl<S: State> PowerBody for Context<S> {
+    #[inline]
+    fn expect_content_type<M>(&self, mime: M) -> Result
+    where
+        M: AsRef<str>,
+    {
+        let expected = media_type(mime.as_ref());
+        if !content_type_is(self, expected) {
+            throw!(
+                StatusCode::BAD_REQUEST,
+                format!("content type should be `{}`", expected)
+            )
+        }
+        Ok(())
+    }
+
     #[inline]
     async fn read(&mut self) -> Result<Vec<u8>> {
         let size_hint = self
@@ -177,21 +551,82 @@ impl<S: State> PowerBody for Context<S> {
             Some(hint) => Vec::with_capacity(hint),
             None => Vec::new(),
         };
-        self.req.reader().read_to_end(&mut data).await?;
+        self.req
+            .reader()
+            .read_to_end(&mut data)
+            .await
+            .map_err(handle_body_error)?;
         Ok(data)
     }
 
+    #[inline]
+    async fn read_body_bytes(&mut self) -> Result<Bytes> {
+        let size_hint: Option<usize> = self
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.parse().ok());
+        let mut stream = self.req.stream();
+        let first = match stream.next().await {
+            None => return Ok(Bytes::new()),
+            Some(chunk) => chunk.map_err(handle_body_error)?,
+        };
+        let second = match stream.next().await {
+            // zero-copy
+            None => return Ok(first),
+            Some(chunk) => chunk.map_err(handle_body_error)?,
+        };
+        let mut data = BytesMut::with_capacity(
+            size_hint.unwrap_or_else(|| first.len() + second.len()),
+        );
+        data.extend_from_slice(&first);
+        data.extend_from_slice(&second);
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk.map_err(handle_body_error)?);
+        }
+        Ok(data.freeze())
+    }
+
     #[cfg(feature = "json")]
     #[inline]
     async fn read_json<B>(&mut self) -> Result<B>
     where
         B: DeserializeOwned,
     {
-        use crate::status;
-        use http::StatusCode;
+        self.read_json_with(CharsetPolicy::default()).await
+    }
+
+    #[cfg(feature = "json")]
+    #[inline]
+    async fn read_json_with<B>(&mut self, policy: CharsetPolicy) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
         let data = self.read().await?;
-        serde_json::from_slice(&data)
-            .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+        let mut data = decode_charset(self, data, policy)?;
+        json::from_slice(json::backend(self), &mut data)
+    }
+
+    #[cfg(feature = "json")]
+    #[inline]
+    fn read_ndjson<T>(&mut self) -> NdJson<T>
+    where
+        T: DeserializeOwned,
+    {
+        NdJson::new(Box::new(self.req.stream()), json::backend(self))
+    }
+
+    #[cfg(feature = "json")]
+    #[inline]
+    async fn read_json_patch(&mut self) -> Result<JsonPatch> {
+        expect_patch_type(self, JSON_PATCH)?;
+        let value: serde_json::Value = self.read_json().await?;
+        JsonPatch::try_from(value)
+    }
+
+    #[cfg(feature = "json")]
+    #[inline]
+    async fn read_merge_patch(&mut self) -> Result<serde_json::Value> {
+        expect_patch_type(self, MERGE_PATCH)?;
+        self.read_json().await
     }
 
     #[cfg(feature = "urlencoded")]
@@ -200,11 +635,18 @@ impl<S: State> PowerBody for Context<S> {
     where
         B: DeserializeOwned,
     {
-        use crate::status;
-        use http::StatusCode;
+        self.read_form_with(CharsetPolicy::default()).await
+    }
+
+    #[cfg(feature = "urlencoded")]
+    #[inline]
+    async fn read_form_with<B>(&mut self, policy: CharsetPolicy) -> Result<B>
+    where
+        B: DeserializeOwned,
+    {
         let data = self.read().await?;
-        serde_urlencoded::from_bytes(&data)
-            .map_err(|err| status!(StatusCode::BAD_REQUEST, err))
+        let data = decode_charset(self, data, policy)?;
+        form::from_bytes(&data).map_err(|err| status!(StatusCode::BAD_REQUEST, err))
     }
 
     #[cfg(feature = "json")]
@@ -213,7 +655,7 @@ impl<S: State> PowerBody for Context<S> {
     where
         B: Serialize,
     {
-        self.resp.write(serde_json::to_vec(data)?);
+        self.resp.write(json::to_vec(json::backend(self), data)?);
         self.resp
             .headers
             .insert(header::CONTENT_TYPE, APPLICATION_JSON.clone());
@@ -255,6 +697,67 @@ impl<S: State> PowerBody for Context<S> {
             .insert(header::CONTENT_TYPE, APPLICATION_OCTET_STREM.clone());
     }
 
+    #[inline]
+    fn write_csv<H, St, R>(&mut self, headers: H, rows: St)
+    where
+        H: IntoIterator,
+        H::Item: AsRef<str>,
+        St: 'static + Stream<Item = io::Result<R>> + Sync + Send,
+        R: IntoIterator,
+        R::Item: AsRef<str>,
+    {
+        let headers: Vec<H::Item> = headers.into_iter().collect();
+        if !headers.is_empty() {
+            self.resp.write(csv::encode_record(headers));
+        }
+        self.resp.write_stream(rows.map_ok(csv::encode_record));
+        self.resp
+            .headers
+            .insert(header::CONTENT_TYPE, TEXT_CSV.clone());
+    }
+
+    #[cfg(feature = "file")]
+    #[inline]
+    fn download_csv<H, St, R>(&mut self, headers: H, rows: St, filename: &str) -> Result
+    where
+        H: IntoIterator,
+        H::Item: AsRef<str>,
+        St: 'static + Stream<Item = io::Result<R>> + Sync + Send,
+        R: IntoIterator,
+        R::Item: AsRef<str>,
+    {
+        self.write_csv(headers, rows);
+        set_attachment(self, filename)
+    }
+
+    #[inline]
+    async fn buffer_response(&mut self, limit: usize) -> Result<Option<Bytes>> {
+        Ok(match self.resp.body {
+            Body::Empty => Some(Bytes::new()),
+            Body::Once(ref bytes) if bytes.len() <= limit => Some(bytes.clone()),
+            _ => None,
+        })
+    }
+
+    #[inline]
+    fn echo_body(&mut self) -> Result {
+        if let Some(limit) = self.req.body_limit() {
+            let length = self
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.parse::<u64>().ok());
+            if length.map(|length| length > limit).unwrap_or(false) {
+                throw!(StatusCode::PAYLOAD_TOO_LARGE, PayloadTooLarge { limit })
+            }
+        }
+        if let Some(content_type) = self.req.headers.get(header::CONTENT_TYPE) {
+            let content_type = content_type.clone();
+            self.resp.headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        let stream = self.req.stream();
+        self.resp.write_stream(stream);
+        Ok(())
+    }
+
     #[cfg(feature = "file")]
     #[inline]
     async fn write_file<P>(&mut self, path: P, typ: DispositionType) -> Result
@@ -263,6 +766,29 @@ impl<S: State> PowerBody for Context<S> {
     {
         write_file(self, path, typ).await
     }
+
+    #[cfg(feature = "file")]
+    #[inline]
+    async fn write_cached_file<P>(
+        &mut self,
+        cache: &FileCache,
+        path: P,
+        typ: DispositionType,
+    ) -> Result
+    where
+        P: Send + AsRef<Path>,
+    {
+        write_cached_file(self, cache, path, typ).await
+    }
+
+    #[cfg(feature = "file")]
+    #[inline]
+    async fn download_file<P>(&mut self, path: P, filename: &str) -> Result
+    where
+        P: Send + AsRef<Path>,
+    {
+        download_file(self, path, filename).await
+    }
 }
 
 #[cfg(all(test, feature = "tcp"))]
@@ -274,7 +800,7 @@ mod tests {
     use askama::Template;
     use async_std::fs::File;
     use async_std::task::spawn;
-    use http::header::CONTENT_TYPE;
+    use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
     use http::StatusCode;
     use serde::{Deserialize, Serialize};
     use std::error::Error;
@@ -304,6 +830,136 @@ mod tests {
         name: "Hexilee",
     };
 
+    #[tokio::test]
+    async fn expect_content_type() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.expect_content_type(mime::APPLICATION_JSON)
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json; charset=utf-8")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "text/plain")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+        let resp = client.post(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_body_bytes() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read_body_bytes().await?;
+            ctx.write(data);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body("Hello, World")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+
+        // multiple chunks
+        let chunks: Vec<Result<_, std::io::Error>> =
+            vec![Ok("Hello"), Ok(", "), Ok("World")];
+        let req = http::Request::post(format!("http://{}", addr))
+            .body(hyper::Body::wrap_stream(futures::stream::iter(chunks)))?;
+        let resp = hyper::Client::new().request(req).await?;
+        let data = hyper::body::to_bytes(resp.into_body()).await?;
+        assert_eq!(&b"Hello, World"[..], &*data);
+
+        let resp = client.post(&format!("http://{}", addr)).send().await?;
+        assert!(resp.text().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn buffer_response() -> Result<(), Box<dyn Error>> {
+        use crate::Next;
+        async fn upper(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            if let Some(body) = ctx.buffer_response(8).await? {
+                ctx.resp.set_body(body.to_ascii_uppercase());
+            }
+            Ok(())
+        }
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let data = ctx.uri().path().replace('/', "");
+            if ctx.uri().query() == Some("stream") {
+                ctx.resp
+                    .write_stream(futures::stream::once(futures::future::ok(
+                        data.into(),
+                    )));
+            } else {
+                ctx.write(data);
+            }
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(upper).end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}/hello/roa", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("HELLOROA", resp.text().await?);
+        // too large
+        let resp = reqwest::get(&format!("http://{}/hello/world", addr)).await?;
+        assert_eq!("helloworld", resp.text().await?);
+        // stream
+        let resp = reqwest::get(&format!("http://{}/hello/roa?stream", addr)).await?;
+        assert_eq!("helloroa", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "sse")]
+    #[tokio::test]
+    async fn buffer_events() -> Result<(), Box<dyn Error>> {
+        use crate::sse::{Event, ServeEvents};
+        use crate::Next;
+        use futures::stream::{self, StreamExt};
+        use std::time::Duration;
+        async fn buffer(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            assert!(ctx.buffer_response(1024).await?.is_none());
+            Ok(())
+        }
+        async fn end(ctx: &mut Context) -> crate::Result {
+            // an event stream never ends
+            let events =
+                stream::iter(vec![Event::new("hello")]).chain(stream::pending());
+            ctx.write_events(events);
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(buffer).end(end).run()?;
+        spawn(server);
+        let mut resp = async_std::future::timeout(
+            Duration::from_secs(1),
+            reqwest::get(&format!("http://{}", addr)),
+        )
+        .await??;
+        assert_eq!(StatusCode::OK, resp.status());
+        let chunk =
+            async_std::future::timeout(Duration::from_secs(1), resp.chunk()).await??;
+        assert_eq!(Some("data: hello\n\n".into()), chunk);
+        Ok(())
+    }
+
     #[cfg(feature = "json")]
     #[tokio::test]
     async fn read_json() -> Result<(), Box<dyn Error>> {
@@ -325,6 +981,226 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn read_json_charset() -> Result<(), Box<dyn Error>> {
+        use super::CharsetPolicy;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_json().await?;
+            assert_eq!(USER, user);
+            Ok(())
+        }
+        async fn reject(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_json_with(CharsetPolicy::Reject).await?;
+            assert_eq!(USER, user);
+            Ok(())
+        }
+        let body = serde_json::to_vec(&USER)?;
+        let client = reqwest::Client::new();
+
+        // charset is ignored by default.
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json; charset=iso-8859-1")
+            .body(body.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let (addr, server) = App::new().end(reject).run()?;
+        spawn(server);
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json; charset=UTF-8")
+            .body(body.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json; charset=iso-8859-1")
+            .body(body)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        assert_eq!("unsupported charset `iso-8859-1`", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "json", feature = "charset"))]
+    #[tokio::test]
+    async fn read_json_transcode() -> Result<(), Box<dyn Error>> {
+        use super::CharsetPolicy;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let user: UserDto = ctx.read_json_with(CharsetPolicy::Transcode).await?;
+            assert_eq!("caf\u{e9}", user.name);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = reqwest::Client::new()
+            .get(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json; charset=iso-8859-1")
+            .body(&b"{\"id\": 0, \"name\": \"caf\xe9\"}"[..])
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn read_ndjson() -> Result<(), Box<dyn Error>> {
+        use futures::StreamExt;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut records = ctx.read_ndjson::<UserDto>().max_line(64);
+            let mut count = 0;
+            while let Some(user) = records.next().await {
+                assert_eq!(USER, user?);
+                count += 1;
+            }
+            ctx.write(count.to_string());
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let line = serde_json::to_string(&USER)?;
+
+        // blank lines and partial trailing line.
+        let body = format!("{}\r\n\n{}\n  \n{}", line, line, line);
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body(body)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("3", resp.text().await?);
+
+        // malformed line.
+        let body = format!("{}\n{{\n", line);
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body(body)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+        // line too long.
+        let body = format!("{}\n{}", line, " ".repeat(128));
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body(body)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn read_json_patch() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut doc = serde_json::json!({"id": 0, "name": "Hexilee"});
+            ctx.read_json_patch().await?.apply(&mut doc)?;
+            assert_eq!(serde_json::json!({"id": 1, "name": "Hexilee"}), doc);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .patch(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "replace", "path": "/id", "value": 1}]"#)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // malformed operation
+        let resp = client
+            .patch(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json-patch+json")
+            .body(r#"[{"op": "replace", "path": "/id"}]"#)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+        // mismatched content type
+        let resp = client
+            .patch(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/merge-patch+json")
+            .body(r#"{"id": 1}"#)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn read_merge_patch() -> Result<(), Box<dyn Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut doc = serde_json::json!({"id": 0, "name": "Hexilee"});
+            super::merge_patch(&mut doc, &ctx.read_merge_patch().await?);
+            assert_eq!(serde_json::json!({"id": 1}), doc);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+
+        let client = reqwest::Client::new();
+        let resp = client
+            .patch(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/merge-patch+json; charset=utf-8")
+            .body(r#"{"id": 1, "name": null}"#)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let resp = client
+            .patch(&format!("http://{}", addr))
+            .json(&serde_json::json!({"id": 1}))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[tokio::test]
+    async fn json_backend() -> Result<(), Box<dyn Error>> {
+        use super::JsonBackend;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let mut doc = serde_json::json!({"id": 0, "name": "Hexilee"});
+            super::merge_patch(&mut doc, &ctx.read_merge_patch().await?);
+            ctx.write_json(&doc)
+        }
+        #[allow(unused_mut)]
+        let mut backends = vec![JsonBackend::SerdeJson];
+        #[cfg(feature = "json-simd")]
+        backends.push(JsonBackend::SimdJson);
+        for backend in backends {
+            let (addr, server) = App::new().gate(backend).end(test).run()?;
+            spawn(server);
+            let resp = reqwest::Client::new()
+                .patch(&format!("http://{}", addr))
+                .header(CONTENT_TYPE, "application/merge-patch+json")
+                .body(r#"{"id": 1, "name": null}"#)
+                .send()
+                .await?;
+            assert_eq!(StatusCode::OK, resp.status());
+            assert_eq!(
+                serde_json::json!({"id": 1}),
+                resp.json::<serde_json::Value>().await?
+            );
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "urlencoded")]
     #[tokio::test]
     async fn read_form() -> Result<(), Box<dyn Error>> {
@@ -375,6 +1251,85 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "file")]
+    #[tokio::test]
+    async fn write_cached_file() -> Result<(), Box<dyn Error>> {
+        use super::{DispositionType, FileCache};
+        use http::header::ETAG;
+        let cache = FileCache::default();
+        async fn test(ctx: &mut Context<FileCache>) -> crate::Result {
+            let files = ctx.state().clone();
+            ctx.write_cached_file(
+                &files,
+                "../assets/author.txt",
+                DispositionType::Inline,
+            )
+            .await
+        }
+        let (addr, server) = App::state(cache.clone()).end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("text/plain", resp.headers()[CONTENT_TYPE]);
+        let etag = resp.headers()[ETAG].clone();
+        assert_eq!("Hexilee", resp.text().await?);
+        assert_eq!(1, cache.len());
+
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(etag, resp.headers()[ETAG]);
+        assert_eq!("Hexilee", resp.text().await?);
+        assert_eq!(1, cache.len());
+        Ok(())
+    }
+
+    #[cfg(feature = "file")]
+    #[tokio::test]
+    async fn download_file() -> Result<(), Box<dyn Error>> {
+        use http::header::CONTENT_DISPOSITION;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.download_file("../assets/author.txt", "作者.txt").await
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("text/plain", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(
+            r#"attachment; filename="__.txt"; filename*=UTF-8''%E4%BD%9C%E8%80%85.txt"#,
+            resp.headers()[CONTENT_DISPOSITION]
+        );
+        assert_eq!("Hexilee", resp.text().await?);
+        Ok(())
+    }
+
+    #[cfg(feature = "file")]
+    #[tokio::test]
+    async fn download_csv() -> Result<(), Box<dyn Error>> {
+        use futures::stream::{self, StreamExt};
+        use http::header::CONTENT_DISPOSITION;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let rows =
+                stream::iter(vec![vec!["1", "roa"], vec!["2", "say \"hi\", roa"]])
+                    .map(Ok);
+            ctx.download_csv(&["id", "name"], rows, "users.csv")
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("text/csv; charset=utf-8", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(
+            r#"attachment; filename="users.csv"; filename*=UTF-8''users.csv"#,
+            resp.headers()[CONTENT_DISPOSITION]
+        );
+        assert_eq!(
+            "id,name\r\n1,roa\r\n2,\"say \"\"hi\"\", roa\"\r\n",
+            resp.text().await?
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn write_octet() -> Result<(), Box<dyn Error>> {
         async fn test(ctx: &mut Context) -> crate::Result {
@@ -392,4 +1347,55 @@ mod tests {
         assert_eq!("Hexilee", resp.text().await?);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn echo_body() -> Result<(), Box<dyn Error>> {
+        use crate::limit::BodyLimit;
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.echo_body()
+        }
+        let (addr, server) = App::new().gate(BodyLimit::new(16)).end(test).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_TYPE, "application/json")
+            .body(r#"{"id":0}"#)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("application/json", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(r#"{"id":0}"#, resp.text().await?);
+
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body("Hello, World! ".repeat(2))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+        // without "Content-Length", the echo is cut off once body exceeds the limit.
+        use crate::Next;
+        async fn chunked(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            ctx.req.headers.remove(CONTENT_LENGTH);
+            next.await
+        }
+        let (addr, server) = App::new()
+            .gate(chunked)
+            .gate(BodyLimit::new(16))
+            .end(test)
+            .run()?;
+        spawn(server);
+        let result = match client
+            .post(&format!("http://{}", addr))
+            .body("Hello, World! ".repeat(2))
+            .send()
+            .await
+        {
+            Ok(resp) => resp.text().await.map(drop),
+            Err(err) => Err(err),
+        };
+        assert!(result.is_err());
+        Ok(())
+    }
 }
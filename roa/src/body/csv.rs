@@ -0,0 +1,44 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+/// Encode a record as a CSV line terminated by CRLF, as RFC 4180 describes.
+///
+/// Fields containing comma, double quote, CR or LF are enclosed in double quotes,
+/// and double quotes in them are escaped by preceding another double quote.
+pub(crate) fn encode_record<I>(fields: I) -> Bytes
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut line = BytesMut::new();
+    for (index, field) in fields.into_iter().enumerate() {
+        let field = field.as_ref();
+        line.reserve(field.len() + 3);
+        if index != 0 {
+            line.put_u8(b',');
+        }
+        if field.contains(|c| c == ',' || c == '"' || c == '\r' || c == '\n') {
+            line.put_u8(b'"');
+            line.put_slice(field.replace('"', r#""""#).as_bytes());
+            line.put_u8(b'"');
+        } else {
+            line.put_slice(field.as_bytes());
+        }
+    }
+    line.extend_from_slice(b"\r\n");
+    line.freeze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::encode_record;
+
+    #[test]
+    fn encode() {
+        assert_eq!("a,b,c\r\n", encode_record(&["a", "b", "c"]));
+        assert_eq!(
+            "\"a,b\",\"say \"\"hi\"\"\",\"1\r\n2\",\r\n",
+            encode_record(vec!["a,b", r#"say "hi""#, "1\r\n2", ""])
+        );
+        assert_eq!("\r\n", encode_record(Vec::<String>::new()));
+    }
+}
@@ -1,15 +1,23 @@
+mod cache;
 mod content_disposition;
 mod help;
-use crate::{http, Context, Result, State};
+use crate::etag::entity_tag;
+use crate::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG};
+use crate::{http, Body, Context, Result, State};
 
 pub use async_std::path::Path;
+pub use cache::FileCache;
 pub use content_disposition::DispositionType;
 
-use async_std::fs::File;
+use async_std::fs::{self, File};
+use cache::CachedFile;
 use content_disposition::ContentDisposition;
 use std::convert::TryInto;
 
 /// Write file to response body then set "Content-Type" and "Context-Disposition".
+///
+/// "Content-Length" is set if the file is the whole body,
+/// middlewares transforming body (like `Compress`) must remove it.
 #[inline]
 pub async fn write_file<S: State>(
     ctx: &mut Context<S>,
@@ -17,24 +25,131 @@ pub async fn write_file<S: State>(
     typ: DispositionType,
 ) -> Result {
     let path = path.as_ref();
-    ctx.resp.write_reader(File::open(path).await?);
+    write_reader(ctx, File::open(path).await?).await?;
 
     if let Some(filename) = path.file_name() {
-        ctx.resp.headers.insert(
-            http::header::CONTENT_TYPE,
-            mime_guess::from_path(&filename)
-                .first_or_octet_stream()
-                .as_ref()
-                .parse()
-                .map_err(help::bug_report)?,
-        );
-
-        let name = filename.to_string_lossy();
-        let content_disposition = ContentDisposition::new(typ, Some(&name));
-        ctx.resp.headers.insert(
-            http::header::CONTENT_DISPOSITION,
-            content_disposition.try_into()?,
-        );
+        set_file_headers(ctx, typ, &filename.to_string_lossy())?;
     }
     Ok(())
 }
+
+/// Write file to response body as an attachment named `filename`,
+/// then set "Content-Type" and "Context-Disposition".
+#[inline]
+pub async fn download_file<S: State>(
+    ctx: &mut Context<S>,
+    path: impl AsRef<Path>,
+    filename: &str,
+) -> Result {
+    write_reader(ctx, File::open(path.as_ref()).await?).await?;
+    set_file_headers(ctx, DispositionType::Attachment, filename)
+}
+
+/// Write file to response body through a file cache,
+/// then set "Content-Type", "Context-Disposition" and "ETag".
+///
+/// Files too large to be cached are written as `write_file` does.
+#[inline]
+pub async fn write_cached_file<S: State>(
+    ctx: &mut Context<S>,
+    cache: &FileCache,
+    path: impl AsRef<Path>,
+    typ: DispositionType,
+) -> Result {
+    let path = path.as_ref();
+    let filename = path
+        .file_name()
+        .map(|filename| filename.to_string_lossy().into_owned());
+    let metadata = fs::metadata(path).await?;
+    let (modified, len) = (metadata.modified()?, metadata.len());
+    let file = match cache.get(path, modified, len) {
+        Some(file) => file,
+        None if cache.accepts(len) => {
+            let data = fs::read(path).await?;
+            let file = CachedFile {
+                modified,
+                len,
+                etag: HeaderValue::from_str(&entity_tag(&data))?,
+                content_type: guess_content_type(filename.as_deref().unwrap_or(""))?,
+                data: data.into(),
+            };
+            cache.insert(path.to_path_buf(), file.clone());
+            file
+        }
+        None => return write_file(ctx, path, typ).await,
+    };
+
+    if let Body::Empty = ctx.resp.body {
+        ctx.resp
+            .headers
+            .insert(CONTENT_LENGTH, file.data.len().into());
+        ctx.resp.headers.insert(ETAG, file.etag);
+    } else {
+        ctx.resp.headers.remove(CONTENT_LENGTH);
+    }
+    ctx.resp.write(file.data);
+    ctx.resp.headers.insert(CONTENT_TYPE, file.content_type);
+    if let Some(filename) = filename {
+        set_disposition(ctx, typ, &filename)?;
+    }
+    Ok(())
+}
+
+/// Write file to response body, set "Content-Length" if the body was empty.
+#[inline]
+async fn write_reader<S>(ctx: &mut Context<S>, file: File) -> Result {
+    let len = file.metadata().await?.len();
+    let whole = matches!(ctx.resp.body, Body::Empty);
+    ctx.resp.write_reader(file);
+    if whole {
+        ctx.resp.headers.insert(CONTENT_LENGTH, len.into());
+    } else {
+        ctx.resp.headers.remove(CONTENT_LENGTH);
+    }
+    Ok(())
+}
+
+/// Set "Context-Disposition" as an attachment named `filename`.
+#[inline]
+pub(crate) fn set_attachment<S>(ctx: &mut Context<S>, filename: &str) -> Result {
+    set_disposition(ctx, DispositionType::Attachment, filename)
+}
+
+/// Guess "Content-Type" by filename.
+#[inline]
+fn guess_content_type(filename: &str) -> Result<HeaderValue> {
+    let value = mime_guess::from_path(filename)
+        .first_or_octet_stream()
+        .as_ref()
+        .parse()
+        .map_err(help::bug_report)?;
+    Ok(value)
+}
+
+/// Set "Content-Type" guessed by filename and "Context-Disposition".
+#[inline]
+fn set_file_headers<S>(
+    ctx: &mut Context<S>,
+    typ: DispositionType,
+    filename: &str,
+) -> Result {
+    ctx.resp
+        .headers
+        .insert(CONTENT_TYPE, guess_content_type(filename)?);
+    set_disposition(ctx, typ, filename)
+}
+
+/// Set "Context-Disposition".
+#[inline]
+fn set_disposition<S>(
+    ctx: &mut Context<S>,
+    typ: DispositionType,
+    filename: &str,
+) -> Result {
+    let content_disposition = ContentDisposition::new(typ, Some(filename));
+    ctx.resp.headers.insert(
+        http::header::CONTENT_DISPOSITION,
+        content_disposition.try_into()?,
+    );
+    Ok(())
+}
@@ -0,0 +1,241 @@
+use crate::http::HeaderValue;
+use async_std::path::{Path, PathBuf};
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Default size budget of a file cache, 16 MiB.
+const DEFAULT_BUDGET: usize = 16 * 1024 * 1024;
+
+/// Default size limit of a cached file, 256 KiB.
+const DEFAULT_MAX_FILE_SIZE: u64 = 256 * 1024;
+
+/// An in-memory LRU cache of small static files,
+/// keyed by path and invalidated when modification time or size of the file changes.
+///
+/// Bytes, "ETag" and "Content-Type" of a file are cached together,
+/// and total bytes of cached files never exceed the budget.
+/// Files larger than `max_file_size` are never cached.
+///
+/// It's cheap to clone and clones share the same cache, so it can be kept in state.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::body::{DispositionType, FileCache};
+/// use roa::preload::*;
+/// use roa::{App, Context, Result};
+///
+/// #[derive(Clone)]
+/// struct State {
+///     files: FileCache,
+/// }
+///
+/// async fn end(ctx: &mut Context<State>) -> Result {
+///     let files = ctx.files.clone();
+///     ctx.write_cached_file(&files, "assets/welcome.html", DispositionType::Inline)
+///         .await
+/// }
+///
+/// let state = State {
+///     files: FileCache::new(64 * 1024 * 1024).max_file_size(1024 * 1024),
+/// };
+/// let app = App::state(state).end(end);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileCache {
+    inner: Arc<Mutex<Lru>>,
+    max_file_size: u64,
+}
+
+/// A cached file.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedFile {
+    pub(crate) modified: SystemTime,
+    pub(crate) len: u64,
+    pub(crate) data: Bytes,
+    pub(crate) etag: HeaderValue,
+    pub(crate) content_type: HeaderValue,
+}
+
+/// Entries with their last used ticks, and paths ordered by last used ticks.
+#[derive(Debug, Default)]
+struct Lru {
+    budget: usize,
+    size: usize,
+    tick: u64,
+    entries: HashMap<PathBuf, (u64, CachedFile)>,
+    order: BTreeMap<u64, PathBuf>,
+}
+
+impl Default for FileCache {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_BUDGET)
+    }
+}
+
+impl FileCache {
+    /// Construct a file cache with a size budget in bytes.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Lru {
+                budget,
+                ..Default::default()
+            })),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+        }
+    }
+
+    /// Set the size limit of a cached file in bytes, 256 KiB by default.
+    pub fn max_file_size(mut self, size: u64) -> Self {
+        self.max_file_size = size;
+        self
+    }
+
+    /// Number of cached files.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    /// Check if no file is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total bytes of cached files.
+    pub fn size(&self) -> usize {
+        self.inner.lock().unwrap().size
+    }
+
+    /// Check if a file of `len` bytes should be cached.
+    #[inline]
+    pub(crate) fn accepts(&self, len: u64) -> bool {
+        len <= self.max_file_size
+    }
+
+    /// Get a cached file, stale one is removed.
+    pub(crate) fn get(
+        &self,
+        path: &Path,
+        modified: SystemTime,
+        len: u64,
+    ) -> Option<CachedFile> {
+        let mut lru = self.inner.lock().unwrap();
+        let fresh = match lru.entries.get(path) {
+            None => return None,
+            Some((_, file)) => file.modified == modified && file.len == len,
+        };
+        if fresh {
+            lru.touch(path)
+        } else {
+            lru.remove(path);
+            None
+        }
+    }
+
+    /// Cache a file, least recently used ones are evicted to fit the budget.
+    pub(crate) fn insert(&self, path: PathBuf, file: CachedFile) {
+        if !self.accepts(file.data.len() as u64) {
+            return;
+        }
+        self.inner.lock().unwrap().insert(path, file)
+    }
+}
+
+impl Lru {
+    fn touch(&mut self, path: &Path) -> Option<CachedFile> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (used, file) = self.entries.get_mut(path)?;
+        let path = self.order.remove(&*used)?;
+        *used = tick;
+        self.order.insert(tick, path);
+        Some(file.clone())
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some((used, file)) = self.entries.remove(path) {
+            self.order.remove(&used);
+            self.size -= file.data.len();
+        }
+    }
+
+    fn insert(&mut self, path: PathBuf, file: CachedFile) {
+        let len = file.data.len();
+        if len > self.budget {
+            return;
+        }
+        self.remove(&path);
+        while self.size + len > self.budget {
+            let oldest = match self.order.keys().next() {
+                Some(used) => *used,
+                None => break,
+            };
+            if let Some(path) = self.order.remove(&oldest) {
+                if let Some((_, evicted)) = self.entries.remove(&path) {
+                    self.size -= evicted.data.len();
+                }
+            }
+        }
+        self.tick += 1;
+        self.size += len;
+        self.order.insert(self.tick, path.clone());
+        self.entries.insert(path, (self.tick, file));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CachedFile, FileCache};
+    use crate::http::HeaderValue;
+    use async_std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn file(data: &'static str, modified: SystemTime) -> CachedFile {
+        CachedFile {
+            modified,
+            len: data.len() as u64,
+            data: data.into(),
+            etag: HeaderValue::from_static("\"tag\""),
+            content_type: HeaderValue::from_static("text/plain"),
+        }
+    }
+
+    #[test]
+    fn lru() {
+        let cache = FileCache::new(8);
+        let time = UNIX_EPOCH;
+        cache.insert(PathBuf::from("a"), file("aaa", time));
+        cache.insert(PathBuf::from("b"), file("bbb", time));
+        assert_eq!(6, cache.size());
+
+        // "a" is used recently, so "b" is evicted.
+        assert!(cache.get(Path::new("a"), time, 3).is_some());
+        cache.insert(PathBuf::from("c"), file("ccc", time));
+        assert_eq!(2, cache.len());
+        assert!(cache.get(Path::new("b"), time, 3).is_none());
+        assert!(cache.get(Path::new("a"), time, 3).is_some());
+        assert!(cache.get(Path::new("c"), time, 3).is_some());
+
+        // files exceeding the budget are never cached.
+        cache.insert(PathBuf::from("d"), file("ddddddddd", time));
+        assert_eq!(2, cache.len());
+        assert_eq!(6, cache.size());
+    }
+
+    #[test]
+    fn invalidate() {
+        let cache = FileCache::default().max_file_size(4);
+        let time = UNIX_EPOCH;
+        cache.insert(PathBuf::from("a"), file("aaa", time));
+        cache.insert(PathBuf::from("b"), file("bbbbb", time));
+        assert_eq!(1, cache.len());
+
+        let later = time + Duration::from_secs(1);
+        assert!(cache.get(Path::new("a"), later, 3).is_none());
+        assert!(cache.is_empty());
+        assert_eq!(0, cache.size());
+    }
+}
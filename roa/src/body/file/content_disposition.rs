@@ -42,7 +42,7 @@ pub enum DispositionType {
 /// A structure to generate value of "Content-Disposition"
 pub struct ContentDisposition {
     typ: DispositionType,
-    encoded_filename: Option<String>,
+    filename: Option<(String, String)>,
 }
 
 impl ContentDisposition {
@@ -51,12 +51,34 @@ impl ContentDisposition {
     pub(crate) fn new(typ: DispositionType, filename: Option<&str>) -> Self {
         Self {
             typ,
-            encoded_filename: filename
-                .map(|name| utf8_percent_encode(name, HTTP_VALUE).to_string()),
+            filename: filename.map(|name| {
+                (
+                    ascii_fallback(name),
+                    utf8_percent_encode(name, HTTP_VALUE).to_string(),
+                )
+            }),
         }
     }
 }
 
+/// Generate an ascii fallback of filename as a quoted-string,
+/// non-ascii or control characters are replaced with `_`.
+#[inline]
+fn ascii_fallback(filename: &str) -> String {
+    let mut fallback = String::with_capacity(filename.len());
+    for c in filename.chars() {
+        match c {
+            '"' | '\\' => {
+                fallback.push('\\');
+                fallback.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => fallback.push(c),
+            _ => fallback.push('_'),
+        }
+    }
+    fallback
+}
+
 impl TryFrom<ContentDisposition> for HeaderValue {
     type Error = Status;
     #[inline]
@@ -71,11 +93,11 @@ impl TryFrom<ContentDisposition> for HeaderValue {
 impl Display for ContentDisposition {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match &self.encoded_filename {
+        match &self.filename {
             None => f.write_fmt(format_args!("{}", self.typ)),
-            Some(name) => f.write_fmt(format_args!(
-                "{}; filename={}; filename*=UTF-8''{}",
-                self.typ, name, name
+            Some((fallback, encoded)) => f.write_fmt(format_args!(
+                r#"{}; filename="{}"; filename*=UTF-8''{}"#,
+                self.typ, fallback, encoded
             )),
         }
     }
@@ -90,3 +112,31 @@ impl Display for DispositionType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ContentDisposition, DispositionType};
+
+    #[test]
+    fn content_disposition() {
+        assert_eq!(
+            "inline",
+            ContentDisposition::new(DispositionType::Inline, None).to_string()
+        );
+        assert_eq!(
+            r#"attachment; filename="author.txt"; filename*=UTF-8''author.txt"#,
+            ContentDisposition::new(DispositionType::Attachment, Some("author.txt"))
+                .to_string()
+        );
+        assert_eq!(
+            r#"attachment; filename="__.txt"; filename*=UTF-8''%E4%BD%9C%E8%80%85.txt"#,
+            ContentDisposition::new(DispositionType::Attachment, Some("作者.txt"))
+                .to_string()
+        );
+        assert_eq!(
+            r#"attachment; filename="a\"b.txt"; filename*=UTF-8''a%22b.txt"#,
+            ContentDisposition::new(DispositionType::Attachment, Some("a\"b.txt"))
+                .to_string()
+        );
+    }
+}
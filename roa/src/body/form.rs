@@ -0,0 +1,397 @@
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_urlencoded::de::Error;
+use std::collections::HashMap;
+use std::iter::once;
+
+/// Deserialize an urlencoded form, pairs are decoded by `serde_urlencoded`.
+///
+/// - Repeated keys, like "tag=a&tag=b", are collected into sequences.
+/// - Keys with suffix "[]", like "tag[]=a&tag[]=b", are treated as repeated keys without suffix.
+/// - A single value is a sequence of one element, and the last one wins if a scalar is expected.
+/// - A missing key of `Option` field is `None`, and an empty value is `Some("")`.
+pub(crate) fn from_bytes<T>(input: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let pairs: Vec<(String, String)> = serde_urlencoded::from_bytes(input)?;
+    let mut fields: Vec<(String, Values)> = Vec::new();
+    let mut indexes = HashMap::new();
+    for (mut key, value) in pairs {
+        if key.ends_with("[]") {
+            key.truncate(key.len() - 2);
+        }
+        match indexes.get(&key) {
+            Some(&index) => {
+                let (_, Values(values)) = &mut fields[index];
+                values.push(value);
+            }
+            None => {
+                indexes.insert(key.clone(), fields.len());
+                fields.push((key, Values(vec![value])));
+            }
+        }
+    }
+    T::deserialize(MapDeserializer::new(fields.into_iter()))
+}
+
+/// All values of a key.
+struct Values(Vec<String>);
+
+/// A single value.
+struct Part(String);
+
+impl Values {
+    #[inline]
+    fn last(mut self) -> Part {
+        Part(self.0.pop().unwrap_or_default())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Values {
+    type Deserializer = Self;
+    #[inline]
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Part {
+    type Deserializer = Self;
+    #[inline]
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_to_last {
+    ($($method:ident)*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.last().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Values {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.len() == 1 {
+            self.last().deserialize_any(visitor)
+        } else {
+            self.deserialize_seq(visitor)
+        }
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer::new(self.0.into_iter().map(Part)))
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        // values exist only if the key is present.
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last().deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_last! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_map
+        deserialize_identifier deserialize_ignored_any
+    }
+
+    #[inline]
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last().deserialize_unit_struct(name, visitor)
+    }
+
+    #[inline]
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last().deserialize_struct(name, fields, visitor)
+    }
+}
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident)*) => {
+        $(
+            #[inline]
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                visitor.$visit(self.0.parse().map_err(de::Error::custom)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Part {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.0)
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer::new(once(self)))
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool
+        deserialize_i8 => visit_i8
+        deserialize_i16 => visit_i16
+        deserialize_i32 => visit_i32
+        deserialize_i64 => visit_i64
+        deserialize_u8 => visit_u8
+        deserialize_u16 => visit_u16
+        deserialize_u32 => visit_u32
+        deserialize_u64 => visit_u64
+        deserialize_f32 => visit_f32
+        deserialize_f64 => visit_f64
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_bytes;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Color {
+        Red,
+        Blue,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Form {
+        name: String,
+        tags: Vec<String>,
+        ids: Vec<u32>,
+        single: Vec<String>,
+        colors: Vec<Color>,
+        age: Option<u8>,
+        nickname: Option<String>,
+        checked: bool,
+        missing: Option<String>,
+        empty: String,
+    }
+
+    #[test]
+    fn multi_value() -> Result<(), Box<dyn std::error::Error>> {
+        let form: Form = from_bytes(
+            b"name=roa&tags=a&tags=b%20c&ids[]=1&ids%5B%5D=2&single=x\
+              &colors=red&colors=blue&age=7&nickname=&checked=true&empty=&name=last",
+        )?;
+        assert_eq!(
+            Form {
+                name: "last".into(),
+                tags: vec!["a".into(), "b c".into()],
+                ids: vec![1, 2],
+                single: vec!["x".into()],
+                colors: vec![Color::Red, Color::Blue],
+                age: Some(7),
+                nickname: Some("".into()),
+                checked: true,
+                missing: None,
+                empty: "".into(),
+            },
+            form
+        );
+        assert!(from_bytes::<Form>(b"name=roa&ids=x").is_err());
+        // an empty value is not a number.
+        assert!(from_bytes::<Form>(b"name=roa&age=").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn nested_option() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Form {
+            ids: Option<Vec<u32>>,
+            names: Vec<Option<String>>,
+            age: Option<u8>,
+            missing: Option<Vec<u32>>,
+        }
+        let form: Form = from_bytes(b"ids=1&ids=2&names=a&names=&names[]=b&age=&age=7")?;
+        assert_eq!(
+            Form {
+                ids: Some(vec![1, 2]),
+                names: vec![Some("a".into()), Some("".into()), Some("b".into())],
+                age: Some(7),
+                missing: None,
+            },
+            form
+        );
+        let form: Form = from_bytes(b"names=")?;
+        assert_eq!(None, form.ids);
+        assert_eq!(vec![Some("".to_string())], form.names);
+        Ok(())
+    }
+
+    #[test]
+    fn decode() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Form {
+            text: String,
+            words: Vec<String>,
+        }
+        let form: Form = from_bytes(
+            b"text=Hello%2C+World%21&words=a+b&words%5B%5D=c%26d&words=%E4%BD%A0",
+        )?;
+        assert_eq!("Hello, World!", form.text);
+        assert_eq!(vec!["a b", "c&d", "\u{4f60}"], form.words);
+
+        // invalid utf-8 is decoded lossily.
+        let form: Form = from_bytes(b"text=%FF&words=\xff")?;
+        assert_eq!("\u{fffd}", form.text);
+        assert_eq!(vec!["\u{fffd}"], form.words);
+        Ok(())
+    }
+
+    #[test]
+    fn enum_variant() -> Result<(), Box<dyn std::error::Error>> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Form {
+            color: Color,
+            favorite: Option<Color>,
+        }
+        let form: Form = from_bytes(b"color=red&color=blue&favorite=red")?;
+        assert_eq!(
+            Form {
+                color: Color::Blue,
+                favorite: Some(Color::Red),
+            },
+            form
+        );
+        let err = from_bytes::<Form>(b"color=green").unwrap_err();
+        assert!(err.to_string().contains("unknown variant `green`"));
+        Ok(())
+    }
+}
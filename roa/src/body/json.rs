@@ -0,0 +1,88 @@
+use crate::http::StatusCode;
+use crate::{async_trait, status, Context, Middleware, Next, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Key of json backend.
+const BACKEND: &str = "backend";
+
+/// Scope of json backend.
+struct JsonScope;
+
+/// A json backend to (de)serialize bodies by `PowerBody`, `SerdeJson` by default.
+///
+/// It's a middleware switching the backend of downstream, handlers stay the same.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::body::JsonBackend;
+/// use roa::App;
+///
+/// let app = App::new().gate(JsonBackend::SerdeJson).end("Hello, World");
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum JsonBackend {
+    /// `serde_json`.
+    SerdeJson,
+
+    /// `simd-json`, which is faster on large documents.
+    #[cfg(feature = "json-simd")]
+    #[cfg_attr(feature = "docs", doc(cfg(feature = "json-simd")))]
+    SimdJson,
+}
+
+impl Default for JsonBackend {
+    #[inline]
+    fn default() -> Self {
+        JsonBackend::SerdeJson
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for JsonBackend {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        ctx.store_scoped(JsonScope, BACKEND, *self);
+        next.await
+    }
+}
+
+/// Get json backend of this request.
+#[inline]
+pub(crate) fn backend<S>(ctx: &Context<S>) -> JsonBackend {
+    ctx.load_scoped::<JsonScope, JsonBackend>(BACKEND)
+        .map(|backend| *backend)
+        .unwrap_or_default()
+}
+
+/// Decode json, 400 BAD REQUEST if it fails.
+///
+/// `simd-json` parses in place, so data may be modified.
+#[inline]
+pub(crate) fn from_slice<T: DeserializeOwned>(
+    backend: JsonBackend,
+    data: &mut [u8],
+) -> Result<T> {
+    match backend {
+        JsonBackend::SerdeJson => serde_json::from_slice(data)
+            .map_err(|err| status!(StatusCode::BAD_REQUEST, err)),
+        #[cfg(feature = "json-simd")]
+        JsonBackend::SimdJson => simd_json::serde::from_slice(data)
+            .map_err(|err| status!(StatusCode::BAD_REQUEST, err)),
+    }
+}
+
+/// Encode json.
+#[inline]
+pub(crate) fn to_vec<T: ?Sized + Serialize>(
+    backend: JsonBackend,
+    data: &T,
+) -> Result<Vec<u8>> {
+    match backend {
+        JsonBackend::SerdeJson => Ok(serde_json::to_vec(data)?),
+        #[cfg(feature = "json-simd")]
+        JsonBackend::SimdJson => Ok(simd_json::serde::to_vec(data)?),
+    }
+}
@@ -0,0 +1,147 @@
+use super::{handle_body_error, json, JsonBackend, PayloadTooLarge};
+use crate::http::StatusCode;
+use crate::{status, Result, Status};
+use bytes::Bytes;
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{self, Poll};
+
+/// Default limit of each line, 1 MiB.
+const DEFAULT_MAX_LINE: usize = 1024 * 1024;
+
+/// Request body stream.
+type BodyStream = Box<dyn Stream<Item = io::Result<Bytes>> + Sync + Send + Unpin>;
+
+/// A stream decoding request body as newline delimited json (NDJSON),
+/// yields a record for each line.
+///
+/// Blank lines are skipped, and the trailing line without "\n" is decoded as well.
+///
+/// - A line fails to be decoded yields a 400 BAD REQUEST.
+/// - A line exceeds `max_line` (default 1 MiB) yields a 413 PAYLOAD TOO LARGE.
+/// - Body exceeds `max_total` (unlimited by default) yields a 413 PAYLOAD TOO LARGE.
+///
+/// The stream ends after yielding an error.
+pub struct NdJson<T> {
+    body: BodyStream,
+    backend: JsonBackend,
+    buffer: Vec<u8>,
+    max_line: usize,
+    max_total: Option<u64>,
+    total: u64,
+    eof: bool,
+    finished: bool,
+    _record: PhantomData<fn() -> T>,
+}
+
+impl<T> NdJson<T> {
+    /// Construct a stream from request body.
+    pub(super) fn new(body: BodyStream, backend: JsonBackend) -> Self {
+        Self {
+            body,
+            backend,
+            buffer: Vec::new(),
+            max_line: DEFAULT_MAX_LINE,
+            max_total: None,
+            total: 0,
+            eof: false,
+            finished: false,
+            _record: PhantomData,
+        }
+    }
+
+    /// Set limit of each line in bytes.
+    pub fn max_line(mut self, bytes: usize) -> Self {
+        self.max_line = bytes;
+        self
+    }
+
+    /// Set limit of total body in bytes.
+    pub fn max_total(mut self, bytes: u64) -> Self {
+        self.max_total = Some(bytes);
+        self
+    }
+
+    /// Take a complete line from buffer, or the rest if body reaches eof.
+    #[inline]
+    fn take_line(&mut self) -> Option<Vec<u8>> {
+        match self.buffer.iter().position(|byte| *byte == b'\n') {
+            Some(index) => {
+                let mut line: Vec<u8> = self.buffer.drain(..=index).collect();
+                line.pop();
+                Some(line)
+            }
+            None if self.eof && !self.buffer.is_empty() => {
+                Some(std::mem::take(&mut self.buffer))
+            }
+            None => None,
+        }
+    }
+
+    /// Yield an error and end the stream.
+    #[inline]
+    fn fail(&mut self, status: Status) -> Poll<Option<Result<T>>> {
+        self.finished = true;
+        Poll::Ready(Some(Err(status)))
+    }
+
+    /// Yield a 413 PAYLOAD TOO LARGE and end the stream.
+    #[inline]
+    fn too_large(&mut self, limit: u64) -> Poll<Option<Result<T>>> {
+        self.fail(status!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            PayloadTooLarge { limit }
+        ))
+    }
+}
+
+impl<T: DeserializeOwned> Stream for NdJson<T> {
+    type Item = Result<T>;
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+            if let Some(mut line) = self.take_line() {
+                if line.len() > self.max_line {
+                    let limit = self.max_line as u64;
+                    return self.too_large(limit);
+                }
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return match json::from_slice(self.backend, &mut line) {
+                    Ok(record) => Poll::Ready(Some(Ok(record))),
+                    Err(status) => self.fail(status),
+                };
+            }
+            if self.eof {
+                self.finished = true;
+                continue;
+            }
+            if self.buffer.len() > self.max_line {
+                let limit = self.max_line as u64;
+                return self.too_large(limit);
+            }
+            match futures::ready!(Pin::new(&mut self.body).poll_next(cx)) {
+                Some(Ok(bytes)) => {
+                    self.total += bytes.len() as u64;
+                    if let Some(limit) = self.max_total {
+                        if self.total > limit {
+                            return self.too_large(limit);
+                        }
+                    }
+                    self.buffer.extend_from_slice(&bytes);
+                }
+                Some(Err(err)) => return self.fail(handle_body_error(err)),
+                None => self.eof = true,
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use super::content_type_is;
+use crate::http::StatusCode;
+use crate::{throw, Context, Result, Status};
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+/// Media type of JSON Patch (RFC 6902).
+pub const JSON_PATCH: &str = "application/json-patch+json";
+
+/// Media type of JSON Merge Patch (RFC 7386).
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+
+/// An operation of JSON Patch (RFC 6902).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    /// Add a value to an object or insert it into an array.
+    Add {
+        /// Target location.
+        path: String,
+        /// Value to add.
+        value: Value,
+    },
+
+    /// Remove the value at the target location.
+    Remove {
+        /// Target location.
+        path: String,
+    },
+
+    /// Replace the value at the target location.
+    Replace {
+        /// Target location.
+        path: String,
+        /// Value to replace with.
+        value: Value,
+    },
+
+    /// Remove the value at a location and add it to the target location.
+    Move {
+        /// Source location.
+        from: String,
+        /// Target location.
+        path: String,
+    },
+
+    /// Copy the value at a location to the target location.
+    Copy {
+        /// Source location.
+        from: String,
+        /// Target location.
+        path: String,
+    },
+
+    /// Test that the value at the target location is equal to a specified value.
+    Test {
+        /// Target location.
+        path: String,
+        /// Expected value.
+        value: Value,
+    },
+}
+
+/// A JSON Patch document (RFC 6902), a sequence of operations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPatch(pub Vec<PatchOperation>);
+
+/// Throw a 400 BAD REQUEST for malformed patch.
+#[inline]
+fn malformed(message: impl ToString) -> Status {
+    Status::new(
+        StatusCode::BAD_REQUEST,
+        format!("{}\nmalformed json patch", message.to_string()),
+        true,
+    )
+}
+
+/// Throw a 422 UNPROCESSABLE ENTITY for patch failing to apply.
+#[inline]
+fn unprocessable(message: impl ToString) -> Status {
+    Status::new(StatusCode::UNPROCESSABLE_ENTITY, message, true)
+}
+
+/// Take a string member from an operation object.
+#[inline]
+fn take_str(op: &mut Map<String, Value>, key: &str) -> Result<String> {
+    match op.remove(key) {
+        Some(Value::String(value)) => Ok(value),
+        _ => Err(malformed(format!("member `{}` should be a string", key))),
+    }
+}
+
+/// Take a value member from an operation object.
+#[inline]
+fn take_value(op: &mut Map<String, Value>) -> Result<Value> {
+    op.remove("value")
+        .ok_or_else(|| malformed("member `value` is required"))
+}
+
+impl TryFrom<Value> for PatchOperation {
+    type Error = Status;
+    fn try_from(value: Value) -> Result<Self> {
+        let mut op = match value {
+            Value::Object(op) => op,
+            _ => return Err(malformed("operation should be an object")),
+        };
+        let operation = match take_str(&mut op, "op")?.as_str() {
+            "add" => PatchOperation::Add {
+                path: take_str(&mut op, "path")?,
+                value: take_value(&mut op)?,
+            },
+            "remove" => PatchOperation::Remove {
+                path: take_str(&mut op, "path")?,
+            },
+            "replace" => PatchOperation::Replace {
+                path: take_str(&mut op, "path")?,
+                value: take_value(&mut op)?,
+            },
+            "move" => PatchOperation::Move {
+                from: take_str(&mut op, "from")?,
+                path: take_str(&mut op, "path")?,
+            },
+            "copy" => PatchOperation::Copy {
+                from: take_str(&mut op, "from")?,
+                path: take_str(&mut op, "path")?,
+            },
+            "test" => PatchOperation::Test {
+                path: take_str(&mut op, "path")?,
+                value: take_value(&mut op)?,
+            },
+            op => return Err(malformed(format!("unknown operation `{}`", op))),
+        };
+        Ok(operation)
+    }
+}
+
+impl TryFrom<Value> for JsonPatch {
+    type Error = Status;
+    fn try_from(value: Value) -> Result<Self> {
+        match value {
+            Value::Array(ops) => Ok(JsonPatch(
+                ops.into_iter()
+                    .map(PatchOperation::try_from)
+                    .collect::<Result<_>>()?,
+            )),
+            _ => Err(malformed("json patch should be an array")),
+        }
+    }
+}
+
+impl JsonPatch {
+    /// Apply this patch to a document.
+    ///
+    /// Operations are applied atomically, the document won't be modified if any operation fails.
+    /// A failed "test" operation returns 409 CONFLICT, other failures return 422 UNPROCESSABLE ENTITY.
+    pub fn apply(&self, doc: &mut Value) -> Result {
+        let mut patched = doc.clone();
+        for op in self.0.iter() {
+            op.apply(&mut patched)?;
+        }
+        *doc = patched;
+        Ok(())
+    }
+}
+
+impl PatchOperation {
+    /// Apply this operation to a document.
+    fn apply(&self, doc: &mut Value) -> Result {
+        match self {
+            PatchOperation::Add { path, value } => add(doc, path, value.clone()),
+            PatchOperation::Remove { path } => remove(doc, path).map(|_| ()),
+            PatchOperation::Replace { path, value } => match doc.pointer_mut(path) {
+                Some(target) => {
+                    *target = value.clone();
+                    Ok(())
+                }
+                None => Err(unprocessable(format!("path `{}` not found", path))),
+            },
+            PatchOperation::Move { from, path } => {
+                if path.starts_with(&format!("{}/", from)) {
+                    throw!(
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("cannot move `{}` into its child `{}`", from, path)
+                    )
+                }
+                let value = remove(doc, from)?;
+                add(doc, path, value)
+            }
+            PatchOperation::Copy { from, path } => match doc.pointer(from) {
+                Some(value) => {
+                    let value = value.clone();
+                    add(doc, path, value)
+                }
+                None => Err(unprocessable(format!("path `{}` not found", from))),
+            },
+            PatchOperation::Test { path, value } => match doc.pointer(path) {
+                Some(target) if target == value => Ok(()),
+                _ => throw!(
+                    StatusCode::CONFLICT,
+                    format!("test on path `{}` failed", path)
+                ),
+            },
+        }
+    }
+}
+
+/// Split a json pointer into parent pointer and unescaped last token.
+#[inline]
+fn split_pointer(path: &str) -> Result<(&str, String)> {
+    match path.rfind('/') {
+        Some(index) if path.starts_with('/') => Ok((
+            &path[..index],
+            path[index + 1..].replace("~1", "/").replace("~0", "~"),
+        )),
+        _ => Err(unprocessable(format!("invalid json pointer `{}`", path))),
+    }
+}
+
+/// Parse an array index.
+#[inline]
+fn parse_index(token: &str, len: usize) -> Result<usize> {
+    match token.parse::<usize>() {
+        Ok(index) if index <= len && (token == "0" || !token.starts_with('0')) => {
+            Ok(index)
+        }
+        _ => Err(unprocessable(format!("invalid array index `{}`", token))),
+    }
+}
+
+/// Add a value at path.
+fn add(doc: &mut Value, path: &str, value: Value) -> Result {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => {
+            let index = if token == "-" {
+                array.len()
+            } else {
+                parse_index(&token, array.len())?
+            };
+            array.insert(index, value);
+            Ok(())
+        }
+        _ => Err(unprocessable(format!("path `{}` not found", parent))),
+    }
+}
+
+/// Remove the value at path.
+fn remove(doc: &mut Value, path: &str) -> Result<Value> {
+    let (parent, token) = split_pointer(path)?;
+    let removed = match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&token),
+        Some(Value::Array(array)) => {
+            let index = parse_index(&token, array.len())?;
+            if index < array.len() {
+                Some(array.remove(index))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| unprocessable(format!("path `{}` not found", path)))
+}
+
+/// Apply a JSON Merge Patch (RFC 7386) to a document.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::body::merge_patch;
+/// use serde_json::json;
+///
+/// let mut doc = json!({"title": "Hello", "author": {"name": "Hexilee", "email": "i@hexilee.me"}});
+/// merge_patch(&mut doc, &json!({"title": "Hi", "author": {"email": null}}));
+/// assert_eq!(json!({"title": "Hi", "author": {"name": "Hexilee"}}), doc);
+/// ```
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    match patch {
+        Value::Object(patch) => {
+            if !target.is_object() {
+                *target = Value::Object(Map::new());
+            }
+            if let Value::Object(target) = target {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        target.remove(key);
+                    } else {
+                        merge_patch(
+                            target.entry(key.as_str()).or_insert(Value::Null),
+                            value,
+                        );
+                    }
+                }
+            }
+        }
+        patch => *target = patch.clone(),
+    }
+}
+
+/// Check media type of request, throw 415 UNSUPPORTED MEDIA TYPE if it mismatches.
+#[inline]
+pub fn expect_patch_type<S>(ctx: &Context<S>, expected: &str) -> Result {
+    if !content_type_is(ctx, expected) {
+        throw!(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("content type should be `{}`", expected)
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_patch, JsonPatch};
+    use crate::http::StatusCode;
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn apply_patch() {
+        let mut doc = json!({"baz": "qux", "foo": "bar", "list": [1, 2]});
+        let patch = JsonPatch::try_from(json!([
+            {"op": "test", "path": "/baz", "value": "qux"},
+            {"op": "replace", "path": "/baz", "value": "boo"},
+            {"op": "add", "path": "/hello", "value": ["world"]},
+            {"op": "add", "path": "/list/-", "value": 3},
+            {"op": "add", "path": "/list/0", "value": 0},
+            {"op": "remove", "path": "/foo"},
+            {"op": "copy", "from": "/baz", "path": "/a~1b"},
+            {"op": "move", "from": "/hello", "path": "/world"},
+        ]))
+        .unwrap();
+        patch.apply(&mut doc).unwrap();
+        assert_eq!(
+            json!({"baz": "boo", "a/b": "boo", "world": ["world"], "list": [0, 1, 2, 3]}),
+            doc
+        );
+    }
+
+    #[test]
+    fn apply_patch_atomically() {
+        let mut doc = json!({"foo": "bar"});
+        let patch = JsonPatch::try_from(json!([
+            {"op": "remove", "path": "/foo"},
+            {"op": "test", "path": "/foo", "value": "bar"},
+        ]))
+        .unwrap();
+        let status = patch.apply(&mut doc).unwrap_err();
+        assert_eq!(StatusCode::CONFLICT, status.status_code);
+        assert_eq!(json!({"foo": "bar"}), doc);
+
+        let patch =
+            JsonPatch::try_from(json!([{"op": "remove", "path": "/missing"}])).unwrap();
+        let status = patch.apply(&mut doc).unwrap_err();
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status.status_code);
+    }
+
+    #[test]
+    fn malformed_patch() {
+        for patch in vec![
+            json!({"op": "add"}),
+            json!([{"op": "unknown", "path": "/"}]),
+            json!([{"op": "add", "path": "/"}]),
+            json!([{"op": "move", "path": "/"}]),
+        ] {
+            let status = JsonPatch::try_from(patch).unwrap_err();
+            assert_eq!(StatusCode::BAD_REQUEST, status.status_code);
+        }
+    }
+
+    #[test]
+    fn apply_merge_patch() {
+        let mut doc = json!({"a": "b", "c": {"d": "e", "f": "g"}});
+        merge_patch(&mut doc, &json!({"a": "z", "c": {"f": null}}));
+        assert_eq!(json!({"a": "z", "c": {"d": "e"}}), doc);
+        merge_patch(&mut doc, &json!(["array"]));
+        assert_eq!(json!(["array"]), doc);
+    }
+}
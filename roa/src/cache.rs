@@ -0,0 +1,777 @@
+//! This module provides a middleware `ResponseCache`, a default store `MemoryStore`
+//! and a context extension `SetCacheControl`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::cache::ResponseCache;
+//! use roa::http::header::ACCEPT_LANGUAGE;
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     // an expensive query.
+//!     ctx.write("Hello, World");
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let cache = ResponseCache::new(Duration::from_secs(60)).vary(ACCEPT_LANGUAGE);
+//! let app = App::new().gate(cache).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::http::header::{
+    HeaderMap, HeaderName, HeaderValue, CACHE_CONTROL, SET_COOKIE, VARY,
+};
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, Body, Context, Middleware, Next, Result};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default capacity of `MemoryStore`.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Statuses cacheable by default, defined in RFC 7231.
+const CACHEABLE: [StatusCode; 10] = [
+    StatusCode::OK,
+    StatusCode::NON_AUTHORITATIVE_INFORMATION,
+    StatusCode::NO_CONTENT,
+    StatusCode::MULTIPLE_CHOICES,
+    StatusCode::MOVED_PERMANENTLY,
+    StatusCode::NOT_FOUND,
+    StatusCode::METHOD_NOT_ALLOWED,
+    StatusCode::GONE,
+    StatusCode::URI_TOO_LONG,
+    StatusCode::NOT_IMPLEMENTED,
+];
+
+/// A buffered response.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// Status code.
+    pub status: StatusCode,
+
+    /// Response headers.
+    pub headers: HeaderMap,
+
+    /// Response body.
+    pub body: Bytes,
+
+    /// The instant this response expires.
+    pub expires: Instant,
+}
+
+/// A store of cached responses.
+pub trait CacheStore: 'static + Sync + Send {
+    /// Get a cached response by key, expired responses are ignored by `ResponseCache`.
+    fn get(&self, key: &str) -> Option<Arc<CachedResponse>>;
+
+    /// Put a response.
+    fn put(&self, key: String, response: Arc<CachedResponse>);
+
+    /// Remove a response, `ResponseCache` removes expired responses once it gets them.
+    fn remove(&self, _key: &str) {}
+}
+
+/// An in-memory store, the least recently used response is evicted when it's full.
+pub struct MemoryStore {
+    capacity: usize,
+    inner: Mutex<Lru>,
+}
+
+/// Entries with their last used ticks, and keys ordered by last used ticks.
+#[derive(Default)]
+struct Lru {
+    tick: u64,
+    entries: HashMap<String, (u64, Arc<CachedResponse>)>,
+    order: BTreeMap<u64, String>,
+}
+
+/// A middleware to cache responses of idempotent GET and HEAD requests.
+///
+/// Responses are keyed by method, path, query and headers declared by `vary`,
+/// and replayed until they expire.
+///
+/// "Vary" of responses is honored as well: a response varying by some request headers
+/// is replayed only to requests with the same values of them,
+/// and a response with "Vary: *" is not cached.
+///
+/// - Requests with "Cache-Control: no-cache" bypass the cache, and the fresh response is cached.
+/// - Requests with "Cache-Control: no-store" bypass the cache, and the response is not cached.
+/// - Only cacheable statuses (like 200 OK and 301 MOVED PERMANENTLY) are cached.
+/// - Responses with "Set-Cookie", or "Cache-Control: no-store" or "private" are not cached.
+pub struct ResponseCache {
+    ttl: Duration,
+    vary: Vec<HeaderName>,
+    store: Arc<dyn CacheStore>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryStore {
+    /// Construct a store with capacity.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Lru::default()),
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Lru {
+    /// Mark an entry as the most recently used one.
+    #[inline]
+    fn touch(&mut self, key: &str) -> Option<Arc<CachedResponse>> {
+        self.tick += 1;
+        let tick = self.tick;
+        let (used, response) = self.entries.get_mut(key)?;
+        let key = self.order.remove(&*used)?;
+        *used = tick;
+        self.order.insert(tick, key);
+        Some(response.clone())
+    }
+
+    #[inline]
+    fn remove(&mut self, key: &str) {
+        if let Some((used, _)) = self.entries.remove(key) {
+            self.order.remove(&used);
+        }
+    }
+
+    #[inline]
+    fn insert(&mut self, key: String, response: Arc<CachedResponse>, capacity: usize) {
+        self.remove(&key);
+        while self.entries.len() >= capacity {
+            let oldest = match self.order.keys().next() {
+                Some(used) => *used,
+                None => break,
+            };
+            if let Some(key) = self.order.remove(&oldest) {
+                self.entries.remove(&key);
+            }
+        }
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (self.tick, response));
+    }
+}
+
+impl CacheStore for MemoryStore {
+    #[inline]
+    fn get(&self, key: &str) -> Option<Arc<CachedResponse>> {
+        self.lock().touch(key)
+    }
+
+    #[inline]
+    fn put(&self, key: String, response: Arc<CachedResponse>) {
+        if self.capacity > 0 {
+            self.lock().insert(key, response, self.capacity)
+        }
+    }
+
+    #[inline]
+    fn remove(&self, key: &str) {
+        self.lock().remove(key)
+    }
+}
+
+impl ResponseCache {
+    /// Construct a middleware caching responses for `ttl`, with a `MemoryStore` of 1024 entries.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            vary: Vec::new(),
+            store: Arc::new(MemoryStore::new(DEFAULT_CAPACITY)),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Add a request header to cache key, responses vary by it.
+    pub fn vary(mut self, name: HeaderName) -> Self {
+        self.vary.push(name);
+        self
+    }
+
+    /// Set the store.
+    pub fn store(mut self, store: impl CacheStore) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Set the clock to read time.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Build cache key of a request.
+    #[inline]
+    fn key<S>(&self, ctx: &Context<S>) -> String {
+        let mut key = format!("{} {}", ctx.method(), ctx.uri());
+        for name in self.vary.iter() {
+            key.push('\n');
+            key.push_str(name.as_str());
+            key.push(':');
+            key.push_str(&ctx.get_joined(name).unwrap_or_default());
+        }
+        key
+    }
+}
+
+/// Parse "Vary" of a header map into lowercase header names, `None` if it's "*".
+#[inline]
+fn vary_names(headers: &HeaderMap) -> Option<Vec<String>> {
+    let mut names = Vec::new();
+    for name in headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+    {
+        if name == "*" {
+            return None;
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Some(names)
+}
+
+/// Build key of a variant by request headers named by "Vary".
+#[inline]
+fn variant_key<S>(ctx: &Context<S>, base: &str, names: &[String]) -> String {
+    let mut key = format!("{}\nvary", base);
+    for name in names {
+        key.push('\n');
+        key.push_str(name);
+        key.push(':');
+        key.push_str(&ctx.get_joined(name.as_str()).unwrap_or_default());
+    }
+    key
+}
+
+/// Collect headers set or changed by downstream, "Vary" is always kept for variant lookup.
+///
+/// Headers set by upstream, like default headers of app, are left out of cache
+/// so they are fresh on every hit.
+#[inline]
+fn downstream_headers(upstream: &HeaderMap, headers: &HeaderMap) -> HeaderMap {
+    let mut changed = HeaderMap::new();
+    for name in headers.keys() {
+        if name == VARY
+            || !headers
+                .get_all(name)
+                .iter()
+                .eq(upstream.get_all(name).iter())
+        {
+            for value in headers.get_all(name) {
+                changed.append(name.clone(), value.clone());
+            }
+        }
+    }
+    changed
+}
+
+/// Replay cached headers onto response, headers set by upstream are kept
+/// unless they are overridden by cached ones.
+#[inline]
+fn replay_headers(headers: &mut HeaderMap, cached: &HeaderMap) {
+    for name in cached.keys() {
+        headers.remove(name);
+        for value in cached.get_all(name) {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+}
+
+/// Check if "Cache-Control" of a header map contains any of directives.
+#[inline]
+fn cache_control(headers: &HeaderMap, directives: &[&str]) -> bool {
+    headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| {
+            let directive = directive.trim();
+            directives
+                .iter()
+                .any(|expected| directive.eq_ignore_ascii_case(expected))
+        })
+}
+
+/// Check if a response is cacheable.
+#[inline]
+fn cacheable<S>(ctx: &Context<S>) -> bool {
+    CACHEABLE.contains(&ctx.resp.status)
+        && !ctx.resp.headers.contains_key(SET_COOKIE)
+        && !cache_control(&ctx.resp.headers, &["no-store", "private"])
+        && vary_names(&ctx.resp.headers).is_some()
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for ResponseCache {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        if *ctx.method() != Method::GET && *ctx.method() != Method::HEAD {
+            return next.await;
+        }
+        if cache_control(&ctx.req.headers, &["no-store"]) {
+            return next.await;
+        }
+        let key = self.key(ctx);
+        if !cache_control(&ctx.req.headers, &["no-cache"]) {
+            // the entry of base key declares "Vary" of the latest response,
+            // find the variant matching this request if it varies.
+            let cached = self.store.get(&key).and_then(|cached| {
+                match vary_names(&cached.headers) {
+                    Some(ref names) if names.is_empty() => Some((key.clone(), cached)),
+                    Some(names) => {
+                        let variant = variant_key(ctx, &key, &names);
+                        let cached = self.store.get(&variant)?;
+                        Some((variant, cached))
+                    }
+                    None => None,
+                }
+            });
+            if let Some((hit, cached)) = cached {
+                if cached.expires > self.clock.now() {
+                    ctx.resp.status = cached.status;
+                    replay_headers(&mut ctx.resp.headers, &cached.headers);
+                    ctx.resp.body = Body::Once(cached.body.clone());
+                    return Ok(());
+                }
+                // evict the expired one.
+                self.store.remove(&hit);
+            }
+        }
+        let upstream = ctx.resp.headers.clone();
+        next.await?;
+        if !cacheable(ctx) {
+            return Ok(());
+        }
+        let body = match std::mem::take(&mut ctx.resp.body) {
+            Body::Empty => Bytes::new(),
+            Body::Once(bytes) => bytes,
+            mut body => {
+                let mut data = Vec::new();
+                while let Some(chunk) = body.next().await {
+                    data.extend_from_slice(&chunk?);
+                }
+                data.into()
+            }
+        };
+        ctx.resp.body = Body::Once(body.clone());
+        let cached = CachedResponse {
+            status: ctx.resp.status,
+            headers: downstream_headers(&upstream, &ctx.resp.headers),
+            body,
+            expires: self.clock.now() + self.ttl,
+        };
+        let cached = Arc::new(cached);
+        if let Some(names) = vary_names(&ctx.resp.headers) {
+            if !names.is_empty() {
+                self.store
+                    .put(variant_key(ctx, &key, &names), cached.clone());
+            }
+        }
+        self.store.put(key, cached);
+        Ok(())
+    }
+}
+
+/// Directives of response header "Cache-Control".
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cache::CacheControl;
+/// use std::time::Duration;
+///
+/// let control = CacheControl {
+///     max_age: Some(Duration::from_secs(60)),
+///     must_revalidate: true,
+///     ..CacheControl::default()
+/// };
+/// assert_eq!("max-age=60, must-revalidate", control.to_string());
+/// assert_eq!("no-store", CacheControl::no_store().to_string());
+/// assert_eq!(
+///     "public, max-age=3600",
+///     CacheControl::public(Duration::from_secs(3600)).to_string()
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheControl {
+    /// "public", the response may be stored by shared caches.
+    pub public: bool,
+
+    /// "private", the response may be stored only by the browser.
+    pub private: bool,
+
+    /// "no-cache", the response must be validated before each reuse.
+    pub no_cache: bool,
+
+    /// "no-store", the response must not be stored.
+    pub no_store: bool,
+
+    /// "max-age", in seconds.
+    pub max_age: Option<Duration>,
+
+    /// "s-maxage", max age for shared caches, in seconds.
+    pub s_maxage: Option<Duration>,
+
+    /// "must-revalidate", the stale response must be validated before reuse.
+    pub must_revalidate: bool,
+
+    /// "immutable", the response will not change while it's fresh.
+    pub immutable: bool,
+}
+
+impl CacheControl {
+    /// "no-store".
+    pub fn no_store() -> Self {
+        Self {
+            no_store: true,
+            ..Self::default()
+        }
+    }
+
+    /// "public, max-age={max_age}".
+    pub fn public(max_age: Duration) -> Self {
+        Self {
+            public: true,
+            max_age: Some(max_age),
+            ..Self::default()
+        }
+    }
+}
+
+impl Display for CacheControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut directives = Vec::new();
+        if self.public {
+            directives.push("public".to_string());
+        }
+        if self.private {
+            directives.push("private".to_string());
+        }
+        if self.no_cache {
+            directives.push("no-cache".to_string());
+        }
+        if self.no_store {
+            directives.push("no-store".to_string());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+        if let Some(s_maxage) = self.s_maxage {
+            directives.push(format!("s-maxage={}", s_maxage.as_secs()));
+        }
+        if self.must_revalidate {
+            directives.push("must-revalidate".to_string());
+        }
+        if self.immutable {
+            directives.push("immutable".to_string());
+        }
+        f.write_str(&directives.join(", "))
+    }
+}
+
+/// A context extension to set "Cache-Control" of response.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cache::CacheControl;
+/// use roa::preload::*;
+/// use roa::{Context, Result};
+/// use std::time::Duration;
+///
+/// async fn get(ctx: &mut Context) -> Result {
+///     ctx.cache_control(CacheControl::public(Duration::from_secs(3600)));
+///     ctx.write("Hello, World");
+///     Ok(())
+/// }
+/// ```
+pub trait SetCacheControl {
+    /// Set "Cache-Control", replacing the existing one.
+    /// Nothing is set if there is no directive.
+    fn cache_control(&mut self, control: CacheControl);
+}
+
+impl<S> SetCacheControl for Context<S> {
+    #[inline]
+    fn cache_control(&mut self, control: CacheControl) {
+        let value = control.to_string();
+        if value.is_empty() {
+            self.resp.headers.remove(CACHE_CONTROL);
+        } else {
+            // directives are visible ascii.
+            let value = HeaderValue::from_str(&value).expect("invalid cache control");
+            self.resp.headers.insert(CACHE_CONTROL, value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{CacheControl, CacheStore, MemoryStore, ResponseCache};
+    use crate::clock::MockClock;
+    use crate::http::header::{
+        HeaderValue, ACCEPT_LANGUAGE, CACHE_CONTROL, SERVER, SET_COOKIE, VARY,
+    };
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{endpoint_fn, middleware_fn, App, Context};
+    use async_std::task::spawn;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn memory_store() {
+        use super::CachedResponse;
+        use crate::http::HeaderMap;
+        use std::time::Instant;
+        let store = MemoryStore::new(2);
+        let response = Arc::new(CachedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: "Hello".into(),
+            expires: Instant::now(),
+        });
+        store.put("a".to_string(), response.clone());
+        store.put("b".to_string(), response.clone());
+        assert!(store.get("a").is_some());
+        // "b" is the least recently used one.
+        store.put("c".to_string(), response);
+        assert!(store.get("a").is_some());
+        assert!(store.get("b").is_none());
+        assert!(store.get("c").is_some());
+        store.remove("a");
+        assert!(store.get("a").is_none());
+        assert_eq!(1, store.lock().entries.len());
+        assert_eq!(1, store.lock().order.len());
+    }
+
+    #[tokio::test]
+    async fn response_cache() -> Result<(), Box<dyn std::error::Error>> {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let clock = MockClock::new();
+        let count = counter.clone();
+        let end = endpoint_fn(move |ctx: &mut Context| {
+            let count = count.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                if ctx.uri().path() == "/cookie" {
+                    ctx.resp.headers.insert(SET_COOKIE, "id=1".parse()?);
+                }
+                let lang = ctx.get(ACCEPT_LANGUAGE).unwrap_or_default().to_string();
+                ctx.write(format!("{} {}", lang, count));
+                Ok(())
+            })
+        });
+        let app = App::new()
+            .gate(
+                ResponseCache::new(Duration::from_secs(60))
+                    .vary(ACCEPT_LANGUAGE)
+                    .clock(clock.clone()),
+            )
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let get = |path: &str, lang: &str, control: &str| {
+            client
+                .get(&format!("http://{}{}", addr, path))
+                .header(ACCEPT_LANGUAGE, lang)
+                .header(CACHE_CONTROL, control)
+                .send()
+        };
+        assert_eq!("en 1", get("/", "en", "").await?.text().await?);
+        assert_eq!("en 1", get("/", "en", "").await?.text().await?);
+        // vary by "Accept-Language".
+        assert_eq!("zh 2", get("/", "zh", "").await?.text().await?);
+        // bypass by "no-cache".
+        assert_eq!("en 3", get("/", "en", "no-cache").await?.text().await?);
+        assert_eq!("en 3", get("/", "en", "").await?.text().await?);
+        // expired.
+        clock.advance(Duration::from_secs(60));
+        assert_eq!("en 4", get("/", "en", "").await?.text().await?);
+        // "Set-Cookie" is not cached.
+        assert_eq!("en 5", get("/cookie", "en", "").await?.text().await?);
+        assert_eq!("en 6", get("/cookie", "en", "").await?.text().await?);
+        assert_eq!(6, counter.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evict_expired() -> Result<(), Box<dyn std::error::Error>> {
+        use super::CachedResponse;
+        struct Counted {
+            inner: MemoryStore,
+            removed: AtomicUsize,
+        }
+        impl CacheStore for Arc<Counted> {
+            fn get(&self, key: &str) -> Option<Arc<CachedResponse>> {
+                self.inner.get(key)
+            }
+            fn put(&self, key: String, response: Arc<CachedResponse>) {
+                self.inner.put(key, response)
+            }
+            fn remove(&self, key: &str) {
+                self.removed.fetch_add(1, Ordering::SeqCst);
+                self.inner.remove(key)
+            }
+        }
+        let store = Arc::new(Counted {
+            inner: MemoryStore::new(16),
+            removed: AtomicUsize::new(0),
+        });
+        let clock = MockClock::new();
+        let app = App::new()
+            .gate(
+                ResponseCache::new(Duration::from_secs(60))
+                    .store(store.clone())
+                    .clock(clock.clone()),
+            )
+            .end("Hello, World");
+        let (addr, server) = app.run()?;
+        spawn(server);
+        reqwest::get(&format!("http://{}", addr)).await?;
+        reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(0, store.removed.load(Ordering::SeqCst));
+        clock.advance(Duration::from_secs(60));
+        reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(1, store.removed.load(Ordering::SeqCst));
+        assert!(store.inner.get("GET /").is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keep_headers() -> Result<(), Box<dyn std::error::Error>> {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        async fn end(ctx: &mut Context) -> crate::Result {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            ctx.resp.headers.append(VARY, "x-a".parse()?);
+            ctx.resp.headers.append(VARY, "x-b".parse()?);
+            ctx.write("Hello, World");
+            Ok(())
+        }
+        let counter = Arc::new(AtomicUsize::new(0));
+        let outer = middleware_fn(move |ctx: &mut Context, next| {
+            let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                ctx.resp
+                    .headers
+                    .insert("x-request-id", count.to_string().parse()?);
+                next.await
+            })
+        });
+        let app = App::new()
+            .default_headers(vec![(SERVER, HeaderValue::from_static("roa"))])
+            .gate(outer)
+            .gate(ResponseCache::new(Duration::from_secs(60)))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        for id in 1..=2 {
+            let resp = reqwest::get(&format!("http://{}", addr)).await?;
+            assert_eq!("roa", resp.headers()[SERVER]);
+            assert_eq!(id.to_string(), resp.headers()["x-request-id"]);
+            assert_eq!(2, resp.headers().get_all(VARY).iter().count());
+            assert_eq!("Hello, World", resp.text().await?);
+        }
+        assert_eq!(1, CALLS.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn vary() -> Result<(), Box<dyn std::error::Error>> {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let count = counter.clone();
+        let end = endpoint_fn(move |ctx: &mut Context| {
+            let count = count.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                let vary = if ctx.uri().path() == "/any" {
+                    "*"
+                } else {
+                    "X-Flavor, Accept-Language"
+                };
+                ctx.resp.headers.insert(VARY, vary.parse()?);
+                let flavor = ctx.get("x-flavor").unwrap_or_default().to_string();
+                let lang = ctx.get(ACCEPT_LANGUAGE).unwrap_or_default().to_string();
+                ctx.write(format!("{} {} {}", flavor, lang, count));
+                Ok(())
+            })
+        });
+        let app = App::new()
+            .gate(ResponseCache::new(Duration::from_secs(60)))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let get = |path: &str, flavor: &str, lang: &str| {
+            client
+                .get(&format!("http://{}{}", addr, path))
+                .header("x-flavor", flavor)
+                .header(ACCEPT_LANGUAGE, lang)
+                .send()
+        };
+        assert_eq!("a en 1", get("/", "a", "en").await?.text().await?);
+        assert_eq!("a en 1", get("/", "a", "en").await?.text().await?);
+        // vary by "X-Flavor" and "Accept-Language" declared by response.
+        assert_eq!("b en 2", get("/", "b", "en").await?.text().await?);
+        assert_eq!("a zh 3", get("/", "a", "zh").await?.text().await?);
+        assert_eq!("a en 1", get("/", "a", "en").await?.text().await?);
+        assert_eq!("b en 2", get("/", "b", "en").await?.text().await?);
+        // "Vary: *" is not cached.
+        assert_eq!("a en 4", get("/any", "a", "en").await?.text().await?);
+        assert_eq!("a en 5", get("/any", "a", "en").await?.text().await?);
+        assert_eq!(5, counter.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn set_cache_control() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.insert(CACHE_CONTROL, "private".parse()?);
+            match ctx.req.uri.query() {
+                Some("store") => {
+                    ctx.cache_control(CacheControl::public(Duration::from_secs(60)))
+                }
+                Some("empty") => ctx.cache_control(CacheControl::default()),
+                _ => ctx.cache_control(CacheControl::no_store()),
+            }
+            Ok(())
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}?store", addr)).await?;
+        assert_eq!("public, max-age=60", resp.headers()[CACHE_CONTROL]);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!("no-store", resp.headers()[CACHE_CONTROL]);
+        let resp = reqwest::get(&format!("http://{}?empty", addr)).await?;
+        assert!(resp.headers().get(CACHE_CONTROL).is_none());
+        Ok(())
+    }
+}
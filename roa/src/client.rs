@@ -0,0 +1,360 @@
+//! This module provides an upstream client `Upstream`,
+//! which maps upstream failures to proper status.
+//!
+//! `Upstream::with_exec` constructs a client on the runtime of app, by `ctx.exec`,
+//! its connections are established by a runtime-agnostic `Connector`.
+//!
+//! Feature "client" also enables "tcp" and "runtime" of hyper, so `hyper::Client::new()` is available,
+//! however, it requires a tokio runtime.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::client::Upstream;
+//! use roa::{App, Context};
+//! use std::time::Duration;
+//!
+//! async fn proxy(ctx: &mut Context) -> roa::Result {
+//!     let users = Upstream::with_exec(ctx.exec.clone())
+//!         .retries(2)
+//!         .timeout(Duration::from_secs(5));
+//!     // 502 BAD GATEWAY or 504 GATEWAY TIMEOUT on failure.
+//!     let resp = users.get("http://127.0.0.1:8000/users").await?;
+//!     ctx.resp.status = resp.status();
+//!     ctx.resp.write(hyper::body::to_bytes(resp.into_body()).await?);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().end(proxy);
+//! ```
+
+use crate::http::request::Parts;
+use crate::http::{Request, Response, StatusCode, Uri};
+use crate::Executor;
+use crate::{status, Result, Status};
+use async_std::net::TcpStream;
+use bytes::Bytes;
+use futures::future::{select, Either};
+use futures::io::{AsyncRead, AsyncWrite};
+use futures_timer::Delay;
+use hyper::client::connect::{Connect, Connected, Connection};
+use hyper::service::Service;
+use hyper::{Body, Client};
+use std::future::Future;
+use std::io;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead as TokioRead, AsyncWrite as TokioWrite};
+
+/// Default timeout of each try, 30 seconds.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A wrapper of hyper client, with retries and timeout of each try.
+///
+/// - Connection failures are retried, and map to 502 BAD GATEWAY.
+/// - Timeouts of idempotent requests are retried, and map to 504 GATEWAY TIMEOUT.
+/// - Other failures are not retried, and map to 502 BAD GATEWAY.
+///
+/// Responses of upstream, including 5xx, are returned as they are.
+#[derive(Debug, Clone)]
+pub struct Upstream<C> {
+    client: Client<C, Body>,
+    retries: usize,
+    timeout: Duration,
+}
+
+/// A runtime-agnostic connector of plain http, based on async-std.
+#[derive(Debug, Clone, Default)]
+pub struct Connector;
+
+/// A connection established by `Connector`.
+#[derive(Debug)]
+pub struct HttpStream(TcpStream);
+
+type ConnectFuture =
+    Pin<Box<dyn 'static + Send + Future<Output = io::Result<HttpStream>>>>;
+
+/// Result of one try.
+enum Failure {
+    Timeout,
+    Hyper(hyper::Error),
+}
+
+impl<C> Upstream<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// Construct an upstream client without retry.
+    pub fn new(client: Client<C, Body>) -> Self {
+        Self {
+            client,
+            retries: 0,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set number of retries, default 0.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set timeout of each try, default 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a GET request.
+    pub async fn get(&self, uri: &str) -> Result<Response<Body>> {
+        let uri: Uri = uri
+            .parse()
+            .map_err(|err| status!(StatusCode::INTERNAL_SERVER_ERROR, err, false))?;
+        let mut req = Request::new(Bytes::new());
+        *req.uri_mut() = uri;
+        self.send(req).await
+    }
+
+    /// Send a request, the body is buffered so that it can be retried.
+    pub async fn send(&self, req: Request<Bytes>) -> Result<Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let mut tries = 0;
+        loop {
+            let failure = match self.try_send(&parts, body.clone()).await {
+                Ok(resp) => return Ok(resp),
+                Err(failure) => failure,
+            };
+            let retryable = match failure {
+                Failure::Timeout => parts.method.is_idempotent(),
+                Failure::Hyper(ref err) => err.is_connect(),
+            };
+            if !retryable || tries >= self.retries {
+                return Err(failure.into_status(&parts.uri));
+            }
+            tries += 1;
+        }
+    }
+
+    /// Send request once.
+    async fn try_send(
+        &self,
+        parts: &Parts,
+        body: Bytes,
+    ) -> std::result::Result<Response<Body>, Failure> {
+        let mut req = Request::new(Body::from(body));
+        *req.method_mut() = parts.method.clone();
+        *req.uri_mut() = parts.uri.clone();
+        *req.version_mut() = parts.version;
+        *req.headers_mut() = parts.headers.clone();
+        match select(self.client.request(req), Delay::new(self.timeout)).await {
+            Either::Left((Ok(resp), _)) => Ok(resp),
+            Either::Left((Err(err), _)) => Err(Failure::Hyper(err)),
+            Either::Right(_) => Err(Failure::Timeout),
+        }
+    }
+}
+
+impl Upstream<Connector> {
+    /// Construct an upstream client without retry, on the runtime of app.
+    ///
+    /// Idle connections are pooled, and they are never closed by a timer.
+    pub fn with_exec(exec: Executor) -> Self {
+        Self::new(
+            Client::builder()
+                .executor(exec)
+                .pool_idle_timeout(None::<Duration>)
+                .build(Connector),
+        )
+    }
+}
+
+impl Service<Uri> for Connector {
+    type Response = HttpStream;
+    type Error = io::Error;
+    type Future = ConnectFuture;
+
+    #[inline]
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    #[inline]
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        Box::pin(async move {
+            if uri.scheme_str() != Some("http") {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported scheme of `{}`, only http is supported", uri),
+                ));
+            }
+            let host = uri
+                .host()
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("host of `{}` is missing", uri),
+                    )
+                })?
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            let stream =
+                TcpStream::connect((host, uri.port_u16().unwrap_or(80))).await?;
+            stream.set_nodelay(true)?;
+            Ok(HttpStream(stream))
+        })
+    }
+}
+
+impl Connection for HttpStream {
+    #[inline]
+    fn connected(&self) -> Connected {
+        Connected::new()
+    }
+}
+
+impl TokioRead for HttpStream {
+    #[inline]
+    unsafe fn prepare_uninitialized_buffer(&self, _buf: &mut [MaybeUninit<u8>]) -> bool {
+        false
+    }
+
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl TokioWrite for HttpStream {
+    #[inline]
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_close(cx)
+    }
+}
+
+impl Failure {
+    /// Map failure to status.
+    #[inline]
+    fn into_status(self, uri: &Uri) -> Status {
+        match self {
+            Failure::Timeout => Status::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                format!("upstream `{}` timed out", uri),
+                false,
+            ),
+            Failure::Hyper(err) => Status::new(
+                StatusCode::BAD_GATEWAY,
+                format!("upstream `{}` failed: {}", uri, err),
+                false,
+            ),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::Upstream;
+    use crate::http::{Request, StatusCode};
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::net::TcpStream;
+    use async_std::task::spawn;
+    use bytes::Bytes;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use futures_timer::Delay;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    async fn slow(_ctx: &mut Context) -> crate::Result {
+        Delay::new(Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    async fn proxy(ctx: &mut Context<SocketAddr>) -> crate::Result {
+        let upstream = Upstream::with_exec(ctx.exec.clone());
+        let resp = upstream.get(&format!("http://{}", *ctx)).await?;
+        ctx.resp.status = resp.status();
+        ctx.resp
+            .write(hyper::body::to_bytes(resp.into_body()).await?);
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn proxy_without_tokio() -> Result<(), Box<dyn std::error::Error>> {
+        let (upstream_addr, server) = App::new().end("Hello, World").run()?;
+        spawn(server);
+        let (addr, server) = App::state(upstream_addr).end(proxy).run()?;
+        spawn(server);
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").await?;
+        let mut data = String::new();
+        stream.read_to_string(&mut data).await?;
+        assert!(data.starts_with("HTTP/1.0 200 OK"));
+        assert!(data.ends_with("Hello, World"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upstream() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().end("Hello, World").run()?;
+        spawn(server);
+        let upstream = Upstream::new(hyper::Client::new());
+        let resp = upstream.get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let body = hyper::body::to_bytes(resp.into_body()).await?;
+        assert_eq!(&b"Hello, World"[..], &*body);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bad_gateway() -> Result<(), Box<dyn std::error::Error>> {
+        // bind and drop to get an unused port.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let upstream = Upstream::new(hyper::Client::new()).retries(2);
+        let status = upstream.get(&format!("http://{}", addr)).await.unwrap_err();
+        assert_eq!(StatusCode::BAD_GATEWAY, status.status_code);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn gateway_timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().end(slow).run()?;
+        spawn(server);
+        let upstream =
+            Upstream::new(hyper::Client::new()).timeout(Duration::from_millis(50));
+        let status = upstream.get(&format!("http://{}", addr)).await.unwrap_err();
+        assert_eq!(StatusCode::GATEWAY_TIMEOUT, status.status_code);
+
+        let mut req = Request::new(Bytes::new());
+        *req.uri_mut() = format!("http://{}", addr).parse()?;
+        let upstream = upstream.timeout(Duration::from_secs(5));
+        assert_eq!(StatusCode::OK, upstream.send(req).await?.status());
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+//! This module provides a time abstraction `Clock` for time-based middlewares,
+//! with a `SystemClock` by default and a `MockClock` for tests.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::clock::MockClock;
+//! use roa::limit::RateLimit;
+//! use std::time::Duration;
+//!
+//! let clock = MockClock::new();
+//! let limit = RateLimit::new(100, Duration::from_secs(60)).clock(clock.clone());
+//! // a new window begins.
+//! clock.advance(Duration::from_secs(60));
+//! ```
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A source of current time.
+pub trait Clock: 'static + Sync + Send {
+    /// Get current monotonic time.
+    fn now(&self) -> Instant;
+
+    /// Get current system time.
+    fn system_now(&self) -> SystemTime;
+}
+
+/// A clock reading time of system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+/// A clock moving only when it's advanced, to test time-based middlewares deterministically.
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock(Arc<Mutex<(Instant, SystemTime)>>);
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[inline]
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl MockClock {
+    /// Construct a clock starting at current time.
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new((Instant::now(), SystemTime::now()))))
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        time.0 += duration;
+        time.1 += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .0
+    }
+
+    #[inline]
+    fn system_now(&self) -> SystemTime {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .1
+    }
+}
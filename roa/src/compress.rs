@@ -1,4 +1,5 @@
-//! This module provides a middleware `Compress`.
+//! This module provides a middleware `Compress`,
+//! and a context extension `ServeUpstream` to serve encoded upstream responses.
 //!
 //! ### Example
 //!
@@ -23,59 +24,435 @@
 
 pub use async_compression::Level;
 
-use crate::http::{header::CONTENT_ENCODING, StatusCode};
-use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use crate::filter::media_type_matches;
+use crate::http::header::{
+    GetAll, HeaderName, HeaderValue, ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING,
+    CONTENT_LENGTH, CONTENT_TYPE, TE, TRANSFER_ENCODING,
+};
+use crate::http::{Response, StatusCode, Version};
+use crate::negotiate::append_vary;
+use crate::{async_trait, throw, Body, Context, Middleware, Next, Result, Status};
 use accept_encoding::{parse, Encoding};
-use async_compression::stream::{BrotliEncoder, GzipEncoder, ZlibEncoder, ZstdEncoder};
+use async_compression::stream::{
+    BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder, ZlibDecoder, ZlibEncoder,
+    ZstdDecoder, ZstdEncoder,
+};
 
 /// A middleware to negotiate with client and compress response body automatically,
 /// supports gzip, deflate, brotli, zstd and identity.
+///
+/// "Content-Length" is removed once body is compressed,
+/// the response will be sent in chunked encoding.
+///
+/// Responses already encoded (with "Content-Encoding" other than identity), like proxied ones,
+/// are left untouched to avoid double encoding.
+///
+/// Options like `level_for` and `transfer_encoding` turn it into a `Compressor`.
 #[derive(Debug, Copy, Clone)]
 pub struct Compress(pub Level);
 
+/// A `Compress` with options, built by `Compress::level_for` or `Compress::transfer_encoding`.
+///
+/// If `transfer_encoding` is enabled, an HTTP/1.1 request accepting gzip by "TE"
+/// gets a hop-by-hop "Transfer-Encoding: gzip, chunked" instead of "Content-Encoding",
+/// so the representation cached by proxies stays unencoded.
+///
+/// Compression level can be tuned by "Content-Type" of response with `level_for`,
+/// types not listed are compressed with the global level.
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    level: Level,
+    levels: Vec<(String, Level)>,
+    transfer_encoding: bool,
+}
+
+impl Compress {
+    /// Set compression level for responses of a media type pattern,
+    /// like "text/html" or "text/*". The first matching pattern wins.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::compress::{Compress, Level};
+    ///
+    /// let compress = Compress(Level::Default)
+    ///     .level_for("text/*", Level::Best)
+    ///     .level_for("application/json", Level::Best)
+    ///     .level_for("application/octet-stream", Level::Fastest);
+    /// ```
+    pub fn level_for(self, pattern: impl AsRef<str>, level: Level) -> Compressor {
+        Compressor::from(self).level_for(pattern, level)
+    }
+
+    /// Honor "TE" of request and compress by "Transfer-Encoding" if it accepts gzip,
+    /// disabled by default.
+    pub fn transfer_encoding(self, enable: bool) -> Compressor {
+        Compressor::from(self).transfer_encoding(enable)
+    }
+}
+
 impl Default for Compress {
     fn default() -> Self {
         Self(Level::Default)
     }
 }
 
+impl Compressor {
+    /// Set compression level for responses of a media type pattern,
+    /// like "text/html" or "text/*". The first matching pattern wins.
+    pub fn level_for(mut self, pattern: impl AsRef<str>, level: Level) -> Self {
+        self.levels
+            .push((pattern.as_ref().trim().to_ascii_lowercase(), level));
+        self
+    }
+
+    /// Honor "TE" of request and compress by "Transfer-Encoding" if it accepts gzip,
+    /// disabled by default.
+    pub fn transfer_encoding(mut self, enable: bool) -> Self {
+        self.transfer_encoding = enable;
+        self
+    }
+}
+
+impl From<Compress> for Compressor {
+    #[inline]
+    fn from(Compress(level): Compress) -> Self {
+        Self {
+            level,
+            levels: Vec::new(),
+            transfer_encoding: false,
+        }
+    }
+}
+
+/// Check if "TE" of request accepts gzip, which means "q" is absent or non-zero.
+#[inline]
+fn te_accepts_gzip<S>(ctx: &Context<S>) -> bool {
+    coding_quality(ctx, TE, "gzip")
+        .map(|q| q > 0.0)
+        .unwrap_or(false)
+}
+
+/// Get quality of the first item of a request header matching coding,
+/// `None` if there isn't one. A malformed "q" is treated as zero.
+#[inline]
+fn coding_quality<S>(ctx: &Context<S>, name: HeaderName, expected: &str) -> Option<f32> {
+    ctx.header_all(name)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|item| {
+            let mut params = item.split(';');
+            let coding = params.next().unwrap_or_default().trim();
+            if !coding.eq_ignore_ascii_case(expected) {
+                return None;
+            }
+            let quality = params
+                .map(str::trim)
+                .find(|param| param.len() > 2 && param[..2].eq_ignore_ascii_case("q="))
+                .map(|param| param[2..].trim().parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            Some(quality)
+        })
+}
+
+/// Get quality of a content coding by "Accept-Encoding",
+/// only identity is acceptable if "Accept-Encoding" is absent.
+#[inline]
+fn accept_quality<S>(ctx: &Context<S>, coding: &str) -> f32 {
+    if !ctx.req.headers.contains_key(ACCEPT_ENCODING) {
+        return if coding == "identity" { 1.0 } else { 0.0 };
+    }
+    coding_quality(ctx, ACCEPT_ENCODING, coding)
+        .or_else(|| coding_quality(ctx, ACCEPT_ENCODING, "*"))
+        .unwrap_or(if coding == "identity" { 1.0 } else { 0.0 })
+}
+
+impl Compressor {
+    /// Get compression level by "Content-Type" of response.
+    #[inline]
+    fn level_of<S>(&self, ctx: &Context<S>) -> Level {
+        let media_type = ctx
+            .resp
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(str::trim)
+            .unwrap_or_default();
+        self.level_of_type(media_type)
+    }
+
+    /// Get compression level of a media type.
+    #[inline]
+    fn level_of_type(&self, media_type: &str) -> Level {
+        self.levels
+            .iter()
+            .find(|(pattern, _)| media_type_matches(media_type, pattern))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.level)
+    }
+}
+
+/// Check if response body is already encoded.
+#[inline]
+fn is_encoded<S>(ctx: &Context<S>) -> bool {
+    ctx.resp
+        .headers
+        .get_all(CONTENT_ENCODING)
+        .iter()
+        .any(|value| {
+            value
+                .to_str()
+                .map(|encodings| {
+                    encodings.split(',').any(|encoding| {
+                        !encoding.trim().eq_ignore_ascii_case("identity")
+                    })
+                })
+                .unwrap_or(true)
+        })
+}
+
 #[async_trait(?Send)]
 impl<'a, S> Middleware<'a, S> for Compress {
     #[allow(clippy::trivially_copy_pass_by_ref)]
     #[inline]
     async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
-        next.await?;
-        let level = self.0;
-        let best_encoding = parse(&ctx.req.headers)
-            .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err, true))?;
+        compress(&Compressor::from(*self), ctx, next).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Compressor {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        compress(self, ctx, next).await
+    }
+}
+
+/// Compress response body with options.
+async fn compress<S>(
+    options: &Compressor,
+    ctx: &mut Context<S>,
+    next: Next<'_>,
+) -> Result {
+    next.await?;
+    if is_encoded(ctx) {
+        return Ok(());
+    }
+    let level = options.level_of(ctx);
+    if options.transfer_encoding
+        && ctx.req.version == Version::HTTP_11
+        && !ctx.resp.headers.contains_key(TRANSFER_ENCODING)
+        && te_accepts_gzip(ctx)
+    {
         let body = std::mem::take(&mut ctx.resp.body);
-        let content_encoding = match best_encoding {
-            None | Some(Encoding::Gzip) => {
-                ctx.resp
-                    .write_stream(GzipEncoder::with_quality(body, level));
-                Encoding::Gzip.to_header_value()
+        ctx.resp.headers.remove(CONTENT_LENGTH);
+        ctx.resp
+            .write_stream(GzipEncoder::with_quality(body, level));
+        ctx.resp
+            .headers
+            .insert(TRANSFER_ENCODING, HeaderValue::from_static("gzip, chunked"));
+        return Ok(());
+    }
+    let best_encoding = parse(&ctx.req.headers)
+        .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err, true))?;
+    let body = std::mem::take(&mut ctx.resp.body);
+    // length of compressed body is unknown.
+    let content_length = ctx.resp.headers.remove(CONTENT_LENGTH);
+    let content_encoding = match best_encoding {
+        None | Some(Encoding::Gzip) => {
+            ctx.resp
+                .write_stream(GzipEncoder::with_quality(body, level));
+            Encoding::Gzip.to_header_value()
+        }
+        Some(Encoding::Deflate) => {
+            ctx.resp
+                .write_stream(ZlibEncoder::with_quality(body, level));
+            Encoding::Deflate.to_header_value()
+        }
+        Some(Encoding::Brotli) => {
+            ctx.resp
+                .write_stream(BrotliEncoder::with_quality(body, level));
+            Encoding::Brotli.to_header_value()
+        }
+        Some(Encoding::Zstd) => {
+            ctx.resp
+                .write_stream(ZstdEncoder::with_quality(body, level));
+            Encoding::Zstd.to_header_value()
+        }
+        Some(Encoding::Identity) => {
+            ctx.resp.body = body;
+            if let Some(content_length) = content_length {
+                ctx.resp.headers.insert(CONTENT_LENGTH, content_length);
             }
-            Some(Encoding::Deflate) => {
-                ctx.resp
-                    .write_stream(ZlibEncoder::with_quality(body, level));
-                Encoding::Deflate.to_header_value()
+            Encoding::Identity.to_header_value()
+        }
+    };
+    ctx.resp.headers.append(CONTENT_ENCODING, content_encoding);
+    Ok(())
+}
+
+/// A context extension to serve upstream responses in a reverse proxy.
+pub trait ServeUpstream {
+    /// Write an upstream response, with its status, headers and body.
+    ///
+    /// An encoded body acceptable by "Accept-Encoding" of request is passed through,
+    /// otherwise it's decoded (gzip, deflate, br and zstd are supported),
+    /// then re-encoded by the best coding client accepts if `level` is `Some`.
+    /// "Accept-Encoding" is added to "Vary" if the upstream body is encoded.
+    ///
+    /// Multiple codings are decoded in the reverse order they were applied.
+    ///
+    /// Hop-by-hop headers of upstream are dropped, so are headers listed in its "Connection".
+    /// Throw 502 BAD GATEWAY if an upstream coding is not supported.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::compress::{Level, ServeUpstream};
+    /// use roa::http::Response;
+    /// use roa::{App, Context};
+    ///
+    /// async fn fetch() -> Response<hyper::Body> {
+    ///     // send request to upstream
+    ///     Response::new(hyper::Body::from("console.log('Hello, World')"))
+    /// }
+    ///
+    /// async fn proxy(ctx: &mut Context) -> roa::Result {
+    ///     let resp = fetch().await;
+    ///     ctx.write_upstream(resp, Some(Level::Fastest))
+    /// }
+    ///
+    /// let app = App::new().end(proxy);
+    /// ```
+    fn write_upstream(
+        &mut self,
+        resp: Response<hyper::Body>,
+        level: Option<Level>,
+    ) -> Result;
+}
+
+/// Content codings able to re-encode, in preference order when qualities are equal.
+const CODINGS: [&str; 4] = ["br", "gzip", "zstd", "deflate"];
+
+/// Hop-by-hop headers not forwarded.
+const HOP_BY_HOP: [&str; 7] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Decode a body encoded by `coding`, return None if it's not supported.
+#[inline]
+fn decode(coding: &str, body: Body) -> Option<Body> {
+    let decoded = match coding {
+        "gzip" | "x-gzip" => Body::stream(GzipDecoder::new(body)),
+        "deflate" => Body::stream(ZlibDecoder::new(body)),
+        "br" => Body::stream(BrotliDecoder::new(body)),
+        "zstd" => Body::stream(ZstdDecoder::new(body)),
+        _ => return None,
+    };
+    Some(decoded)
+}
+
+/// Split comma-separated tokens of headers into lowercase strings.
+#[inline]
+fn tokens(values: GetAll<HeaderValue>) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    for value in values {
+        let value = value
+            .to_str()
+            .map_err(|err| Status::new(StatusCode::BAD_GATEWAY, err, true))?;
+        tokens.extend(
+            value
+                .split(',')
+                .map(|token| token.trim().to_ascii_lowercase())
+                .filter(|token| !token.is_empty()),
+        );
+    }
+    Ok(tokens)
+}
+
+impl<S> ServeUpstream for Context<S> {
+    #[inline]
+    fn write_upstream(
+        &mut self,
+        resp: Response<hyper::Body>,
+        level: Option<Level>,
+    ) -> Result {
+        let (parts, body) = resp.into_parts();
+        self.resp.status = parts.status;
+        let connection = tokens(parts.headers.get_all(CONNECTION))?;
+        for (name, value) in parts.headers.iter() {
+            let name_str = name.as_str();
+            if !HOP_BY_HOP.contains(&name_str)
+                && !connection.iter().any(|token| token == name_str)
+            {
+                self.resp.headers.append(name, value.clone());
             }
-            Some(Encoding::Brotli) => {
-                ctx.resp
-                    .write_stream(BrotliEncoder::with_quality(body, level));
-                Encoding::Brotli.to_header_value()
+        }
+        let mut decoded = Body::from(body);
+        let codings: Vec<String> = tokens(parts.headers.get_all(CONTENT_ENCODING))?
+            .into_iter()
+            .filter(|coding| coding != "identity")
+            .collect();
+        if codings.is_empty() {
+            self.resp.body = decoded;
+            return Ok(());
+        }
+        append_vary(&mut self.resp.headers, ACCEPT_ENCODING);
+        if codings
+            .iter()
+            .all(|coding| accept_quality(self, coding) > 0.0)
+        {
+            // pass through.
+            self.resp.body = decoded;
+            return Ok(());
+        }
+        for coding in codings.iter().rev() {
+            decoded = match decode(coding, decoded) {
+                Some(decoded) => decoded,
+                None => throw!(
+                    StatusCode::BAD_GATEWAY,
+                    format!("unsupported upstream content encoding `{}`", coding)
+                ),
+            };
+        }
+        self.resp.headers.remove(CONTENT_ENCODING);
+        self.resp.headers.remove(CONTENT_LENGTH);
+        let mut best: Option<(&'static str, f32)> = None;
+        if level.is_some() {
+            for &candidate in CODINGS.iter() {
+                let quality = accept_quality(self, candidate);
+                if quality > best.map(|(_, quality)| quality).unwrap_or(0.0) {
+                    best = Some((candidate, quality));
+                }
+            }
+        }
+        self.resp.body = match (best, level) {
+            (Some(("br", _)), Some(level)) => {
+                Body::stream(BrotliEncoder::with_quality(decoded, level))
+            }
+            (Some(("gzip", _)), Some(level)) => {
+                Body::stream(GzipEncoder::with_quality(decoded, level))
             }
-            Some(Encoding::Zstd) => {
-                ctx.resp
-                    .write_stream(ZstdEncoder::with_quality(body, level));
-                Encoding::Zstd.to_header_value()
+            (Some(("zstd", _)), Some(level)) => {
+                Body::stream(ZstdEncoder::with_quality(decoded, level))
             }
-            Some(Encoding::Identity) => {
-                ctx.resp.body = body;
-                Encoding::Identity.to_header_value()
+            (Some(("deflate", _)), Some(level)) => {
+                Body::stream(ZlibEncoder::with_quality(decoded, level))
             }
+            _ => decoded,
         };
-        ctx.resp.headers.append(CONTENT_ENCODING, content_encoding);
+        if let Some((coding, _)) = best {
+            self.resp
+                .headers
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(coding));
+        }
         Ok(())
     }
 }
@@ -83,13 +460,21 @@ impl<'a, S> Middleware<'a, S> for Compress {
 #[cfg(all(test, feature = "tcp", feature = "file"))]
 mod tests {
     use crate::body::DispositionType::*;
-    use crate::compress::{Compress, Level};
-    use crate::http::{header::ACCEPT_ENCODING, StatusCode};
+    use crate::compress::{Compress, Level, ServeUpstream};
+    use crate::http::header::{
+        ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH, TE,
+        TRANSFER_ENCODING, VARY,
+    };
+    use crate::http::{Response, StatusCode};
     use crate::preload::*;
     use crate::{async_trait, App, Context, Middleware, Next};
+    use async_compression::stream::{
+        BrotliDecoder, BrotliEncoder, GzipDecoder, GzipEncoder,
+    };
     use async_std::task::spawn;
     use bytes::Bytes;
-    use futures::Stream;
+    use futures::stream::iter;
+    use futures::{Stream, TryStreamExt};
     use std::io;
     use std::pin::Pin;
     use std::task::{self, Poll};
@@ -165,4 +550,335 @@ mod tests {
         assert_eq!(236, resp.text().await?.len());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn strip_content_length() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let data = "Hello, World! ".repeat(16);
+            ctx.resp
+                .headers
+                .insert(CONTENT_LENGTH, data.len().to_string().parse()?);
+            ctx.write(data);
+            Ok(())
+        }
+        let app = App::new().gate(Compress(Level::Fastest)).end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("gzip", resp.headers()[CONTENT_ENCODING]);
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+        assert!(resp.bytes().await?.len() < 16 * 14);
+
+        // identity
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "identity")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("224", resp.headers()[CONTENT_LENGTH]);
+        assert_eq!(224, resp.bytes().await?.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn compress_file() -> Result<(), Box<dyn std::error::Error>> {
+        use async_compression::stream::GzipDecoder;
+        use futures::stream::iter;
+        use futures::TryStreamExt;
+        let expected = async_std::fs::read("../assets/welcome.html").await?;
+        let app = App::new().gate(Compress(Level::Fastest)).end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("gzip", resp.headers()[CONTENT_ENCODING]);
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+        let body = resp.bytes().await?;
+        let data: Vec<u8> = GzipDecoder::new(iter(vec![Ok::<_, io::Error>(body)]))
+            .map_ok(|bytes| bytes.to_vec())
+            .try_concat()
+            .await?;
+        assert_eq!(expected, data);
+
+        // identity
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "identity")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            expected.len().to_string(),
+            resp.headers()[CONTENT_LENGTH].to_str()?
+        );
+        assert_eq!(expected, resp.bytes().await?.to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn match_media_type() {
+        use super::media_type_matches;
+        assert!(media_type_matches("text/html", "text/html"));
+        assert!(media_type_matches("Text/HTML", "text/*"));
+        assert!(!media_type_matches("application/json", "text/*"));
+        assert!(!media_type_matches("text/html", "text/plain"));
+        assert!(!media_type_matches("", "text/*"));
+    }
+
+    #[test]
+    fn level_of_type() {
+        let compressor = Compress(Level::Default)
+            .level_for("text/*", Level::Best)
+            .level_for("text/plain", Level::Fastest)
+            .level_for("application/octet-stream", Level::Fastest);
+        assert!(matches!(compressor.level_of_type("text/html"), Level::Best));
+        // the first matching pattern wins.
+        assert!(matches!(
+            compressor.level_of_type("text/plain"),
+            Level::Best
+        ));
+        assert!(matches!(
+            compressor.level_of_type("application/octet-stream"),
+            Level::Fastest
+        ));
+        assert!(matches!(
+            compressor.level_of_type("application/json"),
+            Level::Default
+        ));
+        assert!(matches!(compressor.level_of_type(""), Level::Default));
+    }
+
+    #[tokio::test]
+    async fn level_for() -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(
+                Compress(Level::Fastest)
+                    .level_for("text/*", Level::Best)
+                    .level_for("application/octet-stream", Level::Fastest),
+            )
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("gzip", resp.headers()[CONTENT_ENCODING]);
+        let body = resp.bytes().await?;
+        let data: Vec<u8> = GzipDecoder::new(iter(vec![Ok::<_, io::Error>(body)]))
+            .map_ok(|bytes| bytes.to_vec())
+            .try_concat()
+            .await?;
+        assert_eq!(std::fs::read("../assets/welcome.html")?, data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn skip_encoded() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            // pretend to be a proxied brotli response.
+            ctx.resp.headers.insert(CONTENT_ENCODING, "br".parse()?);
+            ctx.resp.write("encoded");
+            Ok(())
+        }
+        let app = App::new().gate(Compress(Level::Fastest)).end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let encodings: Vec<_> =
+            resp.headers().get_all(CONTENT_ENCODING).iter().collect();
+        assert_eq!(1, encodings.len());
+        assert_eq!("br", *encodings[0]);
+        assert_eq!("encoded", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn transfer_encoding() -> Result<(), Box<dyn std::error::Error>> {
+        use async_compression::stream::GzipDecoder;
+        use futures::stream::iter;
+        use futures::TryStreamExt;
+        let app = App::new()
+            .gate(Compress(Level::Fastest).transfer_encoding(true))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(TE, "gzip")
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("gzip, chunked", resp.headers()[TRANSFER_ENCODING]);
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+        let body = resp.bytes().await?;
+        let data: Vec<u8> = GzipDecoder::new(iter(vec![Ok::<_, io::Error>(body)]))
+            .map_ok(|bytes| bytes.to_vec())
+            .try_concat()
+            .await?;
+        assert_eq!(236, data.len());
+
+        // "TE" refuses gzip, fallback to "Content-Encoding".
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(TE, "gzip;q=0, trailers")
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("gzip", resp.headers()[CONTENT_ENCODING]);
+        assert!(resp.headers().get(TRANSFER_ENCODING).is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn write_upstream() -> Result<(), Box<dyn std::error::Error>> {
+        const TEXT: &str = "Hello, World! Hello, World! Hello, World!";
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let encoded: Vec<Bytes> =
+                GzipEncoder::new(iter(vec![Ok::<_, io::Error>(Bytes::from(TEXT))]))
+                    .try_collect()
+                    .await?;
+            let resp = Response::builder()
+                .status(StatusCode::CREATED)
+                .header(CONTENT_ENCODING, "gzip")
+                .header(CONTENT_LENGTH, encoded.concat().len())
+                .header(CONNECTION, "close, x-hop")
+                .header("x-hop", "upstream")
+                .header("x-upstream", "assets")
+                .body(hyper::Body::from(encoded.concat()))?;
+            if ctx.req.uri.query() == Some("multi") {
+                let (mut parts, _) = resp.into_parts();
+                let encoded: Vec<Bytes> =
+                    BrotliEncoder::new(iter(vec![Ok::<_, io::Error>(Bytes::from(
+                        encoded.concat(),
+                    ))]))
+                    .try_collect()
+                    .await?;
+                parts
+                    .headers
+                    .insert(CONTENT_ENCODING, "gzip, br".parse().unwrap());
+                parts.headers.remove(CONTENT_LENGTH);
+                let resp =
+                    Response::from_parts(parts, hyper::Body::from(encoded.concat()));
+                return ctx.write_upstream(resp, None);
+            }
+            let level = match ctx.req.uri.query() {
+                Some("reencode") => Some(Level::Fastest),
+                _ => None,
+            };
+            ctx.write_upstream(resp, level)
+        }
+        async fn decode(
+            body: Bytes,
+            gzip: bool,
+        ) -> Result<String, Box<dyn std::error::Error>> {
+            let chunks = iter(vec![Ok::<_, io::Error>(body)]);
+            let data: Vec<u8> = if gzip {
+                GzipDecoder::new(chunks)
+                    .map_ok(|bytes| bytes.to_vec())
+                    .try_concat()
+                    .await?
+            } else {
+                BrotliDecoder::new(chunks)
+                    .map_ok(|bytes| bytes.to_vec())
+                    .try_concat()
+                    .await?
+            };
+            Ok(String::from_utf8(data)?)
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::builder().gzip(false).build()?;
+
+        // pass through
+        let resp = client
+            .get(&format!("http://{}?reencode", addr))
+            .header(ACCEPT_ENCODING, "br, gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::CREATED, resp.status());
+        assert_eq!("gzip", resp.headers()[CONTENT_ENCODING]);
+        assert_eq!("accept-encoding", resp.headers()[VARY]);
+        assert_eq!("assets", resp.headers()["x-upstream"]);
+        assert!(resp.headers().get("x-hop").is_none());
+        assert!(resp.headers().get(CONTENT_LENGTH).is_some());
+        assert_eq!(TEXT, decode(resp.bytes().await?, true).await?);
+
+        // decode
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT_ENCODING, "gzip;q=0, br")
+            .send()
+            .await?;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(TEXT, resp.text().await?);
+
+        // re-encode
+        let resp = client
+            .get(&format!("http://{}?reencode", addr))
+            .header(ACCEPT_ENCODING, "gzip;q=0, br")
+            .send()
+            .await?;
+        assert_eq!("br", resp.headers()[CONTENT_ENCODING]);
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+        assert_eq!(TEXT, decode(resp.bytes().await?, false).await?);
+
+        // multiple codings
+        let resp = client
+            .get(&format!("http://{}?multi", addr))
+            .header(ACCEPT_ENCODING, "gzip")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::CREATED, resp.status());
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(TEXT, resp.text().await?);
+
+        // multiple codings all acceptable
+        let resp = client
+            .get(&format!("http://{}?multi", addr))
+            .header(ACCEPT_ENCODING, "gzip, br")
+            .send()
+            .await?;
+        assert_eq!("gzip, br", resp.headers()[CONTENT_ENCODING]);
+        let encoded = resp.bytes().await?;
+        let gzipped: Vec<Bytes> =
+            BrotliDecoder::new(iter(vec![Ok::<_, io::Error>(encoded)]))
+                .try_collect()
+                .await?;
+        assert_eq!(TEXT, decode(Bytes::from(gzipped.concat()), true).await?);
+
+        // no "Accept-Encoding"
+        let resp = client
+            .get(&format!("http://{}?reencode", addr))
+            .send()
+            .await?;
+        assert!(resp.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(TEXT, resp.text().await?);
+        Ok(())
+    }
 }
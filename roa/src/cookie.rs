@@ -1,4 +1,5 @@
-//! This module provides a middleware `cookie_parser` and context extensions `CookieGetter` and `CookieSetter`.
+//! This module provides middlewares `cookie_parser`, `cookie_jar`
+//! and context extensions `CookieGetter`, `CookieSetter` and `CookieJarExt`.
 //!
 //! ### Example
 //!
@@ -21,15 +22,24 @@
 //! # }
 //! ```
 
-use crate::http::{header, StatusCode};
-use crate::{throw, Context, Next, Result};
-pub use cookie::Cookie;
-use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
-use std::sync::Arc;
+use crate::http::header::{self, HeaderValue, ValueIter};
+use crate::http::StatusCode;
+use crate::{status, throw, Context, Next, Result};
+pub use cookie::{Cookie, CookieJar};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, NON_ALPHANUMERIC};
+use std::borrow::Cow;
+use std::str::Split;
+use std::sync::{Arc, Mutex};
 
 /// A scope to store and load variables in Context::storage.
 struct CookieScope;
 
+/// A scope to store cookie jar.
+struct JarScope;
+
+/// Key of cookie jar in `JarScope`.
+const JAR: &str = "jar";
+
 /// A context extension.
 /// This extension must be used in downstream of middleware `cookier_parser`,
 /// otherwise you cannot get expected cookie.
@@ -82,6 +92,84 @@ pub trait CookieGetter {
     /// # }
     /// ```
     fn cookie(&self, name: &str) -> Option<Arc<Cookie<'static>>>;
+
+    /// Iterate all request cookies as percent-decoded `(name, value)` pairs.
+    ///
+    /// This method parses "Cookie" headers directly, so it works without `cookie_parser`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::preload::*;
+    /// use roa::{App, Context};
+    ///
+    /// async fn end(ctx: &mut Context) -> roa::Result {
+    ///     for (name, value) in ctx.cookies() {
+    ///         println!("{}: {}", name, value);
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    fn cookies(&self) -> Cookies<'_>;
+}
+
+/// An iterator over cookies of all "Cookie" request headers.
+///
+/// Pairs are split by `;`, surrounding whitespaces and double quotes of values
+/// are trimmed as RFC 6265 describes. Invalid pairs will be skipped.
+pub struct Cookies<'a> {
+    headers: ValueIter<'a, HeaderValue>,
+    pairs: Option<Split<'a, char>>,
+}
+
+impl<'a> Cookies<'a> {
+    /// Construct an iterator by values of "Cookie" headers.
+    #[inline]
+    pub fn new(headers: ValueIter<'a, HeaderValue>) -> Self {
+        Self {
+            headers,
+            pairs: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Cookies<'a> {
+    type Item = (Cow<'a, str>, Cow<'a, str>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(pairs) = &mut self.pairs {
+                for pair in pairs {
+                    if let Some(item) = parse_pair(pair) {
+                        return Some(item);
+                    }
+                }
+            }
+            let value = self.headers.next()?;
+            self.pairs = value.to_str().ok().map(|value| value.split(';'));
+        }
+    }
+}
+
+/// Parse a `name=value` cookie pair.
+#[inline]
+fn parse_pair(pair: &str) -> Option<(Cow<'_, str>, Cow<'_, str>)> {
+    let mut iter = pair.splitn(2, '=');
+    let name = iter.next()?.trim();
+    let mut value = iter.next()?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value = &value[1..value.len() - 1];
+    }
+    Some((
+        percent_decode_str(name).decode_utf8().ok()?,
+        percent_decode_str(value).decode_utf8().ok()?,
+    ))
 }
 
 /// An extension to set cookie.
@@ -110,26 +198,104 @@ pub trait CookieSetter {
     fn set_cookie(&mut self, cookie: Cookie<'_>) -> Result;
 }
 
+/// A context extension to access a mutable cookie jar.
+///
+/// This extension must be used in downstream of middleware `cookie_jar`,
+/// otherwise it returns a 500 INTERNAL SERVER ERROR.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::cookie::{cookie_jar, Cookie};
+/// use roa::preload::*;
+/// use roa::{App, Context, Next};
+/// use std::error::Error;
+///
+/// async fn session(ctx: &mut Context, next: Next<'_>) -> roa::Result {
+///     ctx.jar(|jar| jar.add(Cookie::new("session", "id")))?;
+///     next.await
+/// }
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     // cookies set by different middlewares do not clobber each other.
+///     ctx.jar(|jar| {
+///         jar.add(Cookie::new("csrf", "token"));
+///         jar.remove(Cookie::named("legacy"));
+///     })?;
+///     Ok(())
+/// }
+///
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let app = App::new().gate(cookie_jar).gate(session).end(end);
+/// let (addr, server) = app.run()?;
+/// // server.await
+/// Ok(())
+/// # }
+/// ```
+pub trait CookieJarExt {
+    /// Access the cookie jar, which is initialized by request cookies.
+    fn jar<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut CookieJar) -> T;
+}
+
 /// A middleware to parse cookie.
 #[inline]
 pub async fn cookie_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
-    if let Some(cookies) = ctx.get(header::COOKIE) {
-        for cookie in cookies
-            .split(';')
-            .map(|cookie| cookie.trim())
-            .map(Cookie::parse_encoded)
-            .filter_map(|cookie| cookie.ok())
-            .map(|cookie| cookie.into_owned())
-            .collect::<Vec<_>>()
-            .into_iter()
-        {
-            let name = cookie.name().to_string();
-            ctx.store_scoped(CookieScope, name, cookie);
-        }
+    let cookies: Vec<Cookie<'static>> = ctx
+        .cookies()
+        .map(|(name, value)| Cookie::new(name.into_owned(), value.into_owned()))
+        .collect();
+    for cookie in cookies {
+        let name = cookie.name().to_string();
+        ctx.store_scoped(CookieScope, name, cookie);
     }
     next.await
 }
 
+/// A middleware to manage a cookie jar.
+///
+/// The jar is initialized by request cookies,
+/// changes of it are emitted as "Set-Cookie" after inner middlewares and endpoint return.
+#[inline]
+pub async fn cookie_jar<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    let mut jar = CookieJar::new();
+    for (name, value) in ctx.cookies() {
+        jar.add_original(Cookie::new(name.into_owned(), value.into_owned()));
+    }
+    ctx.store_scoped(JarScope, JAR, Mutex::new(jar));
+    let result = next.await;
+    let set_cookies: Vec<String> = ctx.jar(|jar| {
+        jar.delta()
+            .map(|cookie| cookie.encoded().to_string())
+            .collect()
+    })?;
+    for cookie in set_cookies {
+        ctx.resp.headers.append(header::SET_COOKIE, cookie.parse()?);
+    }
+    result
+}
+
+impl<S> CookieJarExt for Context<S> {
+    #[inline]
+    fn jar<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut CookieJar) -> T,
+    {
+        let jar = self
+            .load_scoped::<JarScope, Mutex<CookieJar>>(JAR)
+            .ok_or_else(|| {
+                status!(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "middleware `cookie_jar` is not set",
+                    false
+                )
+            })?;
+        let mut jar = jar.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Ok(f(&mut jar))
+    }
+}
+
 impl<S> CookieGetter for Context<S> {
     #[inline]
     fn must_cookie(&mut self, name: &str) -> Result<Arc<Cookie<'static>>> {
@@ -153,6 +319,11 @@ impl<S> CookieGetter for Context<S> {
     fn cookie(&self, name: &str) -> Option<Arc<Cookie<'static>>> {
         Some(self.load_scoped::<CookieScope, Cookie>(name)?.value())
     }
+
+    #[inline]
+    fn cookies(&self) -> Cookies<'_> {
+        Cookies::new(self.req.headers.get_all(header::COOKIE).iter())
+    }
 }
 
 impl<S> CookieSetter for Context<S> {
@@ -168,13 +339,13 @@ impl<S> CookieSetter for Context<S> {
 
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use crate::cookie::{cookie_parser, Cookie};
+    use crate::cookie::{cookie_jar, cookie_parser, Cookie};
     use crate::http::{
-        header::{COOKIE, WWW_AUTHENTICATE},
+        header::{COOKIE, SET_COOKIE, WWW_AUTHENTICATE},
         StatusCode,
     };
     use crate::preload::*;
-    use crate::{App, Context};
+    use crate::{App, Context, Next};
     use async_std::task::spawn;
 
     async fn must(ctx: &mut Context) -> crate::Result {
@@ -266,6 +437,37 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn cookies() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let cookies: Vec<(String, String)> = ctx
+                .cookies()
+                .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("bar baz".to_string(), "bar baz".to_string()),
+                    ("foo".to_string(), "quoted".to_string()),
+                    ("name".to_string(), "Hexilee".to_string()),
+                ],
+                cookies
+            );
+            Ok(())
+        }
+
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(COOKIE, "bar%20baz=bar%20baz ;  foo=\"quoted\"; invalid")
+            .header(COOKIE, "name=Hexilee")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn set_cookie() -> Result<(), Box<dyn std::error::Error>> {
         async fn test(ctx: &mut Context) -> crate::Result {
@@ -285,4 +487,47 @@ mod tests {
         assert_eq!(("foo%20baz"), cookies[1].value());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn jar() -> Result<(), Box<dyn std::error::Error>> {
+        async fn session(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            ctx.jar(|jar| jar.add(Cookie::new("session", "id")))?;
+            next.await
+        }
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.jar(|jar| {
+                assert_eq!("Hexilee", jar.get("name").unwrap().value());
+                assert_eq!("id", jar.get("session").unwrap().value());
+                jar.add(Cookie::new("csrf", "token"));
+                jar.remove(Cookie::named("name"));
+            })?;
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(cookie_jar).gate(session).end(end).run()?;
+        spawn(server);
+        let resp = reqwest::Client::new()
+            .get(&format!("http://{}", addr))
+            .header(COOKIE, "name=Hexilee")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let mut cookies: Vec<&str> = resp
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect();
+        cookies.sort();
+        assert_eq!(3, cookies.len());
+        assert_eq!("csrf=token", cookies[0]);
+        assert!(cookies[1].starts_with("name=;"));
+        assert_eq!("session=id", cookies[2]);
+
+        // miss `cookie_jar`
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+        Ok(())
+    }
 }
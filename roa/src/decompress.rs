@@ -0,0 +1,421 @@
+//! This module provides a middleware `Decompress`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::decompress::Decompress;
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let data = ctx.read().await?; // decoded body
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().gate(Decompress::new()).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::body::PayloadTooLarge;
+use crate::http::header::{CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result, Status};
+use async_compression::stream::{BrotliDecoder, GzipDecoder, ZlibDecoder, ZstdDecoder};
+use bytes::Bytes;
+use futures::Stream;
+use hyper::Body;
+use std::error::Error as StdError;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{self, Poll};
+
+/// Default limit of decompressed body, 16 MiB.
+const DEFAULT_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Decompressed bodies smaller than it are never rejected by compression ratio, 64 KiB.
+const RATIO_GRACE: u64 = 64 * 1024;
+
+/// A boxed stream of request body.
+type BoxStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// A middleware to decode request body by "Content-Encoding",
+/// supports gzip, deflate, brotli, zstd and identity.
+///
+/// Multiple codings like "gzip, br" are decoded in the reverse order they were applied,
+/// a request with any unsupported coding gets a 415 UNSUPPORTED MEDIA TYPE.
+/// "Content-Encoding" and "Content-Length" will be removed after decoding.
+///
+/// The size of decompressed body is limited, reading a body exceeding the limit
+/// by `PowerBody` will get a 413 PAYLOAD TOO LARGE.
+/// Compression ratio can be limited as well by `max_ratio`, to defend against zip bombs.
+/// Limits are checked incrementally as the body is decompressed.
+#[derive(Debug, Copy, Clone)]
+pub struct Decompress {
+    limit: u64,
+    ratio: Option<u64>,
+}
+
+/// A stream wrapper to limit the size of decompressed body.
+struct Limit<S> {
+    stream: S,
+    counter: u64,
+    limit: u64,
+    ratio: Option<u64>,
+    compressed: Arc<AtomicU64>,
+}
+
+/// A stream wrapper to count bytes of compressed body.
+struct Counter<S> {
+    stream: S,
+    counter: Arc<AtomicU64>,
+}
+
+impl Decompress {
+    /// Construct a middleware with default limit (16 MiB).
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_LIMIT)
+    }
+
+    /// Construct a middleware with limit of decompressed body in bytes.
+    pub fn with_limit(limit: u64) -> Self {
+        Self { limit, ratio: None }
+    }
+
+    /// Limit the ratio of decompressed size to compressed size, unlimited by default.
+    ///
+    /// Bodies smaller than 64 KiB after decompression are never rejected by ratio,
+    /// as small bodies may be compressed well legitimately.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::decompress::Decompress;
+    ///
+    /// let decompress = Decompress::with_limit(64 * 1024 * 1024).max_ratio(100);
+    /// ```
+    pub fn max_ratio(mut self, ratio: u64) -> Self {
+        self.ratio = Some(ratio);
+        self
+    }
+}
+
+impl Default for Decompress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Limit<S> {
+    #[inline]
+    fn new(
+        stream: S,
+        limit: u64,
+        ratio: Option<u64>,
+        compressed: Arc<AtomicU64>,
+    ) -> Self {
+        Self {
+            stream,
+            counter: 0,
+            limit,
+            ratio,
+            compressed,
+        }
+    }
+
+    /// Get the exceeded limit, if any.
+    #[inline]
+    fn exceeded(&self) -> Option<u64> {
+        if self.counter > self.limit {
+            return Some(self.limit);
+        }
+        match self.ratio {
+            Some(ratio) if self.counter > RATIO_GRACE => {
+                let limit = self
+                    .compressed
+                    .load(Ordering::Relaxed)
+                    .saturating_mul(ratio)
+                    .max(RATIO_GRACE);
+                if self.counter > limit {
+                    Some(limit)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<S> Stream for Counter<S>
+where
+    S: Unpin + Stream<Item = io::Result<Bytes>>,
+{
+    type Item = io::Result<Bytes>;
+
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let item = futures::ready!(Pin::new(&mut self.stream).poll_next(cx));
+        if let Some(Ok(bytes)) = &item {
+            self.counter
+                .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl<S> Stream for Limit<S>
+where
+    S: Unpin + Stream<Item = io::Result<Bytes>>,
+{
+    type Item = std::result::Result<Bytes, Box<dyn StdError + Send + Sync>>;
+
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        match futures::ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+            None => Poll::Ready(None),
+            Some(Err(err)) => Poll::Ready(Some(Err(err.into()))),
+            Some(Ok(bytes)) => {
+                self.counter += bytes.len() as u64;
+                match self.exceeded() {
+                    Some(limit) => {
+                        Poll::Ready(Some(Err(Box::new(PayloadTooLarge { limit }))))
+                    }
+                    None => Poll::Ready(Some(Ok(bytes))),
+                }
+            }
+        }
+    }
+}
+
+/// Wrap a stream with the decoder of a content coding, `None` if it's unsupported.
+#[inline]
+fn decode(coding: &str, stream: BoxStream) -> Option<BoxStream> {
+    let decoded: BoxStream = match coding {
+        "gzip" | "x-gzip" => Box::pin(GzipDecoder::new(stream)),
+        "deflate" => Box::pin(ZlibDecoder::new(stream)),
+        "br" => Box::pin(BrotliDecoder::new(stream)),
+        "zstd" => Box::pin(ZstdDecoder::new(stream)),
+        _ => return None,
+    };
+    Some(decoded)
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Decompress {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let mut codings = Vec::new();
+        for value in ctx.req.headers.get_all(CONTENT_ENCODING).iter() {
+            let value = value
+                .to_str()
+                .map_err(|err| Status::new(StatusCode::BAD_REQUEST, err, true))?;
+            codings.extend(
+                value
+                    .split(',')
+                    .map(|coding| coding.trim().to_ascii_lowercase())
+                    .filter(|coding| !coding.is_empty() && coding != "identity"),
+            );
+        }
+        if codings.is_empty() {
+            return next.await;
+        }
+        let compressed = Arc::new(AtomicU64::new(0));
+        let mut stream: BoxStream = Box::pin(Counter {
+            stream: ctx.req.stream(),
+            counter: compressed.clone(),
+        });
+        // codings are listed in the order they were applied.
+        for coding in codings.iter().rev() {
+            stream = match decode(coding, stream) {
+                Some(decoded) => decoded,
+                None => throw!(
+                    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    format!("unsupported content encoding `{}`", coding)
+                ),
+            };
+        }
+        ctx.req.replace_body(Body::wrap_stream(Limit::new(
+            stream, self.limit, self.ratio, compressed,
+        )));
+        ctx.req.headers.remove(CONTENT_ENCODING);
+        ctx.req.headers.remove(CONTENT_LENGTH);
+        next.await
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::Decompress;
+    use crate::compress::Level;
+    use crate::http::header::CONTENT_ENCODING;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_compression::stream::GzipEncoder;
+    use async_std::task::spawn;
+    use bytes::Bytes;
+    use futures::stream::{once, TryStreamExt};
+    use std::io;
+
+    const TEXT: &str = "Hello, World! Hello, World! Hello, World! Hello, World!";
+
+    async fn gzip(data: impl Into<Bytes>) -> io::Result<Vec<u8>> {
+        let data = data.into();
+        let encoder =
+            GzipEncoder::with_quality(once(async move { Ok(data) }), Level::Default);
+        let chunks: Vec<Bytes> = encoder.try_collect().await?;
+        Ok(chunks.concat())
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        assert!(ctx.req.headers.get(CONTENT_ENCODING).is_none());
+        assert_eq!(TEXT.as_bytes(), &*ctx.read().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn decompress() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(Decompress::new()).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip")
+            .body(gzip(TEXT).await?)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // plain body
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body(TEXT)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // unsupported encoding
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "compress")
+            .body(TEXT)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multiple_codings() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(Decompress::new()).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let twice = gzip(gzip(TEXT).await?).await?;
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip, identity, gzip")
+            .body(twice.clone())
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // in separate header values
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip")
+            .header(CONTENT_ENCODING, "gzip")
+            .body(twice)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "compress, gzip")
+            .body(gzip(TEXT).await?)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn identity() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            assert_eq!(TEXT.as_bytes(), &*ctx.read().await?);
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(Decompress::new()).end(end).run()?;
+        spawn(server);
+        let resp = reqwest::Client::new()
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "identity")
+            .body(TEXT)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn decompress_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) =
+            App::new().gate(Decompress::with_limit(16)).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip")
+            .body(gzip(TEXT).await?)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn decompress_ratio() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.read().await?;
+            Ok(())
+        }
+        let bomb = "0".repeat(1024 * 1024);
+        let (addr, server) = App::new()
+            .gate(Decompress::new().max_ratio(100))
+            .end(end)
+            .run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip")
+            .body(gzip(Box::leak(bomb.into_boxed_str())).await?)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+        // small bodies are never rejected by ratio.
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(CONTENT_ENCODING, "gzip")
+            .body(gzip(TEXT).await?)
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+}
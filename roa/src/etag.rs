@@ -0,0 +1,284 @@
+//! This module provides a context extension `EntityTag`,
+//! to revalidate dynamic responses by "ETag",
+//! and a middleware `conditional` to revalidate all responses carrying validators.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.write_json(&vec!["Hexilee", "Roa"])?;
+//!     // 304 NOT MODIFIED if "If-None-Match" matches.
+//!     ctx.etag().await
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::{
+    HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH, TRANSFER_ENCODING,
+};
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, Body, Context, Next, Result, State};
+use bytes::Bytes;
+use futures::StreamExt;
+use headers::{HeaderMapExt, IfModifiedSince, LastModified};
+
+/// A context extension to tag response body.
+#[async_trait]
+pub trait EntityTag {
+    /// Buffer response body, compute a strong "ETag" over it and set the header.
+    ///
+    /// Response of GET or HEAD becomes a 304 NOT MODIFIED with empty body,
+    /// if it matches "If-None-Match" of request.
+    ///
+    /// It should be called after response body is built.
+    async fn etag(&mut self) -> Result;
+}
+
+/// Compute a strong entity tag over bytes.
+///
+/// CRC-32 is used as it's stable across processes and builds,
+/// so tags stay valid behind a load balancer or after a restart.
+#[inline]
+pub(crate) fn entity_tag(data: &[u8]) -> String {
+    format!("\"{:x}-{:08x}\"", data.len(), crc32fast::hash(data))
+}
+
+/// Strip weak indicator of an entity tag.
+#[inline]
+fn opaque_tag(tag: &str) -> &str {
+    let tag = tag.trim();
+    if tag.starts_with("W/") {
+        &tag[2..]
+    } else {
+        tag
+    }
+}
+
+/// Check if tag matches "If-None-Match", weak comparison is used.
+#[inline]
+fn none_match<S>(ctx: &Context<S>, tag: &str) -> bool {
+    let tag = opaque_tag(tag);
+    ctx.header_all(IF_NONE_MATCH)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(opaque_tag)
+        .any(|item| item == "*" || item == tag)
+}
+
+/// Check if response is not modified since the time client cached it.
+///
+/// "If-Modified-Since" is ignored if "If-None-Match" is present, as RFC 7232 defines.
+#[inline]
+fn not_modified<S>(ctx: &Context<S>) -> bool {
+    if ctx.req.headers.contains_key(IF_NONE_MATCH) {
+        return match ctx.resp.headers.get(ETAG).and_then(|tag| tag.to_str().ok()) {
+            Some(tag) => none_match(ctx, tag),
+            None => false,
+        };
+    }
+    match (
+        ctx.req.headers.typed_get::<IfModifiedSince>(),
+        ctx.resp.headers.typed_get::<LastModified>(),
+    ) {
+        (Some(since), Some(modified)) => !since.is_modified(modified.into()),
+        _ => false,
+    }
+}
+
+/// A middleware to turn responses into 304 NOT MODIFIED
+/// if they are not modified since the time client cached them.
+///
+/// After downstream returns, a successful response of GET or HEAD carrying "ETag" or "Last-Modified"
+/// is checked against "If-None-Match" or "If-Modified-Since" of request.
+/// A matched one gets an empty body, and headers describing body like "Content-Length" are removed,
+/// while validators and caching headers are kept.
+///
+/// So endpoints only need to set validators.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::etag::conditional;
+/// use roa::http::header::ETAG;
+/// use roa::{App, Context};
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     ctx.resp.headers.insert(ETAG, "\"v1\"".parse()?);
+///     ctx.resp.write("Hello, World");
+///     Ok(())
+/// }
+///
+/// let app = App::new().gate(conditional).end(end);
+/// ```
+pub async fn conditional<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    next.await?;
+    let is_safe = *ctx.method() == Method::GET || *ctx.method() == Method::HEAD;
+    if is_safe && ctx.resp.status.is_success() && not_modified(ctx) {
+        ctx.resp.status = StatusCode::NOT_MODIFIED;
+        ctx.resp.body = Body::empty();
+        for name in &[
+            CONTENT_LENGTH,
+            CONTENT_TYPE,
+            CONTENT_ENCODING,
+            CONTENT_RANGE,
+            TRANSFER_ENCODING,
+        ] {
+            ctx.resp.headers.remove(name);
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl<S: State> EntityTag for Context<S> {
+    #[inline]
+    async fn etag(&mut self) -> Result {
+        let data = match std::mem::take(&mut self.resp.body) {
+            Body::Empty => Bytes::new(),
+            Body::Once(bytes) => bytes,
+            mut body => {
+                let mut data = Vec::new();
+                while let Some(chunk) = body.next().await {
+                    data.extend_from_slice(&chunk?);
+                }
+                data.into()
+            }
+        };
+        let tag = entity_tag(&data);
+        let is_safe = *self.method() == Method::GET || *self.method() == Method::HEAD;
+        if is_safe && none_match(self, &tag) {
+            self.resp.status = StatusCode::NOT_MODIFIED;
+            self.resp.headers.remove(CONTENT_LENGTH);
+        } else {
+            self.resp.body = Body::Once(data);
+        }
+        self.resp.headers.insert(ETAG, HeaderValue::from_str(&tag)?);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{conditional, EntityTag};
+    use crate::http::header::{
+        CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED,
+    };
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        ctx.resp.write("Hello, ");
+        ctx.resp.write("World");
+        ctx.etag().await
+    }
+
+    #[tokio::test]
+    async fn etag() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let tag = resp.headers()[ETAG].to_str()?.to_string();
+        assert_eq!("\"c-265b86c6\"", tag);
+        assert_eq!("Hello, World", resp.text().await?);
+
+        // matched
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(IF_NONE_MATCH, format!("\"other\", W/{}", tag))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+        assert_eq!(tag, resp.headers()[ETAG].to_str()?);
+        assert!(resp.text().await?.is_empty());
+
+        // mismatched
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(IF_NONE_MATCH, "\"other\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn conditional_get() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp
+                .headers
+                .insert(CACHE_CONTROL, "max-age=60".parse()?);
+            match ctx.uri().path() {
+                "/etag" => ctx.resp.headers.insert(ETAG, "W/\"v1\"".parse()?),
+                _ => ctx
+                    .resp
+                    .headers
+                    .insert(LAST_MODIFIED, "Wed, 21 Oct 2015 07:28:00 GMT".parse()?),
+            };
+            ctx.write("Hello, World");
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(conditional).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .get(&format!("http://{}/etag", addr))
+            .header(IF_NONE_MATCH, "\"v1\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+        assert_eq!("W/\"v1\"", resp.headers()[ETAG]);
+        assert_eq!("max-age=60", resp.headers()[CACHE_CONTROL]);
+        assert!(resp.headers().get(CONTENT_TYPE).is_none());
+        assert!(resp.text().await?.is_empty());
+
+        let resp = client
+            .get(&format!("http://{}/etag", addr))
+            .header(IF_NONE_MATCH, "\"v2\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let resp = client
+            .get(&format!("http://{}/date", addr))
+            .header(IF_MODIFIED_SINCE, "Wed, 21 Oct 2015 07:28:00 GMT")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::NOT_MODIFIED, resp.status());
+        assert!(resp.headers().get(CONTENT_LENGTH).is_none());
+
+        let resp = client
+            .get(&format!("http://{}/date", addr))
+            .header(IF_MODIFIED_SINCE, "Tue, 20 Oct 2015 07:28:00 GMT")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // unsafe method.
+        let resp = client
+            .post(&format!("http://{}/etag", addr))
+            .header(IF_NONE_MATCH, "\"v1\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+}
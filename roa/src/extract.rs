@@ -0,0 +1,226 @@
+//! This module provides a trait `FromContext` and an endpoint constructor `extract`,
+//! to declare dependencies of handlers as typed arguments.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::extract::{extract, FromContext};
+//! use roa::http::StatusCode;
+//! use roa::{async_trait, throw, App, Context, Result};
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! struct Auth(User);
+//!
+//! #[async_trait(?Send)]
+//! impl<S> FromContext<S> for Auth {
+//!     async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+//!         match ctx.load::<User>("user") {
+//!             Some(user) => Ok(Auth((*user).clone())),
+//!             None => throw!(StatusCode::UNAUTHORIZED),
+//!         }
+//!     }
+//! }
+//!
+//! async fn profile(ctx: &mut Context, Auth(user): Auth) -> Result {
+//!     ctx.resp.write(user.name);
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().end(extract(profile));
+//! ```
+
+use crate::{async_trait, Context, Endpoint, Result};
+use std::future::Future;
+use std::marker::PhantomData;
+
+/// A value extracted from context before the handler is called.
+///
+/// Extraction fails with a status thrown by `from_context`,
+/// so the handler isn't called.
+/// `Option<T>` is `None` if `T` fails with a client error (4xx), like a missing value,
+/// other errors are thrown as they are. Tuples extract elements in order.
+#[async_trait(?Send)]
+pub trait FromContext<S>: Sized {
+    /// Extract a value from context.
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self>;
+}
+
+/// A handler taking context and extracted arguments,
+/// implemented by async functions like `async fn(&mut Context<S>, T) -> Result`.
+pub trait Handler<'a, S, T>: 'static + Sync + Send {
+    /// The future returned by handler.
+    type Future: 'a + Future<Output = Result>;
+
+    /// Call this handler.
+    fn handle(&self, ctx: &'a mut Context<S>, args: T) -> Self::Future;
+}
+
+/// An endpoint extracting arguments before calling the handler.
+pub struct Extract<F, T> {
+    handler: F,
+    _args: PhantomData<fn() -> T>,
+}
+
+/// Construct an endpoint calling `handler` with arguments extracted by `FromContext`.
+///
+/// Use a tuple for multiple arguments.
+pub fn extract<S, T, F>(handler: F) -> Extract<F, T>
+where
+    F: for<'a> Handler<'a, S, T>,
+{
+    Extract {
+        handler,
+        _args: PhantomData,
+    }
+}
+
+impl<'a, S, T, F, Fut> Handler<'a, S, T> for F
+where
+    S: 'a,
+    F: 'static + Sync + Send + Fn(&'a mut Context<S>, T) -> Fut,
+    Fut: 'a + Future<Output = Result>,
+{
+    type Future = Fut;
+
+    #[inline]
+    fn handle(&self, ctx: &'a mut Context<S>, args: T) -> Self::Future {
+        (self)(ctx, args)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, T, F> Endpoint<'a, S> for Extract<F, T>
+where
+    S: 'a,
+    T: FromContext<S>,
+    F: Handler<'a, S, T>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let args = T::from_context(ctx).await?;
+        self.handler.handle(ctx, args).await
+    }
+}
+
+#[async_trait(?Send)]
+impl<S, T> FromContext<S> for Option<T>
+where
+    T: FromContext<S>,
+{
+    #[inline]
+    async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+        match T::from_context(ctx).await {
+            Ok(value) => Ok(Some(value)),
+            Err(status) if status.is_client_error() => Ok(None),
+            Err(status) => Err(status),
+        }
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($arg:ident),+) => {
+        #[async_trait(?Send)]
+        impl<S, $($arg),+> FromContext<S> for ($($arg,)+)
+        where
+            $($arg: FromContext<S>),+
+        {
+            #[inline]
+            async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+                Ok(($($arg::from_context(ctx).await?,)+))
+            }
+        }
+    };
+}
+
+impl_tuple!(A);
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{extract, FromContext};
+    use crate::http::header::AUTHORIZATION;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{async_trait, throw, App, Context, Next, Result};
+    use async_std::task::spawn;
+
+    struct Auth(String);
+
+    struct Lang(String);
+
+    #[async_trait(?Send)]
+    impl<S> FromContext<S> for Auth {
+        async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+            match ctx.load::<String>("user") {
+                Some(user) => Ok(Auth(user.to_string())),
+                None => throw!(StatusCode::UNAUTHORIZED),
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl<S> FromContext<S> for Lang {
+        async fn from_context(ctx: &mut Context<S>) -> Result<Self> {
+            match ctx.get("lang") {
+                Some("error") => throw!(StatusCode::INTERNAL_SERVER_ERROR),
+                Some(lang) => Ok(Lang(lang.to_string())),
+                None => throw!(StatusCode::BAD_REQUEST),
+            }
+        }
+    }
+
+    async fn authenticate(ctx: &mut Context, next: Next<'_>) -> Result {
+        if let Some(user) = ctx.get(AUTHORIZATION) {
+            let user = user.to_string();
+            ctx.store("user", user);
+        }
+        next.await
+    }
+
+    async fn greet(
+        ctx: &mut Context,
+        (Auth(user), lang): (Auth, Option<Lang>),
+    ) -> Result {
+        let lang = lang.map(|Lang(lang)| lang).unwrap_or_default();
+        ctx.resp.write(format!("{} {}", user, lang));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn extract_args() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(authenticate).end(extract(greet)).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(AUTHORIZATION, "alice")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("alice ", resp.text().await?);
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(AUTHORIZATION, "alice")
+            .header("lang", "en")
+            .send()
+            .await?;
+        assert_eq!("alice en", resp.text().await?);
+        // server errors are not swallowed by `Option`.
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(AUTHORIZATION, "alice")
+            .header("lang", "error")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! This module provides a combinator `on_content_type`,
+//! to apply a middleware conditionally by "Content-Type" of response.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::filter::on_content_type;
+//! use roa::compress::{Compress, Level};
+//! use roa::App;
+//!
+//! let app = App::new()
+//!     .gate(on_content_type("text/*", Compress(Level::Fastest)))
+//!     .end("Hello, World");
+//! ```
+
+use crate::http::header::CONTENT_TYPE;
+use crate::{async_trait, Context, Middleware, Next, Result, Status};
+use futures::future::ok;
+
+/// A middleware applying the inner middleware
+/// only if "Content-Type" of response matches a pattern.
+///
+/// The inner middleware is called after the response is produced,
+/// with a `next` which does nothing, so only its post-processing takes effect.
+/// Responses without "Content-Type" don't match.
+pub struct OnContentType<M> {
+    pattern: String,
+    middleware: M,
+}
+
+/// Apply `middleware` only if "Content-Type" of response matches `pattern`,
+/// like "text/html", "text/*" or "*/*". Parameters like charset are ignored.
+pub fn on_content_type<M>(
+    pattern: impl Into<String>,
+    middleware: M,
+) -> OnContentType<M> {
+    OnContentType {
+        pattern: pattern.into(),
+        middleware,
+    }
+}
+
+/// Check if a media type matches a pattern like "text/html" or "text/*".
+#[inline]
+pub(crate) fn media_type_matches(media_type: &str, pattern: &str) -> bool {
+    if pattern == "*/*" {
+        !media_type.is_empty()
+    } else if pattern.ends_with("/*") {
+        let prefix = &pattern[..pattern.len() - 1];
+        media_type.len() >= prefix.len()
+            && media_type[..prefix.len()].eq_ignore_ascii_case(prefix)
+    } else {
+        media_type.eq_ignore_ascii_case(pattern)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, M> Middleware<'a, S> for OnContentType<M>
+where
+    M: for<'b> Middleware<'b, S>,
+{
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        next.await?;
+        let matched = ctx
+            .resp
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|media_type| media_type_matches(media_type.trim(), &self.pattern))
+            .unwrap_or(false);
+        if matched {
+            self.middleware
+                .handle(ctx, &mut ok::<_, Status>(()))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::on_content_type;
+    use crate::http::header::CONTENT_TYPE;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context, Next, Result};
+    use async_std::task::spawn;
+
+    async fn mark(ctx: &mut Context, next: Next<'_>) -> Result {
+        next.await?;
+        ctx.resp.headers.insert("x-marked", "true".parse()?);
+        Ok(())
+    }
+
+    async fn end(ctx: &mut Context) -> Result {
+        if let Some(content_type) = ctx.req.uri.query() {
+            let content_type = content_type.replace("%2F", "/");
+            ctx.resp.headers.insert(CONTENT_TYPE, content_type.parse()?);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn content_type() -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new()
+            .gate(on_content_type("text/*", mark))
+            .end(end)
+            .run()?;
+        spawn(server);
+        let marked = |query: &'static str| async move {
+            let resp = reqwest::get(&format!("http://{}?{}", addr, query)).await?;
+            assert_eq!(StatusCode::OK, resp.status());
+            Ok::<_, reqwest::Error>(resp.headers().contains_key("x-marked"))
+        };
+        assert!(marked("text/html;charset=utf-8").await?);
+        assert!(marked("TEXT%2Fplain").await?);
+        assert!(!marked("application/json").await?);
+        assert!(!marked("").await?);
+        Ok(())
+    }
+}
@@ -2,8 +2,10 @@
 //! which is used to parse `X-Forwarded-*` headers.
 
 use crate::http::header::HOST;
-use crate::{Context, State};
+use crate::http::StatusCode;
+use crate::{throw, Context, Result, State, Status};
 use std::net::IpAddr;
+use url::Url;
 
 /// A context extension `Forward` used to parse `X-Forwarded-*` request headers.
 pub trait Forward {
@@ -30,6 +32,9 @@ pub trait Forward {
     /// - If "x-forwarded-for" is set and valid, use the first ip.
     /// - Else use the ip of `Context::remote_addr()`.
     ///
+    /// "x-forwarded-for" can be forged by clients, never use it for access control;
+    /// `roa::router::allow_ip` decides by remote address and trusted proxies instead.
+    ///
     /// ### Example
     /// ```rust
     /// use roa::{Context, Result};
@@ -75,6 +80,46 @@ pub trait Forward {
     /// }
     /// ```
     fn forwarded_proto(&self) -> Option<&str>;
+
+    /// Check if the request is sent over https.
+    /// - If TLS is terminated by this app (see `Context::is_tls`), return true.
+    /// - Else if uri has a scheme, check if it's "https".
+    /// - Else if "x-forwarded-proto" is set, check if it's "https".
+    /// - Else return false.
+    ///
+    /// "x-forwarded-proto" can be forged by clients,
+    /// only trust it if the app runs behind a proxy that overrides it.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::forward::Forward;
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     println!("secure: {}", ctx.is_secure());
+    ///     Ok(())
+    /// }
+    /// ```
+    fn is_secure(&self) -> bool;
+
+    /// Get full url of the request, assembled by scheme of `is_secure`,
+    /// host of `host` and path and query of uri.
+    /// - If host is missing, throw Err(400 BAD REQUEST).
+    /// - If host is invalid, like "github.com/foo" or "user@github.com",
+    ///   throw Err(400 BAD REQUEST).
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::{Context, Result};
+    /// use roa::forward::Forward;
+    ///
+    /// async fn get(ctx: &mut Context) -> Result {
+    ///     let url = ctx.full_url()?;
+    ///     println!("origin: {}", url.origin().ascii_serialization());
+    ///     Ok(())
+    /// }
+    /// ```
+    fn full_url(&self) -> Result<Url>;
 }
 
 impl<S: State> Forward for Context<S> {
@@ -110,6 +155,47 @@ impl<S: State> Forward for Context<S> {
     fn forwarded_proto(&self) -> Option<&str> {
         self.get("x-forwarded-proto")
     }
+
+    #[inline]
+    fn is_secure(&self) -> bool {
+        if self.is_tls() {
+            return true;
+        }
+        match self.uri().scheme_str() {
+            Some(scheme) => scheme.eq_ignore_ascii_case("https"),
+            None => self
+                .forwarded_proto()
+                .map(|proto| proto.trim().eq_ignore_ascii_case("https"))
+                .unwrap_or(false),
+        }
+    }
+
+    #[inline]
+    fn full_url(&self) -> Result<Url> {
+        let host = match self.host() {
+            Some(host) => host,
+            None => throw!(StatusCode::BAD_REQUEST, "host is required"),
+        };
+        let scheme = if self.is_secure() { "https" } else { "http" };
+        let mut url = Url::parse(&format!("{}://{}", scheme, host)).map_err(|err| {
+            Status::new(
+                StatusCode::BAD_REQUEST,
+                format!("invalid host `{}`: {}", host, err),
+                true,
+            )
+        })?;
+        if !url.username().is_empty()
+            || url.password().is_some()
+            || url.path() != "/"
+            || url.query().is_some()
+            || url.fragment().is_some()
+        {
+            throw!(StatusCode::BAD_REQUEST, format!("invalid host `{}`", host))
+        }
+        url.set_path(self.uri().path());
+        url.set_query(self.uri().query());
+        Ok(url)
+    }
 }
 
 #[cfg(all(test, feature = "tcp"))]
@@ -204,4 +290,53 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn full_url() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.resp.write(ctx.full_url()?.to_string());
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(&format!("http://{}/foo/bar%20baz?name=roa", addr))
+            .header("x-forwarded-host", "github.com:8080")
+            .header("x-forwarded-proto", "https")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            "https://github.com:8080/foo/bar%20baz?name=roa",
+            resp.text().await?
+        );
+
+        let resp = client.get(&format!("http://{}/foo", addr)).send().await?;
+        assert_eq!(format!("http://{}/foo", addr), resp.text().await?);
+
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header("x-forwarded-host", "github.com/foo")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn full_url_without_host() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            ctx.req.headers.remove(HOST);
+            let status = ctx.full_url().unwrap_err();
+            assert_eq!(StatusCode::BAD_REQUEST, status.status_code);
+            assert_eq!("host is required", status.message);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
 }
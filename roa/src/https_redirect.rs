@@ -0,0 +1,188 @@
+//! This module provides a middleware `HttpsRedirect`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::https_redirect::HttpsRedirect;
+//! use roa::preload::*;
+//! use roa::App;
+//! use std::error::Error;
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! // behind a proxy setting "x-forwarded-proto"
+//! let app = App::new().gate(HttpsRedirect::new()).end("Hello, World");
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::forward::Forward;
+use crate::http::header::LOCATION;
+use crate::http::uri::Authority;
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result, State};
+
+/// A middleware to enforce https.
+///
+/// Plaintext requests, as `Forward::is_secure` determines,
+/// are redirected to the https url with the same host (without port), path and query,
+/// or rejected with 403 FORBIDDEN in reject mode.
+#[derive(Debug, Copy, Clone)]
+pub struct HttpsRedirect {
+    status: StatusCode,
+    reject: bool,
+}
+
+impl HttpsRedirect {
+    /// Construct a middleware redirecting with 308 PERMANENT REDIRECT.
+    pub fn new() -> Self {
+        Self {
+            status: StatusCode::PERMANENT_REDIRECT,
+            reject: false,
+        }
+    }
+
+    /// Set status code of redirection, like 301 MOVED PERMANENTLY.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Reject plaintext requests with 403 FORBIDDEN instead of redirecting.
+    pub fn reject(mut self) -> Self {
+        self.reject = true;
+        self
+    }
+}
+
+impl Default for HttpsRedirect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Middleware<'a, S> for HttpsRedirect {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        if ctx.is_secure() {
+            return next.await;
+        }
+        if self.reject {
+            throw!(StatusCode::FORBIDDEN, "https is required")
+        }
+        // port of plaintext server is meaningless for https, so it's dropped.
+        let host = match ctx.uri().authority() {
+            Some(authority) => authority.host().to_string(),
+            None => match ctx.host() {
+                Some(host) => match host.parse::<Authority>() {
+                    Ok(authority) => authority.host().to_string(),
+                    Err(_) => throw!(
+                        StatusCode::BAD_REQUEST,
+                        format!("invalid host `{}`", host)
+                    ),
+                },
+                None => throw!(StatusCode::BAD_REQUEST, "host is required"),
+            },
+        };
+        let path_and_query = ctx
+            .uri()
+            .path_and_query()
+            .map(|value| value.as_str())
+            .unwrap_or("/");
+        let location = format!("https://{}{}", host, path_and_query);
+        ctx.resp.headers.insert(LOCATION, location.parse()?);
+        throw!(self.status)
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::HttpsRedirect;
+    use crate::http::header::{HOST, LOCATION};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::App;
+    use async_std::task::spawn;
+
+    #[tokio::test]
+    async fn redirect() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new()
+            .gate(HttpsRedirect::new().status(StatusCode::MOVED_PERMANENTLY))
+            .end(())
+            .run()?;
+        spawn(server);
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let resp = client
+            .get(&format!("http://{}/path?id=0", addr))
+            .header(HOST, "github.com")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::MOVED_PERMANENTLY, resp.status());
+        assert_eq!("https://github.com/path?id=0", resp.headers()[LOCATION]);
+
+        // port is dropped.
+        let resp = client
+            .get(&format!("http://{}/path?id=0", addr))
+            .header(HOST, "github.com:8080")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::MOVED_PERMANENTLY, resp.status());
+        assert_eq!("https://github.com/path?id=0", resp.headers()[LOCATION]);
+
+        let resp = client
+            .get(&format!("http://{}/path?id=0", addr))
+            .header("x-forwarded-proto", "https")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reject() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new()
+            .gate(HttpsRedirect::new().reject())
+            .end(())
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+        Ok(())
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::tls::internal::pemfile::{certs, rsa_private_keys};
+        use crate::tls::{NoClientAuth, ServerConfig, TlsListener};
+        use std::fs::File;
+        use std::io::BufReader;
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        let mut cert_file = BufReader::new(File::open("../assets/cert.pem")?);
+        let mut key_file = BufReader::new(File::open("../assets/key.pem")?);
+        let cert_chain = certs(&mut cert_file).unwrap();
+        let mut keys = rsa_private_keys(&mut key_file).unwrap();
+        config.set_single_cert(cert_chain, keys.remove(0))?;
+
+        let (addr, server) = App::new()
+            .gate(HttpsRedirect::new())
+            .end("Hello, World")
+            .run_tls(config)?;
+        spawn(server);
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let resp = client
+            .get(&format!("https://localhost:{}", addr.port()))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+}
@@ -73,7 +73,6 @@ pub use jsonwebtoken::{DecodingKey, Validation};
 use crate::http::header::{HeaderValue, WWW_AUTHENTICATE};
 use crate::http::StatusCode;
 use crate::{async_trait, throw, Context, Middleware, Next, Result, Status};
-use headers::{authorization::Bearer, Authorization, HeaderMapExt};
 use jsonwebtoken::decode;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
@@ -160,12 +159,12 @@ impl JwtGuard {
 
     /// Verify token.
     #[inline]
-    fn verify<S>(&self, ctx: &Context<S>) -> Option<(Bearer, Value)> {
-        let bearer = ctx.req.headers.typed_get::<Authorization<Bearer>>()?.0;
-        let value = decode::<Value>(bearer.token(), &self.secret, &self.validation)
+    fn verify<S>(&self, ctx: &Context<S>) -> Option<(String, Value)> {
+        let token = ctx.credentials("Bearer")?;
+        let value = decode::<Value>(token, &self.secret, &self.validation)
             .ok()?
             .claims;
-        Some((bearer, value))
+        Some((token.to_string(), value))
     }
 }
 
@@ -178,9 +177,9 @@ impl<'a, S> Middleware<'a, S> for JwtGuard {
                 set_www_authenticate(ctx);
                 throw!(StatusCode::UNAUTHORIZED)
             }
-            Some((bearer, value)) => {
+            Some((token, value)) => {
                 ctx.store_scoped(JwtScope, "secret", self.secret.clone());
-                ctx.store_scoped(JwtScope, "token", bearer);
+                ctx.store_scoped(JwtScope, "token", token);
                 ctx.store_scoped(JwtScope, "value", value);
                 next.await
             }
@@ -207,17 +206,15 @@ impl<S> JwtVerifier<S> for Context<S> {
         C: 'static + DeserializeOwned,
     {
         let secret = self.load_scoped::<JwtScope, DecodingKey<'static>>("secret");
-        let token = self.load_scoped::<JwtScope, Bearer>("token");
+        let token = self.load_scoped::<JwtScope, String>("token");
         match (secret, token) {
-            (Some(secret), Some(token)) => {
-                match decode(token.token(), &secret, validation) {
-                    Ok(data) => Ok(data.claims),
-                    Err(_) => {
-                        set_www_authenticate(self);
-                        throw!(StatusCode::UNAUTHORIZED)
-                    }
+            (Some(secret), Some(token)) => match decode(&token, &secret, validation) {
+                Ok(data) => Ok(data.claims),
+                Err(_) => {
+                    set_www_authenticate(self);
+                    throw!(StatusCode::UNAUTHORIZED)
                 }
-            }
+            },
             _ => Err(guard_not_set()),
         }
     }
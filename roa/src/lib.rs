@@ -32,18 +32,57 @@ pub mod jwt;
 #[cfg_attr(feature = "docs", doc(cfg(feature = "compress")))]
 pub mod compress;
 
+#[cfg(feature = "compress")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "compress")))]
+pub mod decompress;
+
+#[cfg(feature = "client")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "client")))]
+pub mod client;
+
+#[cfg(feature = "timeout")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "timeout")))]
+pub mod timeout;
+
+#[cfg(feature = "sse")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "sse")))]
+pub mod sse;
+
+#[cfg(feature = "json")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "json")))]
+pub mod problem;
+
 pub mod body;
+pub mod cache;
+pub mod clock;
 pub mod cors;
+pub mod etag;
+pub mod extract;
+pub mod filter;
 pub mod forward;
+pub mod https_redirect;
+pub mod limit;
 pub mod logger;
+pub mod maintenance;
+pub mod negotiate;
+pub mod normalize_path;
 pub mod query;
+pub mod range;
 pub mod stream;
+pub mod timing;
+pub mod trace;
 
 /// Reexport all extension traits.
 pub mod preload {
     pub use crate::body::PowerBody;
+    pub use crate::cache::SetCacheControl;
+    pub use crate::etag::EntityTag;
     pub use crate::forward::Forward;
+    pub use crate::negotiate::Negotiate;
     pub use crate::query::Query;
+    pub use crate::range::ServeRanged;
+    pub use crate::timing::ServerTiming;
+    pub use crate::trace::StatusTrace;
 
     #[cfg(feature = "tcp")]
     #[doc(no_inline)]
@@ -54,11 +93,26 @@ pub mod preload {
     pub use crate::tls::TlsListener;
 
     #[cfg(feature = "cookies")]
-    pub use crate::cookie::{CookieGetter, CookieSetter};
+    pub use crate::cookie::{CookieGetter, CookieJarExt, CookieSetter};
+
+    #[cfg(feature = "compress")]
+    pub use crate::compress::ServeUpstream;
 
     #[cfg(feature = "jwt")]
     pub use crate::jwt::JwtVerifier;
 
     #[cfg(feature = "router")]
     pub use crate::router::RouterParam;
+
+    #[cfg(feature = "timeout")]
+    pub use crate::timeout::Deadline;
+
+    #[cfg(feature = "sse")]
+    pub use crate::sse::ServeEvents;
+
+    #[cfg(feature = "json")]
+    pub use crate::problem::ProblemExt;
+
+    #[cfg(feature = "websocket")]
+    pub use crate::websocket::WebsocketRequest;
 }
@@ -0,0 +1,788 @@
+//! This module provides middlewares to limit resources used by requests.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::limit::ConcurrencyLimit;
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let limit = ConcurrencyLimit::new(1024).retry_after(5);
+//! let in_flight = limit.in_flight();
+//! let app = App::new().gate(limit).end("Hello, World");
+//! let (addr, server) = app.run()?;
+//! println!("in-flight requests: {}", in_flight.get());
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ### Body limit
+//!
+//! ```rust
+//! use roa::limit::BodyLimit;
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     // 413 PAYLOAD TOO LARGE if body exceeds 1 MiB.
+//!     let data = ctx.read().await?;
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().gate(BodyLimit::new(1024 * 1024)).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+//!
+//! ### Rate limit
+//!
+//! ```rust
+//! use roa::limit::RateLimit;
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//! use std::time::Duration;
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! // at most 100 successful requests per minute from each client ip,
+//! // failed requests are not counted.
+//! let limit = RateLimit::new(100, Duration::from_secs(60)).count_success();
+//! let app = App::new().gate(limit).end("Hello, World");
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::http::header::{CONTENT_LENGTH, RETRY_AFTER};
+use crate::http::StatusCode;
+use crate::{
+    async_trait, throw, Context, Middleware, Next, PayloadTooLarge, Request, Result,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A middleware to limit the number of concurrent in-flight requests.
+///
+/// Requests exceeding the limit will not be queued,
+/// they get a 503 SERVICE UNAVAILABLE with "Retry-After" immediately.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+    max: usize,
+    retry_after: u64,
+    counter: InFlight,
+}
+
+/// A shared counter of in-flight requests.
+#[derive(Debug, Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+/// A guard to decrease counter when it's dropped.
+struct InFlightGuard<'a>(&'a InFlight);
+
+impl ConcurrencyLimit {
+    /// Construct a middleware allowing `max` in-flight requests at the same time.
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            retry_after: 1,
+            counter: InFlight::default(),
+        }
+    }
+
+    /// Set value of "Retry-After" in seconds, default 1.
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = seconds;
+        self
+    }
+
+    /// Get a handle of counter, to read the number of in-flight requests.
+    pub fn in_flight(&self) -> InFlight {
+        self.counter.clone()
+    }
+}
+
+impl InFlight {
+    /// Get the number of current in-flight requests.
+    #[inline]
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Try to increase counter, return a guard if counter is less than max.
+    #[inline]
+    fn acquire(&self, max: usize) -> Option<InFlightGuard<'_>> {
+        if self.0.fetch_add(1, Ordering::SeqCst) >= max {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+            None
+        } else {
+            Some(InFlightGuard(self))
+        }
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for ConcurrencyLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        match self.counter.acquire(self.max) {
+            Some(_guard) => next.await,
+            None => {
+                ctx.resp
+                    .headers
+                    .insert(RETRY_AFTER, self.retry_after.to_string().parse()?);
+                throw!(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "too many requests in flight"
+                )
+            }
+        }
+    }
+}
+
+/// Default limit of total header bytes, 16 KiB.
+const DEFAULT_HEADER_SIZE: usize = 16 * 1024;
+
+/// Default limit of header count.
+const DEFAULT_HEADER_COUNT: usize = 100;
+
+/// Room left for the request line in the read buffer, 8 KiB.
+const REQUEST_LINE_SIZE: usize = 8 * 1024;
+
+/// A middleware to limit total bytes and count of request headers.
+///
+/// Requests exceeding the limits get a 431 REQUEST HEADER FIELDS TOO LARGE.
+///
+/// Note that hyper has its own hard limits (at most 100 headers for HTTP/1.1),
+/// requests exceeding them are rejected before reaching this middleware.
+///
+/// Hyper buffers the whole request head before this middleware runs, pass `buf_size`
+/// to `App::max_buf_size` to keep huge heads from being buffered at all.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::limit::HeaderLimit;
+/// use roa::App;
+///
+/// let limit = HeaderLimit::new().max_size(8 * 1024);
+/// let app = App::new()
+///     .max_buf_size(limit.buf_size())
+///     .gate(limit)
+///     .end("Hello, World");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct HeaderLimit {
+    max_size: usize,
+    max_count: usize,
+}
+
+impl HeaderLimit {
+    /// Construct a middleware with default limits (16 KiB, 100 headers).
+    pub fn new() -> Self {
+        Self {
+            max_size: DEFAULT_HEADER_SIZE,
+            max_count: DEFAULT_HEADER_COUNT,
+        }
+    }
+
+    /// Set limit of total header bytes, including names and values.
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Set limit of header count, repeated headers are counted separately.
+    pub fn max_count(mut self, count: usize) -> Self {
+        self.max_count = count;
+        self
+    }
+
+    /// Size of a read buffer holding any request head within the limits,
+    /// including the request line and separators of each header.
+    pub fn buf_size(&self) -> usize {
+        self.max_size
+            .saturating_add(self.max_count.saturating_mul(4))
+            .saturating_add(REQUEST_LINE_SIZE)
+    }
+}
+
+impl Default for HeaderLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for HeaderLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let headers = &ctx.req.headers;
+        if headers.len() > self.max_count {
+            throw!(
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                format!("too many headers, limit is {}", self.max_count)
+            )
+        }
+        let size: usize = headers
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if size > self.max_size {
+            throw!(
+                StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+                format!("headers too large, limit is {} bytes", self.max_size)
+            )
+        }
+        next.await
+    }
+}
+
+/// Default limit of query string length, 8 KiB.
+const DEFAULT_QUERY_LENGTH: usize = 8 * 1024;
+
+/// A middleware to limit length of query string, it should be used before `query_parser`.
+///
+/// Requests exceeding the limit get a 414 URI TOO LONG.
+#[derive(Debug, Copy, Clone)]
+pub struct QueryLimit {
+    max_len: usize,
+}
+
+impl QueryLimit {
+    /// Construct a middleware with default limit (8 KiB).
+    pub fn new() -> Self {
+        Self {
+            max_len: DEFAULT_QUERY_LENGTH,
+        }
+    }
+
+    /// Set limit of query string length in bytes, excluding "?".
+    pub fn max_len(mut self, bytes: usize) -> Self {
+        self.max_len = bytes;
+        self
+    }
+}
+
+impl Default for QueryLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for QueryLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let len = ctx.uri().query().map(str::len).unwrap_or(0);
+        if len > self.max_len {
+            throw!(
+                StatusCode::URI_TOO_LONG,
+                format!("query string too long, limit is {} bytes", self.max_len)
+            )
+        }
+        next.await
+    }
+}
+
+/// A middleware to limit size of request body.
+///
+/// The limit is set by `Request::limit_body`, so it governs all body readers,
+/// including `PowerBody` and roa-multipart. The smaller limit wins when it's nested.
+///
+/// Requests with a "Content-Length" exceeding the limit get a 413 PAYLOAD TOO LARGE immediately,
+/// others get it when the body is read.
+#[derive(Debug, Copy, Clone)]
+pub struct BodyLimit(u64);
+
+impl BodyLimit {
+    /// Construct a middleware with limit of body in bytes.
+    pub fn new(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for BodyLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        ctx.req.limit_body(self.0);
+        let limit = ctx.req.body_limit().unwrap_or(self.0);
+        let content_length = ctx
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.parse::<u64>().ok());
+        if let Some(len) = content_length {
+            if len > limit {
+                throw!(StatusCode::PAYLOAD_TOO_LARGE, PayloadTooLarge { limit })
+            }
+        }
+        next.await
+    }
+}
+
+/// A predicate to decide whether a request should be counted by its result.
+type Predicate = Box<dyn 'static + Fn(&Result) -> bool + Sync + Send>;
+
+/// A function to get the key of a request, requests with the same key share a window.
+type KeyFn = Box<dyn 'static + Fn(&Request, SocketAddr) -> String + Sync + Send>;
+
+/// A middleware to limit the number of requests in a fixed time window.
+///
+/// Requests are limited per key, the ip of peer address by default.
+/// Behind a reverse proxy, set `key_by` to use a forwarded address or a credential instead.
+///
+/// By default, every request is counted before it's handled.
+/// With a predicate set by `count_if`, the inner middlewares run first,
+/// and the request is counted only if the predicate returns true on its result.
+///
+/// Requests exceeding the limit get a 429 TOO MANY REQUESTS with "Retry-After".
+///
+/// Time is read from `SystemClock` by default, it can be replaced by `clock`.
+pub struct RateLimit {
+    max: u64,
+    window: Duration,
+    counter: Mutex<Windows>,
+    predicate: Option<Predicate>,
+    key: KeyFn,
+    clock: Arc<dyn Clock>,
+}
+
+/// Counter of current window.
+struct Window {
+    start: Instant,
+    count: u64,
+}
+
+/// Windows of all keys.
+struct Windows {
+    map: HashMap<String, Window>,
+    pruned: Instant,
+}
+
+impl Windows {
+    /// Construct empty windows.
+    fn new(now: Instant) -> Self {
+        Self {
+            map: HashMap::new(),
+            pruned: now,
+        }
+    }
+}
+
+impl RateLimit {
+    /// Construct a middleware allowing `max` requests in each `window`.
+    pub fn new(max: u64, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            counter: Mutex::new(Windows::new(Instant::now())),
+            predicate: None,
+            key: Box::new(|_req: &Request, addr: SocketAddr| addr.ip().to_string()),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the clock to read time, all windows restart.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.counter = Mutex::new(Windows::new(clock.now()));
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the function to get the key of a request from the request and peer address.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::limit::RateLimit;
+    /// use std::time::Duration;
+    ///
+    /// // limit by api key.
+    /// let limit = RateLimit::new(100, Duration::from_secs(60)).key_by(|req, _addr| {
+    ///     req.headers
+    ///         .get("x-api-key")
+    ///         .and_then(|value| value.to_str().ok())
+    ///         .unwrap_or_default()
+    ///         .to_string()
+    /// });
+    /// ```
+    pub fn key_by(
+        mut self,
+        key: impl 'static + Fn(&Request, SocketAddr) -> String + Sync + Send,
+    ) -> Self {
+        self.key = Box::new(key);
+        self
+    }
+
+    /// Count a request only if the predicate returns true on its result.
+    ///
+    /// As the inner middlewares run before counting,
+    /// concurrent requests may exceed the limit slightly.
+    pub fn count_if(
+        mut self,
+        predicate: impl 'static + Fn(&Result) -> bool + Sync + Send,
+    ) -> Self {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    /// Count successful requests only.
+    pub fn count_success(self) -> Self {
+        self.count_if(|result| result.is_ok())
+    }
+
+    /// Lock the counter and call `f` with window of the key, reset it if it expires.
+    ///
+    /// Expired windows of other keys are dropped at most once per window.
+    #[inline]
+    fn with_window<R>(&self, key: &str, f: impl FnOnce(&mut Window, Instant) -> R) -> R {
+        let mut windows = self
+            .counter
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = self.clock.now();
+        let duration = self.window;
+        if now.duration_since(windows.pruned) >= duration {
+            windows
+                .map
+                .retain(|_, window| now.duration_since(window.start) < duration);
+            windows.pruned = now;
+        }
+        if !windows.map.contains_key(key) {
+            windows.map.insert(
+                key.to_string(),
+                Window {
+                    start: now,
+                    count: 0,
+                },
+            );
+        }
+        let window = windows.map.get_mut(key).expect("window has been inserted");
+        if now.duration_since(window.start) >= duration {
+            window.start = now;
+            window.count = 0;
+        }
+        f(window, now)
+    }
+
+    /// Check if there is quota left for the key, return seconds to wait if not.
+    #[inline]
+    fn check(&self, key: &str, consume: bool) -> std::result::Result<(), u64> {
+        self.with_window(key, |window, now| {
+            if window.count >= self.max {
+                let elapsed = now.duration_since(window.start);
+                let wait = self.window.checked_sub(elapsed).unwrap_or_default();
+                // round up
+                return Err(wait.as_secs() + u64::from(wait.subsec_nanos() > 0));
+            }
+            if consume {
+                window.count += 1;
+            }
+            Ok(())
+        })
+    }
+
+    /// Consume a quota of the key.
+    #[inline]
+    fn consume(&self, key: &str) {
+        self.with_window(key, |window, _| window.count += 1)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for RateLimit {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let key = (self.key)(&ctx.req, ctx.remote_addr);
+        if let Err(retry_after) = self.check(&key, self.predicate.is_none()) {
+            ctx.resp
+                .headers
+                .insert(RETRY_AFTER, retry_after.to_string().parse()?);
+            throw!(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded")
+        }
+        match self.predicate {
+            None => next.await,
+            Some(ref predicate) => {
+                let result = next.await;
+                if predicate(&result) {
+                    self.consume(&key);
+                }
+                result
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{
+        BodyLimit, ConcurrencyLimit, HeaderLimit, InFlight, QueryLimit, RateLimit,
+    };
+    use crate::clock::MockClock;
+    use crate::http::header::{HeaderValue, CONTENT_LENGTH, RETRY_AFTER, SERVER};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{async_trait, throw, App, Context, Endpoint, Next};
+    use async_std::task::spawn;
+    use std::time::Duration;
+
+    struct Assert(InFlight);
+
+    #[async_trait(?Send)]
+    impl<'a> Endpoint<'a> for Assert {
+        async fn call(&'a self, _ctx: &'a mut Context) -> crate::Result {
+            assert_eq!(1, self.0.get());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = ConcurrencyLimit::new(1);
+        let in_flight = limit.in_flight();
+        let (addr, server) = App::new()
+            .gate(limit)
+            .end(Assert(in_flight.clone()))
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(0, in_flight.get());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exceed_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = ConcurrencyLimit::new(0).retry_after(5);
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+        assert_eq!("5", resp.headers()[RETRY_AFTER]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = RateLimit::new(2, Duration::from_secs(60));
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        for _ in 0..2 {
+            let resp = reqwest::get(&format!("http://{}", addr)).await?;
+            assert_eq!(StatusCode::OK, resp.status());
+        }
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert!(resp.headers()[RETRY_AFTER].to_str()?.parse::<u64>()? <= 60);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_key() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = RateLimit::new(1, Duration::from_secs(60)).key_by(|req, _addr| {
+            req.headers
+                .get("x-user")
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        });
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        for user in &["alice", "bob"] {
+            let resp = client
+                .get(&format!("http://{}", addr))
+                .header("x-user", *user)
+                .send()
+                .await?;
+            assert_eq!(StatusCode::OK, resp.status());
+        }
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header("x-user", "alice")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_clock() -> Result<(), Box<dyn std::error::Error>> {
+        let clock = MockClock::new();
+        let limit = RateLimit::new(1, Duration::from_secs(60)).clock(clock.clone());
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        clock.advance(Duration::from_secs(30));
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert_eq!("30", resp.headers()[RETRY_AFTER]);
+        clock.advance(Duration::from_secs(30));
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limit_success() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            if ctx.uri().path() == "/fail" {
+                throw!(StatusCode::UNAUTHORIZED)
+            }
+            Ok(())
+        }
+        let limit = RateLimit::new(1, Duration::from_secs(60)).count_success();
+        let (addr, server) = App::new().gate(limit).end(end).run()?;
+        spawn(server);
+
+        // failed requests are not counted
+        for _ in 0..3 {
+            let resp = reqwest::get(&format!("http://{}/fail", addr)).await?;
+            assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+        }
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = reqwest::get(&format!("http://{}/fail", addr)).await?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn header_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = HeaderLimit::new().max_size(64).max_count(8);
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // too large
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header("x-data", "x".repeat(64))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, resp.status());
+
+        // too many
+        let mut req = client.get(&format!("http://{}", addr));
+        for i in 0..8 {
+            req = req.header(format!("x-{}", i).as_str(), "");
+        }
+        let resp = req.send().await?;
+        assert_eq!(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn header_limit_buf_size() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = HeaderLimit::new().max_size(64).max_count(8);
+        assert_eq!(64 + 32 + 8 * 1024, limit.buf_size());
+        let (addr, server) = App::new()
+            .max_buf_size(limit.buf_size())
+            .default_headers(vec![(SERVER, HeaderValue::from_static("roa"))])
+            .gate(limit)
+            .end(())
+            .run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+
+        // rejected by middleware
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header("x-data", "x".repeat(64))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, resp.status());
+        assert_eq!("roa", resp.headers()[SERVER]);
+
+        // rejected by hyper before buffering all of it
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header("x-data", "x".repeat(32 * 1024))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE, resp.status());
+        assert!(!resp.headers().contains_key(SERVER));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn query_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let limit = QueryLimit::new().max_len(16);
+        let (addr, server) = App::new().gate(limit).end(()).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}?name=roa", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp =
+            reqwest::get(&format!("http://{}?data={}", addr, "x".repeat(16))).await?;
+        assert_eq!(StatusCode::URI_TOO_LONG, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn body_limit() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read().await?;
+            ctx.resp.write(data);
+            Ok(())
+        }
+        let app = App::new()
+            .gate(BodyLimit::new(8))
+            .gate(BodyLimit::new(1024))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body("Hello")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello", resp.text().await?);
+
+        // rejected by "Content-Length"
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body("Hello, World")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+
+        // rejected in reading
+        async fn chunked(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            ctx.req.headers.remove(CONTENT_LENGTH);
+            next.await
+        }
+        let app = App::new().gate(chunked).gate(BodyLimit::new(8)).end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .body("Hello, World")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, resp.status());
+        Ok(())
+    }
+}
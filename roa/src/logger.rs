@@ -1,4 +1,5 @@
-//! This module provides a middleware `logger`.
+//! This module provides a middleware `logger` (or `Logger` reading time from a clock),
+//! and a middleware `BodyLogger` to inspect bodies for debugging.
 //!
 //! ### Example
 //!
@@ -23,19 +24,28 @@
 //! }
 //! ```
 
+use crate::clock::{Clock, SystemClock};
 use crate::http::Uri;
-use crate::{Context, Executor, JoinHandle, Next, Result};
+use crate::{
+    async_trait, Body, Context, Executor, JoinHandle, Middleware, Next, Result,
+};
 use bytes::Bytes;
 use bytesize::ByteSize;
 use futures::task::{self, Poll};
 use futures::{Future, Stream};
-use log::{error, info};
+use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
 use roa_core::http::{Method, StatusCode};
 use std::io;
 use std::mem;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Instant;
 
+lazy_static! {
+    static ref SYSTEM_CLOCK: Arc<dyn Clock> = Arc::new(SystemClock);
+}
+
 /// A finite-state machine to log success information in each successful response.
 enum StreamLogger<S> {
     /// Polling state, as a body stream.
@@ -56,6 +66,7 @@ struct LogTask {
     status_code: StatusCode,
     uri: Uri,
     start: Instant,
+    clock: Arc<dyn Clock>,
     exec: Executor,
 }
 
@@ -68,14 +79,19 @@ impl LogTask {
             status_code,
             uri,
             start,
+            clock,
             exec,
         } = self.clone();
+        let elapsed = clock
+            .now()
+            .checked_duration_since(start)
+            .unwrap_or_default();
         exec.spawn_blocking(move || {
             info!(
                 "<-- {} {} {}ms {} {}",
                 method,
                 uri,
-                start.elapsed().as_millis(),
+                elapsed.as_millis(),
                 ByteSize(counter),
                 status_code,
             )
@@ -124,9 +140,67 @@ where
 ///
 /// Based on crate `log`, the log level must be greater than `INFO` to log all information,
 /// and should be greater than `ERROR` when you need error information only.
+///
+/// Thrown client errors (4xx) are logged as `WARN`, and others as `ERROR`.
 pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    log_request(ctx, next, SYSTEM_CLOCK.clone()).await
+}
+
+/// A middleware working like `logger`, measuring response time by a clock.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::clock::MockClock;
+/// use roa::logger::Logger;
+/// use roa::App;
+///
+/// let app = App::new()
+///     .gate(Logger::new().clock(MockClock::new()))
+///     .end("Hello, World");
+/// ```
+#[derive(Clone)]
+pub struct Logger {
+    clock: Arc<dyn Clock>,
+}
+
+impl Logger {
+    /// Construct a logger reading time from `SystemClock`.
+    pub fn new() -> Self {
+        Self {
+            clock: SYSTEM_CLOCK.clone(),
+        }
+    }
+
+    /// Set the clock to read time.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Logger {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        log_request(ctx, next, self.clock.clone()).await
+    }
+}
+
+/// Log a request, measuring response time by the clock.
+async fn log_request<S>(
+    ctx: &mut Context<S>,
+    next: Next<'_>,
+    clock: Arc<dyn Clock>,
+) -> Result {
     info!("--> {} {}", ctx.method(), ctx.uri().path());
-    let start = Instant::now();
+    let start = clock.now();
     let mut result = next.await;
 
     let method = ctx.method().clone();
@@ -136,6 +210,7 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     match &mut result {
         Err(status) => {
             let status_code = status.status_code;
+            let server_error = status.is_server_error();
             let message = if status.expose {
                 status.message.clone()
             } else {
@@ -147,7 +222,11 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
             };
             ctx.exec
                 .spawn_blocking(move || {
-                    error!("<-- {} {} {}\n{}", method, uri, status_code, message,);
+                    if server_error {
+                        error!("<-- {} {} {}\n{}", method, uri, status_code, message);
+                    } else {
+                        warn!("<-- {} {} {}\n{}", method, uri, status_code, message);
+                    }
                 })
                 .await
         }
@@ -162,6 +241,7 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
                     uri,
                     status_code,
                     start,
+                    clock,
                     exec,
                 },
             };
@@ -170,3 +250,256 @@ pub async fn logger<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     }
     result
 }
+
+/// A middleware to log prefixes of request and response bodies at `DEBUG` level.
+///
+/// Bodies are streamed through a tee, which copies at most `cap` bytes of each body,
+/// handlers still read and write the whole bodies.
+/// A body is logged when it's dropped, even if it's not read completely.
+///
+/// It's enabled in debug builds by default, and a no-op in release builds.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::logger::{logger, BodyLogger};
+/// use roa::preload::*;
+/// use roa::App;
+///
+/// let app = App::new()
+///     .gate(logger)
+///     .gate(BodyLogger::new(1024))
+///     .end("Hello, World");
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct BodyLogger {
+    cap: usize,
+    enabled: bool,
+}
+
+/// A stream copying a prefix of another stream, the prefix is logged on drop.
+struct Tee<S> {
+    stream: S,
+    label: String,
+    cap: usize,
+    prefix: Vec<u8>,
+    total: u64,
+}
+
+impl BodyLogger {
+    /// Construct a middleware logging at most `cap` bytes of each body.
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            enabled: cfg!(debug_assertions),
+        }
+    }
+
+    /// Enable or disable this middleware, overriding the build profile.
+    pub fn enable(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+impl<S> Tee<S> {
+    #[inline]
+    fn new(stream: S, label: String, cap: usize) -> Self {
+        Self {
+            stream,
+            label,
+            cap,
+            prefix: Vec::new(),
+            total: 0,
+        }
+    }
+}
+
+impl<S, E> Stream for Tee<S>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+{
+    type Item = S::Item;
+
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let item = futures::ready!(Pin::new(&mut self.stream).poll_next(cx));
+        if let Some(Ok(bytes)) = &item {
+            let remaining = self.cap.saturating_sub(self.prefix.len());
+            let copied = remaining.min(bytes.len());
+            self.prefix.extend_from_slice(&bytes[..copied]);
+            self.total += bytes.len() as u64;
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl<S> Drop for Tee<S> {
+    fn drop(&mut self) {
+        log_body(&self.label, &self.prefix, self.total)
+    }
+}
+
+/// Log a prefix of body, `total` is the length of the whole body.
+#[inline]
+fn log_body(label: &str, prefix: &[u8], total: u64) {
+    let truncated = total - prefix.len() as u64;
+    if truncated == 0 {
+        debug!("{}\n{}", label, String::from_utf8_lossy(prefix));
+    } else {
+        debug!(
+            "{}\n{}... ({} truncated)",
+            label,
+            String::from_utf8_lossy(prefix),
+            ByteSize(truncated)
+        );
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for BodyLogger {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        if !self.enabled {
+            return next.await;
+        }
+        let label = format!("--> {} {} body", ctx.method(), ctx.uri());
+        let body = ctx.req.raw_body();
+        ctx.req
+            .replace_body(hyper::Body::wrap_stream(Tee::new(body, label, self.cap)));
+        let result = next.await;
+        // the status thrown is applied by upstream, so is the exposed message.
+        let status_code = match result {
+            Ok(()) => ctx.status(),
+            Err(ref status) => status.status_code,
+        };
+        let label = format!("<-- {} {} {} body", ctx.method(), ctx.uri(), status_code);
+        match (&result, &ctx.resp.body) {
+            (Err(status), _) => {
+                let message = if status.expose {
+                    status.message.as_bytes()
+                } else {
+                    &[]
+                };
+                let len = message.len().min(self.cap);
+                log_body(&label, &message[..len], message.len() as u64)
+            }
+            (Ok(()), Body::Empty) => log_body(&label, &[], 0),
+            (Ok(()), Body::Once(bytes)) => {
+                let len = bytes.len().min(self.cap);
+                log_body(&label, &bytes[..len], bytes.len() as u64)
+            }
+            (Ok(()), Body::Stream(_)) => {
+                let body = mem::take(&mut ctx.resp.body);
+                ctx.resp.write_stream(Tee::new(body, label, self.cap));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{BodyLogger, Logger, Tee};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{throw, App, Body, Context, Next};
+    use async_std::task::spawn;
+    use bytes::Bytes;
+    use futures::stream::{iter, StreamExt};
+    use std::io;
+
+    #[async_std::test]
+    async fn tee() {
+        let chunks: Vec<io::Result<Bytes>> =
+            vec![Ok("Hello".into()), Ok(", World".into())];
+        let mut tee = Tee::new(iter(chunks), String::new(), 8);
+        let mut data = Vec::new();
+        while let Some(chunk) = tee.next().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(b"Hello, World", data.as_slice());
+        assert_eq!(b"Hello, W", tee.prefix.as_slice());
+        assert_eq!(12, tee.total);
+    }
+
+    #[tokio::test]
+    async fn body_logger() -> Result<(), Box<dyn std::error::Error>> {
+        async fn echo(ctx: &mut Context) -> crate::Result {
+            let data = ctx.read().await?;
+            ctx.write(data);
+            Ok(())
+        }
+        let (addr, server) = App::new()
+            .gate(BodyLogger::new(4).enable(true))
+            .end(echo)
+            .run()?;
+        spawn(server);
+        let resp = reqwest::Client::new()
+            .post(&format!("http://{}", addr))
+            .body("Hello, World")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mock_clock() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::clock::MockClock;
+        let (addr, server) = App::new()
+            .gate(Logger::new().clock(MockClock::new()))
+            .end("Hello, World")
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keep_once_body() -> Result<(), Box<dyn std::error::Error>> {
+        async fn hello(ctx: &mut Context) -> crate::Result {
+            ctx.write("Hello, World");
+            Ok(())
+        }
+        async fn check(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            match ctx.resp.body {
+                Body::Once(_) => Ok(()),
+                _ => throw!(StatusCode::INTERNAL_SERVER_ERROR, "body is not once"),
+            }
+        }
+        let (addr, server) = App::new()
+            .gate(check)
+            .gate(BodyLogger::new(4).enable(true))
+            .end(hello)
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn log_thrown_status() -> Result<(), Box<dyn std::error::Error>> {
+        async fn bad_request(_ctx: &mut Context) -> crate::Result {
+            throw!(StatusCode::BAD_REQUEST, "bad request")
+        }
+        let (addr, server) = App::new()
+            .gate(BodyLogger::new(4).enable(true))
+            .end(bad_request)
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        assert_eq!("bad request", resp.text().await?);
+        Ok(())
+    }
+}
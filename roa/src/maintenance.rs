@@ -0,0 +1,179 @@
+//! This module provides a middleware `Maintenance`, to turn away requests during maintenance.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::maintenance::{Maintenance, MaintenanceSwitch};
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//!
+//! #[derive(Clone)]
+//! struct State {
+//!     maintenance: MaintenanceSwitch,
+//! }
+//!
+//! async fn end(ctx: &mut Context<State>) -> roa::Result {
+//!     if ctx.path() == "/admin/maintenance" {
+//!         // an admin endpoint to toggle maintenance mode.
+//!         let enabled = ctx.maintenance.toggle();
+//!         ctx.resp.write(format!("maintenance: {}", enabled));
+//!     } else {
+//!         ctx.resp.write("Hello, World");
+//!     }
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! // allow the admin endpoint, or maintenance mode can never be turned off.
+//! let maintenance = Maintenance::new()
+//!     .retry_after(120)
+//!     .allow("/healthz")
+//!     .allow("/admin/maintenance");
+//! let state = State { maintenance: maintenance.switch() };
+//! let app = App::state(state).gate(maintenance).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::RETRY_AFTER;
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A middleware to turn away requests with 503 SERVICE UNAVAILABLE during maintenance.
+///
+/// Maintenance mode is disabled by default and can be toggled at runtime by `MaintenanceSwitch`,
+/// it takes effect on the next request. Paths in allow-list, like "/healthz", are always served.
+#[derive(Debug, Clone)]
+pub struct Maintenance {
+    switch: MaintenanceSwitch,
+    retry_after: Option<u64>,
+    allowed: Vec<String>,
+}
+
+/// A shared switch of maintenance mode.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceSwitch(Arc<AtomicBool>);
+
+impl Maintenance {
+    /// Construct a middleware with maintenance mode disabled.
+    pub fn new() -> Self {
+        Self {
+            switch: MaintenanceSwitch::default(),
+            retry_after: None,
+            allowed: Vec::new(),
+        }
+    }
+
+    /// Set value of "Retry-After" in seconds, not set by default.
+    pub fn retry_after(mut self, seconds: u64) -> Self {
+        self.retry_after = Some(seconds);
+        self
+    }
+
+    /// Allow a path to be served during maintenance, it must match the request path exactly.
+    pub fn allow(mut self, path: impl ToString) -> Self {
+        self.allowed.push(path.to_string());
+        self
+    }
+
+    /// Enable maintenance mode from the beginning.
+    pub fn enabled(self) -> Self {
+        self.switch.enable();
+        self
+    }
+
+    /// Get a handle of switch, to toggle maintenance mode.
+    pub fn switch(&self) -> MaintenanceSwitch {
+        self.switch.clone()
+    }
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaintenanceSwitch {
+    /// Enable maintenance mode.
+    #[inline]
+    pub fn enable(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    /// Disable maintenance mode.
+    #[inline]
+    pub fn disable(&self) {
+        self.0.store(false, Ordering::SeqCst)
+    }
+
+    /// Toggle maintenance mode, return if it's enabled now.
+    #[inline]
+    pub fn toggle(&self) -> bool {
+        !self.0.fetch_xor(true, Ordering::SeqCst)
+    }
+
+    /// Check if maintenance mode is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Maintenance {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        if !self.switch.is_enabled()
+            || self.allowed.iter().any(|path| path == ctx.path())
+        {
+            return next.await;
+        }
+        if let Some(retry_after) = self.retry_after {
+            ctx.resp
+                .headers
+                .insert(RETRY_AFTER, retry_after.to_string().parse()?);
+        }
+        throw!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "service is under maintenance"
+        )
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::Maintenance;
+    use crate::http::header::RETRY_AFTER;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::App;
+    use async_std::task::spawn;
+
+    #[tokio::test]
+    async fn maintenance() -> Result<(), Box<dyn std::error::Error>> {
+        let maintenance = Maintenance::new().retry_after(60).allow("/healthz");
+        let switch = maintenance.switch();
+        let (addr, server) = App::new().gate(maintenance).end("Hello, World").run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        assert!(switch.toggle());
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+        assert_eq!("60", resp.headers()[RETRY_AFTER]);
+        let resp = reqwest::get(&format!("http://{}/healthz", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        switch.disable();
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+}
@@ -0,0 +1,424 @@
+//! This module provides a context extension `Negotiate`,
+//! to parse "Accept" header, negotiate content type and set "Content-Language".
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::negotiate::Negotiate;
+//! use roa::preload::*;
+//! use roa::{throw, App, Context};
+//! use roa::http::StatusCode;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     match ctx.accepts(&["application/json", "text/html"]) {
+//!         Some("application/json") => ctx.write(r#"{"hello": "world"}"#),
+//!         Some(_) => ctx.write("<h1>Hello, World</h1>"),
+//!         None => throw!(StatusCode::NOT_ACCEPTABLE),
+//!     };
+//!     Ok(())
+//! }
+//!
+//! let app = App::new().end(end);
+//! ```
+
+use crate::http::header::{
+    HeaderMap, HeaderName, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CONTENT_LANGUAGE, VARY,
+};
+use crate::http::StatusCode;
+use crate::{Context, Result, Status};
+use std::cmp::{Ordering, Reverse};
+
+/// A media range of "Accept" header, like "text/html;level=1;q=0.8".
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaRange {
+    type_: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+    quality: f32,
+}
+
+/// A context extension to parse "Accept" header and negotiate content type.
+pub trait Negotiate {
+    /// Parse "Accept" header into media ranges, sorted by preference.
+    ///
+    /// Ranges are sorted by quality, and more specific ranges come first when qualities are equal.
+    /// Malformed ranges are skipped.
+    fn parse_accept(&self) -> Vec<MediaRange>;
+
+    /// Choose the best one of offered media types, `None` if none of them is acceptable.
+    ///
+    /// Quality of an offered type is taken from the most specific range matching it,
+    /// the first offered one wins if qualities are equal.
+    /// The first offered one is chosen if "Accept" is absent.
+    fn accepts<'a>(&self, offered: &[&'a str]) -> Option<&'a str>;
+
+    /// Set "Content-Language" of response, and add "Accept-Language" to "Vary",
+    /// so caches key responses on language.
+    ///
+    /// Existing "Vary" is merged rather than overwritten.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::negotiate::Negotiate;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     ctx.set_content_language("zh-CN")?;
+    ///     ctx.resp.write("你好，世界");
+    ///     Ok(())
+    /// }
+    /// ```
+    fn set_content_language(&mut self, lang: &str) -> Result;
+}
+
+/// Add a header name to "Vary", merged with the existing ones.
+///
+/// Nothing changes if the name is already in "Vary" or "Vary" is "*".
+pub(crate) fn append_vary(headers: &mut HeaderMap, name: HeaderName) {
+    let existing: Vec<String> = headers
+        .get_all(VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+    if existing
+        .iter()
+        .any(|value| value == "*" || value.eq_ignore_ascii_case(name.as_str()))
+    {
+        return;
+    }
+    let value = if existing.is_empty() {
+        HeaderValue::from(name)
+    } else {
+        let joined = format!("{}, {}", existing.join(", "), name);
+        // joined by valid header values and a header name.
+        HeaderValue::from_str(&joined).expect("invalid vary")
+    };
+    headers.insert(VARY, value);
+}
+
+impl MediaRange {
+    /// Parse a media range, return `None` if it's malformed.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = split_quoted(value, ';').into_iter();
+        let media_type = parts.next()?.trim();
+        let mut types = media_type.splitn(2, '/');
+        let type_ = types.next()?.trim().to_ascii_lowercase();
+        let subtype = types.next()?.trim().to_ascii_lowercase();
+        if !is_token(&type_) || !is_token(&subtype) || (type_ == "*" && subtype != "*") {
+            return None;
+        }
+        let mut params = Vec::new();
+        let mut quality = 1.0;
+        for param in parts {
+            let mut pair = param.splitn(2, '=');
+            let name = pair.next()?.trim().to_ascii_lowercase();
+            let value = pair.next()?.trim().trim_matches('"').to_string();
+            if !is_token(&name) {
+                return None;
+            }
+            if name == "q" {
+                quality = value.parse().ok()?;
+                if !(0.0..=1.0).contains(&quality) {
+                    return None;
+                }
+            } else {
+                params.push((name, value));
+            }
+        }
+        Some(Self {
+            type_,
+            subtype,
+            params,
+            quality,
+        })
+    }
+
+    /// Type, like "text" or "*".
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// Subtype, like "html" or "*".
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Quality, 1.0 by default.
+    pub fn quality(&self) -> f32 {
+        self.quality
+    }
+
+    /// Parameters except quality.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Get a parameter by name, case-insensitively.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Check if a media type, like "text/html;level=1", matches this range.
+    ///
+    /// Parameters of this range must be present in the media type.
+    pub fn matches(&self, media_type: &str) -> bool {
+        let media_type = match MediaRange::parse(media_type) {
+            Some(media_type) => media_type,
+            None => return false,
+        };
+        (self.type_ == "*" || self.type_ == media_type.type_)
+            && (self.subtype == "*" || self.subtype == media_type.subtype)
+            && self
+                .params
+                .iter()
+                .all(|(name, value)| media_type.param(name) == Some(value.as_str()))
+    }
+
+    /// Specificity of this range, "*/*" < "text/*" < "text/html" < "text/html;level=1".
+    #[inline]
+    fn specificity(&self) -> u8 {
+        match (
+            self.type_ == "*",
+            self.subtype == "*",
+            self.params.is_empty(),
+        ) {
+            (true, _, _) => 0,
+            (false, true, _) => 1,
+            (false, false, true) => 2,
+            (false, false, false) => 3,
+        }
+    }
+}
+
+/// Check if a string is a non-empty token.
+#[inline]
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+}
+
+/// Split by a separator out of quoted strings.
+fn split_quoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&value[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Parse media ranges of "Accept" values, sorted by preference.
+fn parse_accept<'a>(values: impl Iterator<Item = &'a str>) -> Vec<MediaRange> {
+    let mut ranges: Vec<MediaRange> = values
+        .flat_map(|value| split_quoted(value, ','))
+        .filter(|range| !range.trim().is_empty())
+        .filter_map(MediaRange::parse)
+        .collect();
+    ranges.sort_by(|a, b| {
+        b.quality
+            .partial_cmp(&a.quality)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.specificity().cmp(&a.specificity()))
+    });
+    ranges
+}
+
+impl<S> Negotiate for Context<S> {
+    #[inline]
+    fn parse_accept(&self) -> Vec<MediaRange> {
+        parse_accept(
+            self.req
+                .headers
+                .get_all(ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok()),
+        )
+    }
+
+    #[inline]
+    fn accepts<'a>(&self, offered: &[&'a str]) -> Option<&'a str> {
+        if !self.req.headers.contains_key(ACCEPT) {
+            return offered.first().cloned();
+        }
+        let ranges = self.parse_accept();
+        let mut best: Option<(&'a str, f32)> = None;
+        for &media_type in offered {
+            let quality = ranges
+                .iter()
+                .filter(|range| range.matches(media_type))
+                .min_by_key(|range| Reverse(range.specificity()))
+                .map(|range| range.quality)
+                .unwrap_or(0.0);
+            let better = match best {
+                Some((_, best_quality)) => quality > best_quality,
+                None => quality > 0.0,
+            };
+            if better {
+                best = Some((media_type, quality));
+            }
+        }
+        best.map(|(media_type, _)| media_type)
+    }
+
+    #[inline]
+    fn set_content_language(&mut self, lang: &str) -> Result {
+        let value = HeaderValue::from_str(lang).map_err(|err| {
+            Status::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("{}\ninvalid content language `{}`", err, lang),
+                false,
+            )
+        })?;
+        self.resp.headers.insert(CONTENT_LANGUAGE, value);
+        append_vary(&mut self.resp.headers, ACCEPT_LANGUAGE);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{append_vary, parse_accept, MediaRange, Negotiate};
+    use crate::http::header::{
+        HeaderMap, ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONTENT_LANGUAGE, ORIGIN,
+        VARY,
+    };
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+
+    #[test]
+    fn parse() {
+        let ranges = parse_accept(
+            vec![
+                "text/*;q=0.3, text/html;q=0.7, text/html;level=1",
+                "text/html;level=2;q=0.4, */*;q=0.5, invalid, text/;q=1, a/b;q=2",
+            ]
+            .into_iter(),
+        );
+        let ranges: Vec<_> = ranges
+            .iter()
+            .map(|range| (range.type_(), range.subtype(), range.quality()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("text", "html", 1.0),
+                ("text", "html", 0.7),
+                ("*", "*", 0.5),
+                ("text", "html", 0.4),
+                ("text", "*", 0.3),
+            ],
+            ranges
+        );
+
+        let range =
+            MediaRange::parse(r#"application/json; profile="a,b"; q=0.9"#).unwrap();
+        assert_eq!(Some("a,b"), range.param("profile"));
+        assert!((range.quality() - 0.9).abs() < std::f32::EPSILON);
+        assert!(range.matches(r#"application/json; profile="a,b""#));
+        assert!(!range.matches("application/json"));
+        assert!(MediaRange::parse("*/html").is_none());
+    }
+
+    #[tokio::test]
+    async fn accepts() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let chosen = ctx
+                .accepts(&["application/json", "text/html"])
+                .unwrap_or("none");
+            ctx.write(chosen);
+            Ok(())
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let accept = |value: &'static str| {
+            client
+                .get(&format!("http://{}", addr))
+                .header(ACCEPT, value)
+                .send()
+        };
+        let resp = client.get(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("application/json", resp.text().await?);
+        assert_eq!(
+            "text/html",
+            accept("text/html, application/*;q=0.5")
+                .await?
+                .text()
+                .await?
+        );
+        assert_eq!(
+            "application/json",
+            accept("*/*;q=0.1, application/json").await?.text().await?
+        );
+        assert_eq!(
+            "text/html",
+            accept("application/json;q=0, */*").await?.text().await?
+        );
+        assert_eq!("none", accept("image/png").await?.text().await?);
+        Ok(())
+    }
+
+    #[test]
+    fn vary() -> Result<(), Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        append_vary(&mut headers, ACCEPT_LANGUAGE);
+        assert_eq!("accept-language", headers[VARY]);
+        append_vary(&mut headers, ACCEPT_LANGUAGE);
+        assert_eq!("accept-language", headers[VARY]);
+
+        let mut headers = HeaderMap::new();
+        headers.append(VARY, "Origin".parse()?);
+        headers.append(VARY, "Accept-Encoding, Accept-Language".parse()?);
+        append_vary(&mut headers, ACCEPT_LANGUAGE);
+        assert_eq!(
+            vec!["Origin", "Accept-Encoding, Accept-Language"],
+            headers.get_all(VARY).iter().collect::<Vec<_>>()
+        );
+        append_vary(&mut headers, ORIGIN);
+        append_vary(&mut headers, ACCEPT);
+        assert_eq!(
+            "Origin, Accept-Encoding, Accept-Language, accept",
+            headers[VARY]
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, "*".parse()?);
+        append_vary(&mut headers, ACCEPT_ENCODING);
+        assert_eq!("*", headers[VARY]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn content_language() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.insert(VARY, "Accept-Encoding".parse()?);
+            ctx.set_content_language("zh-CN")?;
+            Ok(())
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("zh-CN", resp.headers()[CONTENT_LANGUAGE]);
+        assert_eq!("Accept-Encoding, accept-language", resp.headers()[VARY]);
+        Ok(())
+    }
+}
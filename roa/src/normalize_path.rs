@@ -0,0 +1,188 @@
+//! This module provides a middleware `NormalizePath`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::normalize_path::NormalizePath;
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//! use std::error::Error;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     // "/users//42" and "/api/../users/42" are both "/users/42" here.
+//!     let path = ctx.uri().path().to_string();
+//!     ctx.write(path);
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().gate(NormalizePath::new()).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::LOCATION;
+use crate::http::uri::{PathAndQuery, Uri};
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Middleware, Next, Result};
+use std::borrow::Cow;
+
+/// A middleware to normalize request path before routing.
+///
+/// Duplicate slashes are collapsed, "." segments are removed
+/// and ".." segments are resolved, a trailing slash is kept.
+/// So it must be gated before the router.
+///
+/// Percent-encoded dots ("%2e") are decoded first, so "/%2e%2e/" is resolved as "/../".
+///
+/// The path is rewritten in place by default,
+/// or the request is redirected to the canonical path in redirect mode.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NormalizePath {
+    redirect: Option<StatusCode>,
+}
+
+impl NormalizePath {
+    /// Construct a middleware rewriting request path in place.
+    pub fn new() -> Self {
+        Self { redirect: None }
+    }
+
+    /// Redirect non-canonical paths with status code, like 301 MOVED PERMANENTLY.
+    pub fn redirect(mut self, status: StatusCode) -> Self {
+        self.redirect = Some(status);
+        self
+    }
+}
+
+/// Decode percent-encoded dots ("%2e" or "%2E") of a segment,
+/// so encoded dot segments are resolved as well.
+fn decode_dots(segment: &str) -> Cow<'_, str> {
+    if !segment.contains('%') {
+        return Cow::Borrowed(segment);
+    }
+    Cow::Owned(segment.replace("%2e", ".").replace("%2E", "."))
+}
+
+/// Collapse duplicate slashes and resolve dot segments of a path.
+fn normalize(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut trailing = false;
+    for segment in path.split('/').map(decode_dots) {
+        trailing = match segment.as_ref() {
+            "" | "." => true,
+            ".." => {
+                segments.pop();
+                true
+            }
+            _ => {
+                segments.push(segment);
+                false
+            }
+        };
+    }
+    let mut normalized = String::with_capacity(path.len());
+    for segment in segments.iter() {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if normalized.is_empty() || trailing {
+        normalized.push('/');
+    }
+    normalized
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for NormalizePath {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let path = normalize(ctx.uri().path());
+        if path == ctx.uri().path() {
+            return next.await;
+        }
+        let path_and_query = match ctx.uri().query() {
+            Some(query) => format!("{}?{}", path, query),
+            None => path,
+        };
+        if let Some(status) = self.redirect {
+            ctx.resp.headers.insert(LOCATION, path_and_query.parse()?);
+            throw!(status)
+        }
+        let mut parts = ctx.uri().clone().into_parts();
+        parts.path_and_query = Some(path_and_query.parse::<PathAndQuery>()?);
+        ctx.req.uri = Uri::from_parts(parts)?;
+        next.await
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{normalize, NormalizePath};
+    use crate::http::header::LOCATION;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+
+    #[test]
+    fn normalize_paths() {
+        assert_eq!("/", normalize(""));
+        assert_eq!("/", normalize("/"));
+        assert_eq!("/", normalize("//"));
+        assert_eq!("/users/42", normalize("/users//42"));
+        assert_eq!("/api/x", normalize("/api/./x"));
+        assert_eq!("/x", normalize("/api/../x"));
+        assert_eq!("/x", normalize("/../../x"));
+        assert_eq!("/api/", normalize("/api//"));
+        assert_eq!("/api/", normalize("/api/x/.."));
+        assert_eq!("/api/", normalize("/api/."));
+        assert_eq!("/x", normalize("/api/%2e%2e/x"));
+        assert_eq!("/x", normalize("/api/%2E%2e/x"));
+        assert_eq!("/api/x", normalize("/api/%2e/x"));
+        assert_eq!("/api/", normalize("/api/x/.%2e"));
+        assert_eq!("/api/a.b", normalize("/api/a%2Eb"));
+        assert_eq!("/api/a%2Fb", normalize("/api/a%2Fb"));
+    }
+
+    #[tokio::test]
+    async fn rewrite() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let uri = ctx.uri().to_string();
+            ctx.write(uri);
+            Ok(())
+        }
+        let (addr, server) = App::new().gate(NormalizePath::new()).end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}/users//42/?id=0", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("/users/42/?id=0", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn redirect() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new()
+            .gate(NormalizePath::new().redirect(StatusCode::MOVED_PERMANENTLY))
+            .end(())
+            .run()?;
+        spawn(server);
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        let resp = client
+            .get(&format!("http://{}/api//users//42?id=0", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::MOVED_PERMANENTLY, resp.status());
+        assert_eq!("/api/users/42?id=0", resp.headers()[LOCATION]);
+
+        let resp = client
+            .get(&format!("http://{}/users/42", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+}
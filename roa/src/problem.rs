@@ -0,0 +1,256 @@
+//! This module provides a middleware `problem_details`,
+//! to render errors as "application/problem+json" defined by RFC 7807,
+//! and a context extension `ProblemExt` to describe the error thrown.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::problem::{problem_details, ProblemExt};
+//! use roa::http::StatusCode;
+//! use roa::{throw, App, Context};
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.set_problem_type("https://example.com/probs/out-of-credit");
+//!     ctx.set_problem_instance("/account/12345/msgs/abc");
+//!     throw!(StatusCode::FORBIDDEN, "Your current balance is 30, but that costs 50.")
+//! }
+//!
+//! let app = App::new().gate(problem_details).end(end);
+//! ```
+
+use crate::body::PowerBody;
+use crate::http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+use crate::negotiate::Negotiate;
+use crate::{Body, Context, Next, Result, Status};
+use serde_json::{Map, Value};
+
+/// Media types offered for an error, by preference.
+///
+/// The message is written as plain text if "text/plain" is preferred.
+const OFFERED: &[&str] = &["application/problem+json", "application/json", "text/plain"];
+
+/// Key of problem type URI.
+const TYPE: &str = "type";
+
+/// Key of problem instance URI.
+const INSTANCE: &str = "instance";
+
+/// Scope of problem details.
+struct ProblemScope;
+
+/// A context extension to describe the error thrown by this request,
+/// as "type" and "instance" of problem details (RFC 7807).
+///
+/// They are rendered by `problem_details` only if an error is thrown.
+pub trait ProblemExt {
+    /// Set the problem type URI, "about:blank" by default.
+    fn set_problem_type(&mut self, uri: impl Into<String>);
+
+    /// Set the problem instance URI, identifying the specific occurrence of the problem.
+    fn set_problem_instance(&mut self, uri: impl Into<String>);
+
+    /// Get the problem type URI, if any.
+    fn problem_type(&self) -> Option<String>;
+
+    /// Get the problem instance URI, if any.
+    fn problem_instance(&self) -> Option<String>;
+}
+
+impl<S> ProblemExt for Context<S> {
+    #[inline]
+    fn set_problem_type(&mut self, uri: impl Into<String>) {
+        self.store_scoped(ProblemScope, TYPE, uri.into());
+    }
+
+    #[inline]
+    fn set_problem_instance(&mut self, uri: impl Into<String>) {
+        self.store_scoped(ProblemScope, INSTANCE, uri.into());
+    }
+
+    #[inline]
+    fn problem_type(&self) -> Option<String> {
+        self.load_scoped::<ProblemScope, String>(TYPE)
+            .map(|uri| uri.to_string())
+    }
+
+    #[inline]
+    fn problem_instance(&self) -> Option<String> {
+        self.load_scoped::<ProblemScope, String>(INSTANCE)
+            .map(|uri| uri.to_string())
+    }
+}
+
+/// Serialize a status as problem details.
+///
+/// "type" defaults to "about:blank", and "detail" is present only if the message is exposed.
+fn problem<S>(ctx: &Context<S>, status: &Status) -> Value {
+    let mut object = Map::new();
+    object.insert(
+        "type".to_string(),
+        ctx.problem_type()
+            .unwrap_or_else(|| "about:blank".to_string())
+            .into(),
+    );
+    object.insert(
+        "title".to_string(),
+        status
+            .status_code
+            .canonical_reason()
+            .unwrap_or_default()
+            .into(),
+    );
+    object.insert("status".to_string(), status.status_code.as_u16().into());
+    if status.expose && !status.message.is_empty() {
+        object.insert("detail".to_string(), status.message.as_str().into());
+    }
+    if let Some(instance) = ctx.problem_instance() {
+        object.insert("instance".to_string(), instance.into());
+    }
+    Value::Object(object)
+}
+
+/// A middleware to render errors as problem details (RFC 7807).
+///
+/// A 4xx or 5xx status thrown by downstream is rendered as "application/problem+json"
+/// (or "application/json" if the client prefers it), if the client accepts one of them
+/// rather than "text/plain", as `Negotiate::accepts` ranks;
+/// otherwise it's thrown to upstream as usual.
+///
+/// Rendered statuses are caught, and those not exposed are logged as root handler does.
+pub async fn problem_details<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    let status = match next.await {
+        Ok(()) => return Ok(()),
+        Err(status) => status,
+    };
+    if !(status.is_client_error() || status.is_server_error()) {
+        return Err(status);
+    }
+    let media_type = match ctx.accepts(OFFERED) {
+        Some("text/plain") | None => return Err(status),
+        Some(media_type) => media_type,
+    };
+    let body = problem(ctx, &status);
+    ctx.resp.status = status.status_code;
+    ctx.resp.body = Body::empty();
+    ctx.resp.headers.remove(CONTENT_LENGTH);
+    ctx.write_json(&body)?;
+    ctx.resp
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(media_type));
+    if !status.expose {
+        let level = if status.is_client_error() {
+            log::Level::Warn
+        } else {
+            log::Level::Error
+        };
+        log::log!(level, "Uncaught status: {}", status);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{problem_details, ProblemExt};
+    use crate::http::header::{ACCEPT, CONTENT_TYPE};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{status, throw, App, Context};
+    use async_std::task::spawn;
+    use serde_json::{json, Value};
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        ctx.set_problem_type("https://example.com/probs/out-of-credit");
+        ctx.set_problem_instance("/account/12345");
+        throw!(StatusCode::FORBIDDEN, "out of credit")
+    }
+
+    #[tokio::test]
+    async fn problem() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(problem_details).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT, "application/json")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+        assert_eq!("application/problem+json", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(
+            json!({
+                "type": "https://example.com/probs/out-of-credit",
+                "title": "Forbidden",
+                "status": 403,
+                "detail": "out of credit",
+                "instance": "/account/12345",
+            }),
+            resp.json::<Value>().await?
+        );
+
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT, "text/plain")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+        assert_eq!("out of credit", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn negotiate() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(problem_details).end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        for (accept, content_type) in &[
+            ("*/*", Some("application/problem+json")),
+            ("application/*", Some("application/problem+json")),
+            ("text/html, */*;q=0.8", Some("application/problem+json")),
+            (
+                "application/problem+json;q=0, application/*",
+                Some("application/json"),
+            ),
+            ("application/json;q=0, application/problem+json;q=0", None),
+            ("text/*, application/json;q=0.5", None),
+            ("text/html", None),
+        ] {
+            let resp = client
+                .get(&format!("http://{}", addr))
+                .header(ACCEPT, *accept)
+                .send()
+                .await?;
+            assert_eq!(StatusCode::FORBIDDEN, resp.status());
+            assert_eq!(
+                *content_type,
+                resp.headers()
+                    .get(CONTENT_TYPE)
+                    .map(|value| value.to_str().unwrap())
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn internal_problem() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new()
+            .gate(problem_details)
+            .end(status!(StatusCode::INTERNAL_SERVER_ERROR, "secret", false))
+            .run()?;
+        spawn(server);
+        let resp = reqwest::Client::new()
+            .get(&format!("http://{}", addr))
+            .header(ACCEPT, "application/problem+json, text/html;q=0.5")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, resp.status());
+        assert_eq!(
+            json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+            }),
+            resp.json::<Value>().await?
+        );
+        Ok(())
+    }
+}
@@ -29,7 +29,21 @@
 
 use crate::http::StatusCode;
 use crate::{Context, Next, Result, Status, Variable};
-use url::form_urlencoded::parse;
+use std::borrow::Cow;
+use url::form_urlencoded::{parse, Parse};
+
+/// A lazy iterator of query pairs, yields percent-decoded `(Cow<str>, Cow<str>)`.
+#[derive(Clone)]
+pub struct QueryPairs<'a>(Parse<'a>);
+
+impl<'a> Iterator for QueryPairs<'a> {
+    type Item = (Cow<'a, str>, Cow<'a, str>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
 
 /// A scope to store and load variables in Context::storage.
 struct QueryScope;
@@ -132,9 +146,35 @@ pub trait Query {
     /// }
     /// ```
     fn query<'a>(&self, name: &'a str) -> Option<Variable<'a, String>>;
+
+    /// Iterate query pairs lazily, without `query_parser`.
+    ///
+    /// Values are percent-decoded on demand, borrowing the uri if they're not encoded.
+    /// A key without value (like `?flag`) yields an empty value.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::{App, Context};
+    /// use roa::preload::*;
+    ///
+    /// async fn end(ctx: &mut Context) -> roa::Result {
+    ///     let debug = ctx.query_pairs().any(|(key, _)| key == "debug");
+    ///     let token = ctx
+    ///         .query_pairs()
+    ///         .find(|(key, _)| key == "token")
+    ///         .map(|(_, value)| value.into_owned());
+    ///     Ok(())
+    /// }
+    ///
+    /// let app = App::new().end(end);
+    /// ```
+    fn query_pairs(&self) -> QueryPairs<'_>;
 }
 
 /// A middleware to parse query.
+///
+/// Use `roa::limit::QueryLimit` before it to reject huge query strings.
 #[inline]
 pub async fn query_parser<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
     let query_string = ctx.uri().query().unwrap_or("");
@@ -161,6 +201,11 @@ impl<S> Query for Context<S> {
     fn query<'a>(&self, name: &'a str) -> Option<Variable<'a, String>> {
         self.load_scoped::<QueryScope, String>(name)
     }
+
+    #[inline]
+    fn query_pairs(&self) -> QueryPairs<'_> {
+        QueryPairs(parse(self.uri().query().unwrap_or("").as_bytes()))
+    }
 }
 
 #[cfg(all(test, feature = "tcp"))]
@@ -225,4 +270,32 @@ mod tests {
         assert_eq!(StatusCode::OK, resp.status());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn query_pairs() -> Result<(), Box<dyn std::error::Error>> {
+        async fn test(ctx: &mut Context) -> crate::Result {
+            let pairs: Vec<(String, String)> = ctx
+                .query_pairs()
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect();
+            assert_eq!(
+                vec![
+                    ("name".to_string(), "Hexilee Roa".to_string()),
+                    ("flag".to_string(), "".to_string()),
+                    ("lang".to_string(), "rust".to_string()),
+                ],
+                pairs
+            );
+            Ok(())
+        }
+        let (addr, server) = App::new().end(test).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!(
+            "http://{}?name=Hexilee%20Roa&flag&lang=rust",
+            addr
+        ))
+        .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
 }
@@ -0,0 +1,285 @@
+//! This module provides a context extension `ServeRanged`,
+//! to serve "Range" requests for arbitrary streamed bodies.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::range::ServeRanged;
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//! use bytes::Bytes;
+//! use futures::stream::once;
+//! use std::error::Error;
+//! use std::io;
+//!
+//! const DATA: &[u8] = b"Hello, World";
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     // fetch the requested interval from object storage, for example.
+//!     ctx.serve_ranged(DATA.len() as u64, |range| {
+//!         let slice = &DATA[range.start as usize..range.end as usize];
+//!         once(async move { Ok::<_, io::Error>(Bytes::from_static(slice)) })
+//!     })
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::{
+    HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_RANGE,
+    LAST_MODIFIED, RANGE,
+};
+use crate::http::{Method, StatusCode};
+use crate::{throw, Context, Result};
+use bytes::Bytes;
+use futures::Stream;
+use std::io;
+use std::ops::Range;
+
+/// A context extension to serve "Range" requests.
+pub trait ServeRanged {
+    /// Serve a body of `total` bytes, the closure produces a stream
+    /// of the requested interval.
+    ///
+    /// - "Accept-Ranges: bytes" is always set.
+    /// - A satisfiable single range of GET or HEAD gets a 206 PARTIAL CONTENT with "Content-Range".
+    /// - An unsatisfiable range gets a 416 RANGE NOT SATISFIABLE with "Content-Range: bytes */{total}".
+    /// - Otherwise, the whole body is served, multiple ranges and invalid "Range" are ignored.
+    ///
+    /// Validators like "ETag" or "Last-Modified" should be set before calling it,
+    /// they are kept in response, and "Range" is ignored if "If-Range" matches neither of them.
+    ///
+    /// A HEAD request gets all headers without calling the closure,
+    /// so clients can probe before a ranged GET.
+    fn serve_ranged<F, St>(&mut self, total: u64, stream: F) -> Result
+    where
+        F: FnOnce(Range<u64>) -> St,
+        St: 'static + Stream<Item = io::Result<Bytes>> + Sync + Send;
+}
+
+/// A resolved byte range.
+#[derive(Debug, Eq, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial(Range<u64>),
+    Unsatisfiable,
+}
+
+/// Resolve a "Range" value against total length.
+fn parse_range(value: &str, total: u64) -> ByteRange {
+    let value = value.trim();
+    if value.len() < 6 || !value[..6].eq_ignore_ascii_case("bytes=") {
+        return ByteRange::Full;
+    }
+    let set = &value[6..];
+    if set.contains(',') {
+        return ByteRange::Full;
+    }
+    let mut pair = set.splitn(2, '-');
+    let (first, last) = match (pair.next(), pair.next()) {
+        (Some(first), Some(last)) => (first.trim(), last.trim()),
+        _ => return ByteRange::Full,
+    };
+    if first.is_empty() {
+        return match last.parse::<u64>() {
+            Err(_) => ByteRange::Full,
+            Ok(suffix) if suffix == 0 || total == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(total.saturating_sub(suffix)..total),
+        };
+    }
+    let start = match first.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return ByteRange::Full,
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= total {
+        return ByteRange::Unsatisfiable;
+    }
+    let end = end.map(|end| end.min(total - 1)).unwrap_or(total - 1);
+    ByteRange::Partial(start..end + 1)
+}
+
+/// Check if "If-Range" matches the validators in response, it's true if "If-Range" is absent.
+#[inline]
+fn if_range<S>(ctx: &Context<S>) -> bool {
+    match ctx.get(IF_RANGE) {
+        None => true,
+        Some(value) => {
+            let value = value.trim();
+            // weak tags never match.
+            !value.starts_with("W/")
+                && [ETAG, LAST_MODIFIED].iter().any(|name| {
+                    ctx.resp
+                        .headers
+                        .get(name)
+                        .map(|validator| validator == value)
+                        .unwrap_or(false)
+                })
+        }
+    }
+}
+
+impl<S> ServeRanged for Context<S> {
+    #[inline]
+    fn serve_ranged<F, St>(&mut self, total: u64, stream: F) -> Result
+    where
+        F: FnOnce(Range<u64>) -> St,
+        St: 'static + Stream<Item = io::Result<Bytes>> + Sync + Send,
+    {
+        self.resp
+            .headers
+            .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        let is_head = *self.method() == Method::HEAD;
+        let is_safe = is_head || *self.method() == Method::GET;
+        let range = match self.get(RANGE) {
+            Some(value) if is_safe && if_range(self) => parse_range(value, total),
+            _ => ByteRange::Full,
+        };
+        let range = match range {
+            ByteRange::Full => 0..total,
+            ByteRange::Partial(range) => {
+                self.resp.status = StatusCode::PARTIAL_CONTENT;
+                self.resp.headers.insert(
+                    CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", range.start, range.end - 1, total)
+                        .parse()?,
+                );
+                range
+            }
+            ByteRange::Unsatisfiable => {
+                self.resp
+                    .headers
+                    .insert(CONTENT_RANGE, format!("bytes */{}", total).parse()?);
+                throw!(StatusCode::RANGE_NOT_SATISFIABLE, "range not satisfiable")
+            }
+        };
+        self.resp
+            .headers
+            .insert(CONTENT_LENGTH, (range.end - range.start).into());
+        if !is_head {
+            self.resp.write_stream(stream(range));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{parse_range, ByteRange, ServeRanged};
+    use crate::http::header::{
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_RANGE, RANGE,
+    };
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+    use bytes::Bytes;
+    use futures::stream::once;
+    use std::io;
+
+    const DATA: &[u8] = b"Hello, World";
+
+    #[test]
+    fn parse() {
+        assert_eq!(ByteRange::Partial(0..5), parse_range("bytes=0-4", 12));
+        assert_eq!(ByteRange::Partial(7..12), parse_range("bytes=7-", 12));
+        assert_eq!(ByteRange::Partial(7..12), parse_range("bytes=-5", 12));
+        assert_eq!(ByteRange::Partial(0..12), parse_range("bytes=-20", 12));
+        assert_eq!(ByteRange::Partial(7..12), parse_range("bytes=7-100", 12));
+        assert_eq!(ByteRange::Unsatisfiable, parse_range("bytes=12-", 12));
+        assert_eq!(ByteRange::Unsatisfiable, parse_range("bytes=-0", 12));
+        assert_eq!(ByteRange::Full, parse_range("bytes=0-1,3-4", 12));
+        assert_eq!(ByteRange::Full, parse_range("bytes=4-1", 12));
+        assert_eq!(ByteRange::Full, parse_range("items=0-1", 12));
+        assert_eq!(ByteRange::Full, parse_range("bytes=a-", 12));
+    }
+
+    #[tokio::test]
+    async fn serve_ranged() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.headers.insert(ETAG, "\"hello\"".parse()?);
+            ctx.serve_ranged(DATA.len() as u64, |range| {
+                let slice = &DATA[range.start as usize..range.end as usize];
+                once(async move { Ok::<_, io::Error>(Bytes::from_static(slice)) })
+            })
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}", addr)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("bytes", resp.headers()[ACCEPT_RANGES]);
+        assert_eq!("Hello, World", resp.text().await?);
+
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(RANGE, "bytes=7-")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+        assert_eq!("bytes 7-11/12", resp.headers()[CONTENT_RANGE]);
+        assert_eq!("World", resp.text().await?);
+
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(RANGE, "bytes=20-")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::RANGE_NOT_SATISFIABLE, resp.status());
+        assert_eq!("bytes */12", resp.headers()[CONTENT_RANGE]);
+
+        // probe by HEAD.
+        let resp = client
+            .head(&format!("http://{}", addr))
+            .header(RANGE, "bytes=7-")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+        assert_eq!("bytes", resp.headers()[ACCEPT_RANGES]);
+        assert_eq!("5", resp.headers()[CONTENT_LENGTH]);
+        assert_eq!("\"hello\"", resp.headers()[ETAG]);
+        assert!(resp.text().await?.is_empty());
+
+        // "If-Range" matches.
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(RANGE, "bytes=7-")
+            .header(IF_RANGE, "\"hello\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::PARTIAL_CONTENT, resp.status());
+        assert_eq!("World", resp.text().await?);
+
+        // "If-Range" mismatches.
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(RANGE, "bytes=7-")
+            .header(IF_RANGE, "\"other\"")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+
+        // range of other methods is ignored.
+        let resp = client
+            .post(&format!("http://{}", addr))
+            .header(RANGE, "bytes=7-")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+}
@@ -49,7 +49,13 @@
 
 mod endpoints;
 mod err;
+mod host;
 mod path;
+mod route;
+
+#[cfg(feature = "openapi")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "openapi")))]
+pub mod openapi;
 
 #[doc(inline)]
 pub use endpoints::*;
@@ -57,16 +63,30 @@ pub use endpoints::*;
 #[doc(inline)]
 pub use err::RouterError;
 
-use crate::http::StatusCode;
+#[doc(inline)]
+pub use host::{HostRouter, SUBDOMAIN};
+
+#[doc(inline)]
+pub use route::{parse_param, Route};
+
+#[cfg(feature = "macros")]
+#[cfg_attr(feature = "docs", doc(cfg(feature = "macros")))]
+#[doc(inline)]
+pub use roa_macros::{connect, delete, get, head, options, patch, post, put, trace};
+
+use crate::http::{Method, StatusCode};
 use crate::{
     async_trait, throw, Boxed, Context, Endpoint, EndpointExt, Middleware,
     MiddlewareExt, Result, Shared, Status, Variable,
 };
+use endpoints::{method_not_allowed, sort_methods, ALL_METHODS};
 use err::Conflict;
 use path::{join_path, standardize_path, Path, RegexPath};
 use percent_encoding::percent_decode_str;
 use radix_trie::Trie;
+use std::cmp::Reverse;
 use std::convert::AsRef;
+use std::fmt::{self, Display, Formatter};
 use std::result::Result as StdResult;
 
 /// A private scope to store and load variables in Context::storage.
@@ -76,6 +96,10 @@ struct RouterScope;
 /// This extension must be used in `Router`,
 /// otherwise you cannot get expected router parameters.
 ///
+/// Parameters are extracted once a route matches, before the middlewares of `Router`
+/// and endpoint wrappers like `Guard` or `Dispatcher` run,
+/// so they can make decisions by parameters.
+///
 /// ### Example
 ///
 /// ```rust
@@ -106,9 +130,15 @@ struct RouterScope;
 /// ```
 pub trait RouterParam {
     /// Must get a router parameter, throw 500 INTERNAL SERVER ERROR if it not exists.
+    ///
+    /// Use `param` instead if the parameter is optional.
     fn must_param<'a>(&self, name: &'a str) -> Result<Variable<'a, String>>;
 
     /// Try to get a router parameter, return `None` if it not exists.
+    ///
+    /// It's useful when an endpoint is registered on several paths,
+    /// and a parameter is captured by only some of them.
+    ///
     /// ### Example
     ///
     /// ```rust
@@ -140,15 +170,30 @@ pub trait RouterParam {
 }
 
 /// A builder of `RouteTable`.
+///
+/// ### Precedence
+///
+/// When multiple routes match a path, like "/user/new", "/user/:id" and "/user/*{path}",
+/// the route with the highest priority wins, routes registered by `Router::on` have priority 0.
+/// Routes with the same priority are resolved in order:
+///
+/// 1. Static paths, like "/user/new".
+/// 2. Dynamic paths with only segment variables, like "/user/:id".
+/// 3. Dynamic paths with wildcards, like "/user/*{path}".
+/// 4. Earlier registered paths.
+///
+/// A warning is logged when building `RouteTable` if a route can never be matched,
+/// because it has the same pattern as an earlier route,
+/// or it's static and overridden by a dynamic route with higher priority.
 pub struct Router<S> {
     middleware: Shared<S>,
-    endpoints: Vec<(String, Boxed<S>)>,
+    endpoints: Vec<(String, i32, Boxed<S>)>,
 }
 
 /// An endpoint to route request by uri path.
 pub struct RouteTable<S> {
-    static_route: Trie<String, Boxed<S>>,
-    dynamic_route: Vec<(RegexPath, Boxed<S>)>,
+    static_route: Trie<String, (i32, Boxed<S>)>,
+    dynamic_route: Vec<(RegexPath, i32, Boxed<S>)>,
 }
 
 impl<S> Router<S>
@@ -164,16 +209,69 @@ where
     }
 
     /// Register a new endpoint.
-    pub fn on(
+    pub fn on(self, path: &'static str, endpoint: impl for<'a> Endpoint<'a, S>) -> Self {
+        self.on_priority(path, 0, endpoint)
+    }
+
+    /// Register a new endpoint with priority, see [precedence](#precedence).
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{get, Router};
+    /// use roa::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// // "/user/new" is handled by "/user/*{path}" rather than "/user/:id".
+    /// let router = Router::new()
+    ///     .on("/user/:id", get(end))
+    ///     .on_priority("/user/*{path}", 1, get(end));
+    /// ```
+    pub fn on_priority(
         mut self,
         path: &'static str,
+        priority: i32,
         endpoint: impl for<'a> Endpoint<'a, S>,
     ) -> Self {
         self.endpoints
-            .push((path.to_string(), self.register(endpoint)));
+            .push((path.to_string(), priority, self.register(endpoint)));
         self
     }
 
+    /// Register an endpoint with its method and path, like a function with route attribute.
+    ///
+    /// Routes on the same path with different methods are merged when building `RouteTable`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{get, delete, Router};
+    /// use roa::{Context, Result};
+    ///
+    /// /// Get a user by id.
+    /// #[get("/user/:id")]
+    /// async fn get_user(ctx: &mut Context, id: u64) -> Result {
+    ///     ctx.resp.write(format!("user {}", id));
+    ///     Ok(())
+    /// }
+    ///
+    /// #[delete("/user/:id")]
+    /// async fn delete_user(ctx: &mut Context, id: u64) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().route(get_user).route(delete_user);
+    /// assert!(router.routes("/api").is_ok());
+    /// ```
+    pub fn route(self, route: impl Route<S>) -> Self {
+        let path = route.path();
+        let method = route.method();
+        self.on(path, Dispatcher::default().on_method(method, route))
+    }
+
     /// Chain an endpoint to Router::middleware.
     fn register(&self, endpoint: impl for<'a> Endpoint<'a, S>) -> Boxed<S> {
         self.middleware.clone().end(endpoint).boxed()
@@ -181,13 +279,51 @@ where
 
     /// Include another router with prefix.
     pub fn include(mut self, prefix: &'static str, router: Router<S>) -> Self {
-        for (path, endpoint) in router.endpoints {
-            self.endpoints
-                .push((join_path([prefix, path.as_str()]), self.register(endpoint)))
+        for (path, priority, endpoint) in router.endpoints {
+            self.endpoints.push((
+                join_path([prefix, path.as_str()]),
+                priority,
+                self.register(endpoint),
+            ))
         }
         self
     }
 
+    /// Define a group of routes with prefix in a closure.
+    ///
+    /// The closure receives an empty router, middlewares chained to it
+    /// only apply to routes defined in it, and outer middlewares apply as well.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, get, post};
+    /// use roa::{Context, Next, Result};
+    ///
+    /// async fn auth(ctx: &mut Context, next: Next<'_>) -> Result {
+    ///     next.await
+    /// }
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new()
+    ///     .on("/login", post(end))
+    ///     .scope("/api", |api| {
+    ///         api.gate(auth)
+    ///             .on("/users", get(end))
+    ///             .on("/user/:id", get(end))
+    ///     });
+    /// ```
+    pub fn scope(
+        self,
+        prefix: &'static str,
+        scope: impl FnOnce(Router<S>) -> Router<S>,
+    ) -> Self {
+        self.include(prefix, scope(Router::new()))
+    }
+
     /// Chain a middleware to Router::middleware.
     pub fn gate(self, next: impl for<'a> Middleware<'a, S>) -> Router<S> {
         let Self {
@@ -200,12 +336,60 @@ where
         }
     }
 
+    /// List all registered routes as method and path template pairs,
+    /// including routes of included routers.
+    ///
+    /// Endpoints accepting any method, like functions, are listed with every method.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use roa::router::{Router, get};
+    /// use roa::http::Method;
+    /// use roa::{Context, Result};
+    ///
+    /// async fn end(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let user = Router::new().on("/:id", get(end).delete(end));
+    /// let router = Router::new().include("/user", user);
+    /// assert_eq!(
+    ///     vec![
+    ///         (Method::GET, "/user/:id".to_string()),
+    ///         (Method::DELETE, "/user/:id".to_string()),
+    ///     ],
+    ///     router.routes_list(),
+    /// );
+    /// println!("{}", router); // print route table
+    /// ```
+    pub fn routes_list(&self) -> Vec<(Method, String)> {
+        let mut routes = Vec::new();
+        for (path, _, endpoint) in self.endpoints.iter() {
+            let path = format!("/{}", join_path([path.as_str()]));
+            let methods = endpoint.methods().unwrap_or_else(|| ALL_METHODS.to_vec());
+            for method in methods {
+                routes.push((method, path.clone()))
+            }
+        }
+        routes
+    }
+
     /// Build RouteTable with path prefix.
     pub fn routes(self, prefix: &'static str) -> StdResult<RouteTable<S>, RouterError> {
         let mut route_table = RouteTable::default();
-        for (raw_path, endpoint) in self.endpoints {
-            route_table.insert(join_path([prefix, raw_path.as_str()]), endpoint)?;
+        for (raw_path, priority, endpoint) in self.endpoints {
+            route_table.insert(
+                join_path([prefix, raw_path.as_str()]),
+                priority,
+                endpoint,
+            )?;
         }
+        // stable sort keeps registration order of routes with the same rank.
+        route_table
+            .dynamic_route
+            .sort_by_key(|(path, priority, _)| (Reverse(*priority), path.wildcard));
+        route_table.warn_unreachable();
         Ok(route_table)
     }
 }
@@ -221,22 +405,132 @@ where
         }
     }
 
-    /// Insert endpoint to table.
+    /// Insert endpoint to table, merged endpoints take the higher priority.
     fn insert(
         &mut self,
         raw_path: impl AsRef<str>,
+        priority: i32,
         endpoint: Boxed<S>,
     ) -> StdResult<(), RouterError> {
         match raw_path.as_ref().parse()? {
             Path::Static(path) => {
-                if self.static_route.insert(path.clone(), endpoint).is_some() {
-                    return Err(Conflict::Path(path).into());
+                let route = match self.static_route.remove(&path) {
+                    Some((existing_priority, existing)) => (
+                        existing_priority.max(priority),
+                        merge(&path, existing, endpoint)?,
+                    ),
+                    None => (priority, endpoint),
+                };
+                self.static_route.insert(path, route);
+            }
+            Path::Dynamic(regex_path) => {
+                let index = self
+                    .dynamic_route
+                    .iter()
+                    .position(|(path, _, _)| path.raw == regex_path.raw);
+                match index {
+                    Some(index)
+                        if endpoint.methods().is_some()
+                            && self.dynamic_route[index].2.methods().is_some() =>
+                    {
+                        let (path, existing_priority, existing) =
+                            self.dynamic_route.remove(index);
+                        let endpoint = merge(&path.raw, existing, endpoint)?;
+                        self.dynamic_route.insert(
+                            index,
+                            (path, existing_priority.max(priority), endpoint),
+                        );
+                    }
+                    _ => self.dynamic_route.push((regex_path, priority, endpoint)),
                 }
             }
-            Path::Dynamic(regex_path) => self.dynamic_route.push((regex_path, endpoint)),
         }
         Ok(())
     }
+
+    /// Warn routes that can never be matched, dynamic routes must be sorted.
+    fn warn_unreachable(&self) {
+        for (index, (path, priority, _)) in self.dynamic_route.iter().enumerate() {
+            let shape = path.shape();
+            if let Some((earlier, _, _)) = self.dynamic_route[..index]
+                .iter()
+                .find(|(earlier, _, _)| earlier.shape() == shape)
+            {
+                log::warn!(
+                    "route `{}` (priority {}) is unreachable, it's shadowed by `{}`",
+                    path.raw,
+                    priority,
+                    earlier.raw,
+                );
+            }
+        }
+        for (path, (priority, _)) in self.static_route.iter() {
+            if let Some((dynamic, dynamic_priority, _)) =
+                self.dynamic_route
+                    .iter()
+                    .find(|(dynamic, dynamic_priority, _)| {
+                        dynamic_priority > priority && dynamic.re.is_match(path)
+                    })
+            {
+                log::warn!(
+                    "route `{}` (priority {}) is unreachable, it's shadowed by `{}` (priority {})",
+                    path,
+                    priority,
+                    dynamic.raw,
+                    dynamic_priority,
+                );
+            }
+        }
+    }
+}
+
+/// Endpoints on the same path, with disjoint methods.
+struct Merged<S>(Vec<(Vec<Method>, Boxed<S>)>);
+
+/// Merge two endpoints on the same path, they conflict unless their methods are disjoint.
+fn merge<S: 'static>(
+    path: &str,
+    existing: Boxed<S>,
+    endpoint: Boxed<S>,
+) -> StdResult<Boxed<S>, RouterError> {
+    let (existing_methods, methods) = match (existing.methods(), endpoint.methods()) {
+        (Some(existing_methods), Some(methods)) => (existing_methods, methods),
+        _ => return Err(Conflict::Path(path.to_string()).into()),
+    };
+    if let Some(method) = methods
+        .iter()
+        .find(|method| existing_methods.contains(method))
+    {
+        return Err(Conflict::Method(path.to_string(), method.clone()).into());
+    }
+    Ok(Merged(vec![(existing_methods, existing), (methods, endpoint)]).boxed())
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Endpoint<'a, S> for Merged<S>
+where
+    S: 'static,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        for (methods, endpoint) in self.0.iter() {
+            if methods.contains(ctx.method()) {
+                return endpoint.call(ctx).await;
+            }
+        }
+        method_not_allowed(ctx.method())
+    }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        let mut methods: Vec<Method> = self
+            .0
+            .iter()
+            .flat_map(|(methods, _)| methods.iter().cloned())
+            .collect();
+        sort_methods(&mut methods);
+        Some(methods)
+    }
 }
 
 impl<S> Default for Router<S>
@@ -248,6 +542,18 @@ where
     }
 }
 
+impl<S> Display for Router<S>
+where
+    S: 'static,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (method, path) in self.routes_list() {
+            writeln!(f, "{:<8}{}", method.as_str(), path)?;
+        }
+        Ok(())
+    }
+}
+
 impl<S> Default for RouteTable<S>
 where
     S: 'static,
@@ -281,14 +587,18 @@ where
                 },
             )?);
 
-        // search static routes
-        if let Some(end) = self.static_route.get(&path) {
-            return end.call(ctx).await;
-        }
+        let static_route = self.static_route.get(&path);
 
-        // search dynamic routes
-        for (regexp_path, end) in self.dynamic_route.iter() {
+        // search dynamic routes with higher priority than the static route.
+        for (regexp_path, priority, end) in self.dynamic_route.iter() {
+            if let Some((static_priority, _)) = static_route {
+                if priority <= static_priority {
+                    break;
+                }
+            }
             if let Some(cap) = regexp_path.re.captures(&path) {
+                // store variables before calling endpoint,
+                // so that router middlewares and guards can access them.
                 for var in regexp_path.vars.iter() {
                     ctx.store_scoped(
                         RouterScope,
@@ -300,6 +610,11 @@ where
             }
         }
 
+        // search static routes
+        if let Some((_, end)) = static_route {
+            return end.call(ctx).await;
+        }
+
         // 404 NOT FOUND
         throw!(StatusCode::NOT_FOUND)
     }
@@ -324,10 +639,11 @@ impl<S> RouterParam for Context<S> {
 
 #[cfg(all(test, feature = "tcp"))]
 mod tests {
-    use super::Router;
-    use crate::http::StatusCode;
+    use super::{allow, get, post, Router, RouterParam};
+    use crate::http::{Method, StatusCode};
+    use crate::query::{query_parser, Query};
     use crate::tcp::Listener;
-    use crate::{App, Context, Next, Status};
+    use crate::{throw, App, Context, Next, Status};
     use async_std::task::spawn;
     use encoding::EncoderTrap;
     use percent_encoding::NON_ALPHANUMERIC;
@@ -366,6 +682,124 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn gate_with_param() -> Result<(), Box<dyn std::error::Error>> {
+        async fn auth(ctx: &mut Context, next: Next<'_>) -> Result<(), Status> {
+            let admin = ctx.query("admin").map(|value| value.as_str() == "true");
+            if ctx.must_param("name")?.as_str() == "admin" || admin == Some(true) {
+                throw!(StatusCode::FORBIDDEN)
+            }
+            next.await
+        }
+        async fn end(ctx: &mut Context) -> Result<(), Status> {
+            assert_eq!("guest", ctx.must_param("name")?.as_str());
+            Ok(())
+        }
+        let router = Router::new()
+            .gate(query_parser)
+            .gate(auth)
+            .on("/:name", allow([Method::GET], end));
+        let app = App::new().end(router.routes("/user")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client
+            .get(&format!("http://{}/user/guest", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        // denied by path parameter
+        let resp = client
+            .get(&format!("http://{}/user/admin", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+
+        // denied by query parameter
+        let resp = client
+            .get(&format!("http://{}/user/guest?admin=true", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+
+        // the guard runs after gates
+        let resp = client
+            .post(&format!("http://{}/user/guest", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+        let resp = client
+            .post(&format!("http://{}/user/guest?admin=true", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::FORBIDDEN, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn optional_param() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> Result<(), Status> {
+            let page = match ctx.param("page") {
+                Some(page) => page.parse()?,
+                None => 1u64,
+            };
+            ctx.resp.write(page.to_string());
+            Ok(())
+        }
+        let router = Router::new()
+            .on("/posts", get(end))
+            .on("/posts/:page", get(end));
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}/posts", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("1", resp.text().await?);
+        let resp = reqwest::get(&format!("http://{}/posts/3", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("3", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scope() -> Result<(), Box<dyn std::error::Error>> {
+        async fn deny(_ctx: &mut Context, _next: Next<'_>) -> Result<(), Status> {
+            throw!(StatusCode::UNAUTHORIZED)
+        }
+        let router = Router::new()
+            .gate(gate)
+            .on("/public", test)
+            .scope("/admin", |admin| admin.gate(deny).on("/users", get(test)))
+            .scope("/user", |user| user.on("/:id", get(test)));
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}/public", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = reqwest::get(&format!("http://{}/user/1", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = reqwest::get(&format!("http://{}/admin/users", addr)).await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+        Ok(())
+    }
+
+    #[test]
+    fn routes_list() {
+        let user_router = Router::new()
+            .on("/:id", get(test).put(test))
+            .on("/", allow([Method::POST, Method::GET], test));
+        let router = Router::new().on("/", test).include("/user", user_router);
+        let routes = router.routes_list();
+        assert_eq!(9 + 2 + 2, routes.len());
+        assert_eq!((Method::GET, "/".to_string()), routes[0]);
+        assert_eq!((Method::GET, "/user/:id".to_string()), routes[9]);
+        assert_eq!((Method::PUT, "/user/:id".to_string()), routes[10]);
+        assert_eq!((Method::GET, "/user".to_string()), routes[11]);
+        assert_eq!((Method::POST, "/user".to_string()), routes[12]);
+        assert!(router.to_string().ends_with("POST    /user\n"));
+    }
+
     #[test]
     fn conflict_path() -> Result<(), Box<dyn std::error::Error>> {
         let evil_router = Router::new().on("/endpoint", test);
@@ -377,6 +811,82 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn merge_methods() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new()
+            .on("/user/:id", get(test))
+            .on("/user/:id", post(test).put(test))
+            .on("/users", get(test))
+            .on("/users", post(test));
+        let app = App::new().gate(gate).end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}/users", addr)).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&format!("http://{}/users", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .put(&format!("http://{}/user/0", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .delete(&format!("http://{}/user/0", addr))
+            .send()
+            .await?;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+
+        let router = Router::new()
+            .on("/users", get(test))
+            .on("/users", get(test).post(test));
+        assert!(router.routes("/").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn precedence() -> Result<(), Box<dyn std::error::Error>> {
+        fn named(name: &'static str) -> impl for<'a> crate::Endpoint<'a> {
+            crate::endpoint_fn::<(), _>(move |ctx| {
+                ctx.resp.write(name);
+                Box::pin(async { Ok(()) })
+            })
+        }
+        let router = Router::new()
+            .on("/user/*{path}", named("wildcard"))
+            .on("/user/:id", named("variable"))
+            .on("/user/new", named("static"))
+            .on("/file/:name", named("variable"))
+            .on_priority("/file/*{path}", 1, named("wildcard"))
+            .on("/file/index", named("static"))
+            .on_priority("/post/new", 1, named("static"))
+            .on_priority("/post/:id", 1, named("variable"));
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        for (path, expected) in vec![
+            ("/user/new", "static"),
+            ("/user/1", "variable"),
+            ("/user/1/name", "wildcard"),
+            ("/file/index", "wildcard"),
+            ("/file/readme", "wildcard"),
+            ("/post/new", "static"),
+            ("/post/1", "variable"),
+        ] {
+            let resp = client
+                .get(&format!("http://{}{}", addr, path))
+                .send()
+                .await?;
+            assert_eq!(StatusCode::OK, resp.status());
+            assert_eq!(expected, resp.text().await?, "path: {}", path);
+        }
+        Ok(())
+    }
+
     #[tokio::test]
     async fn route_not_found() -> Result<(), Box<dyn std::error::Error>> {
         let app = App::new().end(Router::default().routes("/")?);
@@ -407,4 +917,281 @@ mod tests {
             .ends_with("path `/%C2%B7%D3%C9` is not a valid utf-8 string"));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn accepts() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::CONTENT_TYPE;
+        async fn upload(_ctx: &mut Context) -> Result<(), Status> {
+            Ok(())
+        }
+        let router = Router::new().on(
+            "/upload",
+            super::post(upload)
+                .accepts(mime::MULTIPART_FORM_DATA)
+                .accepts(mime::APPLICATION_OCTET_STREAM),
+        );
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/upload", addr);
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=x")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "application/octet-stream")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "application/json")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        let resp = client.post(&url).send().await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        // method is checked first.
+        let resp = client.get(&url).send().await?;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accepts_bodiless_get() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::CONTENT_TYPE;
+        async fn handle(_ctx: &mut Context) -> Result<(), Status> {
+            Ok(())
+        }
+        let router = Router::new().on(
+            "/user",
+            super::get(handle)
+                .post(handle)
+                .accepts(mime::APPLICATION_JSON),
+        );
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/user", addr);
+        let resp = client.get(&url).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client.head(&url).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "application/json")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "text/plain")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        // a GET request with body is still checked.
+        let resp = client
+            .get(&url)
+            .header(CONTENT_TYPE, "text/plain")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn require_header() -> Result<(), Box<dyn std::error::Error>> {
+        use super::{require_header, require_header_value};
+        let router = Router::new()
+            .on(
+                "/api",
+                require_header("X-Api-Key", get(test)).status(StatusCode::UNAUTHORIZED),
+            )
+            .on("/hook", require_header_value("X-Event", "push", post(test)));
+        let app = App::new().gate(gate).end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let resp = client.get(&format!("http://{}/api", addr)).send().await?;
+        assert_eq!(StatusCode::UNAUTHORIZED, resp.status());
+        assert_eq!("header `x-api-key` is required", resp.text().await?);
+        let resp = client
+            .get(&format!("http://{}/api", addr))
+            .header("x-api-key", "secret")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let url = format!("http://{}/hook", addr);
+        let resp = client.post(&url).header("x-event", "pull").send().await?;
+        assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+        let resp = client.post(&url).header("x-event", "push").send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        // method is checked first.
+        let resp = client.get(&url).send().await?;
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn accepts_bodiless_get() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::http::header::CONTENT_TYPE;
+        async fn handle(_ctx: &mut Context) -> Result<(), Status> {
+            Ok(())
+        }
+        let router = Router::new().on(
+            "/user",
+            super::get(handle)
+                .post(handle)
+                .accepts(mime::APPLICATION_JSON),
+        );
+        let app = App::new().end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/user", addr);
+        let resp = client.get(&url).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client.head(&url).send().await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "application/json")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let resp = client
+            .post(&url)
+            .header(CONTENT_TYPE, "text/plain")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        // a GET request with body is still checked.
+        let resp = client
+            .get(&url)
+            .header(CONTENT_TYPE, "text/plain")
+            .body("{}")
+            .send()
+            .await?;
+        assert_eq!(StatusCode::UNSUPPORTED_MEDIA_TYPE, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ip_guard() -> Result<(), Box<dyn std::error::Error>> {
+        use super::{allow_ip, deny_ip};
+        let router = Router::new()
+            .on("/admin", allow_ip(vec!["127.0.0.0/8", "::1"], get(test)))
+            .on("/internal", allow_ip(vec!["10.0.0.0/8"], get(test)))
+            .on(
+                "/proxied",
+                allow_ip(vec!["10.0.0.0/8"], get(test)).trust_proxies(vec!["127.0.0.1"]),
+            )
+            .on(
+                "/public",
+                deny_ip(vec!["192.0.2.0/24", "2001:db8::/32"], get(test))
+                    .trust_proxies(vec!["127.0.0.1"]),
+            );
+        let app = App::new().gate(gate).end(router.routes("/")?);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let get = |path: &str, forwarded: &str| {
+            client
+                .get(&format!("http://{}{}", addr, path))
+                .header("x-forwarded-for", forwarded)
+                .send()
+        };
+        // decided by remote address if no proxy is trusted.
+        assert_eq!(StatusCode::OK, get("/admin", "192.0.2.7").await?.status());
+        // a spoofed "X-Forwarded-For" is ignored.
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get("/internal", "10.0.0.1").await?.status()
+        );
+        // the rightmost hop not trusted is the client.
+        assert_eq!(StatusCode::OK, get("/proxied", "10.0.0.1").await?.status());
+        assert_eq!(
+            StatusCode::OK,
+            get("/proxied", "192.0.2.7, 10.0.0.1").await?.status()
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get("/proxied", "10.0.0.1, 192.0.2.7").await?.status()
+        );
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            get("/proxied", "10.0.0.1, unknown").await?.status()
+        );
+        assert_eq!(StatusCode::OK, get("/public", "10.0.0.1").await?.status());
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get("/public", "192.0.2.7").await?.status()
+        );
+        assert_eq!(
+            StatusCode::FORBIDDEN,
+            get("/public", "2001:db8::1").await?.status()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn host_router() -> Result<(), Box<dyn std::error::Error>> {
+        use super::HostRouter;
+        use crate::http::header::HOST;
+        async fn tenant(ctx: &mut Context) -> Result<(), Status> {
+            let name = ctx.must_param("subdomain")?;
+            ctx.resp.write(format!("tenant {}", &*name));
+            Ok(())
+        }
+        let api = Router::new().on("/users", get("api users"));
+        let hosts = HostRouter::new()
+            .host("api.example.com", api.routes("/")?)
+            .host("*.example.com", tenant)
+            .host("*.eu.example.com", "eu");
+        let (addr, server) = App::new().end(hosts).run()?;
+        spawn(server);
+        let client = reqwest::Client::new();
+        let get_host = |host: &'static str, path: &'static str| {
+            client
+                .get(&format!("http://{}{}", addr, path))
+                .header(HOST, host)
+                .send()
+        };
+        let resp = get_host("API.example.com:8080", "/users").await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("api users", resp.text().await?);
+        let resp = get_host("foo.example.com", "/").await?;
+        assert_eq!("tenant foo", resp.text().await?);
+        let resp = get_host("foo.eu.example.com", "/").await?;
+        assert_eq!("eu", resp.text().await?);
+        let resp = get_host("example.com", "/").await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status());
+        let resp = get_host("other.org", "/").await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status());
+
+        // fallback
+        let hosts = HostRouter::new()
+            .host("api.example.com", "api")
+            .fallback("fallback");
+        let (addr, server) = App::new().end(hosts).run()?;
+        spawn(server);
+        let resp = client
+            .get(&format!("http://{}", addr))
+            .header(HOST, "other.org")
+            .send()
+            .await?;
+        assert_eq!("fallback", resp.text().await?);
+        Ok(())
+    }
 }
@@ -1,11 +1,38 @@
+mod accepts;
 mod dispatcher;
 mod guard;
+mod header;
+mod ip;
 
 use crate::http::{Method, StatusCode};
 use crate::{throw, Result};
 
+/// All http methods can be routed.
+pub(crate) const ALL_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::OPTIONS,
+    Method::DELETE,
+    Method::HEAD,
+    Method::TRACE,
+    Method::CONNECT,
+];
+
+/// Sort methods in order of `ALL_METHODS`.
+#[inline]
+pub(crate) fn sort_methods(methods: &mut Vec<Method>) {
+    methods.sort_by_key(|method| {
+        ALL_METHODS
+            .iter()
+            .position(|item| item == method)
+            .unwrap_or(ALL_METHODS.len())
+    })
+}
+
 #[inline]
-fn method_not_allowed(method: &Method) -> Result {
+pub(crate) fn method_not_allowed(method: &Method) -> Result {
     throw!(
         StatusCode::METHOD_NOT_ALLOWED,
         format!("Method {} not allowed", method)
@@ -16,4 +43,10 @@ pub use dispatcher::{
     connect, delete, get, head, options, patch, post, put, trace, Dispatcher,
 };
 
+pub use accepts::{accepts, Accepts};
+
 pub use guard::{allow, deny, Guard};
+
+pub use header::{require_header, require_header_value, RequireHeader};
+
+pub use ip::{allow_ip, deny_ip, IpGuard};
@@ -0,0 +1,97 @@
+use crate::body::{content_type_is, media_type};
+use crate::http::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, throw, Context, Endpoint, Result};
+
+/// An endpoint wrapper to guard endpoint by media type of request.
+pub struct Accepts<E> {
+    media_types: Vec<String>,
+    endpoint: E,
+}
+
+/// A function to construct guard by media type.
+///
+/// Only requests with "Content-Type" matching the media type can access this endpoint,
+/// otherwise will get a 415 UNSUPPORTED MEDIA TYPE. Parameters like "charset" are ignored.
+///
+/// Requests with a method not allowed by the endpoint still get a 405 METHOD NOT ALLOWED.
+///
+/// Requests of safe methods (like GET and HEAD) without a body are not checked,
+/// so a dispatcher serving both GET and POST can be guarded as a whole.
+///
+/// ```
+/// use roa::{App, Context, Result};
+/// use roa::router::{accepts, post, Router};
+///
+/// async fn upload(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let router = Router::new()
+///     .on("/upload", post(upload).accepts(mime::MULTIPART_FORM_DATA))
+///     .on("/form", accepts(mime::APPLICATION_WWW_FORM_URLENCODED, post(upload)));
+/// ```
+pub fn accepts<E>(mime: impl AsRef<str>, endpoint: E) -> Accepts<E> {
+    Accepts {
+        media_types: vec![],
+        endpoint,
+    }
+    .accepts(mime)
+}
+
+impl<E> Accepts<E> {
+    /// Accept one more media type.
+    pub fn accepts(mut self, mime: impl AsRef<str>) -> Self {
+        self.media_types.push(media_type(mime.as_ref()).to_string());
+        self
+    }
+}
+
+/// Check if request carries a body,
+/// requests of safe methods are considered bodiless without "Content-Length" or "Transfer-Encoding".
+#[inline]
+fn has_body<S>(ctx: &Context<S>) -> bool {
+    if !ctx.method().is_safe() || ctx.req.headers.contains_key(TRANSFER_ENCODING) {
+        return true;
+    }
+    match ctx.req.headers.get(CONTENT_LENGTH) {
+        Some(len) => len != "0",
+        None => false,
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, E> Endpoint<'a, S> for Accepts<E>
+where
+    E: Endpoint<'a, S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let allowed = self
+            .endpoint
+            .methods()
+            .map(|methods| methods.contains(ctx.method()))
+            .unwrap_or(true);
+        if allowed
+            && has_body(ctx)
+            && !self
+                .media_types
+                .iter()
+                .any(|expected| content_type_is(ctx, expected))
+        {
+            throw!(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!(
+                    "content type should be `{}`",
+                    self.media_types.join("` or `")
+                )
+            )
+        }
+        self.endpoint.call(ctx).await
+    }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.endpoint.methods()
+    }
+}
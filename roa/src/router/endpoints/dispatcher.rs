@@ -1,4 +1,4 @@
-use super::method_not_allowed;
+use super::{accepts, method_not_allowed, sort_methods, Accepts};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use doc_comment::doc_comment;
@@ -80,6 +80,21 @@ impl<S> Dispatcher<S> {
     impl_http_methods!(head, Method::HEAD);
     impl_http_methods!(trace, Method::TRACE);
     impl_http_methods!(connect, Method::CONNECT);
+
+    /// Add or override endpoint on a method.
+    pub(crate) fn on_method(
+        mut self,
+        method: Method,
+        endpoint: impl for<'a> Endpoint<'a, S>,
+    ) -> Self {
+        self.0.insert(method, Box::new(endpoint));
+        self
+    }
+
+    /// Guard this dispatcher by media type of request, see `accepts`.
+    pub fn accepts(self, mime: impl AsRef<str>) -> Accepts<Self> {
+        accepts(mime, self)
+    }
 }
 
 /// Empty dispatcher.
@@ -101,4 +116,11 @@ where
             None => method_not_allowed(ctx.method()),
         }
     }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        let mut methods: Vec<Method> = self.0.keys().cloned().collect();
+        sort_methods(&mut methods);
+        Some(methods)
+    }
 }
@@ -1,22 +1,9 @@
-use super::method_not_allowed;
+use super::{method_not_allowed, sort_methods, ALL_METHODS};
 use crate::http::Method;
 use crate::{async_trait, Context, Endpoint, Result};
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-/// Methods allowed in `Guard`.
-const ALL_METHODS: [Method; 9] = [
-    Method::GET,
-    Method::POST,
-    Method::PUT,
-    Method::PATCH,
-    Method::OPTIONS,
-    Method::DELETE,
-    Method::HEAD,
-    Method::TRACE,
-    Method::CONNECT,
-];
-
 /// An endpoint wrapper to guard endpoint by http method.
 pub struct Guard<E> {
     white_list: HashSet<Method>,
@@ -87,4 +74,17 @@ where
             method_not_allowed(ctx.method())
         }
     }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        let mut methods: Vec<Method> = match self.endpoint.methods() {
+            Some(methods) => methods
+                .into_iter()
+                .filter(|method| self.white_list.contains(method))
+                .collect(),
+            None => self.white_list.iter().cloned().collect(),
+        };
+        sort_methods(&mut methods);
+        Some(methods)
+    }
 }
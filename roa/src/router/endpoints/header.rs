@@ -0,0 +1,118 @@
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, throw, Context, Endpoint, Result};
+
+/// An endpoint wrapper to guard endpoint by a request header.
+pub struct RequireHeader<E> {
+    name: HeaderName,
+    value: Option<HeaderValue>,
+    status: StatusCode,
+    endpoint: E,
+}
+
+/// Parse a header name, panic if it's invalid.
+fn header_name(name: &str) -> HeaderName {
+    HeaderName::from_bytes(name.as_bytes())
+        .unwrap_or_else(|err| panic!("{}\ninvalid header name `{}`", err, name))
+}
+
+/// A function to construct guard by a required header.
+///
+/// Only requests with the header can access this endpoint,
+/// otherwise will get a 400 BAD REQUEST, or the status set by `RequireHeader::status`.
+///
+/// Requests with a method not allowed by the endpoint still get a 405 METHOD NOT ALLOWED.
+///
+/// It panics if the header name is invalid.
+///
+/// ```
+/// use roa::{App, Context, Result};
+/// use roa::http::StatusCode;
+/// use roa::router::{get, require_header, Router};
+///
+/// async fn foo(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let router = Router::new()
+///     .on("/tenant", require_header("X-Tenant-Id", get(foo)))
+///     .on("/api", require_header("X-Api-Key", get(foo)).status(StatusCode::UNAUTHORIZED));
+/// ```
+pub fn require_header<E>(name: impl AsRef<str>, endpoint: E) -> RequireHeader<E> {
+    RequireHeader {
+        name: header_name(name.as_ref()),
+        value: None,
+        status: StatusCode::BAD_REQUEST,
+        endpoint,
+    }
+}
+
+/// A function to construct guard by a required header with a specific value.
+///
+/// It's like `require_header`, but the value of header must be equal to the expected one.
+///
+/// It panics if the header name or value is invalid.
+///
+/// ```
+/// use roa::{App, Context, Result};
+/// use roa::router::{post, require_header_value};
+///
+/// async fn hook(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let app = App::new().end(require_header_value("X-Event", "push", post(hook)));
+/// ```
+pub fn require_header_value<E>(
+    name: impl AsRef<str>,
+    value: impl AsRef<str>,
+    endpoint: E,
+) -> RequireHeader<E> {
+    let value = HeaderValue::from_str(value.as_ref()).unwrap_or_else(|err| {
+        panic!("{}\ninvalid header value `{}`", err, value.as_ref())
+    });
+    RequireHeader {
+        value: Some(value),
+        ..require_header(name, endpoint)
+    }
+}
+
+impl<E> RequireHeader<E> {
+    /// Set status code to reject requests, like 401 UNAUTHORIZED.
+    pub fn status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, E> Endpoint<'a, S> for RequireHeader<E>
+where
+    E: Endpoint<'a, S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let allowed = self
+            .endpoint
+            .methods()
+            .map(|methods| methods.contains(ctx.method()))
+            .unwrap_or(true);
+        if allowed {
+            match (ctx.req.headers.get(&self.name), &self.value) {
+                (None, _) => {
+                    throw!(self.status, format!("header `{}` is required", self.name))
+                }
+                (Some(value), Some(expected)) if value != expected => {
+                    throw!(self.status, format!("header `{}` is invalid", self.name))
+                }
+                _ => (),
+            }
+        }
+        self.endpoint.call(ctx).await
+    }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.endpoint.methods()
+    }
+}
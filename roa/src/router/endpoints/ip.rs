@@ -0,0 +1,276 @@
+use crate::http::{Method, StatusCode};
+use crate::{async_trait, throw, Context, Endpoint, Result, State};
+use std::net::IpAddr;
+
+/// An endpoint wrapper to guard endpoint by client ip.
+///
+/// Client ip is the remote address of connection by default,
+/// "X-Forwarded-For" is honored only if the remote address is a trusted proxy,
+/// see `IpGuard::trust_proxies`.
+pub struct IpGuard<E> {
+    ranges: Vec<IpRange>,
+    proxies: Vec<IpRange>,
+    allow: bool,
+    endpoint: E,
+}
+
+/// An ip range in CIDR notation, like "10.0.0.0/8" or "fd00::/8".
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IpRange {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    /// Parse a CIDR, a bare ip is a range of itself. Panic if it's invalid.
+    fn parse(cidr: &str) -> Self {
+        let mut parts = cidr.trim().splitn(2, '/');
+        let addr: IpAddr = parts
+            .next()
+            .and_then(|addr| addr.parse().ok())
+            .unwrap_or_else(|| invalid(cidr));
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match parts.next() {
+            None => max,
+            Some(prefix) => prefix.parse().unwrap_or_else(|_| invalid(cidr)),
+        };
+        if prefix > max {
+            invalid(cidr)
+        }
+        Self { addr, prefix }
+    }
+
+    /// Check if an ip is in this range, IPv4-mapped IPv6 addresses are treated as IPv4.
+    fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => match v6.to_ipv4() {
+                Some(v4) if v6.segments()[..6] == [0, 0, 0, 0, 0, 0xffff] => {
+                    IpAddr::V4(v4)
+                }
+                _ => ip,
+            },
+            ip => ip,
+        };
+        match (self.addr, ip) {
+            (IpAddr::V4(range), IpAddr::V4(ip)) => masked(
+                u32::from(range).into(),
+                u32::from(ip).into(),
+                32,
+                self.prefix,
+            ),
+            (IpAddr::V6(range), IpAddr::V6(ip)) => {
+                masked(u128::from(range), u128::from(ip), 128, self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Panic on an invalid CIDR.
+fn invalid<T>(cidr: &str) -> T {
+    panic!("invalid CIDR `{}`", cidr)
+}
+
+/// Check if the first `prefix` bits of two addresses of `bits` bits are equal.
+#[inline]
+fn masked(range: u128, ip: u128, bits: u8, prefix: u8) -> bool {
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix;
+    range >> shift == ip >> shift
+}
+
+/// Parse CIDR list.
+fn ranges<I>(cidrs: I) -> Vec<IpRange>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    cidrs
+        .into_iter()
+        .map(|cidr| IpRange::parse(cidr.as_ref()))
+        .collect()
+}
+
+/// A function to construct guard by a white list of ip ranges.
+///
+/// Only requests from client ip in ranges can access this endpoint,
+/// otherwise will get a 403 FORBIDDEN. Both IPv4 and IPv6 CIDRs are supported.
+///
+/// Client ip is the remote address of connection unless proxies are trusted
+/// by `IpGuard::trust_proxies`, as "X-Forwarded-For" can be forged by clients.
+///
+/// Requests with a method not allowed by the endpoint still get a 405 METHOD NOT ALLOWED.
+///
+/// It panics if any CIDR is invalid.
+///
+/// ```
+/// use roa::{App, Context, Result};
+/// use roa::router::{allow_ip, get, Router};
+///
+/// async fn metrics(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let router = Router::new()
+///     .on("/metrics", allow_ip(vec!["10.0.0.0/8", "127.0.0.1", "::1"], get(metrics)));
+/// ```
+pub fn allow_ip<E, I>(cidrs: I, endpoint: E) -> IpGuard<E>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    IpGuard {
+        ranges: ranges(cidrs),
+        proxies: Vec::new(),
+        allow: true,
+        endpoint,
+    }
+}
+
+/// A function to construct guard by a black list of ip ranges.
+///
+/// Requests from client ip in ranges will get a 403 FORBIDDEN, see `allow_ip` for more details.
+///
+/// ```
+/// use roa::{App, Context, Result};
+/// use roa::router::deny_ip;
+///
+/// async fn foo(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// let app = App::new().end(deny_ip(vec!["192.0.2.0/24", "2001:db8::/32"], foo));
+/// ```
+pub fn deny_ip<E, I>(cidrs: I, endpoint: E) -> IpGuard<E>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    IpGuard {
+        ranges: ranges(cidrs),
+        proxies: Vec::new(),
+        allow: false,
+        endpoint,
+    }
+}
+
+impl<E> IpGuard<E> {
+    /// Trust proxies in ranges, none is trusted by default. It panics if any CIDR is invalid.
+    ///
+    /// If the remote address is a trusted proxy, hops of "X-Forwarded-For" are walked
+    /// from right to left, and the first one not trusted is taken as client ip.
+    /// A request with invalid hop gets a 400 BAD REQUEST.
+    ///
+    /// ```
+    /// use roa::{App, Context, Result};
+    /// use roa::router::{allow_ip, get, Router};
+    ///
+    /// async fn metrics(ctx: &mut Context) -> Result {
+    ///     Ok(())
+    /// }
+    ///
+    /// let guard = allow_ip(vec!["10.0.0.0/8"], get(metrics)).trust_proxies(vec!["172.16.0.0/12"]);
+    /// let router = Router::new().on("/metrics", guard);
+    /// ```
+    pub fn trust_proxies<I>(mut self, cidrs: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.proxies = ranges(cidrs);
+        self
+    }
+
+    /// Check if an ip is a trusted proxy.
+    #[inline]
+    fn trusted(&self, ip: IpAddr) -> bool {
+        self.proxies.iter().any(|range| range.contains(ip))
+    }
+
+    /// Get client ip by remote address and hops forwarded by trusted proxies.
+    fn client_ip<S>(&self, ctx: &Context<S>) -> Result<IpAddr> {
+        let mut ip = ctx.remote_addr.ip();
+        if !self.trusted(ip) {
+            return Ok(ip);
+        }
+        let hops = ctx
+            .header_all("x-forwarded-for")
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|hop| !hop.is_empty())
+            .collect::<Vec<_>>();
+        for hop in hops.into_iter().rev() {
+            if !self.trusted(ip) {
+                break;
+            }
+            ip = match hop.parse() {
+                Ok(ip) => ip,
+                Err(_) => throw!(
+                    StatusCode::BAD_REQUEST,
+                    format!("invalid hop `{}` in x-forwarded-for", hop)
+                ),
+            };
+        }
+        Ok(ip)
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, E> Endpoint<'a, S> for IpGuard<E>
+where
+    S: State,
+    E: Endpoint<'a, S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        let allowed = self
+            .endpoint
+            .methods()
+            .map(|methods| methods.contains(ctx.method()))
+            .unwrap_or(true);
+        if allowed {
+            let ip = self.client_ip(ctx)?;
+            let matched = self.ranges.iter().any(|range| range.contains(ip));
+            if matched != self.allow {
+                throw!(StatusCode::FORBIDDEN, format!("ip {} is forbidden", ip))
+            }
+        }
+        self.endpoint.call(ctx).await
+    }
+
+    #[inline]
+    fn methods(&self) -> Option<Vec<Method>> {
+        self.endpoint.methods()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IpRange;
+
+    #[test]
+    fn ip_range() {
+        let range = IpRange::parse("10.0.0.0/8");
+        assert!(range.contains("10.1.2.3".parse().unwrap()));
+        assert!(range.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!range.contains("11.0.0.1".parse().unwrap()));
+        assert!(!range.contains("::a01:203".parse().unwrap()));
+
+        let range = IpRange::parse("2001:db8::/32");
+        assert!(range.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!range.contains("2001:db9::1".parse().unwrap()));
+
+        assert!(IpRange::parse("0.0.0.0/0").contains("1.2.3.4".parse().unwrap()));
+        assert!(IpRange::parse("::1").contains("::1".parse().unwrap()));
+        assert!(!IpRange::parse("::1").contains("::2".parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid CIDR `10.0.0.0/33`")]
+    fn invalid_cidr() {
+        IpRange::parse("10.0.0.0/33");
+    }
+}
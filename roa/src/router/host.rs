@@ -0,0 +1,134 @@
+use super::RouterScope;
+use crate::http::header::HOST;
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Boxed, Context, Endpoint, EndpointExt, Result};
+use std::collections::HashMap;
+
+/// Name of router parameter captured by wildcard hosts.
+pub const SUBDOMAIN: &str = "subdomain";
+
+/// An endpoint to route request by "Host" header, for virtual hosting.
+///
+/// - An exact host like "api.example.com" takes precedence over wildcards.
+/// - A wildcard host like "*.example.com" matches any subdomain,
+///   which can be accessed by `ctx.param("subdomain")`; the longest suffix wins.
+/// - Unmatched hosts fall through to the fallback endpoint, or get a 404 NOT FOUND.
+///
+/// Hosts are matched case-insensitively, and port is ignored.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::router::{get, HostRouter, Router, RouterParam};
+/// use roa::{App, Context, Result};
+///
+/// async fn api(ctx: &mut Context) -> Result {
+///     Ok(())
+/// }
+///
+/// async fn tenant(ctx: &mut Context) -> Result {
+///     let name = ctx.must_param("subdomain")?;
+///     ctx.resp.write(format!("Hello, {}", &*name));
+///     Ok(())
+/// }
+///
+/// # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// let api_router = Router::new().on("/users", get(api));
+/// let hosts = HostRouter::new()
+///     .host("api.example.com", api_router.routes("/")?)
+///     .host("*.example.com", tenant);
+/// let app = App::new().end(hosts);
+/// # Ok(())
+/// # }
+/// ```
+pub struct HostRouter<S> {
+    exact: HashMap<String, Boxed<S>>,
+    wildcards: Vec<(String, Boxed<S>)>,
+    fallback: Option<Boxed<S>>,
+}
+
+impl<S> HostRouter<S>
+where
+    S: 'static,
+{
+    /// Construct an empty host router.
+    pub fn new() -> Self {
+        Self {
+            exact: HashMap::new(),
+            wildcards: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    /// Route a host, or a wildcard host starts with "*.", to an endpoint.
+    pub fn host(mut self, host: &str, endpoint: impl for<'a> Endpoint<'a, S>) -> Self {
+        let host = host.trim().to_ascii_lowercase();
+        if host.starts_with("*.") {
+            // keep the leading dot to match subdomains only.
+            self.wildcards
+                .push((host[1..].to_string(), endpoint.boxed()));
+            self.wildcards
+                .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()));
+        } else {
+            self.exact.insert(host, endpoint.boxed());
+        }
+        self
+    }
+
+    /// Set the endpoint for unmatched hosts.
+    pub fn fallback(mut self, endpoint: impl for<'a> Endpoint<'a, S>) -> Self {
+        self.fallback = Some(endpoint.boxed());
+        self
+    }
+}
+
+impl<S> Default for HostRouter<S>
+where
+    S: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get host of request without port, from "Host" header or uri.
+#[inline]
+fn request_host<S>(ctx: &Context<S>) -> Option<String> {
+    let host = ctx.get(HOST).or_else(|| ctx.uri().host())?;
+    let host = if host.starts_with('[') {
+        // ipv6 literal.
+        match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        }
+    } else {
+        host.split(':').next().unwrap_or(host)
+    };
+    Some(host.trim_end_matches('.').to_ascii_lowercase())
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Endpoint<'a, S> for HostRouter<S>
+where
+    S: 'static,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        if let Some(host) = request_host(ctx) {
+            if let Some(end) = self.exact.get(&host) {
+                return end.call(ctx).await;
+            }
+            for (suffix, end) in self.wildcards.iter() {
+                if host.len() > suffix.len() && host.ends_with(suffix.as_str()) {
+                    let subdomain = host[..host.len() - suffix.len()].to_string();
+                    ctx.store_scoped(RouterScope, SUBDOMAIN, subdomain);
+                    return end.call(ctx).await;
+                }
+            }
+        }
+        match &self.fallback {
+            Some(end) => end.call(ctx).await,
+            None => throw!(StatusCode::NOT_FOUND),
+        }
+    }
+}
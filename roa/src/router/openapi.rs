@@ -0,0 +1,393 @@
+//! This module provides a minimal OpenAPI 3 document generator `OpenApi`.
+//!
+//! Paths and methods are collected from `Router`,
+//! parameters and request body can be declared by `Operation` with typed `Schema`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::router::{Router, RouterParam, get};
+//! use roa::router::openapi::{OpenApi, Operation, Param};
+//! use roa::http::Method;
+//! use roa::{App, Context, Result};
+//!
+//! async fn user(ctx: &mut Context) -> Result {
+//!     let id: u64 = ctx.must_param("id")?.parse()?;
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+//! let router = Router::new().on("/user/:id", get(user));
+//! let spec = OpenApi::new("user service", "1.0.0")
+//!     .routes("/api", &router)
+//!     .operation(
+//!         Method::GET,
+//!         "/api/user/:id",
+//!         Operation::new()
+//!             .summary("get user by id")
+//!             .param(Param::path::<u64>("id")),
+//!     );
+//! let router = router.on("/openapi.json", get(spec));
+//! let app = App::new().end(router.routes("/api")?);
+//! # Ok(())
+//! # }
+//! ```
+
+use super::path::{join_path, must_build, WILDCARD};
+use super::Router;
+use crate::body::PowerBody;
+use crate::http::Method;
+use crate::{async_trait, Context, Endpoint, Result, State};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+
+lazy_static! {
+    static ref WILDCARD_RE: Regex = must_build(WILDCARD);
+}
+
+/// Types can be described by a json schema.
+pub trait Schema {
+    /// Json schema of this type.
+    fn schema() -> Value;
+}
+
+macro_rules! impl_schema {
+    ($type_name:expr, $($t:ty),+) => {
+        $(
+            impl Schema for $t {
+                #[inline]
+                fn schema() -> Value {
+                    json!({ "type": $type_name })
+                }
+            }
+        )+
+    };
+}
+
+impl_schema!("boolean", bool);
+impl_schema!("integer", u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+impl_schema!("number", f32, f64);
+impl_schema!("string", char, String, &'static str);
+
+impl<T: Schema> Schema for Option<T> {
+    #[inline]
+    fn schema() -> Value {
+        T::schema()
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    #[inline]
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+/// Location of a parameter.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Location {
+    /// Router parameter.
+    Path,
+    /// Query parameter.
+    Query,
+    /// Request header.
+    Header,
+}
+
+/// A declared parameter.
+#[derive(Debug, Clone)]
+pub struct Param {
+    name: String,
+    location: Location,
+    required: bool,
+    schema: Value,
+}
+
+/// A declared operation, an endpoint of a method and a path.
+#[derive(Debug, Clone, Default)]
+pub struct Operation {
+    summary: Option<String>,
+    params: Vec<Param>,
+    body: Option<Value>,
+}
+
+/// An OpenAPI 3 document, it's also an endpoint responding the document in json.
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    operations: Vec<(Method, String, Operation)>,
+}
+
+impl Location {
+    #[inline]
+    fn as_str(self) -> &'static str {
+        match self {
+            Location::Path => "path",
+            Location::Query => "query",
+            Location::Header => "header",
+        }
+    }
+}
+
+impl Param {
+    /// Construct a parameter.
+    pub fn new<T: Schema>(name: impl ToString, location: Location) -> Self {
+        Self {
+            name: name.to_string(),
+            location,
+            required: location == Location::Path,
+            schema: T::schema(),
+        }
+    }
+
+    /// Declare a router parameter, which is always required.
+    pub fn path<T: Schema>(name: impl ToString) -> Self {
+        Self::new::<T>(name, Location::Path)
+    }
+
+    /// Declare an optional query parameter.
+    pub fn query<T: Schema>(name: impl ToString) -> Self {
+        Self::new::<T>(name, Location::Query)
+    }
+
+    /// Declare an optional header.
+    pub fn header<T: Schema>(name: impl ToString) -> Self {
+        Self::new::<T>(name, Location::Header)
+    }
+
+    /// Set whether this parameter is required.
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    #[inline]
+    fn document(&self) -> Value {
+        json!({
+            "name": self.name,
+            "in": self.location.as_str(),
+            "required": self.required,
+            "schema": self.schema,
+        })
+    }
+}
+
+impl Operation {
+    /// Construct an empty operation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set summary.
+    pub fn summary(mut self, summary: impl ToString) -> Self {
+        self.summary = Some(summary.to_string());
+        self
+    }
+
+    /// Declare a parameter.
+    pub fn param(mut self, param: Param) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    /// Declare a json request body.
+    pub fn json_body<T: Schema>(mut self) -> Self {
+        self.body = Some(T::schema());
+        self
+    }
+
+    #[inline]
+    fn document(&self, path_vars: &[String]) -> Value {
+        let mut params: Vec<Value> = self.params.iter().map(Param::document).collect();
+        // router parameters not declared are strings.
+        for var in path_vars {
+            let declared = self
+                .params
+                .iter()
+                .any(|param| param.location == Location::Path && &param.name == var);
+            if !declared {
+                params.push(Param::path::<String>(var).document());
+            }
+        }
+        let mut operation = Map::new();
+        if let Some(ref summary) = self.summary {
+            operation.insert("summary".to_string(), json!(summary));
+        }
+        if !params.is_empty() {
+            operation.insert("parameters".to_string(), Value::Array(params));
+        }
+        if let Some(ref schema) = self.body {
+            operation.insert(
+                "requestBody".to_string(),
+                json!({ "content": { "application/json": { "schema": schema } } }),
+            );
+        }
+        operation.insert(
+            "responses".to_string(),
+            json!({ "default": { "description": "response" } }),
+        );
+        Value::Object(operation)
+    }
+}
+
+/// Standardize path as the ones listed by router.
+#[inline]
+fn standardize(path: &str) -> String {
+    format!("/{}", join_path([path]))
+}
+
+/// Convert router path to OpenAPI path template, and collect variables.
+///
+/// `/user/:id/*{path}` => `/user/{id}/{path}`
+fn template(path: &str) -> (String, Vec<String>) {
+    let mut vars = Vec::new();
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            if segment.starts_with(':') {
+                vars.push(segment[1..].to_string());
+                format!("{{{}}}", &segment[1..])
+            } else {
+                for cap in WILDCARD_RE.captures_iter(segment) {
+                    vars.push(cap["var"].to_string());
+                }
+                WILDCARD_RE.replace_all(segment, "{$var}").to_string()
+            }
+        })
+        .collect();
+    (segments.join("/"), vars)
+}
+
+impl OpenApi {
+    /// Construct an empty document.
+    pub fn new(title: impl ToString, version: impl ToString) -> Self {
+        Self {
+            title: title.to_string(),
+            version: version.to_string(),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Collect routes of a router, the prefix should be the same as the one passed to `Router::routes`.
+    pub fn routes<S: 'static>(mut self, prefix: &str, router: &Router<S>) -> Self {
+        for (method, path) in router.routes_list() {
+            let path = standardize(&join_path([prefix, path.as_str()]));
+            self.operations.push((method, path, Operation::new()));
+        }
+        self
+    }
+
+    /// Declare an operation, override the collected one with the same method and path.
+    pub fn operation(
+        mut self,
+        method: Method,
+        path: &str,
+        operation: Operation,
+    ) -> Self {
+        let path = standardize(path);
+        match self
+            .operations
+            .iter_mut()
+            .find(|(m, p, _)| *m == method && *p == path)
+        {
+            Some((_, _, op)) => *op = operation,
+            None => self.operations.push((method, path, operation)),
+        }
+        self
+    }
+
+    /// Generate the document.
+    pub fn document(&self) -> Value {
+        let mut paths = Map::new();
+        for (method, path, operation) in self.operations.iter() {
+            let (template, vars) = template(path);
+            let item = paths
+                .entry(template)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(item) = item {
+                item.insert(
+                    method.as_str().to_ascii_lowercase(),
+                    operation.document(&vars),
+                );
+            }
+        }
+        json!({
+            "openapi": "3.0.3",
+            "info": { "title": self.title, "version": self.version },
+            "paths": paths,
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S: State> Endpoint<'a, S> for OpenApi {
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        ctx.write_json(&self.document())
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{template, OpenApi, Operation, Param};
+    use crate::http::{Method, StatusCode};
+    use crate::preload::*;
+    use crate::router::{get, Router};
+    use crate::{App, Context};
+    use async_std::task::spawn;
+    use serde_json::Value;
+
+    async fn end(_ctx: &mut Context) -> crate::Result {
+        Ok(())
+    }
+
+    #[test]
+    fn path_template() {
+        assert_eq!(
+            (
+                "/user/{id}/{path}".to_string(),
+                vec!["id".to_string(), "path".to_string()]
+            ),
+            template("/user/:id/*{path}")
+        );
+        assert_eq!(("/user".to_string(), Vec::new()), template("/user"));
+    }
+
+    #[tokio::test]
+    async fn openapi() -> Result<(), Box<dyn std::error::Error>> {
+        let router = Router::new()
+            .on("/user/:id", get(end).delete(end))
+            .on("/user", get(end));
+        let spec = OpenApi::new("user", "1.0.0")
+            .routes("/api", &router)
+            .operation(
+                Method::GET,
+                "/api/user/:id",
+                Operation::new()
+                    .summary("get user")
+                    .param(Param::path::<u64>("id"))
+                    .param(Param::query::<bool>("verbose")),
+            );
+        let router = router.on("/openapi.json", get(spec));
+        let (addr, server) = App::new().end(router.routes("/api")?).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}/api/openapi.json", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        let doc: Value = resp.json().await?;
+        assert_eq!("3.0.3", doc["openapi"]);
+        let user = &doc["paths"]["/api/user/{id}"];
+        assert_eq!("get user", user["get"]["summary"]);
+        assert_eq!("integer", user["get"]["parameters"][0]["schema"]["type"]);
+        assert_eq!("query", user["get"]["parameters"][1]["in"]);
+        assert_eq!("string", user["delete"]["parameters"][0]["schema"]["type"]);
+        assert_eq!(
+            Some(true),
+            user["delete"]["parameters"][0]["required"].as_bool()
+        );
+        assert!(doc["paths"]["/api/user"]["get"].is_object());
+        assert!(doc["paths"]["/api/openapi.json"].is_null());
+        Ok(())
+    }
+}
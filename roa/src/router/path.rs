@@ -5,7 +5,7 @@ use std::convert::AsRef;
 use std::str::FromStr;
 
 /// Match pattern *{variable}
-const WILDCARD: &str = r"\*\{(?P<var>\w*)\}";
+pub const WILDCARD: &str = r"\*\{(?P<var>\w*)\}";
 
 /// Match pattern /:variable/
 const VARIABLE: &str = r"/:(?P<var>\w*)/";
@@ -27,7 +27,7 @@ pub fn join_path<'a>(paths: impl 'a + AsRef<[&'a str]>) -> String {
 }
 
 /// Build pattern.
-fn must_build(pattern: &str) -> Regex {
+pub fn must_build(pattern: &str) -> Regex {
     Regex::new(pattern).unwrap_or_else(|err| {
         panic!(
             r#"{}
@@ -51,6 +51,16 @@ pub struct RegexPath {
     pub raw: String,
     pub vars: HashSet<String>,
     pub re: Regex,
+    pub wildcard: bool,
+}
+
+impl RegexPath {
+    /// The pattern without variable names, paths with the same shape match the same uris.
+    pub fn shape(&self) -> String {
+        must_build(r"\(\?P<\w+>")
+            .replace_all(self.re.as_str(), "(")
+            .into_owned()
+    }
 }
 
 impl FromStr for Path {
@@ -60,6 +70,7 @@ impl FromStr for Path {
         Ok(match path_to_regexp(&path)? {
             None => Path::Static(path),
             Some((pattern, vars)) => Path::Dynamic(RegexPath {
+                wildcard: must_build(WILDCARD).is_match(&path),
                 raw: path,
                 vars,
                 re: must_build(&format!(r"^{}$", pattern)),
@@ -238,6 +249,18 @@ mod tests {
         path_not_match(r"/srv/:path/", path)
     }
 
+    #[test]
+    fn shape() {
+        let shape = |pattern: &str| match pattern.parse().unwrap() {
+            Path::Static(pattern) => panic!(format!("`{}` should be dynamic", pattern)),
+            Path::Dynamic(re) => (re.shape(), re.wildcard),
+        };
+        assert_eq!(shape("/user/:id"), shape("/user/:name"));
+        assert_ne!(shape("/user/:id"), shape("/users/:id"));
+        assert!(!shape("/user/:id").1);
+        assert!(shape("/user/*{path}").1);
+    }
+
     #[should_panic]
     #[test]
     fn must_build_fails() {
@@ -0,0 +1,30 @@
+use super::RouterParam;
+use crate::http::Method;
+use crate::{Context, Endpoint, Result};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// An endpoint bound to a method and a path template.
+///
+/// It's usually implemented by route attributes like `#[get("/user/:id")]`
+/// with feature "macros", and registered by `Router::route`.
+pub trait Route<S>: for<'a> Endpoint<'a, S> {
+    /// Path template, like "/user/:id".
+    fn path(&self) -> &'static str;
+
+    /// Http method.
+    fn method(&self) -> Method;
+}
+
+/// Parse a router variable, throw 400 BAD REQUEST if it's invalid.
+///
+/// It's used by route attributes to extract typed arguments.
+#[doc(hidden)]
+#[inline]
+pub fn parse_param<S, T>(ctx: &Context<S>, name: &str) -> Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    ctx.must_param(name)?.parse()
+}
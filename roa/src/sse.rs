@@ -0,0 +1,259 @@
+//! This module provides a context extension `ServeEvents` to stream server-sent events,
+//! with periodic keepalive comments.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::sse::{Event, ServeEvents};
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//! use futures::stream;
+//! use std::error::Error;
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let events = stream::iter(vec![
+//!         Event::new("hello").event("greeting"),
+//!         Event::new("world").id("2"),
+//!     ]);
+//!     ctx.write_events_with(events, Duration::from_secs(30));
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE};
+use crate::{Cancelled, Context};
+use bytes::Bytes;
+use futures::{Future, Stream};
+use futures_timer::Delay;
+use std::io;
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+/// Default interval of keepalive comments.
+const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// The keepalive comment.
+const KEEPALIVE: &[u8] = b":keepalive\n\n";
+
+/// A server-sent event.
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// A context extension to stream server-sent events.
+///
+/// The event stream is stopped when the request is cancelled,
+/// and it's dropped when the client disconnects.
+pub trait ServeEvents {
+    /// Write an event stream, with a keepalive comment every 15 seconds.
+    fn write_events<S>(&mut self, events: S) -> &mut Self
+    where
+        S: 'static + Stream<Item = Event> + Sync + Send;
+
+    /// Write an event stream, with a keepalive comment every `interval`.
+    ///
+    /// The keepalive timer is reset whenever an event is sent.
+    fn write_events_with<S>(&mut self, events: S, interval: Duration) -> &mut Self
+    where
+        S: 'static + Stream<Item = Event> + Sync + Send;
+}
+
+/// A stream merging events and keepalive comments.
+struct KeepAlive<S> {
+    events: Pin<Box<S>>,
+    cancelled: Cancelled,
+    interval: Duration,
+    timer: Delay,
+    done: bool,
+}
+
+impl Event {
+    /// Construct an event with data, multi-line data is split into "data" fields.
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set event type.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Set event id.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Set reconnection time.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Encode into the event stream format.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = String::new();
+        if let Some(ref event) = self.event {
+            field(&mut buf, "event", event);
+        }
+        if let Some(ref id) = self.id {
+            field(&mut buf, "id", id);
+        }
+        if let Some(retry) = self.retry {
+            field(&mut buf, "retry", &retry.as_millis().to_string());
+        }
+        for line in self.data.lines() {
+            field(&mut buf, "data", line);
+        }
+        if self.data.is_empty() {
+            field(&mut buf, "data", "");
+        }
+        buf.push('\n');
+        buf.into()
+    }
+}
+
+/// Write a field, line breaks in value are dropped.
+#[inline]
+fn field(buf: &mut String, name: &str, value: &str) {
+    buf.push_str(name);
+    buf.push_str(": ");
+    buf.extend(value.chars().filter(|&c| c != '\n' && c != '\r'));
+    buf.push('\n');
+}
+
+impl<S> Stream for KeepAlive<S>
+where
+    S: Stream<Item = Event>,
+{
+    type Item = io::Result<Bytes>;
+    #[inline]
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        if Pin::new(&mut self.cancelled).poll(cx).is_ready() {
+            self.done = true;
+            return Poll::Ready(None);
+        }
+        match self.events.as_mut().poll_next(cx) {
+            Poll::Ready(Some(event)) => {
+                let interval = self.interval;
+                self.timer.reset(interval);
+                return Poll::Ready(Some(Ok(event.to_bytes())));
+            }
+            Poll::Ready(None) => {
+                self.done = true;
+                return Poll::Ready(None);
+            }
+            Poll::Pending => (),
+        }
+        match Pin::new(&mut self.timer).poll(cx) {
+            Poll::Ready(()) => {
+                let interval = self.interval;
+                self.timer.reset(interval);
+                Poll::Ready(Some(Ok(Bytes::from_static(KEEPALIVE))))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S> ServeEvents for Context<S> {
+    #[inline]
+    fn write_events<E>(&mut self, events: E) -> &mut Self
+    where
+        E: 'static + Stream<Item = Event> + Sync + Send,
+    {
+        self.write_events_with(events, DEFAULT_KEEPALIVE)
+    }
+
+    #[inline]
+    fn write_events_with<E>(&mut self, events: E, interval: Duration) -> &mut Self
+    where
+        E: 'static + Stream<Item = Event> + Sync + Send,
+    {
+        self.resp
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+        self.resp
+            .headers
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        self.resp.write_stream(KeepAlive {
+            events: Box::pin(events),
+            cancelled: self.cancelled(),
+            interval,
+            timer: Delay::new(interval),
+            done: false,
+        });
+        self
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{Event, ServeEvents};
+    use crate::http::header::CONTENT_TYPE;
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+    use futures::{stream, StreamExt};
+    use futures_timer::Delay;
+    use std::time::Duration;
+
+    #[test]
+    fn encode() {
+        let event = Event::new("hello\nworld")
+            .event("greeting")
+            .id("1")
+            .retry(Duration::from_secs(3));
+        assert_eq!(
+            "event: greeting\nid: 1\nretry: 3000\ndata: hello\ndata: world\n\n",
+            event.to_bytes()
+        );
+        assert_eq!("data: \n\n", Event::new("").to_bytes());
+    }
+
+    #[tokio::test]
+    async fn keepalive() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            let late = stream::once(async {
+                Delay::new(Duration::from_millis(300)).await;
+                Event::new("late")
+            });
+            let events = stream::iter(vec![Event::new("early")]).chain(late);
+            ctx.write_events_with(events, Duration::from_millis(100));
+            Ok(())
+        }
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("text/event-stream", resp.headers()[CONTENT_TYPE]);
+        let body = resp.text().await?;
+        assert!(body.starts_with("data: early\n\n:keepalive\n\n"));
+        assert!(body.ends_with(":keepalive\n\ndata: late\n\n"));
+        Ok(())
+    }
+}
@@ -39,11 +39,23 @@
 //! # }
 //! ```
 
+mod error;
+mod handle;
 mod incoming;
 mod listener;
+mod proxy;
 
 #[doc(inline)]
-pub use incoming::TcpIncoming;
+pub use error::BindError;
+
+#[doc(inline)]
+pub use handle::ServerHandle;
+
+#[doc(inline)]
+pub use incoming::{MultiIncoming, TcpIncoming};
 
 #[doc(inline)]
 pub use listener::Listener;
+
+#[doc(inline)]
+pub use proxy::{ProxyIncoming, ProxyStream};
@@ -0,0 +1,126 @@
+use std::error::Error as StdError;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::SocketAddr;
+
+/// An error occurring in binding listeners.
+///
+/// It's wrapped in the `io::Error` returned by `TcpIncoming::bind` or `Listener`,
+/// with the same `io::ErrorKind`, use `BindError::of` to get it.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::App;
+/// use roa::tcp::{BindError, Listener};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let (addr, _server) = App::new().end(()).run()?;
+/// let err = App::new().end(()).bind(addr).unwrap_err();
+/// match BindError::of(&err) {
+///     Some(BindError::AddrInUse(addr)) => println!("{} is in use, try another port", addr),
+///     Some(BindError::PermissionDenied(addr)) => println!("permission denied on {}", addr),
+///     _ => return Err(err),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum BindError {
+    /// The address cannot be resolved or parsed.
+    InvalidAddr(String),
+
+    /// The address is already in use.
+    AddrInUse(SocketAddr),
+
+    /// Permission denied, like binding a privileged port.
+    PermissionDenied(SocketAddr),
+
+    /// The address is not available on this machine.
+    AddrNotAvailable(SocketAddr),
+
+    /// Other io errors.
+    Io(SocketAddr, io::Error),
+}
+
+impl BindError {
+    /// Get the bind error wrapped in an io error.
+    pub fn of(err: &io::Error) -> Option<&BindError> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    /// Classify an io error occurring in binding an address.
+    pub(crate) fn from_io(addr: SocketAddr, err: io::Error) -> io::Error {
+        let kind = err.kind();
+        let bind_error = match kind {
+            io::ErrorKind::AddrInUse => BindError::AddrInUse(addr),
+            io::ErrorKind::PermissionDenied => BindError::PermissionDenied(addr),
+            io::ErrorKind::AddrNotAvailable => BindError::AddrNotAvailable(addr),
+            _ => BindError::Io(addr, err),
+        };
+        io::Error::new(kind, bind_error)
+    }
+
+    /// Wrap an error occurring in resolving addresses.
+    pub(crate) fn invalid_addr(err: io::Error) -> io::Error {
+        io::Error::new(err.kind(), BindError::InvalidAddr(err.to_string()))
+    }
+}
+
+impl Display for BindError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BindError::InvalidAddr(err) => {
+                f.write_fmt(format_args!("invalid address: {}", err))
+            }
+            BindError::AddrInUse(addr) => {
+                f.write_fmt(format_args!("address {} is already in use", addr))
+            }
+            BindError::PermissionDenied(addr) => {
+                f.write_fmt(format_args!("permission denied to bind {}", addr))
+            }
+            BindError::AddrNotAvailable(addr) => {
+                f.write_fmt(format_args!("address {} is not available", addr))
+            }
+            BindError::Io(addr, err) => {
+                f.write_fmt(format_args!("fail to bind {}: {}", addr, err))
+            }
+        }
+    }
+}
+
+impl StdError for BindError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            BindError::Io(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BindError;
+    use crate::tcp::{Listener, TcpIncoming};
+    use crate::App;
+    use std::io;
+
+    #[test]
+    fn bind_error() -> Result<(), Box<dyn std::error::Error>> {
+        let incoming = TcpIncoming::bind("127.0.0.1:0")?;
+        let addr = incoming.local_addr();
+        let err = App::new().end(()).bind(addr).unwrap_err();
+        assert_eq!(io::ErrorKind::AddrInUse, err.kind());
+        match BindError::of(&err) {
+            Some(BindError::AddrInUse(in_use)) => assert_eq!(addr, *in_use),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let err = TcpIncoming::bind("not an address").unwrap_err();
+        match BindError::of(&err) {
+            Some(BindError::InvalidAddr(_)) => (),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        Ok(())
+    }
+}
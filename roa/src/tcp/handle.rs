@@ -0,0 +1,75 @@
+use async_std::task::JoinHandle;
+use futures::channel::oneshot::Sender;
+use std::net::SocketAddr;
+
+/// A handle of a running server.
+///
+/// Dropping the handle will not stop the server,
+/// the server runs in background until `stop` is called.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::App;
+/// use roa::preload::*;
+/// use roa::http::StatusCode;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut handle = App::new().end("Hello, World").start("127.0.0.1:0")?;
+///     let resp = reqwest::get(&format!("http://{}", handle.addr())).await?;
+///     assert_eq!(StatusCode::OK, resp.status());
+///     handle.stop();
+///     handle.join().await?;
+///     Ok(())
+/// }
+/// ```
+pub struct ServerHandle {
+    addrs: Vec<SocketAddr>,
+    stop: Option<Sender<()>>,
+    join: JoinHandle<hyper::Result<()>>,
+}
+
+impl ServerHandle {
+    /// Construct a handle.
+    pub(crate) fn new(
+        addrs: Vec<SocketAddr>,
+        stop: Sender<()>,
+        join: JoinHandle<hyper::Result<()>>,
+    ) -> Self {
+        Self {
+            addrs,
+            stop: Some(stop),
+            join,
+        }
+    }
+
+    /// The real addr the server binds, the first one if it binds more than one.
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addrs[0]
+    }
+
+    /// All the real addrs the server binds, in order.
+    #[inline]
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Trigger graceful shutdown, the server stops accepting new connections
+    /// and waits for in-flight connections to complete.
+    ///
+    /// Calling it more than once takes no effect.
+    #[inline]
+    pub fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+
+    /// Wait for the server to exit.
+    #[inline]
+    pub async fn join(self) -> hyper::Result<()> {
+        self.join.await
+    }
+}
@@ -2,15 +2,18 @@ use async_std::net::{SocketAddr, TcpListener, TcpStream};
 use futures::FutureExt as _;
 use futures_timer::Delay;
 use log::{debug, error, trace};
-use roa_core::{Accept, AddrStream};
+use roa_core::{Accept, AddrStream, Transport};
+use socket2::{Domain, Protocol, Socket, Type};
 use std::fmt;
 use std::future::Future;
 use std::io;
-use std::net::{TcpListener as StdListener, ToSocketAddrs};
+use std::net::{Ipv6Addr, TcpListener as StdListener, ToSocketAddrs};
 use std::pin::Pin;
 use std::task::{self, Poll};
 use std::time::Duration;
 
+use super::BindError;
+
 /// A stream of connections from binding to an address.
 /// As an implementation of roa_core::Accept.
 #[must_use = "streams do nothing unless polled"]
@@ -20,17 +23,98 @@ pub struct TcpIncoming {
     sleep_on_errors: bool,
     tcp_nodelay: bool,
     timeout: Option<Delay>,
+    min_backoff: Duration,
+    max_backoff: Duration,
+    backoff: Duration,
+}
+
+/// A stream of connections from multiple listeners, served by one app.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::App;
+/// use roa::tcp::{MultiIncoming, TcpIncoming};
+///
+/// # fn main() -> std::io::Result<()> {
+/// let incoming = MultiIncoming::new(vec![
+///     TcpIncoming::bind("127.0.0.1:0")?,
+///     TcpIncoming::bind("127.0.0.1:0")?,
+/// ]);
+/// let addrs = incoming.local_addrs();
+/// let server = App::new().end("Hello, World").accept(incoming);
+/// // server.await
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct MultiIncoming {
+    incomings: Vec<TcpIncoming>,
+    next: usize,
 }
 
+/// Default backoff on accept errors, 1 second.
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Default backlog of dual-stack listeners.
+const DEFAULT_BACKLOG: i32 = 1024;
+
 impl TcpIncoming {
     /// Creates a new `TcpIncoming` binding to provided socket address.
+    ///
+    /// Each resolved address is tried until one succeeds,
+    /// the returned error wraps a `BindError` of the last one.
     pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
-        let listener = StdListener::bind(addr)?;
-        TcpIncoming::from_std(listener)
+        let addrs = addr.to_socket_addrs().map_err(BindError::invalid_addr)?;
+        let mut last_err = None;
+        for addr in addrs {
+            match StdListener::bind(addr) {
+                Ok(listener) => return TcpIncoming::from_std(listener),
+                Err(err) => last_err = Some(BindError::from_io(addr, err)),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| {
+            BindError::invalid_addr(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            ))
+        }))
+    }
+
+    /// Creates a new `TcpIncoming` binding to `[::]:port` with `IPV6_V6ONLY` off,
+    /// serving both IPv6 and IPv4 (as IPv4-mapped addresses) by one socket.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,no_run
+    /// use roa::App;
+    /// use roa::tcp::TcpIncoming;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let incoming = TcpIncoming::bind_dual_stack(8000)?;
+    /// let (addr, server) = App::new().end("Hello, World").serve_on(incoming)?;
+    /// // server.await
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind_dual_stack(port: u16) -> io::Result<Self> {
+        let addr = SocketAddr::from((Ipv6Addr::UNSPECIFIED, port));
+        let socket = Socket::new(Domain::ipv6(), Type::stream(), Some(Protocol::tcp()))?;
+        socket.set_only_v6(false)?;
+        socket.set_reuse_address(true)?;
+        socket
+            .bind(&addr.into())
+            .map_err(|err| BindError::from_io(addr, err))?;
+        socket.listen(DEFAULT_BACKLOG)?;
+        TcpIncoming::from_std(socket.into_tcp_listener())
     }
 
     /// Creates a new `TcpIncoming` from std TcpListener.
+    ///
+    /// The listener will be set in non-blocking mode.
     pub fn from_std(listener: StdListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
         let addr = listener.local_addr()?;
         Ok(TcpIncoming {
             listener: listener.into(),
@@ -38,6 +122,9 @@ impl TcpIncoming {
             sleep_on_errors: true,
             tcp_nodelay: false,
             timeout: None,
+            min_backoff: DEFAULT_BACKOFF,
+            max_backoff: DEFAULT_BACKOFF,
+            backoff: DEFAULT_BACKOFF,
         })
     }
 
@@ -60,7 +147,7 @@ impl TcpIncoming {
     /// the application will likely close some files (or connections), and try
     /// to accept the connection again. If this option is `true`, the error
     /// will be logged at the `error` level, since it is still a big deal,
-    /// and then the listener will sleep for 1 second.
+    /// and then the listener will sleep for a backoff (1 second by default, see `set_error_backoff`).
     ///
     /// In other cases, hitting the max open files should be treat similarly
     /// to being out-of-memory, and simply error (and shutdown). Setting
@@ -71,6 +158,29 @@ impl TcpIncoming {
         self.sleep_on_errors = val;
     }
 
+    /// Set backoff to sleep on accept errors, it takes effect only if `sleep_on_errors` is `true`.
+    ///
+    /// The backoff starts from `min`, doubles on each consecutive error up to `max`,
+    /// and is reset after a connection is accepted successfully.
+    ///
+    /// Default is 1 second for both.
+    pub fn set_error_backoff(&mut self, min: Duration, max: Duration) -> &mut Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self.backoff = min;
+        self
+    }
+
+    /// Get current backoff and double the next one.
+    #[inline]
+    fn next_backoff(&mut self) -> Duration {
+        let backoff = self.backoff;
+        self.backoff = backoff
+            .checked_mul(2)
+            .map_or(self.max_backoff, |next| next.min(self.max_backoff));
+        backoff
+    }
+
     /// Poll TcpStream.
     fn poll_stream(
         &mut self,
@@ -94,6 +204,7 @@ impl TcpIncoming {
                     if let Err(e) = stream.set_nodelay(self.tcp_nodelay) {
                         trace!("error trying to set TCP nodelay: {}", e);
                     }
+                    self.backoff = self.min_backoff;
                     return Poll::Ready(Ok((stream, addr)));
                 }
                 Poll::Pending => return Poll::Pending,
@@ -106,14 +217,14 @@ impl TcpIncoming {
                     }
 
                     if self.sleep_on_errors {
-                        error!("accept error: {}", e);
+                        let backoff = self.next_backoff();
+                        error!("accept error: {}, retry in {:?}", e, backoff);
 
-                        // Sleep 1s.
-                        let mut timeout = Delay::new(Duration::from_secs(1));
+                        let mut timeout = Delay::new(backoff);
 
                         match Pin::new(&mut timeout).poll(cx) {
                             Poll::Ready(()) => {
-                                // Wow, it's been a second already? Ok then...
+                                // Wow, it's been the backoff already? Ok then...
                                 continue;
                             }
                             Poll::Pending => {
@@ -130,6 +241,91 @@ impl TcpIncoming {
     }
 }
 
+impl Transport for TcpIncoming {
+    type Io = TcpStream;
+    type Incoming = Self;
+
+    #[inline]
+    fn incoming(self) -> io::Result<(SocketAddr, Self)> {
+        Ok((self.addr, self))
+    }
+}
+
+impl Transport for StdListener {
+    type Io = TcpStream;
+    type Incoming = TcpIncoming;
+
+    #[inline]
+    fn incoming(self) -> io::Result<(SocketAddr, TcpIncoming)> {
+        TcpIncoming::from_std(self)?.incoming()
+    }
+}
+
+impl MultiIncoming {
+    /// Construct from listeners.
+    pub fn new(incomings: Vec<TcpIncoming>) -> Self {
+        Self { incomings, next: 0 }
+    }
+
+    /// Bind each address.
+    pub fn bind(addrs: impl IntoIterator<Item = SocketAddr>) -> io::Result<Self> {
+        let incomings = addrs
+            .into_iter()
+            .map(TcpIncoming::bind)
+            .collect::<io::Result<_>>()?;
+        Ok(Self::new(incomings))
+    }
+
+    /// Get the local addresses bound to these listeners, in order.
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.incomings.iter().map(TcpIncoming::local_addr).collect()
+    }
+}
+
+impl Transport for MultiIncoming {
+    type Io = TcpStream;
+    type Incoming = Self;
+
+    /// The addr of the first listener is returned.
+    #[inline]
+    fn incoming(self) -> io::Result<(SocketAddr, Self)> {
+        match self.incomings.first() {
+            Some(incoming) => Ok((incoming.local_addr(), self)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no listener to accept",
+            )),
+        }
+    }
+}
+
+impl Accept for MultiIncoming {
+    type Conn = AddrStream<TcpStream>;
+    type Error = io::Error;
+
+    /// Poll listeners in turn, starting from the one next to last accepted, to be fair.
+    #[inline]
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let len = self.incomings.len();
+        if len == 0 {
+            return Poll::Ready(None);
+        }
+        for offset in 0..len {
+            let index = (self.next + offset) % len;
+            if let Poll::Ready(item) =
+                Pin::new(&mut self.incomings[index]).poll_accept(cx)
+            {
+                self.next = (index + 1) % len;
+                return Poll::Ready(item);
+            }
+        }
+        Poll::Pending
+    }
+}
+
 impl Accept for TcpIncoming {
     type Conn = AddrStream<TcpStream>;
     type Error = io::Error;
@@ -166,6 +362,70 @@ impl fmt::Debug for TcpIncoming {
             .field("addr", &self.addr)
             .field("sleep_on_errors", &self.sleep_on_errors)
             .field("tcp_nodelay", &self.tcp_nodelay)
+            .field("min_backoff", &self.min_backoff)
+            .field("max_backoff", &self.max_backoff)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MultiIncoming, TcpIncoming};
+    use crate::http::StatusCode;
+    use crate::App;
+    use async_std::task::spawn;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn multi_incoming() -> Result<(), Box<dyn std::error::Error>> {
+        let incoming = MultiIncoming::bind(vec![
+            ([127, 0, 0, 1], 0).into(),
+            ([127, 0, 0, 1], 0).into(),
+        ])?;
+        let addrs = incoming.local_addrs();
+        assert_eq!(2, addrs.len());
+        assert_ne!(addrs[0], addrs[1]);
+        spawn(App::new().end("Hello, World").accept(incoming));
+        for addr in addrs {
+            let resp = reqwest::get(&format!("http://{}", addr)).await?;
+            assert_eq!(StatusCode::OK, resp.status());
+            assert_eq!("Hello, World", resp.text().await?);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dual_stack() -> Result<(), Box<dyn std::error::Error>> {
+        if std::net::TcpListener::bind("[::1]:0").is_err() {
+            // IPv6 is not available.
+            return Ok(());
+        }
+        let incoming = TcpIncoming::bind_dual_stack(0)?;
+        let addr = incoming.local_addr();
+        assert!(addr.is_ipv6());
+        spawn(App::new().end("Hello, World").accept(incoming));
+        let resp = reqwest::get(&format!("http://127.0.0.1:{}", addr.port())).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[test]
+    fn error_backoff() -> std::io::Result<()> {
+        let mut incoming = TcpIncoming::bind("127.0.0.1:0")?;
+        incoming
+            .set_error_backoff(Duration::from_millis(100), Duration::from_millis(300));
+        assert_eq!(Duration::from_millis(100), incoming.next_backoff());
+        assert_eq!(Duration::from_millis(200), incoming.next_backoff());
+        assert_eq!(Duration::from_millis(300), incoming.next_backoff());
+        assert_eq!(Duration::from_millis(300), incoming.next_backoff());
+
+        // saturate instead of overflow
+        incoming.set_error_backoff(
+            Duration::from_secs(std::u64::MAX),
+            Duration::from_secs(std::u64::MAX),
+        );
+        assert_eq!(Duration::from_secs(std::u64::MAX), incoming.next_backoff());
+        assert_eq!(Duration::from_secs(std::u64::MAX), incoming.next_backoff());
+        Ok(())
+    }
+}
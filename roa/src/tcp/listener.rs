@@ -1,7 +1,10 @@
-use super::TcpIncoming;
+use super::{MultiIncoming, ServerHandle, TcpIncoming};
 use async_std::sync::Arc;
+use async_std::task::spawn;
+use futures::channel::oneshot::channel;
+use futures::future::pending;
 use roa_core::{App, Endpoint, Executor, Server, State};
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
 
 /// An app extension.
 pub trait Listener {
@@ -9,11 +12,35 @@ pub trait Listener {
     type Server;
 
     /// Listen on a socket addr, return a server and the real addr it binds.
+    ///
+    /// The returned error wraps a `BindError` if binding fails.
     fn bind(
         self,
         addr: impl ToSocketAddrs,
     ) -> std::io::Result<(SocketAddr, Self::Server)>;
 
+    /// Serve on an already bound listener, return a server and the real addr it binds.
+    ///
+    /// It's useful for socket activation, or listeners with custom options like `SO_REUSEPORT`.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::App;
+    /// use roa::tcp::Listener;
+    /// use std::net::TcpListener;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let listener = TcpListener::bind("127.0.0.1:0")?;
+    /// let (addr, server) = App::new().end(()).run_on(listener)?;
+    /// // server.await
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn run_on(
+        self,
+        listener: TcpListener,
+    ) -> std::io::Result<(SocketAddr, Self::Server)>;
+
     /// Listen on a socket addr, return a server, and pass real addr to the callback.
     fn listen(
         self,
@@ -44,6 +71,32 @@ pub trait Listener {
     /// }
     /// ```
     fn run(self) -> std::io::Result<(SocketAddr, Self::Server)>;
+
+    /// Listen on a socket addr, spawn the server in background and return its handle.
+    ///
+    /// The server can be stopped gracefully by `ServerHandle::stop`.
+    fn start(self, addr: impl ToSocketAddrs) -> std::io::Result<ServerHandle>;
+
+    /// Listen on multiple socket addrs served by one app, spawn the server in background
+    /// and return its handle, which reports all the real addrs it binds.
+    ///
+    /// ### Example
+    /// ```rust
+    /// use roa::App;
+    /// use roa::tcp::Listener;
+    ///
+    /// # fn main() -> std::io::Result<()> {
+    /// let handle = App::new()
+    ///     .end("Hello, World")
+    ///     .start_all(vec![([127, 0, 0, 1], 0).into(), ([127, 0, 0, 1], 0).into()])?;
+    /// assert_eq!(2, handle.addrs().len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn start_all(
+        self,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+    ) -> std::io::Result<ServerHandle>;
 }
 
 impl<S, E> Listener for App<S, Arc<E>>
@@ -56,9 +109,14 @@ where
         self,
         addr: impl ToSocketAddrs,
     ) -> std::io::Result<(SocketAddr, Self::Server)> {
-        let incoming = TcpIncoming::bind(addr)?;
-        let local_addr = incoming.local_addr();
-        Ok((local_addr, self.accept(incoming)))
+        self.serve_on(TcpIncoming::bind(addr)?)
+    }
+
+    fn run_on(
+        self,
+        listener: TcpListener,
+    ) -> std::io::Result<(SocketAddr, Self::Server)> {
+        self.serve_on(listener)
     }
 
     fn listen(
@@ -74,4 +132,134 @@ where
     fn run(self) -> std::io::Result<(SocketAddr, Self::Server)> {
         self.bind("127.0.0.1:0")
     }
+
+    fn start(self, addr: impl ToSocketAddrs) -> std::io::Result<ServerHandle> {
+        start_on(self, MultiIncoming::new(vec![TcpIncoming::bind(addr)?]))
+    }
+
+    fn start_all(
+        self,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+    ) -> std::io::Result<ServerHandle> {
+        start_on(self, MultiIncoming::bind(addrs)?)
+    }
+}
+
+/// Spawn a server accepting incoming in background.
+fn start_on<S, E>(
+    app: App<S, Arc<E>>,
+    incoming: MultiIncoming,
+) -> std::io::Result<ServerHandle>
+where
+    S: State,
+    E: for<'a> Endpoint<'a, S>,
+{
+    let addrs = incoming.local_addrs();
+    let (_, server) = app.serve_on(incoming)?;
+    let (stop, signal) = channel::<()>();
+    let server = server.with_graceful_shutdown(async move {
+        // do not stop if the handle is dropped.
+        if signal.await.is_err() {
+            pending::<()>().await
+        }
+    });
+    Ok(ServerHandle::new(addrs, stop, spawn(server)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Listener;
+    use crate::http::StatusCode;
+    use crate::App;
+    use async_std::task::spawn;
+    use std::net::TcpListener;
+
+    #[tokio::test]
+    async fn start_and_stop() -> Result<(), Box<dyn std::error::Error>> {
+        let mut handle = App::new().end(()).start("127.0.0.1:0")?;
+        let addr = handle.addr();
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        handle.stop();
+        handle.join().await?;
+        assert!(reqwest::get(&format!("http://{}", addr)).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn serve_on() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::tcp::TcpIncoming;
+        let mut incoming = TcpIncoming::bind("127.0.0.1:0")?;
+        incoming.set_nodelay(true);
+        let bound_addr = incoming.local_addr();
+        let (addr, server) = App::new().end(()).serve_on(incoming)?;
+        assert_eq!(bound_addr, addr);
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn start_all() -> Result<(), Box<dyn std::error::Error>> {
+        let mut handle = App::new()
+            .end(())
+            .start_all(vec![([127, 0, 0, 1], 0).into(), ([127, 0, 0, 1], 0).into()])?;
+        assert_eq!(2, handle.addrs().len());
+        assert_eq!(handle.addrs()[0], handle.addr());
+        for addr in handle.addrs() {
+            let resp = reqwest::get(&format!("http://{}", addr)).await?;
+            assert_eq!(StatusCode::OK, resp.status());
+        }
+        let addrs = handle.addrs().to_vec();
+        handle.stop();
+        handle.join().await?;
+        for addr in addrs {
+            assert!(reqwest::get(&format!("http://{}", addr)).await.is_err());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_on() -> Result<(), Box<dyn std::error::Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let bound_addr = listener.local_addr()?;
+        let (addr, server) = App::new().end(()).run_on(listener)?;
+        assert_eq!(bound_addr, addr);
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn report_malformed_request() -> Result<(), Box<dyn std::error::Error>> {
+        use async_std::net::TcpStream;
+        use futures::channel::mpsc::unbounded;
+        use futures::{AsyncReadExt, AsyncWriteExt, StreamExt};
+        use std::io::ErrorKind;
+        let (sender, mut receiver) = unbounded();
+        let (addr, server) = App::new()
+            .on_connection_error(move |addr, err| {
+                sender.unbounded_send((addr, err.kind())).unwrap();
+            })
+            .end("Hello, World")
+            .run()?;
+        spawn(server);
+
+        // well-formed requests are not reported.
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET / HTTP/1.1\r\nHost\r\n\r\n").await?;
+        let mut data = Vec::new();
+        let _ = stream.read_to_end(&mut data).await;
+        assert!(data.starts_with(b"HTTP/1.1 400"));
+        assert_eq!(
+            Some((stream.local_addr()?, ErrorKind::InvalidData)),
+            receiver.next().await
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,478 @@
+use async_std::net::TcpStream;
+use futures::future::{select, Either};
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, IoSlice};
+use futures::stream::{FuturesUnordered, StreamExt};
+use futures::Future;
+use futures_timer::Delay;
+use log::debug;
+use roa_core::{Accept, AddrStream, Transport};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{self, Poll};
+use std::time::Duration;
+
+/// Signature of PROXY protocol v1.
+const V1_SIGNATURE: &[u8] = b"PROXY ";
+
+/// Signature of PROXY protocol v2.
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Max length of a v1 header, including CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// Length of the fixed part of a v2 header.
+const V2_HEADER_LEN: usize = 16;
+
+/// Default timeout to read a header.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Handshake =
+    Pin<Box<dyn 'static + Send + Future<Output = io::Result<AddrStream<ProxyStream>>>>>;
+
+/// An acceptor parsing PROXY protocol (v1 and v2) headers from the connection prefix,
+/// so that `Context::remote_addr` is the real client address behind a TCP load balancer.
+///
+/// Headers are read concurrently, a slow connection doesn't block others.
+/// Connections with malformed headers, or without headers in strict mode, are closed.
+///
+/// Strict mode is on by default, otherwise any client reaching the listener directly
+/// could forge its address by a header. Turn it off only if the listener also accepts
+/// connections not coming from the load balancer, and the source addresses are not trusted.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{App, Context};
+/// use roa::tcp::{ProxyIncoming, TcpIncoming};
+/// use std::io;
+///
+/// async fn end(ctx: &mut Context) -> roa::Result {
+///     ctx.resp.write(ctx.remote_addr.to_string());
+///     Ok(())
+/// }
+///
+/// # fn main() -> io::Result<()> {
+/// let incoming = ProxyIncoming::new(TcpIncoming::bind("127.0.0.1:0")?);
+/// let (addr, server) = App::new().end(end).serve_on(incoming)?;
+/// // server.await
+/// # Ok(())
+/// # }
+/// ```
+pub struct ProxyIncoming<I> {
+    incoming: I,
+    strict: bool,
+    timeout: Duration,
+    handshakes: FuturesUnordered<Handshake>,
+    closed: bool,
+}
+
+/// A stream replaying bytes read after the PROXY protocol header.
+pub struct ProxyStream {
+    prefix: Vec<u8>,
+    offset: usize,
+    stream: TcpStream,
+}
+
+/// Result of parsing a connection prefix.
+#[derive(Debug, PartialEq)]
+enum Parsed {
+    /// More bytes are needed.
+    Incomplete,
+
+    /// The prefix is not a PROXY protocol header.
+    Absent,
+
+    /// A header of `len` bytes, with the source address if it's proxied.
+    Header(usize, Option<SocketAddr>),
+}
+
+impl<I> ProxyIncoming<I> {
+    /// Construct from inner incoming, headers are required and the timeout is 5 seconds.
+    pub fn new(incoming: I) -> Self {
+        Self {
+            incoming,
+            strict: true,
+            timeout: DEFAULT_TIMEOUT,
+            handshakes: FuturesUnordered::new(),
+            closed: false,
+        }
+    }
+
+    /// Reject connections lacking a header if `strict` is true, default true.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Set timeout to read a header.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get the inner incoming.
+    pub fn get_ref(&self) -> &I {
+        &self.incoming
+    }
+}
+
+/// Parse a connection prefix.
+fn parse(buf: &[u8]) -> io::Result<Parsed> {
+    if starts_with(buf, V1_SIGNATURE) {
+        if buf.len() < V1_SIGNATURE.len() {
+            return Ok(Parsed::Incomplete);
+        }
+        return parse_v1(buf);
+    }
+    if starts_with(buf, V2_SIGNATURE) {
+        if buf.len() < V2_HEADER_LEN {
+            return Ok(Parsed::Incomplete);
+        }
+        return parse_v2(buf);
+    }
+    Ok(Parsed::Absent)
+}
+
+/// Check if `buf` and `signature` agree on their common prefix.
+#[inline]
+fn starts_with(buf: &[u8], signature: &[u8]) -> bool {
+    let len = buf.len().min(signature.len());
+    buf[..len] == signature[..len]
+}
+
+#[inline]
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parse a v1 header, like "PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\n".
+fn parse_v1(buf: &[u8]) -> io::Result<Parsed> {
+    let end = match buf.windows(2).position(|window| window == b"\r\n") {
+        Some(end) => end,
+        None if buf.len() >= V1_MAX_LEN => {
+            return Err(invalid("PROXY v1 header is too long"))
+        }
+        None => return Ok(Parsed::Incomplete),
+    };
+    let len = end + 2;
+    if len > V1_MAX_LEN {
+        return Err(invalid("PROXY v1 header is too long"));
+    }
+    let line = std::str::from_utf8(&buf[..end])
+        .map_err(|_| invalid("PROXY v1 header is not valid utf-8"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+    match fields.get(1).cloned() {
+        Some("UNKNOWN") => Ok(Parsed::Header(len, None)),
+        Some("TCP4") | Some("TCP6") if fields.len() == 6 => {
+            let ip: IpAddr = fields[2]
+                .parse()
+                .map_err(|_| invalid("invalid source address in PROXY v1 header"))?;
+            if ip.is_ipv4() != (fields[1] == "TCP4") {
+                return Err(invalid("address family mismatch in PROXY v1 header"));
+            }
+            let port: u16 = fields[4]
+                .parse()
+                .map_err(|_| invalid("invalid source port in PROXY v1 header"))?;
+            Ok(Parsed::Header(len, Some(SocketAddr::new(ip, port))))
+        }
+        _ => Err(invalid("malformed PROXY v1 header")),
+    }
+}
+
+/// Parse a v2 header.
+fn parse_v2(buf: &[u8]) -> io::Result<Parsed> {
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    let addr_len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    let len = V2_HEADER_LEN + addr_len;
+    if buf.len() < len {
+        return Ok(Parsed::Incomplete);
+    }
+    let addrs = &buf[V2_HEADER_LEN..len];
+    match version_command & 0x0f {
+        // LOCAL, health checks of the proxy itself.
+        0x0 => return Ok(Parsed::Header(len, None)),
+        // PROXY
+        0x1 => (),
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+    let addr = match buf[13] {
+        // TCP over IPv4
+        0x11 if addrs.len() >= 12 => {
+            let mut ip = [0; 4];
+            ip.copy_from_slice(&addrs[..4]);
+            let port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port))
+        }
+        // TCP over IPv6
+        0x21 if addrs.len() >= 36 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&addrs[..16]);
+            let port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port))
+        }
+        0x11 | 0x21 => return Err(invalid("truncated addresses in PROXY v2 header")),
+        // UNSPEC, UDP or UNIX, keep the real address.
+        _ => None,
+    };
+    Ok(Parsed::Header(len, addr))
+}
+
+/// Read a header from the connection prefix.
+async fn handshake(
+    mut stream: TcpStream,
+    remote_addr: SocketAddr,
+    strict: bool,
+) -> io::Result<AddrStream<ProxyStream>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0; 512];
+    loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..read]);
+        let (len, addr) = match parse(&buf)? {
+            Parsed::Incomplete => continue,
+            Parsed::Absent if strict => {
+                return Err(invalid("connection lacks PROXY protocol header"))
+            }
+            Parsed::Absent => (0, None),
+            Parsed::Header(len, addr) => (len, addr),
+        };
+        let stream = ProxyStream {
+            prefix: buf,
+            offset: len,
+            stream,
+        };
+        return Ok(AddrStream::new(addr.unwrap_or(remote_addr), stream));
+    }
+}
+
+/// Read a header with timeout.
+async fn handshake_timeout(
+    stream: TcpStream,
+    remote_addr: SocketAddr,
+    strict: bool,
+    timeout: Duration,
+) -> io::Result<AddrStream<ProxyStream>> {
+    let handshake = Box::pin(handshake(stream, remote_addr, strict));
+    match select(handshake, Delay::new(timeout)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "timeout to read PROXY protocol header",
+        )),
+    }
+}
+
+impl<I> Transport for ProxyIncoming<I>
+where
+    I: Transport<Io = TcpStream>,
+    I::Incoming: Unpin,
+{
+    type Io = ProxyStream;
+    type Incoming = ProxyIncoming<I::Incoming>;
+
+    #[inline]
+    fn incoming(self) -> io::Result<(SocketAddr, Self::Incoming)> {
+        let (addr, incoming) = self.incoming.incoming()?;
+        let incoming = ProxyIncoming::new(incoming)
+            .strict(self.strict)
+            .timeout(self.timeout);
+        Ok((addr, incoming))
+    }
+}
+
+impl<I> Accept for ProxyIncoming<I>
+where
+    I: Unpin + Accept<Conn = AddrStream<TcpStream>>,
+{
+    type Conn = AddrStream<ProxyStream>;
+    type Error = I::Error;
+
+    #[inline]
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        while !self.closed {
+            match Pin::new(&mut self.incoming).poll_accept(cx) {
+                Poll::Ready(Some(Ok(AddrStream {
+                    stream,
+                    remote_addr,
+                    ..
+                }))) => {
+                    let handshake = handshake_timeout(
+                        stream,
+                        remote_addr,
+                        self.strict,
+                        self.timeout,
+                    );
+                    self.handshakes.push(Box::pin(handshake));
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+                Poll::Ready(None) => self.closed = true,
+                Poll::Pending => break,
+            }
+        }
+        loop {
+            match self.handshakes.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(conn))) => return Poll::Ready(Some(Ok(conn))),
+                Poll::Ready(Some(Err(err))) => {
+                    debug!("PROXY protocol handshake failed: {}", err)
+                }
+                Poll::Ready(None) if self.closed => return Poll::Ready(None),
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl AsyncRead for ProxyStream {
+    #[inline]
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.offset < self.prefix.len() {
+            let len = buf.len().min(self.prefix.len() - self.offset);
+            buf[..len].copy_from_slice(&self.prefix[self.offset..self.offset + len]);
+            self.offset += len;
+            if self.offset == self.prefix.len() {
+                self.prefix = Vec::new();
+                self.offset = 0;
+            }
+            return Poll::Ready(Ok(len));
+        }
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProxyStream {
+    #[inline]
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    #[inline]
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    #[inline]
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    #[inline]
+    fn poll_close(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Parsed, ProxyIncoming};
+    use crate::tcp::TcpIncoming;
+    use crate::{App, Context};
+    use async_std::net::TcpStream;
+    use async_std::task::spawn;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use std::net::SocketAddr;
+
+    #[test]
+    fn parse_v1() -> std::io::Result<()> {
+        let header = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /";
+        let addr: SocketAddr = "192.168.0.1:56324".parse().unwrap();
+        assert_eq!(Parsed::Header(47, Some(addr)), parse(header)?);
+        assert_eq!(Parsed::Incomplete, parse(b"PROXY TCP4 192.168")?);
+        assert_eq!(Parsed::Incomplete, parse(b"PRO")?);
+        assert_eq!(Parsed::Header(15, None), parse(b"PROXY UNKNOWN\r\n")?);
+        assert_eq!(Parsed::Absent, parse(b"GET / HTTP/1.1\r\n")?);
+        assert!(parse(b"PROXY TCP4 ::1 ::1 1 2\r\n").is_err());
+        let mut long = b"PROXY ".to_vec();
+        long.extend_from_slice(&[b'0'; 200]);
+        assert!(parse(&long).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_v2() -> std::io::Result<()> {
+        let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1f, 0x90, 0, 80]);
+        let addr: SocketAddr = "10.0.0.1:8080".parse().unwrap();
+        assert_eq!(Parsed::Incomplete, parse(&header[..20])?);
+        assert_eq!(Parsed::Header(28, Some(addr)), parse(&header)?);
+        // LOCAL command
+        header[12] = 0x20;
+        assert_eq!(Parsed::Header(28, None), parse(&header)?);
+        // version 1 in binary format
+        header[12] = 0x11;
+        assert!(parse(&header).is_err());
+        Ok(())
+    }
+
+    async fn request(addr: SocketAddr, prefix: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(prefix).await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await?;
+        Ok(resp)
+    }
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        ctx.resp.write(ctx.remote_addr.to_string());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn proxy_incoming() -> Result<(), Box<dyn std::error::Error>> {
+        let incoming =
+            ProxyIncoming::new(TcpIncoming::bind("127.0.0.1:0")?).strict(false);
+        let (addr, server) = App::new().end(end).serve_on(incoming)?;
+        spawn(server);
+        let resp = request(addr, b"PROXY TCP4 1.2.3.4 5.6.7.8 1000 80\r\n").await?;
+        assert!(resp.ends_with("1.2.3.4:1000"));
+        let resp = request(addr, b"").await?;
+        assert!(resp.contains("127.0.0.1:"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn strict() -> Result<(), Box<dyn std::error::Error>> {
+        let incoming = ProxyIncoming::new(TcpIncoming::bind("127.0.0.1:0")?);
+        let (addr, server) = App::new().end(end).serve_on(incoming)?;
+        spawn(server);
+        let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+        header.extend_from_slice(&[0x21, 0x11, 0, 12]);
+        header.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2, 0x1f, 0x90, 0, 80]);
+        let resp = request(addr, &header).await?;
+        assert!(resp.ends_with("10.0.0.1:8080"));
+        let resp = request(addr, b"").await.unwrap_or_default();
+        assert!(resp.is_empty());
+        Ok(())
+    }
+}
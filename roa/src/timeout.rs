@@ -0,0 +1,260 @@
+//! This module provides a middleware `Timeout` and a context extension `Deadline`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::timeout::{Deadline, Timeout};
+//! use roa::{App, Context};
+//! use roa::preload::*;
+//! use std::error::Error;
+//! use std::time::Duration;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     if let Some(remaining) = ctx.remaining() {
+//!         // pass the remaining budget to database or upstream client.
+//!     }
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().gate(Timeout::new(Duration::from_secs(30))).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::clock::{Clock, SystemClock};
+use crate::http::StatusCode;
+use crate::{async_trait, status, Context, Middleware, Next, Result};
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A private scope.
+struct TimeoutScope;
+
+/// Key of deadline in `TimeoutScope`.
+const DEADLINE: &str = "deadline";
+
+/// Key of clock in `TimeoutScope`.
+const CLOCK: &str = "clock";
+
+/// A middleware to limit time of handling requests.
+///
+/// It stores an absolute deadline in context, which can be got by `Deadline::deadline`.
+/// Inner middlewares and endpoints will be cancelled when the deadline expires,
+/// and the request gets a 503 SERVICE UNAVAILABLE.
+///
+/// The earlier deadline wins when `Timeout` is nested,
+/// and the outer deadline is restored once the inner `Timeout` returns.
+///
+/// Time is read from `SystemClock` by default, it can be replaced by `clock`.
+#[derive(Clone)]
+pub struct Timeout {
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+/// A context extension to get deadline set by `Timeout`.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{Context, Result};
+/// use roa::timeout::Deadline;
+///
+/// async fn get(ctx: &mut Context) -> Result {
+///     match ctx.deadline() {
+///         Some(deadline) => println!("deadline: {:?}", deadline),
+///         None => println!("no deadline"),
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait Deadline {
+    /// Get the absolute deadline, return `None` if there is no `Timeout`.
+    fn deadline(&self) -> Option<Instant>;
+
+    /// Get the remaining time before deadline, return `None` if there is no `Timeout`.
+    ///
+    /// It's zero when the deadline has passed.
+    fn remaining(&self) -> Option<Duration>;
+}
+
+impl Timeout {
+    /// Construct a timeout middleware.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the clock to read time.
+    ///
+    /// The deadline and remaining time are computed by it,
+    /// while the timer still waits for the remaining time in real time.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+}
+
+impl<S> Deadline for Context<S> {
+    #[inline]
+    fn deadline(&self) -> Option<Instant> {
+        self.load_scoped::<TimeoutScope, Option<Instant>>(DEADLINE)
+            .and_then(|deadline| *deadline)
+    }
+
+    #[inline]
+    fn remaining(&self) -> Option<Duration> {
+        let deadline = self.deadline()?;
+        let now = match self
+            .load_scoped::<TimeoutScope, Option<Arc<dyn Clock>>>(CLOCK)
+            .and_then(|clock| (*clock).clone())
+        {
+            Some(clock) => clock.now(),
+            None => Instant::now(),
+        };
+        Some(deadline.checked_duration_since(now).unwrap_or_default())
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S> Middleware<'a, S> for Timeout {
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        let mut deadline = self.clock.now() + self.timeout;
+        if let Some(outer) = ctx.deadline() {
+            if outer < deadline {
+                deadline = outer;
+            }
+        }
+        let outer_deadline = ctx.store_scoped(TimeoutScope, DEADLINE, Some(deadline));
+        let outer_clock =
+            ctx.store_scoped(TimeoutScope, CLOCK, Some(self.clock.clone()));
+        let remaining = ctx.remaining().unwrap_or_default();
+        let result = match select(next, Delay::new(remaining)).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(status!(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "request timed out"
+            )),
+        };
+        // upstream sees its own deadline again.
+        ctx.store_scoped(TimeoutScope, DEADLINE, outer_deadline.and_then(|d| *d));
+        ctx.store_scoped(
+            TimeoutScope,
+            CLOCK,
+            outer_clock.and_then(|clock| (*clock).clone()),
+        );
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{Deadline, Timeout};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{endpoint_fn, App, Context, Next};
+    use async_std::task::spawn;
+    use futures_timer::Delay;
+    use std::time::Duration;
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        let remaining = ctx.remaining().unwrap();
+        assert!(remaining <= Duration::from_millis(100));
+        Delay::new(remaining / 2).await;
+        ctx.resp.write("Hello, World");
+        Ok(())
+    }
+
+    async fn slow(_ctx: &mut Context) -> crate::Result {
+        Delay::new(Duration::from_millis(500)).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn propagate_deadline() -> Result<(), Box<dyn std::error::Error>> {
+        // the earlier deadline wins.
+        let app = App::new()
+            .gate(Timeout::new(Duration::from_millis(100)))
+            .gate(Timeout::new(Duration::from_secs(10)))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_deadline() -> Result<(), Box<dyn std::error::Error>> {
+        async fn outside(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            assert_eq!(None, ctx.deadline());
+            Ok(())
+        }
+        async fn between(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            assert!(ctx.remaining().unwrap() > Duration::from_secs(1));
+            Ok(())
+        }
+        let app = App::new()
+            .gate(outside)
+            .gate(Timeout::new(Duration::from_secs(10)))
+            .gate(between)
+            .gate(Timeout::new(Duration::from_millis(100)))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!("Hello, World", resp.text().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mock_clock() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::clock::MockClock;
+        let clock = MockClock::new();
+        let mock = clock.clone();
+        let end = endpoint_fn(move |ctx: &mut Context| {
+            let clock = mock.clone();
+            Box::pin(async move {
+                assert_eq!(Some(Duration::from_secs(10)), ctx.remaining());
+                clock.advance(Duration::from_secs(4));
+                assert_eq!(Some(Duration::from_secs(6)), ctx.remaining());
+                clock.advance(Duration::from_secs(10));
+                assert_eq!(Some(Duration::from_secs(0)), ctx.remaining());
+                Ok(())
+            })
+        });
+        let app = App::new()
+            .gate(Timeout::new(Duration::from_secs(10)).clock(clock))
+            .end(end);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn timeout() -> Result<(), Box<dyn std::error::Error>> {
+        let app = App::new()
+            .gate(Timeout::new(Duration::from_millis(50)))
+            .end(slow);
+        let (addr, server) = app.run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+        assert_eq!("request timed out", resp.text().await?);
+        Ok(())
+    }
+}
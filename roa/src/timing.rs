@@ -0,0 +1,233 @@
+//! This module provides a middleware `server_timing` and a context extension `ServerTiming`.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::timing::server_timing;
+//! use roa::preload::*;
+//! use roa::{App, Context};
+//! use std::error::Error;
+//! use std::time::Instant;
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     let start = Instant::now();
+//!     // query database
+//!     ctx.timing("db", start.elapsed());
+//!     Ok(())
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! let app = App::new().gate(server_timing).end(end);
+//! let (addr, server) = app.run()?;
+//! // server.await
+//! Ok(())
+//! # }
+//! ```
+
+use crate::http::header::{HeaderName, HeaderValue};
+use crate::{Context, Next, Result};
+use log::warn;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Name of header "Server-Timing".
+const SERVER_TIMING: &str = "server-timing";
+
+/// A private scope.
+struct TimingScope;
+
+/// A collector of timing metrics.
+#[derive(Default)]
+struct Metrics(Mutex<Vec<Metric>>);
+
+/// A timing metric.
+struct Metric {
+    name: String,
+    description: Option<String>,
+    duration: Duration,
+}
+
+/// A context extension to record timing metrics.
+///
+/// Metrics will be emitted as "Server-Timing" by middleware `server_timing`,
+/// recording is a no-op if `server_timing` is not set.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::{Context, Result};
+/// use roa::timing::ServerTiming;
+/// use std::time::Duration;
+///
+/// async fn get(ctx: &mut Context) -> Result {
+///     ctx.timing("cache", Duration::from_millis(2));
+///     ctx.timing_desc("db", "query users", Duration::from_millis(53));
+///     Ok(())
+/// }
+/// ```
+pub trait ServerTiming {
+    /// Record a timing metric.
+    fn timing(&self, name: &str, duration: Duration);
+
+    /// Record a timing metric with description.
+    fn timing_desc(&self, name: &str, description: &str, duration: Duration);
+}
+
+impl<S> ServerTiming for Context<S> {
+    #[inline]
+    fn timing(&self, name: &str, duration: Duration) {
+        record(self, name, None, duration)
+    }
+
+    #[inline]
+    fn timing_desc(&self, name: &str, description: &str, duration: Duration) {
+        record(self, name, Some(description), duration)
+    }
+}
+
+/// Push a metric into collector if it exists.
+#[inline]
+fn record<S>(
+    ctx: &Context<S>,
+    name: &str,
+    description: Option<&str>,
+    duration: Duration,
+) {
+    if let Some(metrics) = ctx.load_scoped::<TimingScope, Metrics>("metrics") {
+        metrics
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(Metric {
+                name: name.to_string(),
+                description: description.map(ToString::to_string),
+                duration,
+            })
+    }
+}
+
+impl Metric {
+    /// Serialize metric as an entry of "Server-Timing".
+    #[inline]
+    fn serialize(&self) -> String {
+        let mut entry = format!(
+            "{};dur={:.3}",
+            self.name,
+            self.duration.as_secs_f64() * 1000.0
+        );
+        if let Some(ref description) = self.description {
+            let _ = write!(
+                entry,
+                r#";desc="{}""#,
+                description.replace('\\', r"\\").replace('"', r#"\""#)
+            );
+        }
+        entry
+    }
+
+    /// Check if metric can be emitted, the name must be a token,
+    /// and the entry must be a valid header value.
+    #[inline]
+    fn is_valid(&self, entry: &str) -> bool {
+        !self.name.is_empty()
+            && self.name.bytes().all(|byte| {
+                byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+            })
+            && HeaderValue::from_str(entry).is_ok()
+    }
+}
+
+impl Metrics {
+    /// Serialize metrics as value of "Server-Timing".
+    ///
+    /// Invalid metrics are skipped with a warning.
+    #[inline]
+    fn serialize(&self) -> String {
+        let metrics = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut value = String::new();
+        for metric in metrics.iter() {
+            let entry = metric.serialize();
+            if !metric.is_valid(&entry) {
+                warn!("invalid Server-Timing metric `{}` is skipped", entry);
+                continue;
+            }
+            if !value.is_empty() {
+                value.push_str(", ");
+            }
+            value.push_str(&entry);
+        }
+        value
+    }
+}
+
+/// A middleware to collect timing metrics and emit them as "Server-Timing".
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::timing::server_timing;
+/// use roa::App;
+///
+/// let app = App::new().gate(server_timing).end("Hello, World");
+/// ```
+pub async fn server_timing<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    ctx.store_scoped(TimingScope, "metrics", Metrics::default());
+    let result = next.await;
+    if let Some(metrics) = ctx.load_scoped::<TimingScope, Metrics>("metrics") {
+        let value = metrics.serialize();
+        if !value.is_empty() {
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                ctx.resp
+                    .headers
+                    .append(HeaderName::from_static(SERVER_TIMING), value);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{server_timing, ServerTiming};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context};
+    use async_std::task::spawn;
+    use std::time::Duration;
+
+    async fn end(ctx: &mut Context) -> crate::Result {
+        ctx.timing("db", Duration::from_micros(53200));
+        ctx.timing_desc("render", r#"render "user""#, Duration::from_millis(4));
+        // invalid metrics are skipped.
+        ctx.timing("cache hit", Duration::from_millis(1));
+        ctx.timing_desc("cache", "line\nbreak", Duration::from_millis(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn timing() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().gate(server_timing).end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(
+            r#"db;dur=53.200, render;dur=4.000;desc="render \"user\"""#,
+            resp.headers()["server-timing"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn timing_disabled() -> Result<(), Box<dyn std::error::Error>> {
+        let (addr, server) = App::new().end(end).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(resp.headers().get("server-timing").is_none());
+        Ok(())
+    }
+}
@@ -174,12 +174,14 @@ where
                 Some(Ok(AddrStream {
                     stream,
                     remote_addr,
+                    ..
                 })) => {
                     let accept_future = self.acceptor.accept(stream);
                     Some(Ok(AddrStream::new(
                         remote_addr,
                         Handshaking(Box::new(accept_future)),
-                    )))
+                    )
+                    .secure()))
                 }
                 Some(Err(err)) => Some(Err(err)),
                 None => None,
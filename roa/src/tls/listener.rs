@@ -163,4 +163,34 @@ mod tests {
         assert_eq!("Hello, World!", text);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn handshake_error() -> Result<(), Box<dyn std::error::Error>> {
+        use async_std::net::TcpStream;
+        use futures::channel::mpsc::unbounded;
+        use futures::{AsyncWriteExt, StreamExt};
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        let mut cert_file = BufReader::new(File::open("../assets/cert.pem")?);
+        let mut key_file = BufReader::new(File::open("../assets/key.pem")?);
+        let cert_chain = certs(&mut cert_file).unwrap();
+        let mut keys = rsa_private_keys(&mut key_file).unwrap();
+        config.set_single_cert(cert_chain, keys.remove(0))?;
+
+        let (sender, mut receiver) = unbounded();
+        let app = App::new()
+            .on_connection_error(move |addr, _err| {
+                sender.unbounded_send(addr).unwrap();
+            })
+            .end(end);
+        let (addr, server) = app.run_tls(config)?;
+        spawn(server);
+
+        // plain http to a https server.
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
+        let mut data = Vec::new();
+        let _ = stream.read_to_end(&mut data).await;
+        assert_eq!(Some(stream.local_addr()?), receiver.next().await);
+        Ok(())
+    }
 }
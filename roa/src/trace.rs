@@ -0,0 +1,349 @@
+//! This module provides a middleware `status_trace`, a wrapper `Traced`
+//! and a context extension `StatusTrace`, to find out which middleware set the status.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use roa::trace::{status_trace, traced};
+//! use roa::http::StatusCode;
+//! use roa::{App, Context, Next};
+//!
+//! async fn mangle(ctx: &mut Context, next: Next<'_>) -> roa::Result {
+//!     next.await?;
+//!     ctx.resp.status = StatusCode::ACCEPTED;
+//!     Ok(())
+//! }
+//!
+//! async fn end(ctx: &mut Context) -> roa::Result {
+//!     ctx.resp.status = StatusCode::CREATED;
+//!     Ok(())
+//! }
+//!
+//! // status changes are logged at debug level,
+//! // "end" set 201 CREATED then "mangle" set 202 ACCEPTED.
+//! let app = App::new()
+//!     .gate(status_trace)
+//!     .gate(traced("mangle", mangle))
+//!     .end(traced("end", end));
+//! ```
+
+use crate::http::StatusCode;
+use crate::{async_trait, Context, Endpoint, Middleware, Next, Result};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Mutex;
+
+/// Name of changes not made by any traced middleware.
+const UNTRACED: &str = "untraced";
+
+/// A private scope.
+struct TraceScope;
+
+/// A recorder of status changes.
+struct Trace(Mutex<Recorder>);
+
+/// Traced middlewares on going and changes recorded.
+struct Recorder {
+    stack: Vec<Cow<'static, str>>,
+    status: StatusCode,
+    error: Option<StatusCode>,
+    changes: Vec<StatusChange>,
+}
+
+/// A change of response status.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StatusChange {
+    /// Name of the traced middleware or endpoint making this change, "untraced" if unknown.
+    pub by: String,
+
+    /// Status before this change.
+    pub from: StatusCode,
+
+    /// Status after this change.
+    pub to: StatusCode,
+
+    /// If the status is thrown as an error rather than set to response.
+    pub thrown: bool,
+}
+
+/// A wrapper of middleware or endpoint, to attribute status changes to it by name.
+///
+/// Tracing is a no-op if `status_trace` is not set.
+pub struct Traced<M> {
+    name: Cow<'static, str>,
+    inner: M,
+}
+
+/// A context extension to query status changes recorded by `status_trace`.
+///
+/// ### Example
+///
+/// ```rust
+/// use roa::trace::StatusTrace;
+/// use roa::{Context, Next, Result};
+///
+/// async fn gate(ctx: &mut Context, next: Next<'_>) -> Result {
+///     let result = next.await;
+///     for change in ctx.status_changes() {
+///         println!("{}", change);
+///     }
+///     result
+/// }
+/// ```
+pub trait StatusTrace {
+    /// Status changes in order, empty if `status_trace` is not set.
+    fn status_changes(&self) -> Vec<StatusChange>;
+}
+
+/// Wrap a middleware or endpoint with a name, to trace status changes made by it.
+pub fn traced<M>(name: impl Into<Cow<'static, str>>, inner: M) -> Traced<M> {
+    Traced {
+        name: name.into(),
+        inner,
+    }
+}
+
+impl Recorder {
+    /// Attribute status change since the last checkpoint.
+    fn check(&mut self, status: StatusCode, by: &str) {
+        if status != self.status {
+            self.changes.push(StatusChange {
+                by: by.to_string(),
+                from: self.status,
+                to: status,
+                thrown: false,
+            });
+            self.status = status;
+        }
+    }
+
+    /// Attribute thrown status which is not seen before.
+    fn check_result(&mut self, result: &Result, by: &str) {
+        let error = result.as_ref().err().map(|status| status.status_code);
+        if error != self.error {
+            if let Some(to) = error {
+                self.changes.push(StatusChange {
+                    by: by.to_string(),
+                    from: self.status,
+                    to,
+                    thrown: true,
+                });
+            }
+            self.error = error;
+        }
+    }
+}
+
+impl Display for StatusChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let verb = if self.thrown { "threw" } else { "set" };
+        write!(f, "{} {} {}", self.by, verb, self.to)?;
+        if self.thrown {
+            Ok(())
+        } else {
+            write!(f, " (was {})", self.from)
+        }
+    }
+}
+
+/// Run a closure on recorder if tracing is enabled.
+#[inline]
+fn record<S>(ctx: &Context<S>, f: impl FnOnce(&mut Recorder, StatusCode)) {
+    if let Some(trace) = ctx.load_scoped::<TraceScope, Trace>("trace") {
+        let mut recorder = trace
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut recorder, ctx.resp.status)
+    }
+}
+
+/// Changes before entering are made by the enclosing traced middleware.
+#[inline]
+fn enter<S>(ctx: &Context<S>, name: &Cow<'static, str>) {
+    record(ctx, |recorder, status| {
+        let by = recorder
+            .stack
+            .last()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| UNTRACED.to_string());
+        recorder.check(status, &by);
+        recorder.stack.push(name.clone());
+        recorder.error = None;
+    })
+}
+
+/// Changes before exiting are made by this traced middleware.
+#[inline]
+fn exit<S>(ctx: &Context<S>, name: &str, result: &Result) {
+    record(ctx, |recorder, status| {
+        recorder.check(status, name);
+        recorder.check_result(result, name);
+        recorder.stack.pop();
+    })
+}
+
+/// A middleware to enable status tracing, changes are logged at debug level after downstream returns.
+///
+/// It should be the outermost middleware to record all changes.
+pub async fn status_trace<S>(ctx: &mut Context<S>, next: Next<'_>) -> Result {
+    ctx.store_scoped(
+        TraceScope,
+        "trace",
+        Trace(Mutex::new(Recorder {
+            stack: Vec::new(),
+            status: ctx.resp.status,
+            error: None,
+            changes: Vec::new(),
+        })),
+    );
+    let result = next.await;
+    record(ctx, |recorder, status| {
+        recorder.check(status, UNTRACED);
+        recorder.check_result(&result, UNTRACED);
+    });
+    for change in ctx.status_changes() {
+        log::debug!("{} {}: {}", ctx.method(), ctx.uri(), change);
+    }
+    result
+}
+
+impl<S> StatusTrace for Context<S> {
+    #[inline]
+    fn status_changes(&self) -> Vec<StatusChange> {
+        let mut changes = Vec::new();
+        record(self, |recorder, _| changes = recorder.changes.clone());
+        changes
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, M> Middleware<'a, S> for Traced<M>
+where
+    M: for<'b> Middleware<'b, S>,
+{
+    #[inline]
+    async fn handle(&'a self, ctx: &'a mut Context<S>, next: Next<'a>) -> Result {
+        enter(ctx, &self.name);
+        let result = self.inner.handle(ctx, next).await;
+        exit(ctx, &self.name, &result);
+        result
+    }
+}
+
+#[async_trait(?Send)]
+impl<'a, S, M> Endpoint<'a, S> for Traced<M>
+where
+    M: for<'b> Endpoint<'b, S>,
+{
+    #[inline]
+    async fn call(&'a self, ctx: &'a mut Context<S>) -> Result {
+        enter(ctx, &self.name);
+        let result = self.inner.call(ctx).await;
+        exit(ctx, &self.name, &result);
+        result
+    }
+}
+
+#[cfg(all(test, feature = "tcp"))]
+mod tests {
+    use super::{status_trace, traced, StatusChange, StatusTrace};
+    use crate::http::StatusCode;
+    use crate::preload::*;
+    use crate::{App, Context, Next};
+    use async_std::task::spawn;
+
+    fn change(by: &str, from: StatusCode, to: StatusCode, thrown: bool) -> StatusChange {
+        StatusChange {
+            by: by.to_string(),
+            from,
+            to,
+            thrown,
+        }
+    }
+
+    async fn teapot(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+        ctx.resp.status = StatusCode::IM_A_TEAPOT;
+        next.await
+    }
+
+    async fn mangle(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+        next.await?;
+        ctx.resp.status = StatusCode::ACCEPTED;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn status_changes() -> Result<(), Box<dyn std::error::Error>> {
+        async fn check(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            next.await?;
+            assert_eq!(
+                vec![
+                    change("teapot", StatusCode::OK, StatusCode::IM_A_TEAPOT, false),
+                    change("end", StatusCode::IM_A_TEAPOT, StatusCode::CREATED, false),
+                    change("mangle", StatusCode::CREATED, StatusCode::ACCEPTED, false),
+                ],
+                ctx.status_changes()
+            );
+            Ok(())
+        }
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.status = StatusCode::CREATED;
+            Ok(())
+        }
+        let (addr, server) = App::new()
+            .gate(status_trace)
+            .gate(check)
+            .gate(traced("teapot", teapot))
+            .gate(traced("mangle", mangle))
+            .end(traced("end", end))
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::ACCEPTED, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn thrown() -> Result<(), Box<dyn std::error::Error>> {
+        async fn check(ctx: &mut Context, next: Next<'_>) -> crate::Result {
+            let result = next.await;
+            assert_eq!(
+                vec![
+                    change("teapot", StatusCode::OK, StatusCode::IM_A_TEAPOT, false),
+                    change("end", StatusCode::IM_A_TEAPOT, StatusCode::NOT_FOUND, true),
+                ],
+                ctx.status_changes()
+            );
+            result
+        }
+        async fn end(_ctx: &mut Context) -> crate::Result {
+            Err(crate::status!(StatusCode::NOT_FOUND))
+        }
+        let (addr, server) = App::new()
+            .gate(status_trace)
+            .gate(check)
+            .gate(traced("teapot", teapot))
+            .gate(traced("mangle", mangle))
+            .end(traced("end", end))
+            .run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::NOT_FOUND, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn trace_disabled() -> Result<(), Box<dyn std::error::Error>> {
+        async fn end(ctx: &mut Context) -> crate::Result {
+            ctx.resp.status = StatusCode::CREATED;
+            assert!(ctx.status_changes().is_empty());
+            Ok(())
+        }
+        let (addr, server) = App::new().end(traced("end", end)).run()?;
+        spawn(server);
+        let resp = reqwest::get(&format!("http://{}", addr)).await?;
+        assert_eq!(StatusCode::CREATED, resp.status());
+        Ok(())
+    }
+}
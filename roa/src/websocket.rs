@@ -21,13 +21,12 @@
 //! # }
 //! ```
 
-use crate::http::header::UPGRADE;
-use crate::http::StatusCode;
-use crate::{async_trait, throw, Context, Endpoint, State, Status};
-use headers::{
-    Connection, HeaderMapExt, SecWebsocketAccept, SecWebsocketKey, SecWebsocketVersion,
-    Upgrade,
+use crate::http::header::{
+    HeaderMap, HeaderName, CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE,
 };
+use crate::http::StatusCode;
+use crate::{async_trait, throw, Context, Endpoint, Request, State, Status};
+use headers::{Connection, HeaderMapExt, SecWebsocketAccept, SecWebsocketKey, Upgrade};
 use hyper::upgrade::Upgraded;
 use std::future::Future;
 use std::marker::PhantomData;
@@ -41,6 +40,57 @@ use tokio_tungstenite::WebSocketStream;
 /// An alias for WebSocketStream<Upgraded>.
 pub type SocketStream = WebSocketStream<Upgraded>;
 
+/// A request extension to detect websocket upgrade requests.
+///
+/// ### Example
+/// ```
+/// use roa::websocket::WebsocketRequest;
+/// use roa::{Context, Result};
+///
+/// async fn end(ctx: &mut Context) -> Result {
+///     if ctx.req.is_websocket() {
+///         // upgrade
+///     } else {
+///         ctx.resp.write("Hello, World");
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait WebsocketRequest {
+    /// Check if it's a websocket upgrade request, with "Upgrade: websocket",
+    /// "Connection: Upgrade", "Sec-WebSocket-Version: 13" and a "Sec-WebSocket-Key".
+    ///
+    /// Header values are compared case-insensitively.
+    fn is_websocket(&self) -> bool;
+}
+
+impl WebsocketRequest for Request {
+    #[inline]
+    fn is_websocket(&self) -> bool {
+        is_websocket(&self.headers)
+    }
+}
+
+/// Check if any comma-separated token of a header equals to the expected one, ignoring case.
+fn contains_token(headers: &HeaderMap, name: HeaderName, expected: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case(expected))
+}
+
+fn is_websocket(headers: &HeaderMap) -> bool {
+    contains_token(headers, UPGRADE, "websocket")
+        && contains_token(headers, CONNECTION, "upgrade")
+        && headers
+            .get(SEC_WEBSOCKET_VERSION)
+            .map(|version| version.as_bytes() == b"13")
+            .unwrap_or(false)
+        && headers.contains_key(SEC_WEBSOCKET_KEY)
+}
+
 /// The Websocket middleware.
 ///
 /// ### Example
@@ -153,15 +203,11 @@ where
 {
     #[inline]
     async fn call(&'a self, ctx: &'a mut Context<S>) -> Result<(), Status> {
-        let header_map = &ctx.req.headers;
-        let key = header_map
-            .typed_get::<Upgrade>()
-            .filter(|upgrade| upgrade == &Upgrade::websocket())
-            .and(header_map.typed_get::<Connection>())
-            .filter(|connection| connection.contains(UPGRADE))
-            .and(header_map.typed_get::<SecWebsocketVersion>())
-            .filter(|version| version == &SecWebsocketVersion::V13)
-            .and(header_map.typed_get::<SecWebsocketKey>());
+        let key = if ctx.req.is_websocket() {
+            ctx.req.headers.typed_get::<SecWebsocketKey>()
+        } else {
+            None
+        };
 
         match key {
             None => throw!(StatusCode::BAD_REQUEST, "invalid websocket upgrade request"),
@@ -200,3 +246,38 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_websocket;
+    use crate::http::header::{
+        HeaderMap, HeaderValue, CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION,
+        UPGRADE,
+    };
+
+    #[test]
+    fn websocket_request() {
+        let mut headers = HeaderMap::new();
+        headers.insert(UPGRADE, HeaderValue::from_static("WebSocket"));
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive, Upgrade"));
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        assert!(!is_websocket(&headers));
+
+        headers.insert(
+            SEC_WEBSOCKET_KEY,
+            HeaderValue::from_static("dGhlIHNhbXBsZSBub25jZQ=="),
+        );
+        assert!(is_websocket(&headers));
+
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("8"));
+        assert!(!is_websocket(&headers));
+
+        headers.insert(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+        headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
+        assert!(!is_websocket(&headers));
+
+        headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+        headers.insert(UPGRADE, HeaderValue::from_static("h2c"));
+        assert!(!is_websocket(&headers));
+    }
+}
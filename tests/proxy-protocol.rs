@@ -0,0 +1,40 @@
+use async_std::net::TcpStream;
+use async_std::task::spawn;
+use futures::{AsyncReadExt, AsyncWriteExt};
+use roa::tcp::{ProxyIncoming, TcpIncoming};
+use roa::{App, Context};
+use std::net::SocketAddr;
+
+async fn end(ctx: &mut Context) -> roa::Result {
+    ctx.resp.write(ctx.remote_addr.to_string());
+    Ok(())
+}
+
+async fn request(addr: SocketAddr, prefix: &[u8]) -> std::io::Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(prefix).await?;
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut resp = String::new();
+    stream.read_to_string(&mut resp).await?;
+    Ok(resp)
+}
+
+#[tokio::test]
+async fn reject_connection_without_header() -> Result<(), Box<dyn std::error::Error>> {
+    let incoming = ProxyIncoming::new(TcpIncoming::bind("127.0.0.1:0")?);
+    let (addr, server) = App::new().end(end).serve_on(incoming)?;
+    spawn(server);
+
+    // a client reaching the listener directly is rejected.
+    assert!(reqwest::get(&format!("http://{}", addr)).await.is_err());
+    let resp = request(addr, b"").await.unwrap_or_default();
+    assert!(resp.is_empty());
+
+    // a client behind the load balancer.
+    let resp = request(addr, b"PROXY TCP4 1.2.3.4 5.6.7.8 1000 80\r\n").await?;
+    assert!(resp.starts_with("HTTP/1.1 200 OK"));
+    assert!(resp.ends_with("1.2.3.4:1000"));
+    Ok(())
+}
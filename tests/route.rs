@@ -0,0 +1,104 @@
+use async_std::task::spawn;
+use http::StatusCode;
+use roa::preload::*;
+use roa::router::{delete, get, post, Router};
+use roa::{App, Context, State};
+
+/// Get a user by id.
+#[get("/user/:id")]
+async fn get_user(ctx: &mut Context, id: u64) -> roa::Result {
+    ctx.write(format!("user {}", id));
+    Ok(())
+}
+
+#[delete("/user/:id")]
+async fn delete_user(ctx: &mut Context, id: u64) -> roa::Result {
+    ctx.write(format!("delete {}", id));
+    Ok(())
+}
+
+#[post("/post/:year/:title")]
+async fn create_post(ctx: &mut Context, title: String, year: u16) -> roa::Result {
+    ctx.write(format!("{} {}", year, title));
+    Ok(())
+}
+
+#[get("/files/*{path}")]
+async fn file(ctx: &mut Context, path: String) -> roa::Result {
+    ctx.write(path);
+    Ok(())
+}
+
+#[get("/greet/:name")]
+async fn greet<S>(ctx: &mut Context<S>, name: String) -> roa::Result
+where
+    S: State + AsRef<str>,
+{
+    let greeting = format!("{}, {}", ctx.state().as_ref(), name);
+    ctx.write(greeting);
+    Ok(())
+}
+
+#[tokio::test]
+async fn route_attributes() -> Result<(), Box<dyn std::error::Error>> {
+    let router = Router::new()
+        .route(get_user)
+        .route(delete_user)
+        .route(create_post)
+        .route(file);
+    let app = App::new().end(router.routes("/api")?);
+    let (addr, server) = app.run()?;
+    spawn(server);
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .get(&format!("http://{}/api/user/1", addr))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("user 1", resp.text().await?);
+
+    let resp = client
+        .delete(&format!("http://{}/api/user/1", addr))
+        .send()
+        .await?;
+    assert_eq!("delete 1", resp.text().await?);
+
+    let resp = client
+        .put(&format!("http://{}/api/user/1", addr))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::METHOD_NOT_ALLOWED, resp.status());
+
+    // invalid variable
+    let resp = client
+        .get(&format!("http://{}/api/user/one", addr))
+        .send()
+        .await?;
+    assert_eq!(StatusCode::BAD_REQUEST, resp.status());
+
+    let resp = client
+        .post(&format!("http://{}/api/post/2020/roa", addr))
+        .send()
+        .await?;
+    assert_eq!("2020 roa", resp.text().await?);
+
+    let resp = client
+        .get(&format!("http://{}/api/files/assets/welcome.html", addr))
+        .send()
+        .await?;
+    assert_eq!("assets/welcome.html", resp.text().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn generic_route() -> Result<(), Box<dyn std::error::Error>> {
+    let router = Router::new().route(greet);
+    let app = App::state("Hello".to_string()).end(router.routes("/")?);
+    let (addr, server) = app.run()?;
+    spawn(server);
+    let resp = reqwest::get(&format!("http://{}/greet/roa", addr)).await?;
+    assert_eq!(StatusCode::OK, resp.status());
+    assert_eq!("Hello, roa", resp.text().await?);
+    Ok(())
+}
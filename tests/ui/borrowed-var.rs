@@ -0,0 +1,8 @@
+use roa::router::get;
+
+#[get("/user/:name")]
+async fn get_user(ctx: &mut roa::Context, name: &str) -> roa::Result {
+    Ok(())
+}
+
+fn main() {}
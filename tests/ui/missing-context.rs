@@ -0,0 +1,8 @@
+use roa::router::get;
+
+#[get("/user/:id")]
+async fn get_user(id: u64) -> roa::Result {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use roa::router::get;
+
+#[get("/user/:id")]
+async fn get_user<T>(ctx: &mut roa::Context, id: u64) -> roa::Result {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use roa::router::get;
+
+#[get("/user/:id")]
+async fn get_user(ctx: &mut roa::Context, name: u64) -> roa::Result {
+    Ok(())
+}
+
+fn main() {}